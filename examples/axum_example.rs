@@ -1,11 +1,9 @@
-use std::fs::File;
-
 use axum::extract::Extension;
 use axum::routing::post;
 use axum::Router;
-use oauth_fcm::create_shared_token_manager;
-use oauth_fcm::send_fcm_message;
-use oauth_fcm::SharedTokenManager;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmError;
+use oauth_fcm::Message;
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -14,34 +12,42 @@ struct MyData {
     count: i32,
 }
 
+// With the `axum` feature, `FcmError` implements `IntoResponse`, so errors are
+// returned with a fitting status code, e.g. 410 for unregistered devices.
 async fn send_notification(
-    Extension(token_manager): Extension<SharedTokenManager>,
-) -> Result<String, String> {
-    // It is a good idea to load these from an .env file. Additionally, you can
-    // store them in a shared `Config` state.
+    Extension(client): Extension<FcmClient>,
+) -> Result<&'static str, FcmError> {
+    // It is a good idea to load this from an .env file. Additionally, you can
+    // store it in a shared `Config` state.
     let device_token = "YOUR_DEVICE_TOKEN";
-    let project_id = "YOUR_PROJECT_ID";
     let data = MyData {
         message: "Hello from Axum!".to_string(),
         count: 42,
     };
 
-    send_fcm_message(device_token, None, Some(data), &token_manager, project_id)
-        .await
-        .map_err(|e| e.to_string())?;
+    let message = Message::builder()
+        .token(device_token)
+        .data(&data)
+        // FCM only accepts strings, so `count` is sent as "42".
+        .stringify_data(true)
+        .build()?;
+    client.send(&message).await?;
 
-    Ok("FCM message sent successfully".to_string())
+    Ok("FCM message sent successfully")
 }
 
 #[tokio::main]
 async fn main() {
-    let shared_token_manager =
-        create_shared_token_manager(File::open("path/to/google/credentials.json").unwrap())
-            .expect("Could not find credentials.json");
+    let client = FcmClient::builder()
+        .credentials(std::path::Path::new("path/to/google/credentials.json"))
+        .project_id("YOUR_PROJECT_ID")
+        .build()
+        .await
+        .expect("Could not read credentials.json");
 
     let app = Router::new()
         .route("/send", post(send_notification))
-        .layer(Extension(shared_token_manager));
+        .layer(Extension(client));
 
     let listener = tokio::net::TcpListener::bind(format!("{}:{}", "127.0.0.1", "8080"))
         .await
@@ -1,8 +1,5 @@
-use std::fs::File;
-
-use oauth_fcm::create_shared_token_manager;
-use oauth_fcm::send_fcm_message;
-use oauth_fcm::SharedTokenManager;
+use oauth_fcm::FcmClient;
+use oauth_fcm::Message;
 use rocket::post;
 use rocket::State;
 use serde::Serialize;
@@ -14,37 +11,38 @@ struct MyData {
 }
 
 #[post("/send")]
-async fn send_notification(token_manager: &State<SharedTokenManager>) -> Result<String, String> {
-    // It is a good idea to load these from an .env file. Additionally, you can
-    // store them in a shared `Config` state.
+async fn send_notification(client: &State<FcmClient>) -> Result<String, String> {
+    // It is a good idea to load this from an .env file. Additionally, you can
+    // store it in a shared `Config` state.
     let device_token = "YOUR_DEVICE_TOKEN";
-    let project_id = "YOUR_PROJECT_ID";
     let data = MyData {
         message: "Hello from Rocket!".to_string(),
         count: 42,
     };
 
-    send_fcm_message(
-        device_token,
-        None,
-        Some(data),
-        token_manager.inner(),
-        project_id,
-    )
-    .await
-    .map_err(|e| e.to_string())?;
+    let message = Message::builder()
+        .token(device_token)
+        .data(&data)
+        // FCM only accepts strings, so `count` is sent as "42".
+        .stringify_data(true)
+        .build()
+        .map_err(|e| e.to_string())?;
+    client.send(&message).await.map_err(|e| e.to_string())?;
 
     Ok("FCM message sent successfully".to_string())
 }
 
 #[rocket::main]
 async fn main() {
-    let shared_token_manager =
-        create_shared_token_manager(File::open("path/to/google/credentials.json").unwrap())
-            .unwrap();
+    let client = FcmClient::builder()
+        .credentials(std::path::Path::new("path/to/google/credentials.json"))
+        .project_id("YOUR_PROJECT_ID")
+        .build()
+        .await
+        .unwrap();
 
     rocket::build()
-        .manage(shared_token_manager)
+        .manage(client)
         .mount("/", rocket::routes![send_notification])
         .launch()
         .await
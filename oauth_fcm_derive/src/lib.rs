@@ -0,0 +1,201 @@
+//! Derive macros for the [`oauth_fcm`](https://docs.rs/oauth_fcm) crate.
+//!
+//! Use them through the `derive` feature of `oauth_fcm` instead of depending
+//! on this crate directly.
+
+#![forbid(unsafe_code)]
+#![warn(clippy::pedantic, clippy::nursery, unused_qualifications)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Fields;
+use syn::GenericArgument;
+use syn::LitStr;
+use syn::PathArguments;
+use syn::Type;
+
+/// Types whose `ToString` output is used as data value.
+const SUPPORTED_TYPES: &[&str] = &[
+    "String", "str", "char", "bool", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16",
+    "u32", "u64", "u128", "usize", "f32", "f64",
+];
+
+/// Keys, which FCM doesn't allow in the data payload.
+const RESERVED_KEYS: &[&str] = &["from", "notification", "message_type"];
+
+/// Key prefixes, which FCM doesn't allow in the data payload.
+const RESERVED_PREFIXES: &[&str] = &["google", "gcm"];
+
+/// Derives `oauth_fcm::ToDataPayload` for a struct with named fields.
+///
+/// Every field must be a `String`, `&str`, `char`, `bool`, a number or an
+/// `Option` of one of these types. `None` values are left out of the payload.
+/// Use `#[fcm(rename = "key")]` to change the key of a field.
+///
+/// Keys are checked at compile time, so reserved keys like `from` or keys
+/// starting with `google` are rejected.
+#[proc_macro_derive(FcmData, attributes(fcm))]
+pub fn derive_fcm_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.span(),
+            "FcmData can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            data.fields.span(),
+            "FcmData can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut inserts = Vec::with_capacity(fields.named.len());
+    for field in &fields.named {
+        let ident = field
+            .ident
+            .as_ref()
+            .expect("Named fields always have an identifier");
+        let key = field_key(field)?;
+        validate_key(&key, field.span())?;
+
+        let insert = if let Some(inner) = option_inner_type(&field.ty) {
+            check_supported_type(inner)?;
+            quote! {
+                if let ::core::option::Option::Some(value) = &self.#ident {
+                    payload.insert(#key.to_string(), value.to_string());
+                }
+            }
+        } else {
+            check_supported_type(&field.ty)?;
+            quote! {
+                payload.insert(#key.to_string(), self.#ident.to_string());
+            }
+        };
+        inserts.push(insert);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let capacity = inserts.len();
+
+    Ok(quote! {
+        impl #impl_generics ::oauth_fcm::ToDataPayload for #name #ty_generics #where_clause {
+            fn to_data_payload(&self) -> ::std::collections::HashMap<::std::string::String, ::std::string::String> {
+                let mut payload = ::std::collections::HashMap::with_capacity(#capacity);
+                #(#inserts)*
+                payload
+            }
+        }
+
+    })
+}
+
+fn field_key(field: &syn::Field) -> syn::Result<String> {
+    let mut key = field
+        .ident
+        .as_ref()
+        .expect("Named fields always have an identifier")
+        .to_string()
+        .trim_start_matches("r#")
+        .to_string();
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("fcm"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                key = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("unsupported fcm attribute, expected `rename`"))
+            }
+        })?;
+    }
+
+    Ok(key)
+}
+
+fn validate_key(key: &str, span: proc_macro2::Span) -> syn::Result<()> {
+    if key.is_empty() {
+        return Err(syn::Error::new(span, "data payload keys must not be empty"));
+    }
+
+    if RESERVED_KEYS.contains(&key) {
+        return Err(syn::Error::new(
+            span,
+            format!("`{key}` is a reserved key in FCM data payloads"),
+        ));
+    }
+
+    if let Some(prefix) = RESERVED_PREFIXES
+        .iter()
+        .find(|prefix| key.starts_with(*prefix))
+    {
+        return Err(syn::Error::new(
+            span,
+            format!("data payload keys must not start with the reserved prefix `{prefix}`"),
+        ));
+    }
+
+    Ok(())
+}
+
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+
+    match arguments.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn check_supported_type(ty: &Type) -> syn::Result<()> {
+    let ty_without_reference = match ty {
+        Type::Reference(reference) => &*reference.elem,
+        ty => ty,
+    };
+
+    let supported = match ty_without_reference {
+        Type::Path(path) if path.qself.is_none() => {
+            path.path.segments.last().is_some_and(|segment| {
+                segment.arguments.is_empty()
+                    && SUPPORTED_TYPES.contains(&segment.ident.to_string().as_str())
+            })
+        }
+        _ => false,
+    };
+
+    if supported {
+        Ok(())
+    } else {
+        Err(syn::Error::new(
+            ty.span(),
+            "unsupported field type for FcmData, expected a string, char, bool, number or an \
+             Option of these",
+        ))
+    }
+}
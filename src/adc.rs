@@ -0,0 +1,106 @@
+//! Discovery of Application Default Credentials (ADC).
+//!
+//! The search order follows the other Google client libraries, see
+//! [`TokenManager::from_adc`].
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::Client;
+
+use crate::FcmError;
+use crate::TokenManager;
+
+pub const METADATA_FLAVOR_HEADER: &str = "Metadata-Flavor";
+pub const METADATA_FLAVOR: &str = "Google";
+
+const CREDENTIALS_ENV: &str = "GOOGLE_APPLICATION_CREDENTIALS";
+const METADATA_HOST_ENV: &str = "GCE_METADATA_HOST";
+const DEFAULT_METADATA_HOST: &str = "metadata.google.internal";
+const WELL_KNOWN_FILE: &str = "application_default_credentials.json";
+/// Off Google Cloud, the metadata server doesn't exist, so the probe should
+/// give up quickly.
+const METADATA_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Returns the host of the metadata server, which can be overridden with
+/// `GCE_METADATA_HOST`, e.g. for an emulator.
+pub fn metadata_host() -> String {
+    std::env::var(METADATA_HOST_ENV)
+        .ok()
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| DEFAULT_METADATA_HOST.to_string())
+}
+
+pub fn metadata_token_uri(host: &str) -> String {
+    format!("http://{host}/computeMetadata/v1/instance/service-accounts/default/token")
+}
+
+fn metadata_project_id_uri(host: &str) -> String {
+    format!("http://{host}/computeMetadata/v1/project/project-id")
+}
+
+/// Searches the Application Default Credentials and creates a
+/// `TokenManager` for the first source found.
+pub async fn find_default_credentials(http_client: &Client) -> Result<TokenManager, FcmError> {
+    if let Some(path) = std::env::var_os(CREDENTIALS_ENV).filter(|path| !path.is_empty()) {
+        info!("Using credentials from {}", CREDENTIALS_ENV);
+        return TokenManager::new(PathBuf::from(path));
+    }
+
+    if let Some(path) = well_known_file().filter(|path| path.is_file()) {
+        info!("Using credentials from {}", path.display());
+        return TokenManager::new(path);
+    }
+
+    let host = metadata_host();
+    let Some(project_id) = probe_metadata_server(http_client, &host).await else {
+        return Err(FcmError::DefaultCredentialsNotFound);
+    };
+    info!("Using credentials from the metadata server at {}", host);
+    Ok(TokenManager::metadata_server(&host, project_id).with_http_client(http_client.clone()))
+}
+
+/// Returns the path of the credentials file written by
+/// `gcloud auth application-default`.
+fn well_known_file() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("CLOUDSDK_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(std::env::var_os("APPDATA")?).join("gcloud"),
+        None => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".config")
+            .join("gcloud"),
+    };
+    Some(config_dir.join(WELL_KNOWN_FILE))
+}
+
+/// Checks if the metadata server is reachable.
+///
+/// Returns `None` if it isn't. Otherwise, returns the project ID, if it could
+/// be read.
+async fn probe_metadata_server(http_client: &Client, host: &str) -> Option<Option<String>> {
+    let response = http_client
+        .get(metadata_project_id_uri(host))
+        .header(METADATA_FLAVOR_HEADER, METADATA_FLAVOR)
+        .timeout(METADATA_PROBE_TIMEOUT)
+        .send()
+        .await
+        .inspect_err(|error| debug!("Metadata server isn't reachable: {}", error))
+        .ok()?;
+
+    // Other servers, e.g. a captive portal, don't send this header.
+    if response.headers().get(METADATA_FLAVOR_HEADER)? != METADATA_FLAVOR {
+        return None;
+    }
+
+    let project_id = if response.status().is_success() {
+        response
+            .text()
+            .await
+            .ok()
+            .map(|project_id| project_id.trim().to_string())
+            .filter(|project_id| !project_id.is_empty())
+    } else {
+        None
+    };
+    Some(project_id)
+}
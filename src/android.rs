@@ -0,0 +1,252 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::DeliveryPriority;
+
+/// Android specific options of an FCM message.
+///
+/// Serialized into the `android` section of the message. It is combined with
+/// the `FcmNotification` of the message, so only the Android specific parts
+/// need to be set here.
+///
+/// # Example
+///
+/// A notification, whose strings are resolved from the string resources of
+/// the app:
+///
+/// ```rust
+/// use oauth_fcm::AndroidConfig;
+/// use oauth_fcm::AndroidNotification;
+///
+/// let android = AndroidConfig {
+///     notification: Some(AndroidNotification {
+///         title_loc_key: Some("new_message_title".to_string()),
+///         body_loc_key: Some("new_message_body".to_string()),
+///         body_loc_args: vec!["Alice".to_string()],
+///         ..AndroidNotification::default()
+///     }),
+///     ..AndroidConfig::default()
+/// };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AndroidConfig {
+    /// The Android specific notification options.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification: Option<AndroidNotification>,
+    /// Options for features provided by the FCM SDK for Android.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fcm_options: Option<AndroidFcmOptions>,
+    /// How long FCM keeps the message while the device is offline, at most
+    /// 28 days. Serialized in seconds, e.g. `"3600s"`.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_ttl"
+    )]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    pub ttl: Option<Duration>,
+    /// The delivery priority of the message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<DeliveryPriority>,
+    /// Messages with the same collapse key replace each other while the
+    /// device is offline, only the last one is delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapse_key: Option<String>,
+}
+
+impl AndroidConfig {
+    /// Returns `true` if no option is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.notification
+            .as_ref()
+            .is_none_or(AndroidNotification::is_empty)
+            && self.fcm_options.is_none()
+            && self.ttl.is_none()
+            && self.priority.is_none()
+            && self.collapse_key.is_none()
+    }
+
+    /// Fills the fields, which aren't set, from `defaults`.
+    ///
+    /// The merge is shallow: a field set in `self` is kept as a whole, e.g. a
+    /// notification without a channel ID doesn't get the default channel ID.
+    pub(crate) fn merge_defaults(&mut self, defaults: &Self) {
+        if self.notification.is_none() {
+            self.notification.clone_from(&defaults.notification);
+        }
+        if self.fcm_options.is_none() {
+            self.fcm_options.clone_from(&defaults.fcm_options);
+        }
+        if self.ttl.is_none() {
+            self.ttl = defaults.ttl;
+        }
+        if self.priority.is_none() {
+            self.priority = defaults.priority;
+        }
+        if self.collapse_key.is_none() {
+            self.collapse_key.clone_from(&defaults.collapse_key);
+        }
+    }
+}
+
+/// Options for features provided by the FCM SDK for Android.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AndroidFcmOptions {
+    /// The label of the message in the delivery metrics of the Firebase
+    /// console, overriding the label of the message for Android. See
+    /// [`MessageBuilder::analytics_label`](crate::MessageBuilder::analytics_label)
+    /// for the allowed labels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analytics_label: Option<String>,
+}
+
+/// The notification options of an Android message.
+///
+/// Unset fields are left out of the payload.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AndroidNotification {
+    /// The key of the title string in the string resources of the app.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_loc_key: Option<String>,
+    /// The format arguments of the title string.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub title_loc_args: Vec<String>,
+    /// The key of the body string in the string resources of the app.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_loc_key: Option<String>,
+    /// The format arguments of the body string.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub body_loc_args: Vec<String>,
+    /// The ID of the notification channel, the notification is posted to.
+    /// The channel must be created by the app.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    /// The number of items the notification represents, shown e.g. as the
+    /// badge of the launcher icon.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification_count: Option<u32>,
+}
+
+impl AndroidNotification {
+    /// Returns `true` if no option is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.title_loc_key.is_none()
+            && self.title_loc_args.is_empty()
+            && self.body_loc_key.is_none()
+            && self.body_loc_args.is_empty()
+            && self.channel_id.is_none()
+            && self.notification_count.is_none()
+    }
+}
+
+/// Serializes a duration in the `"3.5s"` format of FCM.
+// The signature is given by `serialize_with`.
+#[allow(clippy::ref_option)]
+fn serialize_ttl<S: Serializer>(ttl: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    let ttl = ttl.unwrap_or_default();
+    if ttl.subsec_nanos() == 0 {
+        serializer.serialize_str(&format!("{}s", ttl.as_secs()))
+    } else {
+        let nanos = format!("{:09}", ttl.subsec_nanos());
+        serializer.serialize_str(&format!(
+            "{}.{}s",
+            ttl.as_secs(),
+            nanos.trim_end_matches('0')
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_config_serializes_to_empty_object() {
+        let config = AndroidConfig {
+            notification: Some(AndroidNotification::default()),
+            ..AndroidConfig::default()
+        };
+
+        assert!(config.is_empty());
+        assert_eq!(
+            serde_json::to_value(AndroidConfig::default()).unwrap(),
+            json!({})
+        );
+    }
+
+    #[test]
+    fn test_config_serializes_all_sections() {
+        let config = AndroidConfig {
+            notification: Some(AndroidNotification {
+                title_loc_key: Some("title_key".to_string()),
+                title_loc_args: Vec::new(),
+                body_loc_key: Some("body_key".to_string()),
+                body_loc_args: vec!["Alice".to_string(), "3".to_string()],
+                channel_id: Some("messages".to_string()),
+                notification_count: Some(3),
+            }),
+            fcm_options: Some(AndroidFcmOptions {
+                analytics_label: Some("sale".to_string()),
+            }),
+            ttl: Some(Duration::from_millis(3_500)),
+            priority: Some(DeliveryPriority::High),
+            collapse_key: Some("score".to_string()),
+        };
+
+        assert!(!config.is_empty());
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({
+                "notification": {
+                    "title_loc_key": "title_key",
+                    "body_loc_key": "body_key",
+                    "body_loc_args": ["Alice", "3"],
+                    "channel_id": "messages",
+                    "notification_count": 3
+                },
+                "fcm_options": { "analytics_label": "sale" },
+                "ttl": "3.5s",
+                "priority": "HIGH",
+                "collapse_key": "score"
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_defaults_keeps_set_fields() {
+        let defaults = AndroidConfig {
+            notification: Some(AndroidNotification {
+                channel_id: Some("default".to_string()),
+                ..AndroidNotification::default()
+            }),
+            fcm_options: Some(AndroidFcmOptions {
+                analytics_label: Some("default".to_string()),
+            }),
+            ..AndroidConfig::default()
+        };
+        let mut config = AndroidConfig {
+            notification: Some(AndroidNotification {
+                title_loc_key: Some("title_key".to_string()),
+                ..AndroidNotification::default()
+            }),
+            ..AndroidConfig::default()
+        };
+
+        config.merge_defaults(&defaults);
+
+        assert_eq!(
+            config.notification.unwrap().channel_id,
+            None,
+            "the notification is merged as a whole"
+        );
+        assert_eq!(config.fcm_options, defaults.fcm_options);
+    }
+}
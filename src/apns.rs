@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde::Serializer;
+
+/// Apple Push Notification service (APNs) specific options of an FCM message.
+///
+/// Serialized into the `apns` section of the message. See the
+/// [APNs documentation](https://developer.apple.com/documentation/usernotifications/generating-a-remote-notification)
+/// for the meaning of the headers and payload keys.
+///
+/// # Example
+///
+/// A silent background push, which doesn't need a notification or data
+/// payload:
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use oauth_fcm::ApnsConfig;
+/// use oauth_fcm::ApnsPayload;
+/// use oauth_fcm::Aps;
+///
+/// let apns = ApnsConfig {
+///     headers: HashMap::from([
+///         ("apns-priority".to_string(), "5".to_string()),
+///         ("apns-push-type".to_string(), "background".to_string()),
+///     ]),
+///     payload: Some(ApnsPayload {
+///         aps: Aps {
+///             content_available: true,
+///             ..Aps::default()
+///         },
+///         ..ApnsPayload::default()
+///     }),
+///     ..ApnsConfig::default()
+/// };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ApnsConfig {
+    /// HTTP headers of the APNs request, e.g. `apns-priority`,
+    /// `apns-push-type` or `apns-expiration`.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// The APNs payload, containing the `aps` dictionary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<ApnsPayload>,
+    /// Options for features provided by the FCM SDK for iOS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fcm_options: Option<ApnsFcmOptions>,
+}
+
+impl ApnsConfig {
+    /// Returns `true` if no option is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+            && self.payload.as_ref().is_none_or(ApnsPayload::is_empty)
+            && self.fcm_options.is_none()
+    }
+
+    /// Fills the fields, which aren't set, from `defaults`.
+    ///
+    /// Headers are merged by name, keeping the headers of `self`. The payload
+    /// and the FCM options are kept as a whole, if set.
+    pub(crate) fn merge_defaults(&mut self, defaults: &Self) {
+        for (name, value) in &defaults.headers {
+            self.headers
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        if self.payload.is_none() {
+            self.payload.clone_from(&defaults.payload);
+        }
+        if self.fcm_options.is_none() {
+            self.fcm_options.clone_from(&defaults.fcm_options);
+        }
+    }
+}
+
+/// Options for features provided by the FCM SDK for iOS.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ApnsFcmOptions {
+    /// The label of the message in the delivery metrics of the Firebase
+    /// console, overriding the label of the message for APNs. See
+    /// [`MessageBuilder::analytics_label`](crate::MessageBuilder::analytics_label)
+    /// for the allowed labels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analytics_label: Option<String>,
+    /// The URL of an image shown in the notification.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+/// The APNs payload of an FCM message.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ApnsPayload {
+    /// The `aps` dictionary.
+    pub aps: Aps,
+    /// Custom keys, which are sent next to the `aps` dictionary.
+    #[serde(flatten)]
+    pub custom: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ApnsPayload {
+    /// Returns `true` if neither the `aps` dictionary nor a custom key is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.aps.is_empty() && self.custom.is_empty()
+    }
+}
+
+/// The `aps` dictionary of an APNs payload.
+///
+/// Unset fields are left out of the payload.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct Aps {
+    /// The alert to display.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<ApsAlert>,
+    /// The number to display in the badge of the app icon. `0` removes the
+    /// badge.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badge: Option<u32>,
+    /// The name of a sound file in the app bundle, or `"default"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound: Option<String>,
+    /// Wakes the app in the background to fetch new content. Serialized as
+    /// `content-available: 1`.
+    #[serde(
+        default,
+        skip_serializing_if = "std::ops::Not::not",
+        serialize_with = "serialize_flag"
+    )]
+    #[cfg_attr(feature = "schemars", schemars(with = "u8"))]
+    pub content_available: bool,
+    /// Lets a notification service extension modify the notification.
+    /// Serialized as `mutable-content: 1`.
+    #[serde(
+        default,
+        skip_serializing_if = "std::ops::Not::not",
+        serialize_with = "serialize_flag"
+    )]
+    #[cfg_attr(feature = "schemars", schemars(with = "u8"))]
+    pub mutable_content: bool,
+}
+
+impl Aps {
+    /// Returns `true` if no key is set.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.alert.is_none()
+            && self.badge.is_none()
+            && self.sound.is_none()
+            && !self.content_available
+            && !self.mutable_content
+    }
+}
+
+/// The alert of an APNs notification.
+///
+/// The `loc` fields resolve the strings from the `Localizable.strings` file of
+/// the app instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct ApsAlert {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// The key of the title string, serialized as `title-loc-key`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_loc_key: Option<String>,
+    /// The format arguments of the title string, serialized as
+    /// `title-loc-args`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub title_loc_args: Vec<String>,
+    /// The key of the body string, serialized as `loc-key`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loc_key: Option<String>,
+    /// The format arguments of the body string, serialized as `loc-args`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub loc_args: Vec<String>,
+}
+
+// The signature is given by `serialize_with`.
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn serialize_flag<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u8(u8::from(*value))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_config_serializes_to_empty_object() {
+        let config = ApnsConfig {
+            payload: Some(ApnsPayload::default()),
+            ..ApnsConfig::default()
+        };
+
+        assert!(config.is_empty());
+        assert_eq!(
+            serde_json::to_value(ApnsConfig::default()).unwrap(),
+            json!({})
+        );
+    }
+
+    #[test]
+    fn test_aps_serializes_with_apns_keys() {
+        let config = ApnsConfig {
+            headers: HashMap::from([("apns-priority".to_string(), "10".to_string())]),
+            payload: Some(ApnsPayload {
+                aps: Aps {
+                    alert: Some(ApsAlert {
+                        title: Some("Title".to_string()),
+                        body: Some("Body".to_string()),
+                        ..ApsAlert::default()
+                    }),
+                    badge: Some(3),
+                    sound: Some("default".to_string()),
+                    content_available: true,
+                    mutable_content: true,
+                },
+                custom: serde_json::Map::from_iter([("custom".to_string(), json!("value"))]),
+            }),
+            fcm_options: Some(ApnsFcmOptions {
+                analytics_label: Some("sale".to_string()),
+                image: None,
+            }),
+        };
+
+        assert!(!config.is_empty());
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({
+                "headers": { "apns-priority": "10" },
+                "payload": {
+                    "aps": {
+                        "alert": { "title": "Title", "body": "Body" },
+                        "badge": 3,
+                        "sound": "default",
+                        "content-available": 1,
+                        "mutable-content": 1
+                    },
+                    "custom": "value"
+                },
+                "fcm_options": { "analytics_label": "sale" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_unset_flags_are_omitted() {
+        let payload = ApnsPayload::default();
+
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            json!({ "aps": {} })
+        );
+    }
+
+    #[test]
+    fn test_merge_defaults_keeps_set_headers() {
+        let defaults = ApnsConfig {
+            headers: HashMap::from([
+                ("apns-priority".to_string(), "10".to_string()),
+                ("apns-topic".to_string(), "com.example.app".to_string()),
+            ]),
+            ..ApnsConfig::default()
+        };
+        let mut config = ApnsConfig {
+            headers: HashMap::from([("apns-priority".to_string(), "5".to_string())]),
+            ..ApnsConfig::default()
+        };
+
+        config.merge_defaults(&defaults);
+
+        assert_eq!(
+            config.headers,
+            HashMap::from([
+                ("apns-priority".to_string(), "5".to_string()),
+                ("apns-topic".to_string(), "com.example.app".to_string()),
+            ])
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::SystemTime;
+
+use crate::FcmError;
+use crate::FcmResponse;
+
+/// A record of one send of an [`FcmClient`](crate::FcmClient), see
+/// [`AuditSink`].
+///
+/// The entry contains no payload contents. Device tokens are redacted, unless
+/// [`FcmClientBuilder::log_full_tokens`](crate::FcmClientBuilder::log_full_tokens)
+/// is enabled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// The time the send started.
+    pub timestamp: SystemTime,
+    /// The ID of the Firebase project.
+    pub project_id: String,
+    /// The recipient, e.g. `"topic news"` or `"device abcd…wxyz"`.
+    pub target: String,
+    /// The size of the FCM request body in bytes.
+    pub message_size: usize,
+    /// `true` if the message was only validated by FCM.
+    pub validate_only: bool,
+    /// The final outcome of the send, after all retries.
+    pub outcome: AuditOutcome,
+}
+
+/// The final outcome of an audited send.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    /// FCM accepted the message.
+    Delivered {
+        /// The message ID assigned by FCM.
+        message_id: Option<String>,
+    },
+    /// The message could not be sent.
+    Failed {
+        /// The FCM error code, e.g. `UNREGISTERED`, or the
+        /// [`kind`](FcmError::kind) of the error if FCM didn't send one.
+        error_code: String,
+    },
+}
+
+impl AuditOutcome {
+    pub(crate) fn new(result: &Result<FcmResponse, FcmError>) -> Self {
+        match result {
+            Ok(response) => Self::Delivered {
+                message_id: response.message_id.clone(),
+            },
+            Err(error) => Self::Failed {
+                error_code: error.code_or_kind(),
+            },
+        }
+    }
+}
+
+/// Receives a record of every send, e.g. for a compliance log.
+///
+/// Register a sink with
+/// [`FcmClientBuilder::audit_sink`](crate::FcmClientBuilder::audit_sink). It
+/// is called exactly once per call of
+/// [`FcmClient::send`](crate::FcmClient::send), after the final outcome is
+/// known, also for messages which were rejected before any request. It is
+/// called on the sending task, so it should return quickly, e.g. by passing the
+/// entry to a channel.
+///
+/// A sink can't fail a send: a panic inside [`record`](Self::record) is
+/// caught and logged.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::AuditEntry;
+/// use oauth_fcm::AuditSink;
+///
+/// struct PrintSink;
+///
+/// impl AuditSink for PrintSink {
+///     fn record(&self, entry: AuditEntry) {
+///         println!("{} {:?}", entry.target, entry.outcome);
+///     }
+/// }
+/// ```
+pub trait AuditSink: Send + Sync {
+    /// Records a send.
+    fn record(&self, entry: AuditEntry);
+}
+
+/// An [`AuditSink`] keeping all entries in memory, e.g. for tests.
+#[derive(Debug, Default)]
+pub struct InMemoryAuditSink {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl InMemoryAuditSink {
+    /// Creates an empty sink.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the recorded entries in the order of the sends.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl AuditSink for InMemoryAuditSink {
+    fn record(&self, entry: AuditEntry) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(entry);
+    }
+}
+
+/// A registered [`AuditSink`], which can be printed by `Debug`.
+#[derive(Clone)]
+pub struct SharedAuditSink(pub Arc<dyn AuditSink>);
+
+impl SharedAuditSink {
+    /// Passes the entry to the sink and logs a panic of the sink instead of
+    /// propagating it.
+    pub fn record(&self, entry: AuditEntry) {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| self.0.record(entry)));
+        if let Err(panic) = result {
+            let reason = panic
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic");
+            error!("The audit sink panicked while recording a send: {}", reason);
+        }
+    }
+}
+
+impl std::fmt::Debug for SharedAuditSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AuditSink")
+    }
+}
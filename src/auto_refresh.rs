@@ -0,0 +1,68 @@
+use tokio::task::JoinHandle;
+
+use crate::RetryConfig;
+use crate::SharedTokenManager;
+
+/// A handle to the background token refresh started with
+/// [`TokenManager::spawn_auto_refresh`](crate::TokenManager::spawn_auto_refresh).
+///
+/// The refresh stops when the handle is dropped or [`shutdown`](Self::shutdown)
+/// is called.
+#[must_use = "the background refresh stops when the handle is dropped"]
+#[derive(Debug)]
+pub struct RefreshHandle {
+    task: JoinHandle<()>,
+}
+
+impl RefreshHandle {
+    pub(crate) fn spawn(token_manager: SharedTokenManager) -> Self {
+        Self {
+            task: tokio::spawn(run(token_manager)),
+        }
+    }
+
+    /// Stops the background refresh and waits until it has stopped.
+    ///
+    /// A refresh in progress is cancelled, which keeps the previous token.
+    pub async fn shutdown(mut self) {
+        self.task.abort();
+        // The task never finishes by itself, so it can only be cancelled.
+        let _ = (&mut self.task).await;
+    }
+}
+
+impl Drop for RefreshHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Refreshes the token shortly before it expires, until the task is aborted.
+///
+/// Failed refreshes are retried with the backoff of the default
+/// [`RetryConfig`]. In the meantime `get_token` keeps refreshing the token on
+/// demand, as without the background refresh.
+async fn run(token_manager: SharedTokenManager) {
+    let token_manager = token_manager.lock().await.share();
+    let backoff = RetryConfig::default();
+    let mut failed_attempts = 0;
+
+    loop {
+        tokio::time::sleep(token_manager.refresh_delay()).await;
+
+        match token_manager.refresh_token().await {
+            Ok(_) => failed_attempts = 0,
+            Err(error) => {
+                failed_attempts += 1;
+                let delay = backoff.backoff(failed_attempts);
+                warn!(
+                    attempt = failed_attempts,
+                    delay_ms = delay.as_millis(),
+                    error = %error,
+                    "Background token refresh failed, retrying"
+                );
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
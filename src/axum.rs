@@ -0,0 +1,86 @@
+//! Integration with the [axum](https://docs.rs/axum) web framework.
+//!
+//! Requires the `axum` feature.
+
+use std::convert::Infallible;
+
+use ::axum::extract::FromRef;
+use ::axum::extract::FromRequestParts;
+use ::axum::http::header;
+use ::axum::http::request::Parts;
+use ::axum::http::StatusCode;
+use ::axum::response::IntoResponse;
+use ::axum::response::Response;
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::FcmError;
+use crate::SharedTokenManager;
+
+/// Converts an `FcmError` into a response, so handlers can return it directly.
+///
+/// The status code is taken from [`FcmError::suggested_status_code`], e.g.
+/// `400` for invalid payloads, `410` for unregistered device tokens and `502`
+/// for OAuth failures. The body is a JSON object containing the error
+/// message and the [kind](FcmError::kind) of the error:
+///
+/// ```json
+/// { "error": "FCM returned status 404 (UNREGISTERED): ...", "kind": "FcmResponseError" }
+/// ```
+impl IntoResponse for FcmError {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.suggested_status_code())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let body = json!({ "error": self.to_string(), "kind": self.kind() });
+
+        (
+            status,
+            [(header::CONTENT_TYPE, "application/json")],
+            body.to_string(),
+        )
+            .into_response()
+    }
+}
+
+/// An extractor, which takes the `SharedTokenManager` from the app state.
+///
+/// The state must provide the token manager through [`FromRef`], which is the
+/// case if the state is the `SharedTokenManager` itself or a struct deriving
+/// `FromRef`.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::fs::File;
+///
+/// use axum::routing::post;
+/// use axum::Router;
+/// use oauth_fcm::axum::TokenManagerExtractor;
+/// use oauth_fcm::create_shared_token_manager;
+/// use oauth_fcm::FcmError;
+///
+/// async fn send(TokenManagerExtractor(token_manager): TokenManagerExtractor) -> Result<&'static str, FcmError> {
+///     oauth_fcm::send_fcm_message("DEVICE_TOKEN", None, Some(serde_json::json!({})), &token_manager, "PROJECT_ID").await?;
+///     Ok("FCM message sent successfully")
+/// }
+///
+/// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+/// let app: Router = Router::new()
+///     .route("/send", post(send))
+///     .with_state(token_manager);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TokenManagerExtractor(pub SharedTokenManager);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for TokenManagerExtractor
+where
+    SharedTokenManager: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(SharedTokenManager::from_ref(state)))
+    }
+}
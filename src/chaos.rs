@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::HttpRequest;
+use crate::HttpResponse;
+use crate::HttpTransport;
+use crate::NetworkError;
+
+type TokenPredicate = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// An [`HttpTransport`] injecting FCM failures into the requests of another
+/// transport, e.g. to test retries deterministically.
+///
+/// The rules only apply to FCM send requests. All other requests, e.g. for
+/// access tokens, are passed to the inner transport unchanged and don't count
+/// as requests. The rules are applied in this order:
+///
+/// 1. [`delay`](Self::delay) delays every request.
+/// 2. [`fail_nth`](Self::fail_nth) answers a request with an error status.
+/// 3. [`unregistered_if`](Self::unregistered_if) answers a request to a
+///    matching device token with `UNREGISTERED`.
+///
+/// Requests without a matching rule are passed to the inner transport.
+///
+/// Requires the `test-util` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use oauth_fcm::ChaosTransport;
+/// use oauth_fcm::ReqwestTransport;
+///
+/// // The first send request fails with a 503, all others reach FCM late.
+/// let transport = ChaosTransport::new(ReqwestTransport::default())
+///     .fail_nth(1, 503)
+///     .delay(Duration::from_millis(200))
+///     .unregistered_if(|token| token.starts_with("stale"));
+/// ```
+pub struct ChaosTransport<T> {
+    inner: T,
+    failures: BTreeMap<usize, u16>,
+    delay: Option<Duration>,
+    unregistered: Option<TokenPredicate>,
+    requests: AtomicUsize,
+}
+
+impl<T: HttpTransport> ChaosTransport<T> {
+    /// Wraps the transport without any rules.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            failures: BTreeMap::new(),
+            delay: None,
+            unregistered: None,
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Answers the `n`th send request, counted from 1, with the status and
+    /// the matching canonical error, e.g. `UNAVAILABLE` for a 503.
+    ///
+    /// Can be called multiple times to fail several requests.
+    #[must_use]
+    pub fn fail_nth(mut self, n: usize, status: u16) -> Self {
+        self.failures.insert(n, status);
+        self
+    }
+
+    /// Delays every send request.
+    #[must_use]
+    pub const fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Answers send requests to device tokens matching the predicate with
+    /// `UNREGISTERED`.
+    #[must_use]
+    pub fn unregistered_if(
+        mut self,
+        predicate: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.unregistered = Some(Box::new(predicate));
+        self
+    }
+
+    /// Returns the number of send requests so far, including failed ones.
+    pub fn request_count(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    /// Returns the injected response for the `n`th send request, if any.
+    fn injected_response(&self, n: usize, request: &HttpRequest) -> Option<HttpResponse> {
+        if let Some(&status) = self.failures.get(&n) {
+            return Some(error_response(status, canonical_status(status), None));
+        }
+
+        let predicate = self.unregistered.as_ref()?;
+        let body: serde_json::Value = serde_json::from_slice(&request.body).ok()?;
+        let token = body.pointer("/message/token")?.as_str()?;
+        predicate(token).then(|| error_response(404, "NOT_FOUND", Some("UNREGISTERED")))
+    }
+}
+
+#[async_trait]
+impl<T: HttpTransport> HttpTransport for ChaosTransport<T> {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        if !request.url.ends_with("/messages:send") {
+            return self.inner.send(request).await;
+        }
+
+        let n = self.requests.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        match self.injected_response(n, &request) {
+            Some(response) => Ok(response),
+            None => self.inner.send(request).await,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for ChaosTransport<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChaosTransport")
+            .field("failures", &self.failures)
+            .field("delay", &self.delay)
+            .field("unregistered", &self.unregistered.is_some())
+            .field("requests", &self.requests)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the canonical error status FCM sends with the HTTP status.
+const fn canonical_status(status: u16) -> &'static str {
+    match status {
+        400 => "INVALID_ARGUMENT",
+        401 => "UNAUTHENTICATED",
+        403 => "PERMISSION_DENIED",
+        404 => "NOT_FOUND",
+        429 => "RESOURCE_EXHAUSTED",
+        500 => "INTERNAL",
+        503 => "UNAVAILABLE",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Builds an error response in the format of the FCM v1 API.
+fn error_response(status: u16, canonical: &str, error_code: Option<&str>) -> HttpResponse {
+    let mut error = json!({
+        "code": status,
+        "message": "Injected by ChaosTransport",
+        "status": canonical,
+    });
+    if let Some(error_code) = error_code {
+        error["details"] = json!([{
+            "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+            "errorCode": error_code,
+        }]);
+    }
+    HttpResponse::new(status, json!({ "error": error }).to_string())
+}
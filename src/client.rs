@@ -0,0 +1,874 @@
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use futures::stream;
+use futures::StreamExt;
+use reqwest::header::HeaderValue;
+use tracing::instrument;
+
+use crate::audit::SharedAuditSink;
+use crate::endpoint::endpoint_url;
+use crate::endpoint::env_override;
+use crate::endpoint::FCM_ENDPOINT;
+use crate::fcm::send_request;
+use crate::fcm::RequestOptions;
+use crate::in_flight::InFlightLimit;
+use crate::interceptor::SharedInterceptor;
+use crate::message::MessageDefaults;
+use crate::observer::SharedObserver;
+use crate::validator::SharedValidator;
+use crate::AndroidConfig;
+use crate::ApnsConfig;
+use crate::AuditEntry;
+use crate::AuditOutcome;
+use crate::AuditSink;
+use crate::FcmClientConfig;
+use crate::FcmEndpoint;
+use crate::FcmError;
+use crate::FcmInterceptor;
+use crate::FcmObserver;
+use crate::FcmResponse;
+use crate::IntoCredentials;
+use crate::Message;
+use crate::PayloadValidator;
+use crate::RateLimiter;
+use crate::RetryConfig;
+use crate::SecretString;
+use crate::SendFailure;
+use crate::SendOutcome;
+use crate::SharedTokenManager;
+use crate::TokenManager;
+use crate::TokenStatus;
+use crate::WebpushConfig;
+
+/// The environment variable with the ID of the Firebase project, see
+/// [`FcmClientBuilder::from_env`].
+const PROJECT_ID_ENV: &str = "FCM_PROJECT_ID";
+/// The environment variable with the path of the service account key.
+const CREDENTIALS_PATH_ENV: &str = "GOOGLE_APPLICATION_CREDENTIALS";
+/// The environment variable with the base64 encoded service account key.
+const CREDENTIALS_BASE64_ENV: &str = "FCM_CREDENTIALS_B64";
+/// The environment variable with the request timeout in seconds.
+const REQUEST_TIMEOUT_ENV: &str = "FCM_REQUEST_TIMEOUT_SECS";
+/// The environment variable with the maximum number of concurrent requests.
+const SEND_CONCURRENCY_ENV: &str = "FCM_SEND_CONCURRENCY";
+
+/// A client for sending FCM messages to one Firebase project.
+///
+/// The client bundles everything needed to send a message: the token manager,
+/// the project ID and the endpoint. This is the recommended way to send
+/// messages. Cloning the client is cheap and all clones share the token
+/// manager.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use oauth_fcm::FcmClient;
+/// use oauth_fcm::FcmNotification;
+/// use oauth_fcm::Message;
+///
+/// # tokio_test::block_on(async {
+/// let client = FcmClient::builder()
+///     .credentials(std::path::Path::new("path_to_google_credentials.json"))
+///     .project_id("project_id")
+///     .build()
+///     .await
+///     .expect("Failed to create FcmClient");
+///
+/// let message = Message::builder()
+///     .token("device_token")
+///     .notification(FcmNotification::new("Test Title", "Test Body"))
+///     .build()
+///     .expect("Invalid message");
+/// client
+///     .send(&message)
+///     .await
+///     .expect("Error while sending FCM message");
+/// # });
+/// ```
+#[derive(Clone, Debug)]
+pub struct FcmClient {
+    token_manager: SharedTokenManager,
+    project_id: String,
+    endpoint: String,
+    fcm_url: String,
+    timeout: Option<Duration>,
+    log_full_tokens: bool,
+    observer: Option<SharedObserver>,
+    rate_limiter: Option<RateLimiter>,
+    in_flight: Option<InFlightLimit>,
+    interceptors: Vec<SharedInterceptor>,
+    quota_project_id: Option<HeaderValue>,
+    retry: Option<RetryConfig>,
+    payload_validator: Option<SharedValidator>,
+    defaults: MessageDefaults,
+    audit_sink: Option<SharedAuditSink>,
+}
+
+impl FcmClient {
+    /// Returns a new [`FcmClientBuilder`].
+    #[must_use]
+    pub fn builder() -> FcmClientBuilder {
+        FcmClientBuilder::default()
+    }
+
+    /// Creates a client from the environment, see
+    /// [`FcmClientBuilder::from_env`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidEnvVar` naming the variable, if a variable is missing,
+    /// invalid or conflicts with another one, and the errors of
+    /// [`FcmClientBuilder::build`].
+    pub async fn from_env() -> Result<Self, FcmError> {
+        FcmClientBuilder::from_env()?
+            .build()
+            .await
+            .map_err(|error| match error {
+                FcmError::MissingProjectId => FcmError::InvalidEnvVar {
+                    var: PROJECT_ID_ENV,
+                    reason: "is not set and the credentials don't contain a project ID".to_string(),
+                },
+                error => error,
+            })
+    }
+
+    /// Creates a client from an [`FcmClientConfig`], see
+    /// [`FcmClientConfig::into_builder`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`FcmClientConfig::into_builder`] and
+    /// [`FcmClientBuilder::build`].
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// use oauth_fcm::FcmClient;
+    /// use oauth_fcm::FcmClientConfig;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let config: FcmClientConfig = serde_json::from_str(
+    ///     r#"{ "credentials_path": "path_to_google_credentials.json", "timeout_secs": 10 }"#,
+    /// )
+    /// .expect("Invalid config");
+    /// let client = FcmClient::from_config(config)
+    ///     .await
+    ///     .expect("Failed to create FcmClient");
+    /// # });
+    /// ```
+    pub async fn from_config(config: FcmClientConfig) -> Result<Self, FcmError> {
+        config.into_builder()?.build().await
+    }
+
+    /// Sends a [`Message`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the FCM message could not be sent,
+    /// the data payload or analytics label errors of
+    /// [`MessageBuilder::build`](crate::MessageBuilder::build) if the merged
+    /// client defaults are invalid, or `PayloadRejected` if the
+    /// [`payload_validator`](FcmClientBuilder::payload_validator) rejected it.
+    /// On success, it returns the [`FcmResponse`] containing the message ID.
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(project_id = %self.project_id, sandbox = self.defaults.validate_only)
+    )]
+    pub async fn send(&self, message: &Message) -> Result<FcmResponse, FcmError> {
+        self.send_to_url(&self.project_id, &self.fcm_url, message)
+            .await
+    }
+
+    /// Sends a [`Message`] to another Firebase project than the one of the
+    /// client.
+    ///
+    /// This function behaves exactly as [`send`](Self::send), but replaces
+    /// the project ID in the URL for this message. The token manager and all
+    /// other settings of the client are reused, so the service account must
+    /// have access to the project.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidProjectId` if the project ID is empty or contains a
+    /// `/`, and the errors of [`send`](Self::send).
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// # use oauth_fcm::FcmClient;
+    /// # use oauth_fcm::Message;
+    /// # tokio_test::block_on(async {
+    /// # let client: FcmClient = unimplemented!();
+    /// # let message: Message = unimplemented!();
+    /// for tenant_project_id in ["tenant-a", "tenant-b"] {
+    ///     client
+    ///         .send_to_project(tenant_project_id, &message)
+    ///         .await
+    ///         .expect("Error while sending FCM message");
+    /// }
+    /// # });
+    /// ```
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(project_id = %project_id, sandbox = self.defaults.validate_only)
+    )]
+    pub async fn send_to_project(
+        &self,
+        project_id: &str,
+        message: &Message,
+    ) -> Result<FcmResponse, FcmError> {
+        if project_id.is_empty() || project_id.contains('/') {
+            return Err(FcmError::InvalidProjectId {
+                project_id: project_id.to_string(),
+            });
+        }
+        self.send_to_url(
+            project_id,
+            &endpoint_url(&self.endpoint, project_id),
+            message,
+        )
+        .await
+    }
+
+    /// Sends the message to the FCM URL and reports the result to the audit
+    /// sink and the observer.
+    async fn send_to_url(
+        &self,
+        project_id: &str,
+        fcm_url: &str,
+        message: &Message,
+    ) -> Result<FcmResponse, FcmError> {
+        info!(
+            "Sending FCM message to {}",
+            message.target().for_log(self.log_full_tokens)
+        );
+
+        let started = Instant::now();
+        let timestamp = SystemTime::now();
+        let options = RequestOptions {
+            timeout: self.timeout,
+            rate_limiter: self.rate_limiter.as_ref(),
+            in_flight: self.in_flight.as_ref(),
+            interceptors: &self.interceptors,
+            quota_project_id: self.quota_project_id.as_ref(),
+            retry: self.retry.as_ref(),
+        };
+        let (sent, result) = match self.prepare(message) {
+            Ok(prepared) => {
+                let result = send_request(&prepared, &self.token_manager, fcm_url, options).await;
+                (prepared, result)
+            }
+            Err(error) => (Cow::Borrowed(message), Err(error)),
+        };
+
+        if let Some(audit_sink) = &self.audit_sink {
+            audit_sink.record(AuditEntry {
+                timestamp,
+                project_id: project_id.to_string(),
+                target: sent.target().for_log(self.log_full_tokens).to_string(),
+                message_size: sent.to_request_body().to_string().len(),
+                validate_only: sent.validate_only(),
+                outcome: AuditOutcome::new(&result),
+            });
+        }
+
+        if let Some(SharedObserver(observer)) = &self.observer {
+            let outcome = match &result {
+                Ok(response) => SendOutcome::Delivered(response),
+                Err(error) => SendOutcome::Failed(error),
+            };
+            observer.on_send(outcome, started.elapsed());
+        }
+        result
+    }
+
+    /// Merges the default platform configs into the message and runs the
+    /// payload validator, if one is set.
+    fn prepare<'a>(&self, message: &'a Message) -> Result<Cow<'a, Message>, FcmError> {
+        let message = message.with_defaults(&self.defaults)?;
+        if let Some(SharedValidator(validator)) = &self.payload_validator {
+            validator
+                .validate(&message)
+                .map_err(FcmError::PayloadRejected)?;
+        }
+        Ok(message)
+    }
+
+    /// Sends a [`Message`] and classifies a failure by what to do next.
+    ///
+    /// This function behaves exactly as [`send`](Self::send), but returns a
+    /// [`SendFailure`], which tells whether the device token should be
+    /// deleted, the message should be retried or dropped. See
+    /// [`SendFailure`] for the mapping of the FCM errors.
+    ///
+    /// # Errors
+    ///
+    /// Returns the classified error if the message could not be sent.
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// use oauth_fcm::FcmClient;
+    /// use oauth_fcm::Message;
+    /// use oauth_fcm::SendFailure;
+    ///
+    /// # tokio_test::block_on(async {
+    /// # let client: FcmClient = unimplemented!();
+    /// # let message: Message = unimplemented!();
+    /// match client.send_classified(&message).await {
+    ///     Ok(response) => println!("Delivered as {:?}", response.message_id),
+    ///     Err(SendFailure::TokenInvalid(error)) => println!("Delete the device token: {error}"),
+    ///     Err(SendFailure::Throttled { retry_after }) => println!("Retry after {retry_after:?}"),
+    ///     Err(SendFailure::Transient(error)) => println!("Requeue: {error}"),
+    ///     Err(SendFailure::Permanent(error)) => println!("Drop: {error}"),
+    /// }
+    /// # });
+    /// ```
+    pub async fn send_classified(&self, message: &Message) -> Result<FcmResponse, SendFailure> {
+        self.send(message).await.map_err(SendFailure::from)
+    }
+
+    /// Sends different messages concurrently, e.g. a personalized message per
+    /// user.
+    ///
+    /// Every message is sent as by [`send`](Self::send), so the sends share
+    /// the token manager, rate limiter, in-flight limit and retries of the
+    /// client. At most `concurrency` messages are sent at the same time. The
+    /// results are returned in the order of the messages, and a failed
+    /// message doesn't affect the others.
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// use oauth_fcm::FcmClient;
+    /// use oauth_fcm::FcmNotification;
+    /// use oauth_fcm::Message;
+    ///
+    /// # tokio_test::block_on(async {
+    /// # let client: FcmClient = unimplemented!();
+    /// let digests = [("device_token_1", "3 new posts"), ("device_token_2", "1 new post")];
+    /// let messages = digests
+    ///     .iter()
+    ///     .map(|(token, body)| {
+    ///         Message::builder()
+    ///             .token(*token)
+    ///             .notification(FcmNotification::new("Your digest", *body))
+    ///             .build()
+    ///     })
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .expect("Invalid message");
+    ///
+    /// for ((token, _), result) in digests.iter().zip(client.send_each(messages, 16).await) {
+    ///     if let Err(error) = result {
+    ///         println!("Failed to send to {token}: {error}");
+    ///     }
+    /// }
+    /// # });
+    /// ```
+    #[instrument(level = "info", skip_all, fields(project_id = %self.project_id))]
+    pub async fn send_each(
+        &self,
+        messages: Vec<Message>,
+        concurrency: usize,
+    ) -> Vec<Result<FcmResponse, FcmError>> {
+        info!("Sending {} FCM messages", messages.len());
+
+        stream::iter(&messages)
+            .map(|message| self.send(message))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Checks which device tokens are still registered, without delivering a
+    /// message.
+    ///
+    /// A data message without content is sent to every token with
+    /// `validate_only`, so FCM only validates it. The sends use the retries,
+    /// rate limiter and in-flight limit of the client and at most
+    /// `concurrency` of them run at the same time. The statuses are returned
+    /// in the order of the tokens.
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// use oauth_fcm::FcmClient;
+    /// use oauth_fcm::TokenStatus;
+    ///
+    /// # tokio_test::block_on(async {
+    /// # let client: FcmClient = unimplemented!();
+    /// let tokens = vec!["device_token_1".to_string(), "device_token_2".to_string()];
+    /// for (token, status) in client.check_token_validity(&tokens, 16).await {
+    ///     if matches!(status, TokenStatus::Invalid) {
+    ///         println!("Delete the device token {token}");
+    ///     }
+    /// }
+    /// # });
+    /// ```
+    #[instrument(level = "info", skip_all, fields(project_id = %self.project_id))]
+    pub async fn check_token_validity(
+        &self,
+        tokens: &[String],
+        concurrency: usize,
+    ) -> Vec<(String, TokenStatus)> {
+        info!("Checking the validity of {} device tokens", tokens.len());
+
+        stream::iter(tokens)
+            .map(|token| async move {
+                let result = Message::builder()
+                    .token(token.as_str())
+                    .data(&serde_json::Map::new())
+                    .validate_only(true)
+                    .build();
+                let result = match result {
+                    Ok(message) => self.send(&message).await,
+                    Err(error) => Err(error),
+                };
+                (token.clone(), TokenStatus::new(result))
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Returns the token manager of the client.
+    #[must_use]
+    pub const fn token_manager(&self) -> &SharedTokenManager {
+        &self.token_manager
+    }
+
+    /// Returns the ID of the Firebase project, messages are sent to.
+    #[must_use]
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
+    /// Returns the rate limiter of the client, e.g. to report the available
+    /// permits.
+    #[must_use]
+    pub const fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+}
+
+/// A builder for [`FcmClient`].
+///
+/// Either [`credentials`](Self::credentials) or
+/// [`token_manager`](Self::token_manager) is required. The project ID is taken
+/// from the credentials, unless it is set with
+/// [`project_id`](Self::project_id).
+#[derive(Debug, Default)]
+pub struct FcmClientBuilder {
+    token_manager: Option<Result<SharedTokenManager, FcmError>>,
+    project_id: Option<String>,
+    endpoint: Option<String>,
+    timeout: Option<Duration>,
+    log_full_tokens: bool,
+    observer: Option<SharedObserver>,
+    rate_limiter: Option<RateLimiter>,
+    in_flight: Option<InFlightLimit>,
+    interceptors: Vec<SharedInterceptor>,
+    quota_project_id: Option<String>,
+    retry: Option<RetryConfig>,
+    payload_validator: Option<SharedValidator>,
+    defaults: MessageDefaults,
+    audit_sink: Option<SharedAuditSink>,
+}
+
+impl FcmClientBuilder {
+    /// Creates a builder configured by environment variables:
+    ///
+    /// * `GOOGLE_APPLICATION_CREDENTIALS`: the path of the service account key,
+    ///   or
+    /// * `FCM_CREDENTIALS_B64`: the base64 encoded service account key,
+    /// * `FCM_PROJECT_ID` (optional): the project ID, which defaults to the
+    ///   project ID of the credentials,
+    /// * `FCM_REQUEST_TIMEOUT_SECS` (optional): the [`timeout`](Self::timeout)
+    ///   in seconds,
+    /// * `FCM_SEND_CONCURRENCY` (optional): the
+    ///   [`max_in_flight`](Self::max_in_flight) requests.
+    ///
+    /// Empty variables count as not set. The endpoint can be overridden with
+    /// [`FCM_ENDPOINT_OVERRIDE`](crate::FCM_ENDPOINT_OVERRIDE) as usual. All
+    /// options can still be changed on the returned builder.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidEnvVar` naming the variable, if none or both of the
+    /// credential variables are set, or a variable isn't valid.
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// use std::time::Duration;
+    ///
+    /// use oauth_fcm::FcmClientBuilder;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let client = FcmClientBuilder::from_env()
+    ///     .expect("Invalid FCM environment")
+    ///     .timeout(Duration::from_secs(5))
+    ///     .build()
+    ///     .await
+    ///     .expect("Failed to create FcmClient");
+    /// # });
+    /// ```
+    pub fn from_env() -> Result<Self, FcmError> {
+        let credentials_path = env_var(CREDENTIALS_PATH_ENV)?;
+        let credentials_base64 = env_var(CREDENTIALS_BASE64_ENV)?.map(SecretString::new);
+        let mut builder = match (credentials_path, credentials_base64) {
+            (Some(path), None) => Self::default().credentials(PathBuf::from(path)),
+            (None, Some(encoded)) => Self {
+                token_manager: Some(
+                    TokenManager::from_base64(encoded.expose())
+                        .map(|token_manager| Arc::new(tokio::sync::Mutex::new(token_manager))),
+                ),
+                ..Self::default()
+            },
+            (Some(_), Some(_)) => {
+                return Err(FcmError::InvalidEnvVar {
+                    var: CREDENTIALS_BASE64_ENV,
+                    reason: format!("conflicts with {CREDENTIALS_PATH_ENV}, only one can be set"),
+                })
+            }
+            (None, None) => {
+                return Err(FcmError::InvalidEnvVar {
+                    var: CREDENTIALS_PATH_ENV,
+                    reason: format!("is not set and neither is {CREDENTIALS_BASE64_ENV}"),
+                })
+            }
+        };
+
+        if let Some(project_id) = env_var(PROJECT_ID_ENV)? {
+            builder = builder.project_id(project_id);
+        }
+        if let Some(seconds) = parse_env_var::<u64>(REQUEST_TIMEOUT_ENV)? {
+            builder = builder.timeout(Duration::from_secs(seconds));
+        }
+        if let Some(limit) = parse_env_var::<usize>(SEND_CONCURRENCY_ENV)? {
+            if limit == 0 {
+                return Err(FcmError::InvalidEnvVar {
+                    var: SEND_CONCURRENCY_ENV,
+                    reason: "must be at least 1".to_string(),
+                });
+            }
+            builder = builder.max_in_flight(limit, None);
+        }
+        Ok(builder)
+    }
+
+    /// Creates a new token manager from the Google service account
+    /// credentials.
+    ///
+    /// The credentials are parsed immediately. Parsing errors are returned by
+    /// [`build`](Self::build).
+    #[must_use]
+    pub fn credentials(mut self, credentials: impl IntoCredentials) -> Self {
+        self.token_manager = Some(
+            TokenManager::new(credentials)
+                .map(|token_manager| Arc::new(tokio::sync::Mutex::new(token_manager))),
+        );
+        self
+    }
+
+    /// Uses an existing token manager, e.g. to share it with other clients.
+    #[must_use]
+    pub fn token_manager(mut self, token_manager: SharedTokenManager) -> Self {
+        self.token_manager = Some(Ok(token_manager));
+        self
+    }
+
+    /// Sets the ID of the Firebase project, overriding the project ID of the
+    /// credentials.
+    #[must_use]
+    pub fn project_id(mut self, project_id: impl Into<String>) -> Self {
+        self.project_id = Some(project_id.into());
+        self
+    }
+
+    /// Sets the ID of the project, which is billed for the quota of the FCM
+    /// requests. It is sent as `x-goog-user-project` header.
+    ///
+    /// Defaults to the `quota_project_id` of the credentials, if they contain
+    /// one.
+    #[must_use]
+    pub fn quota_project_id(mut self, quota_project_id: impl Into<String>) -> Self {
+        self.quota_project_id = Some(quota_project_id.into());
+        self
+    }
+
+    /// Sets the base URL of the FCM API, e.g. for a mock server or an
+    /// emulator. Defaults to the base URL of the
+    /// [`FCM_ENDPOINT_OVERRIDE`](crate::FCM_ENDPOINT_OVERRIDE) environment
+    /// variable if it is set, and to `https://fcm.googleapis.com` otherwise.
+    ///
+    /// The path `/v1/projects/{project_id}/messages:send` is appended.
+    #[must_use]
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Sets the base URL of the FCM API and the project ID at once.
+    #[must_use]
+    pub fn fcm_endpoint(self, endpoint: FcmEndpoint) -> Self {
+        let (base_url, project_id) = endpoint.into_parts();
+        self.endpoint(base_url).project_id(project_id)
+    }
+
+    /// Sets the timeout of each FCM request. By default, the request timeout
+    /// of the token manager applies, see
+    /// [`TokenManager::with_timeouts`](crate::TokenManager::with_timeouts).
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Logs device tokens in full instead of only their first and last four
+    /// characters, e.g. for debugging locally.
+    ///
+    /// Device tokens identify a user's device, so this shouldn't be enabled in
+    /// production.
+    #[must_use]
+    pub const fn log_full_tokens(mut self, enabled: bool) -> Self {
+        self.log_full_tokens = enabled;
+        self
+    }
+
+    /// Registers an observer, which is notified about every send, e.g. to
+    /// collect metrics.
+    ///
+    /// Token refreshes are observed by the token manager, see
+    /// [`TokenManager::with_observer`].
+    #[must_use]
+    pub fn observer(mut self, observer: Arc<dyn FcmObserver>) -> Self {
+        self.observer = Some(SharedObserver(observer));
+        self
+    }
+
+    /// Retries sends, which failed with a transient error, see
+    /// [`RetryConfig`]. By default, sends aren't retried.
+    ///
+    /// Every attempt is rate limited and intercepted like the first one.
+    #[must_use]
+    pub const fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Limits the rate of FCM requests, e.g. to stay below the FCM quota of
+    /// the project.
+    ///
+    /// A permit is taken before each FCM request, including retries of
+    /// transient failures and the retry after a rejected access token. The
+    /// limiter is shared by all clones of the
+    /// client, and can be shared with other clients by passing a clone of it.
+    #[must_use]
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Limits the number of concurrent FCM requests of the client and its
+    /// clones to `limit`, at least 1.
+    ///
+    /// Requests beyond the limit wait until another request finished. With an
+    /// `acquire_timeout`, a request fails with `InFlightLimitTimeout` if it
+    /// waited longer. Unlike the [`rate_limiter`](Self::rate_limiter), this
+    /// limits the requests at the same time instead of the requests per
+    /// second.
+    #[must_use]
+    pub fn max_in_flight(mut self, limit: usize, acquire_timeout: Option<Duration>) -> Self {
+        self.in_flight = Some(InFlightLimit::new(limit, acquire_timeout));
+        self
+    }
+
+    /// Sets a sink, which receives a record of every send, e.g. for a
+    /// compliance log. See [`AuditSink`].
+    #[must_use]
+    pub fn audit_sink(mut self, audit_sink: Arc<dyn AuditSink>) -> Self {
+        self.audit_sink = Some(SharedAuditSink(audit_sink));
+        self
+    }
+
+    /// Registers an interceptor, which runs around every FCM request, e.g. to
+    /// add headers. Interceptors run in the order they are registered.
+    ///
+    /// Token requests are intercepted by the token manager, see
+    /// [`TokenManager::with_interceptor`].
+    #[must_use]
+    pub fn interceptor(mut self, interceptor: Arc<dyn FcmInterceptor>) -> Self {
+        self.interceptors.push(SharedInterceptor(interceptor));
+        self
+    }
+
+    /// Sets the Android options, which are merged into every message.
+    ///
+    /// The merge is shallow: each field of [`AndroidConfig`], which the
+    /// message sets, is kept as a whole, and the others are taken from the
+    /// default. E.g. a message with its own `notification` doesn't get the
+    /// default channel ID.
+    #[must_use]
+    pub fn default_android(mut self, android: AndroidConfig) -> Self {
+        self.defaults.android = Some(android);
+        self
+    }
+
+    /// Sets the APNs options, which are merged into every message.
+    ///
+    /// The headers are merged by name, with the headers of the message taking
+    /// precedence. The `payload` and `fcm_options` of the message are kept as
+    /// a whole, if set, and otherwise taken from the default.
+    #[must_use]
+    pub fn default_apns(mut self, apns: ApnsConfig) -> Self {
+        self.defaults.apns = Some(apns);
+        self
+    }
+
+    /// Sets the Webpush options, which are merged into every message.
+    ///
+    /// The headers and data are merged by key, with the values of the message
+    /// taking precedence. The `notification` and `fcm_options` of the message
+    /// are kept as a whole, if set, and otherwise taken from the default.
+    #[must_use]
+    pub fn default_webpush(mut self, webpush: WebpushConfig) -> Self {
+        self.defaults.webpush = Some(webpush);
+        self
+    }
+
+    /// Sets data, which is merged into the data payload of every message.
+    ///
+    /// Keys of the message take precedence over the defaults. The merged data
+    /// payload is validated again, so a default with a reserved key fails
+    /// with `ReservedDataKey` and a message, which becomes too large, with
+    /// `PayloadTooLarge`. Messages without a data payload get the defaults as
+    /// their data payload.
+    #[must_use]
+    pub fn default_data<K, V>(mut self, data: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.defaults.data = data
+            .into_iter()
+            .map(|(key, value)| (key.into(), serde_json::Value::String(value.into())))
+            .collect();
+        self
+    }
+
+    /// Enables the sandbox mode, which sets
+    /// [`validate_only`](crate::MessageBuilder::validate_only) on every
+    /// message, e.g. in a staging environment. FCM validates the messages
+    /// and responds as usual, but never delivers them.
+    ///
+    /// The `sandbox` field of the tracing span of every send is set to `true`.
+    #[must_use]
+    pub const fn sandbox(mut self, enabled: bool) -> Self {
+        self.defaults.validate_only = enabled;
+        self
+    }
+
+    /// Sets a validator, which checks every message before it is sent, e.g.
+    /// to enforce application specific rules.
+    ///
+    /// The validator runs once per [`send`](FcmClient::send), before the
+    /// first request. A rejected message isn't sent and the send fails with
+    /// `PayloadRejected`. See [`PayloadValidator`].
+    #[must_use]
+    pub fn payload_validator(mut self, validator: Arc<dyn PayloadValidator>) -> Self {
+        self.payload_validator = Some(SharedValidator(validator));
+        self
+    }
+
+    /// Builds the client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the credentials could not be parsed, if no
+    /// credentials are set or the quota project ID isn't a valid header value
+    /// (`InvalidClientConfig`), or if no project ID is set and the credentials
+    /// don't contain one (`MissingProjectId`).
+    ///
+    /// Reading the project IDs of the credentials waits until the token
+    /// manager isn't locked by another task.
+    pub async fn build(self) -> Result<FcmClient, FcmError> {
+        let token_manager = self.token_manager.ok_or(FcmError::InvalidClientConfig(
+            "neither credentials nor a token manager are set",
+        ))??;
+        let (credentials_project_id, credentials_quota_project_id) = {
+            let token_manager = token_manager.lock().await;
+            (
+                token_manager.project_id().map(str::to_owned),
+                token_manager.quota_project_id().map(str::to_owned),
+            )
+        };
+        let project_id = self
+            .project_id
+            .or(credentials_project_id)
+            .ok_or(FcmError::MissingProjectId)?;
+        let quota_project_id = self
+            .quota_project_id
+            .or(credentials_quota_project_id)
+            .map(HeaderValue::try_from)
+            .transpose()
+            .map_err(|_| {
+                FcmError::InvalidClientConfig("the quota project ID is not a valid header value")
+            })?;
+        let endpoint = self
+            .endpoint
+            .or_else(env_override)
+            .unwrap_or_else(|| FCM_ENDPOINT.to_string());
+        let fcm_url = endpoint_url(&endpoint, &project_id);
+
+        Ok(FcmClient {
+            token_manager,
+            project_id,
+            endpoint,
+            fcm_url,
+            timeout: self.timeout,
+            log_full_tokens: self.log_full_tokens,
+            observer: self.observer,
+            rate_limiter: self.rate_limiter,
+            in_flight: self.in_flight,
+            interceptors: self.interceptors,
+            quota_project_id,
+            retry: self.retry,
+            payload_validator: self.payload_validator,
+            defaults: self.defaults,
+            audit_sink: self.audit_sink,
+        })
+    }
+}
+
+/// Reads an environment variable, which counts as not set if it is empty.
+fn env_var(var: &'static str) -> Result<Option<String>, FcmError> {
+    match std::env::var(var) {
+        Ok(value) if value.is_empty() => Ok(None),
+        Ok(value) => Ok(Some(value)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(std::env::VarError::NotUnicode(_)) => Err(FcmError::InvalidEnvVar {
+            var,
+            reason: "is not valid unicode".to_string(),
+        }),
+    }
+}
+
+/// Reads an environment variable containing a number.
+fn parse_env_var<T: std::str::FromStr>(var: &'static str) -> Result<Option<T>, FcmError> {
+    env_var(var)?
+        .map(|value| {
+            value.trim().parse().map_err(|_| FcmError::InvalidEnvVar {
+                var,
+                reason: format!("{value:?} is not a non-negative integer"),
+            })
+        })
+        .transpose()
+}
@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::credentials::key_from_json_or_base64;
+use crate::FcmClientBuilder;
+use crate::FcmError;
+use crate::RateLimiter;
+use crate::RetryConfig;
+use crate::SecretString;
+
+/// The configuration of an [`FcmClient`](crate::FcmClient), e.g. deserialized
+/// from a section of a config file.
+///
+/// Exactly one of `credentials_path` and `credentials` must be set. The
+/// credentials file is read by
+/// [`FcmClient::from_config`](crate::FcmClient::from_config), not when the
+/// config is deserialized. Unknown fields are rejected, so typos don't go
+/// unnoticed.
+///
+/// # Example
+///
+/// ```toml
+/// [fcm]
+/// project_id = "my-project"
+/// credentials_path = "/etc/secrets/fcm.json"
+/// timeout_secs = 10
+/// max_in_flight = 50
+///
+/// [fcm.retry]
+/// max_attempts = 5
+/// initial_backoff_ms = 500
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FcmClientConfig {
+    /// The ID of the Firebase project. Defaults to the project ID of the
+    /// credentials.
+    pub project_id: Option<String>,
+    /// The path of the service account key.
+    pub credentials_path: Option<PathBuf>,
+    /// The service account key as JSON or base64 encoded JSON.
+    pub credentials: Option<SecretString>,
+    /// The quota project ID, see
+    /// [`FcmClientBuilder::quota_project_id`].
+    pub quota_project_id: Option<String>,
+    /// The base URL of the FCM API, see [`FcmClientBuilder::endpoint`].
+    pub endpoint: Option<String>,
+    /// The timeout of each FCM request in seconds.
+    pub timeout_secs: Option<u64>,
+    /// Retries transient failures, see [`FcmClientBuilder::retry`].
+    pub retry: Option<RetrySettings>,
+    /// The maximum number of concurrent FCM requests, see
+    /// [`FcmClientBuilder::max_in_flight`].
+    pub max_in_flight: Option<usize>,
+    /// The maximum time in milliseconds to wait for an in-flight request slot.
+    /// Requires `max_in_flight`.
+    pub in_flight_timeout_ms: Option<u64>,
+    /// Limits the rate of FCM requests, see [`RateLimiter`].
+    pub rate_limit: Option<RateLimitSettings>,
+}
+
+/// The retry settings of an [`FcmClientConfig`], see [`RetryConfig`].
+///
+/// Unset fields default to the values of [`RetryConfig::default`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetrySettings {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: Option<u32>,
+    /// The delay before the first retry in milliseconds.
+    pub initial_backoff_ms: Option<u64>,
+    /// The maximum delay between two attempts in milliseconds.
+    pub max_backoff_ms: Option<u64>,
+    /// Randomizes each delay between half and the full backoff.
+    pub jitter: Option<bool>,
+}
+
+impl From<RetrySettings> for RetryConfig {
+    fn from(settings: RetrySettings) -> Self {
+        let defaults = Self::default();
+        Self {
+            max_attempts: settings.max_attempts.unwrap_or(defaults.max_attempts),
+            initial_backoff: settings
+                .initial_backoff_ms
+                .map_or(defaults.initial_backoff, Duration::from_millis),
+            max_backoff: settings
+                .max_backoff_ms
+                .map_or(defaults.max_backoff, Duration::from_millis),
+            jitter: settings.jitter.unwrap_or(defaults.jitter),
+        }
+    }
+}
+
+/// The rate limit of an [`FcmClientConfig`], see [`RateLimiter::new`].
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitSettings {
+    /// The average number of requests per second.
+    pub requests_per_second: u32,
+    /// The maximum number of requests at once. Defaults to
+    /// `requests_per_second`.
+    pub burst: Option<u32>,
+}
+
+impl FcmClientConfig {
+    /// Validates the config and returns a builder configured by it, so
+    /// options can still be changed in code.
+    ///
+    /// The credentials, also a credentials file, are read here and not when
+    /// the config is deserialized. Errors of a credentials file are returned
+    /// by [`FcmClientBuilder::build`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidClientConfig` if none or both of the credential fields
+    /// are set, `max_in_flight` is 0 or `in_flight_timeout_ms` is set without
+    /// `max_in_flight`, and `InvalidCredentials` if the inline credentials
+    /// can't be parsed.
+    pub fn into_builder(self) -> Result<FcmClientBuilder, FcmError> {
+        let mut builder = match (self.credentials_path, self.credentials) {
+            (Some(path), None) => FcmClientBuilder::default().credentials(path),
+            (None, Some(credentials)) => FcmClientBuilder::default()
+                .credentials(key_from_json_or_base64(credentials.expose())?),
+            (Some(_), Some(_)) => {
+                return Err(FcmError::InvalidClientConfig(
+                    "only one of credentials_path and credentials can be set",
+                ))
+            }
+            (None, None) => {
+                return Err(FcmError::InvalidClientConfig(
+                    "neither credentials_path nor credentials are set",
+                ))
+            }
+        };
+
+        if let Some(project_id) = self.project_id {
+            builder = builder.project_id(project_id);
+        }
+        if let Some(quota_project_id) = self.quota_project_id {
+            builder = builder.quota_project_id(quota_project_id);
+        }
+        if let Some(endpoint) = self.endpoint {
+            builder = builder.endpoint(endpoint);
+        }
+        if let Some(seconds) = self.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(seconds));
+        }
+        if let Some(retry) = self.retry {
+            builder = builder.retry(retry.into());
+        }
+        match (self.max_in_flight, self.in_flight_timeout_ms) {
+            (Some(0), _) => {
+                return Err(FcmError::InvalidClientConfig(
+                    "max_in_flight must be at least 1",
+                ))
+            }
+            (Some(limit), timeout) => {
+                builder = builder.max_in_flight(limit, timeout.map(Duration::from_millis));
+            }
+            (None, Some(_)) => {
+                return Err(FcmError::InvalidClientConfig(
+                    "in_flight_timeout_ms requires max_in_flight",
+                ))
+            }
+            (None, None) => {}
+        }
+        if let Some(rate_limit) = self.rate_limit {
+            builder = builder.rate_limiter(RateLimiter::new(
+                rate_limit.requests_per_second,
+                rate_limit.burst.unwrap_or(rate_limit.requests_per_second),
+            ));
+        }
+        Ok(builder)
+    }
+}
@@ -0,0 +1,351 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+use base64::alphabet;
+use base64::engine::DecodePaddingMode;
+use base64::engine::GeneralPurpose;
+use base64::engine::GeneralPurposeConfig;
+use base64::Engine;
+use jsonwebtoken::EncodingKey;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+use crate::secret::SecretString;
+use crate::FcmError;
+
+/// The parsed content of a Google service account JSON key.
+///
+/// This is usually created implicitly from one of the [`IntoCredentials`]
+/// sources, but it can also be deserialized directly, e.g. as part of a larger
+/// configuration file.
+///
+/// The key is validated while it is parsed: the `type` must be
+/// `service_account` if present, the `client_email` must be an email address
+/// and the `private_key` must be a PEM encoded RSA key. So broken credentials
+/// fail at startup instead of on the first send.
+#[derive(Deserialize)]
+#[serde(try_from = "RawServiceAccountKey")]
+pub struct ServiceAccountKey {
+    /// The parsed `private_key`. The PEM itself is not kept.
+    pub(crate) encoding_key: EncodingKey,
+    pub(crate) client_email: String,
+    pub(crate) private_key_id: String,
+    pub(crate) project_id: Option<String>,
+    pub(crate) quota_project_id: Option<String>,
+    pub(crate) token_uri: Option<String>,
+}
+
+/// A JSON key before it is validated.
+///
+/// All fields are optional, so missing fields can be reported as
+/// [`FcmError::InvalidCredentials`].
+#[derive(Deserialize)]
+struct RawServiceAccountKey {
+    #[serde(rename = "type")]
+    key_type: Option<String>,
+    private_key: Option<String>,
+    client_email: Option<String>,
+    private_key_id: Option<String>,
+    project_id: Option<String>,
+    quota_project_id: Option<String>,
+    token_uri: Option<String>,
+    // OAuth client ID files contain one of these instead of a key.
+    web: Option<IgnoredAny>,
+    installed: Option<IgnoredAny>,
+}
+
+impl TryFrom<RawServiceAccountKey> for ServiceAccountKey {
+    type Error = FcmError;
+
+    fn try_from(raw: RawServiceAccountKey) -> Result<Self, Self::Error> {
+        if raw.web.is_some() || raw.installed.is_some() {
+            return Err(FcmError::InvalidCredentials(
+                "this is an OAuth client ID file, not a service account key".to_string(),
+            ));
+        }
+
+        if let Some(key_type) = raw.key_type {
+            if key_type != "service_account" {
+                return Err(FcmError::InvalidCredentials(format!(
+                    "field `type` is {key_type:?}, expected \"service_account\""
+                )));
+            }
+        }
+
+        let private_key = SecretString::new(required_field(raw.private_key, "private_key")?);
+        let client_email = required_field(raw.client_email, "client_email")?;
+        let private_key_id = required_field(raw.private_key_id, "private_key_id")?;
+
+        if !is_email(&client_email) {
+            return Err(FcmError::InvalidCredentials(format!(
+                "field `client_email` is not an email address: {client_email:?}"
+            )));
+        }
+
+        let encoding_key =
+            EncodingKey::from_rsa_pem(private_key.expose().as_bytes()).map_err(|_| {
+                FcmError::InvalidCredentials(
+                    "field `private_key` is not a PEM encoded RSA private key".to_string(),
+                )
+            })?;
+
+        Ok(Self {
+            encoding_key,
+            client_email,
+            private_key_id,
+            project_id: raw.project_id,
+            quota_project_id: raw.quota_project_id,
+            token_uri: raw.token_uri,
+        })
+    }
+}
+
+fn required_field(value: Option<String>, name: &str) -> Result<String, FcmError> {
+    value.ok_or_else(|| FcmError::InvalidCredentials(format!("missing field `{name}`")))
+}
+
+fn is_email(value: &str) -> bool {
+    value.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty()
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && !value.chars().any(char::is_whitespace)
+    })
+}
+
+/// Parses and validates a JSON key.
+fn parse_key(
+    raw: Result<RawServiceAccountKey, serde_json::Error>,
+) -> Result<ServiceAccountKey, FcmError> {
+    raw?.try_into()
+}
+
+/// Parses a JSON key, naming the failed step in the error.
+pub fn key_from_json(json: &[u8]) -> Result<ServiceAccountKey, FcmError> {
+    let raw: RawServiceAccountKey = serde_json::from_slice(json).map_err(|error| {
+        FcmError::InvalidCredentials(format!("credentials are not valid JSON: {error}"))
+    })?;
+    raw.try_into()
+}
+
+/// Parses a base64 encoded JSON key.
+///
+/// Padding is optional and whitespace is ignored, so the line wrapped output of
+/// `base64` can be used as well.
+pub fn key_from_base64(encoded: &str) -> Result<ServiceAccountKey, FcmError> {
+    const ENGINE: GeneralPurpose = GeneralPurpose::new(
+        &alphabet::STANDARD,
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+
+    let encoded = SecretString::new(encoded.split_whitespace().collect());
+    let json = ENGINE.decode(encoded.expose()).map_err(|error| {
+        FcmError::InvalidCredentials(format!("credentials are not valid base64: {error}"))
+    })?;
+    key_from_json(&json)
+}
+
+/// Parses a JSON key from an environment variable, which contains either the
+/// JSON itself or the base64 encoded JSON.
+pub fn key_from_env(var: &str) -> Result<ServiceAccountKey, FcmError> {
+    let value = std::env::var(var)
+        .map(SecretString::new)
+        .map_err(|source| FcmError::CredentialsEnvError {
+            var: var.to_string(),
+            source,
+        })?;
+
+    key_from_json_or_base64(value.expose())
+}
+
+/// Parses a JSON key from a string, which contains either the JSON itself or
+/// the base64 encoded JSON.
+pub fn key_from_json_or_base64(value: &str) -> Result<ServiceAccountKey, FcmError> {
+    // Base64 never contains `{`, so the value can only be JSON in that case.
+    if value.trim_start().starts_with('{') {
+        key_from_json(value.as_bytes())
+    } else {
+        key_from_base64(value)
+    }
+}
+
+impl Debug for ServiceAccountKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Destructured, so a new field can't be forgotten here. The key is
+        // never printed.
+        let Self {
+            encoding_key: _,
+            client_email,
+            private_key_id,
+            project_id,
+            quota_project_id,
+            token_uri,
+        } = self;
+
+        f.debug_struct("ServiceAccountKey")
+            .field("private_key", &("[REDACTED]".to_string()))
+            .field("client_email", client_email)
+            .field("private_key_id", private_key_id)
+            .field("project_id", project_id)
+            .field("quota_project_id", quota_project_id)
+            .field("token_uri", token_uri)
+            .finish()
+    }
+}
+
+/// Wraps any [`Read`] implementation, so it can be used as credentials source.
+///
+/// `File`, `BufReader` and byte slices can be passed directly. Use this wrapper
+/// for every other reader.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::io::Cursor;
+///
+/// use oauth_fcm::CredentialsReader;
+/// use oauth_fcm::TokenManager;
+///
+/// let json = std::fs::read("path_to_google_credentials.json").expect("Failed to read file");
+/// let token_manager =
+///     TokenManager::new(CredentialsReader(Cursor::new(json))).expect("Failed to create TokenManager");
+/// ```
+#[derive(Debug)]
+pub struct CredentialsReader<R>(pub R);
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// A source of Google service account credentials.
+///
+/// This trait is sealed and implemented for:
+///
+/// * `&str` and `String` containing the JSON key
+/// * `&[u8]` and `Vec<u8>` containing the JSON key
+/// * `&Path`, `&PathBuf` and `PathBuf` pointing to the JSON key file
+/// * `File` and `BufReader<R>` reading the JSON key
+/// * [`CredentialsReader`] wrapping any other [`Read`] implementation
+/// * `serde_json::Value` holding the parsed JSON key
+/// * [`ServiceAccountKey`]
+pub trait IntoCredentials: private::Sealed {
+    /// Parses the source into a [`ServiceAccountKey`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the credentials could not be read or parsed.
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError>;
+}
+
+impl private::Sealed for &str {}
+
+impl IntoCredentials for &str {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        parse_key(serde_json::from_str(self))
+    }
+}
+
+impl private::Sealed for String {}
+
+impl IntoCredentials for String {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        self.as_str().into_credentials()
+    }
+}
+
+impl private::Sealed for &[u8] {}
+
+impl IntoCredentials for &[u8] {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        parse_key(serde_json::from_slice(self))
+    }
+}
+
+impl private::Sealed for Vec<u8> {}
+
+impl IntoCredentials for Vec<u8> {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        self.as_slice().into_credentials()
+    }
+}
+
+impl private::Sealed for &Path {}
+
+impl IntoCredentials for &Path {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        let file = File::open(self).map_err(|source| FcmError::CredentialsFileError {
+            path: self.to_path_buf(),
+            source,
+        })?;
+
+        let raw: RawServiceAccountKey =
+            serde_json::from_reader(BufReader::new(file)).map_err(|source| {
+                FcmError::CredentialsFileError {
+                    path: self.to_path_buf(),
+                    source: source.into(),
+                }
+            })?;
+        raw.try_into()
+    }
+}
+
+impl private::Sealed for &PathBuf {}
+
+impl IntoCredentials for &PathBuf {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        self.as_path().into_credentials()
+    }
+}
+
+impl private::Sealed for PathBuf {}
+
+impl IntoCredentials for PathBuf {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        self.as_path().into_credentials()
+    }
+}
+
+impl private::Sealed for File {}
+
+impl IntoCredentials for File {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        CredentialsReader(BufReader::new(self)).into_credentials()
+    }
+}
+
+impl<R: Read> private::Sealed for BufReader<R> {}
+
+impl<R: Read> IntoCredentials for BufReader<R> {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        CredentialsReader(self).into_credentials()
+    }
+}
+
+impl<R: Read> private::Sealed for CredentialsReader<R> {}
+
+impl<R: Read> IntoCredentials for CredentialsReader<R> {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        parse_key(serde_json::from_reader(self.0))
+    }
+}
+
+impl private::Sealed for serde_json::Value {}
+
+impl IntoCredentials for serde_json::Value {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        parse_key(serde_json::from_value(self))
+    }
+}
+
+impl private::Sealed for ServiceAccountKey {}
+
+impl IntoCredentials for ServiceAccountKey {
+    fn into_credentials(self) -> Result<ServiceAccountKey, FcmError> {
+        Ok(self)
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Serialize;
+
+use crate::message::MAX_PAYLOAD_SIZE;
+use crate::FcmError;
+
+/// A type that can be rendered into an FCM data payload.
+///
+/// FCM only accepts string keys and string values in the data payload.
+/// Implement this trait, or derive it with `#[derive(FcmData)]` when the
+/// `derive` feature is enabled, to make that constraint part of the type.
+///
+/// Pass it to
+/// [`MessageBuilder::data_payload`](crate::MessageBuilder::data_payload)
+/// to send it as data payload of a message.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use oauth_fcm::FcmData;
+/// use oauth_fcm::ToDataPayload;
+///
+/// #[derive(FcmData)]
+/// struct ChatMessage {
+///     #[fcm(rename = "chat_id")]
+///     id: u64,
+///     text: String,
+///     reply_to: Option<u64>,
+/// }
+///
+/// let payload = ChatMessage {
+///     id: 42,
+///     text: "Hello".to_string(),
+///     reply_to: None,
+/// }
+/// .to_data_payload();
+///
+/// assert_eq!(payload["chat_id"], "42");
+/// assert_eq!(payload["text"], "Hello");
+/// assert!(!payload.contains_key("reply_to"));
+/// # }
+/// ```
+pub trait ToDataPayload {
+    /// Renders `self` into a map of data payload keys and values.
+    fn to_data_payload(&self) -> HashMap<String, String>;
+}
+
+/// A string value in an FCM data payload.
+///
+/// FCM only accepts strings as data values. `DataValue` serializes as a plain
+/// string and can be used as value type of a data payload map. Use
+/// [`DataValue::binary`] to send binary data.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use oauth_fcm::DataValue;
+///
+/// let mut data = HashMap::new();
+/// data.insert("kind", DataValue::from("envelope"));
+/// data.insert(
+///     "envelope",
+///     DataValue::binary(&[0xfb, 0xff]).expect("Value too large"),
+/// );
+///
+/// assert_eq!(data["envelope"].as_str(), "+/8=");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(transparent)]
+pub struct DataValue(String);
+
+impl DataValue {
+    /// Encodes binary data, e.g. a protobuf message, as data value.
+    ///
+    /// The bytes are encoded with the standard base64 alphabet from RFC 4648
+    /// (`A-Z`, `a-z`, `0-9`, `+`, `/`) and are always padded with `=`. The
+    /// receiver must decode the value with a padded standard base64 decoder,
+    /// e.g. `Base64.decode(value, Base64.DEFAULT)` on Android or
+    /// `Data(base64Encoded:)` on iOS. URL-safe decoders won't accept the value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FcmError::PayloadTooLarge` if the encoded value alone exceeds
+    /// the FCM message size limit. Base64 grows the data by a third, so the
+    /// limit is reached with about 3 KB of binary data. The size of the whole
+    /// notification and data payload is checked again when the message is
+    /// built.
+    pub fn binary(bytes: &[u8]) -> Result<Self, FcmError> {
+        let size = base64::encoded_len(bytes.len(), true).unwrap_or(usize::MAX);
+        if size > MAX_PAYLOAD_SIZE {
+            return Err(FcmError::PayloadTooLarge {
+                size,
+                limit: MAX_PAYLOAD_SIZE,
+            });
+        }
+
+        Ok(Self(STANDARD.encode(bytes)))
+    }
+
+    /// Returns the value as string slice.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for DataValue {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for DataValue {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl From<DataValue> for String {
+    fn from(value: DataValue) -> Self {
+        value.0
+    }
+}
+
+impl Display for DataValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_uses_padded_standard_alphabet() {
+        assert_eq!(DataValue::binary(&[0xfb, 0xff]).unwrap().as_str(), "+/8=");
+        assert_eq!(DataValue::binary(&[0xfb]).unwrap().as_str(), "+w==");
+        assert_eq!(DataValue::binary(&[]).unwrap().as_str(), "");
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let value = DataValue::binary(&bytes).unwrap();
+
+        assert_eq!(STANDARD.decode(value.as_str()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_binary_size_accounts_for_encoding() {
+        // 3072 bytes encode to exactly 4096 characters.
+        assert!(DataValue::binary(&[0; 3072]).is_ok());
+
+        let error = DataValue::binary(&[0; 3073]).unwrap_err();
+        assert!(matches!(
+            error,
+            FcmError::PayloadTooLarge {
+                size: 4100,
+                limit: MAX_PAYLOAD_SIZE
+            }
+        ));
+    }
+
+    #[test]
+    fn test_data_value_serializes_as_string() {
+        let value = DataValue::from("value");
+
+        assert_eq!(serde_json::to_value(&value).unwrap(), "value");
+        assert_eq!(value.to_string(), "value");
+        assert_eq!(String::from(value), "value");
+    }
+}
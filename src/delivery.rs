@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+
+use crate::AndroidConfig;
+use crate::AndroidNotification;
+use crate::ApnsConfig;
+use crate::ApnsPayload;
+use crate::FcmError;
+use crate::WebpushConfig;
+
+/// The longest time FCM and the Webpush services keep an undelivered message.
+pub const MAX_TTL: Duration = Duration::from_secs(28 * 24 * 60 * 60);
+
+/// The maximum length of the `apns-collapse-id` header in bytes.
+const MAX_APNS_COLLAPSE_ID_LENGTH: usize = 64;
+
+/// The maximum length of the Webpush `Topic` header.
+const MAX_WEBPUSH_TOPIC_LENGTH: usize = 32;
+
+/// The delivery priority of a message, see
+/// [`MessageBuilder::priority`](crate::MessageBuilder::priority).
+///
+/// Serialized as the Android priority, `"HIGH"` or `"NORMAL"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeliveryPriority {
+    /// Delivered immediately, waking a sleeping device. Use it for messages
+    /// the user has to see now.
+    High,
+    /// Delivered immediately to an awake device, otherwise delayed to save
+    /// battery.
+    Normal,
+}
+
+impl DeliveryPriority {
+    /// The value of the `apns-priority` header.
+    const fn apns_priority(self) -> &'static str {
+        match self {
+            Self::High => "10",
+            Self::Normal => "5",
+        }
+    }
+
+    /// The value of the Webpush `Urgency` header.
+    const fn urgency(self) -> &'static str {
+        match self {
+            Self::High => "high",
+            Self::Normal => "normal",
+        }
+    }
+}
+
+/// Delivery options, which are set once on a
+/// [`MessageBuilder`](crate::MessageBuilder) and mapped to the Android, APNs
+/// and Webpush representation of each option.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeliveryOptions {
+    pub expires_in: Option<Duration>,
+    pub priority: Option<DeliveryPriority>,
+    pub badge: Option<u32>,
+    pub collapse_group: Option<String>,
+}
+
+impl DeliveryOptions {
+    /// Checks the collapse group against the platforms, whose configs are set
+    /// on the builder.
+    pub fn validate(&self, has_apns: bool, has_webpush: bool) -> Result<(), FcmError> {
+        let Some(group) = &self.collapse_group else {
+            return Ok(());
+        };
+        let reason = if group.is_empty() {
+            Some("it is empty")
+        } else {
+            has_apns
+                .then(|| apns_collapse_id_violation(group))
+                .flatten()
+                .or_else(|| {
+                    has_webpush
+                        .then(|| webpush_topic_violation(group))
+                        .flatten()
+                })
+        };
+
+        reason.map_or(Ok(()), |reason| {
+            Err(FcmError::InvalidCollapseGroup {
+                group: group.clone(),
+                reason,
+            })
+        })
+    }
+
+    /// Sets the options in the platform configs, creating them if needed.
+    /// Values, which are already set in a platform config, are kept.
+    pub fn apply(
+        &self,
+        android: &mut Option<AndroidConfig>,
+        apns: &mut Option<ApnsConfig>,
+        webpush: &mut Option<WebpushConfig>,
+    ) {
+        if let Some(expires_in) = self.expires_in {
+            let ttl = expires_in.min(MAX_TTL);
+            let android = android.get_or_insert_with(AndroidConfig::default);
+            android.ttl.get_or_insert(ttl);
+
+            // APNs expects the point in time, `0` only tries to deliver once.
+            let expiration = if ttl.is_zero() {
+                0
+            } else {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                (now + ttl).as_secs()
+            };
+            insert_header(
+                &mut apns.get_or_insert_with(ApnsConfig::default).headers,
+                "apns-expiration",
+                expiration.to_string(),
+            );
+            insert_header(
+                &mut webpush.get_or_insert_with(WebpushConfig::default).headers,
+                "TTL",
+                ttl.as_secs().to_string(),
+            );
+        }
+
+        // The badge is set first, it makes a background push an alerting one.
+        if let Some(badge) = self.badge {
+            android
+                .get_or_insert_with(AndroidConfig::default)
+                .notification
+                .get_or_insert_with(AndroidNotification::default)
+                .notification_count
+                .get_or_insert(badge);
+            apns.get_or_insert_with(ApnsConfig::default)
+                .payload
+                .get_or_insert_with(ApnsPayload::default)
+                .aps
+                .badge
+                .get_or_insert(badge);
+        }
+
+        if let Some(priority) = self.priority {
+            android
+                .get_or_insert_with(AndroidConfig::default)
+                .priority
+                .get_or_insert(priority);
+
+            let apns = apns.get_or_insert_with(ApnsConfig::default);
+            let apns_priority = if priority == DeliveryPriority::High
+                && apns.payload.as_ref().is_some_and(is_background_only)
+            {
+                warn!("APNs requires priority 5 for background pushes, sending it with priority 5");
+                DeliveryPriority::Normal.apns_priority()
+            } else {
+                priority.apns_priority()
+            };
+            insert_header(
+                &mut apns.headers,
+                "apns-priority",
+                apns_priority.to_string(),
+            );
+            insert_header(
+                &mut webpush.get_or_insert_with(WebpushConfig::default).headers,
+                "Urgency",
+                priority.urgency().to_string(),
+            );
+        }
+
+        if let Some(group) = &self.collapse_group {
+            android
+                .get_or_insert_with(AndroidConfig::default)
+                .collapse_key
+                .get_or_insert_with(|| group.clone());
+            // A violation is left only for platforms without a config, see
+            // `validate`. They don't get the group.
+            if apns_collapse_id_violation(group).is_none() {
+                insert_header(
+                    &mut apns.get_or_insert_with(ApnsConfig::default).headers,
+                    "apns-collapse-id",
+                    group.clone(),
+                );
+            }
+            if webpush_topic_violation(group).is_none() {
+                insert_header(
+                    &mut webpush.get_or_insert_with(WebpushConfig::default).headers,
+                    "Topic",
+                    group.clone(),
+                );
+            }
+        }
+    }
+}
+
+fn apns_collapse_id_violation(group: &str) -> Option<&'static str> {
+    (group.len() > MAX_APNS_COLLAPSE_ID_LENGTH)
+        .then_some("the APNs `apns-collapse-id` header is limited to 64 bytes")
+}
+
+fn webpush_topic_violation(group: &str) -> Option<&'static str> {
+    if group.len() > MAX_WEBPUSH_TOPIC_LENGTH {
+        Some("the Webpush `Topic` header is limited to 32 characters")
+    } else if !group
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Some("the Webpush `Topic` header only allows letters, digits, `-` and `_`")
+    } else {
+        None
+    }
+}
+
+/// Returns `true` if the payload only wakes the app, without an alert, badge
+/// or sound.
+const fn is_background_only(payload: &ApnsPayload) -> bool {
+    let aps = &payload.aps;
+    aps.content_available && aps.alert.is_none() && aps.badge.is_none() && aps.sound.is_none()
+}
+
+/// Inserts the header, unless it is already set. Header names are compared
+/// case-insensitively.
+fn insert_header(headers: &mut HashMap<String, String>, name: &str, value: String) {
+    if !headers.keys().any(|key| key.eq_ignore_ascii_case(name)) {
+        headers.insert(name.to_string(), value);
+    }
+}
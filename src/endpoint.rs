@@ -0,0 +1,116 @@
+/// The base URL of the FCM v1 API.
+pub const FCM_ENDPOINT: &str = "https://fcm.googleapis.com";
+
+/// The environment variable, which overrides the base URL of the FCM API, e.g.
+/// `http://localhost:9099` for the Firebase Local Emulator Suite.
+///
+/// It is read by [`FcmEndpoint::from_env`], which is used by the send functions
+/// taking a project ID and by [`FcmClientBuilder`](crate::FcmClientBuilder)
+/// when no endpoint is set.
+pub const FCM_ENDPOINT_OVERRIDE: &str = "FCM_ENDPOINT_OVERRIDE";
+
+/// The FCM API of a Firebase project.
+///
+/// Combines the base URL of the API with the project ID, so messages can be
+/// redirected to an emulator or a mock server without building the URL by
+/// hand. The path `/v1/projects/{project_id}/messages:send` is appended
+/// automatically.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::FcmEndpoint;
+///
+/// let endpoint = FcmEndpoint::new("http://localhost:9099/", "demo-project");
+/// assert_eq!(
+///     endpoint.send_url(),
+///     "http://localhost:9099/v1/projects/demo-project/messages:send"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FcmEndpoint {
+    base_url: String,
+    project_id: String,
+}
+
+impl FcmEndpoint {
+    /// Creates an endpoint for the project at the given base URL.
+    pub fn new(base_url: impl Into<String>, project_id: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            project_id: project_id.into(),
+        }
+    }
+
+    /// Creates an endpoint for the project at the production FCM API.
+    pub fn production(project_id: impl Into<String>) -> Self {
+        Self::new(FCM_ENDPOINT, project_id)
+    }
+
+    /// Creates an endpoint for the project at the base URL of the
+    /// [`FCM_ENDPOINT_OVERRIDE`] environment variable, or at the production
+    /// FCM API if it isn't set.
+    pub fn from_env(project_id: impl Into<String>) -> Self {
+        match env_override() {
+            Some(base_url) => Self::new(base_url, project_id),
+            None => Self::production(project_id),
+        }
+    }
+
+    /// Returns the base URL of the API.
+    #[must_use]
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Returns the ID of the Firebase project.
+    #[must_use]
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
+    pub(crate) fn into_parts(self) -> (String, String) {
+        (self.base_url, self.project_id)
+    }
+
+    /// Returns the URL of the send method.
+    #[must_use]
+    pub fn send_url(&self) -> String {
+        endpoint_url(&self.base_url, &self.project_id)
+    }
+}
+
+/// Returns the base URL of the [`FCM_ENDPOINT_OVERRIDE`] environment variable,
+/// if it is set and not empty.
+pub fn env_override() -> Option<String> {
+    let base_url = std::env::var(FCM_ENDPOINT_OVERRIDE)
+        .ok()
+        .filter(|base_url| !base_url.trim().is_empty())?;
+    debug!("Sending FCM messages to {base_url} set by {FCM_ENDPOINT_OVERRIDE}");
+    Some(base_url)
+}
+
+/// Returns the URL of the send method for the project at the given endpoint.
+pub fn endpoint_url(endpoint: &str, project_id: &str) -> String {
+    format!(
+        "{}/v1/projects/{project_id}/messages:send",
+        endpoint.trim_end_matches('/')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_url_appends_path() {
+        assert_eq!(
+            FcmEndpoint::production("project").send_url(),
+            "https://fcm.googleapis.com/v1/projects/project/messages:send"
+        );
+        assert_eq!(
+            FcmEndpoint::new("http://localhost:9099//", "demo-project").send_url(),
+            "http://localhost:9099/v1/projects/demo-project/messages:send"
+        );
+    }
+}
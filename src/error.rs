@@ -1,3 +1,10 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::RetryInfo;
+
 /// Enum representing the possible errors that can occur in the Firebase Cloud
 /// Messaging (FCM) service.
 ///
@@ -10,12 +17,132 @@ pub enum FcmError {
     #[error("Error while sending OAuth request: {0}")]
     OAuthNetworkError(NetworkError),
 
+    /// The OAuth token endpoint rejected the token request with a standard
+    /// OAuth error response.
+    #[error(
+        "OAuth server returned status {status}: {error}{}",
+        .error_description.as_deref().map_or_else(String::new, |description| format!(" ({description})"))
+    )]
+    OAuthServerError {
+        /// The HTTP status code of the response.
+        status: u16,
+        /// The OAuth error code, e.g. `invalid_grant`.
+        error: String,
+        /// The human readable description of the error, e.g. `Invalid JWT
+        /// Signature.`.
+        error_description: Option<String>,
+    },
+
+    #[error("OAuth server returned a token with an implausible lifetime of {0} seconds")]
+    InvalidTokenLifetime(u64),
+
     #[error("Error while sending FCM: {0}")]
     FcmNetworkError(NetworkError),
 
-    #[error("FCM payload neither contains data or notification payload")]
+    /// FCM rejected the message with a structured error response.
+    #[error("FCM returned status {status} ({code}): {message}")]
+    FcmResponseError {
+        /// The HTTP status code of the response.
+        status: u16,
+        /// The FCM error code, e.g. `UNREGISTERED`.
+        code: FcmErrorCode,
+        /// The human readable error message of FCM.
+        message: String,
+        /// The raw response body.
+        body: String,
+        /// The delay requested by the `Retry-After` header, if present.
+        retry_after: Option<Duration>,
+    },
+
+    /// A send failed after it was retried. Only returned if a
+    /// [`RetryConfig`](crate::RetryConfig) is used and the send was retried
+    /// at least once.
+    ///
+    /// The classification methods, e.g. [`is_retryable`](Self::is_retryable),
+    /// return the result of the last error.
+    #[error("{error} (after {retry_info})")]
+    RetriesExhausted {
+        /// The error of the last attempt.
+        error: Box<Self>,
+        /// The attempts of the send.
+        retry_info: RetryInfo,
+    },
+
+    #[error("FCM payload neither contains a notification, a data payload or a platform config")]
     FcmInvalidPayloadError,
 
+    #[error("Invalid FCM message target: {0}")]
+    InvalidMessageTarget(&'static str),
+
+    #[error(
+        "Topic name {topic:?} must be non-empty and only contain letters, digits and `-_.~%`{}",
+        .character.map(|character| format!(", but contains {character:?}")).unwrap_or_default()
+    )]
+    InvalidTopicName {
+        topic: String,
+        /// The first invalid character, or `None` if the name is empty.
+        character: Option<char>,
+    },
+
+    #[error("Invalid device token: {0}")]
+    InvalidDeviceToken(String),
+
+    #[error("Invalid condition {condition:?}: {reason}")]
+    InvalidCondition {
+        condition: String,
+        reason: &'static str,
+    },
+
+    /// A message has several violations, see
+    /// [`MessageBuilder::report_all_violations`](crate::MessageBuilder::report_all_violations).
+    #[error(
+        "Invalid FCM message: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    InvalidMessage(Vec<Self>),
+
+    #[error("Invalid raw FCM message: {0}")]
+    InvalidRawMessage(&'static str),
+
+    #[error("Analytics label {label:?} must be 1 to 50 characters of letters, digits and `-_.~%`")]
+    InvalidAnalyticsLabel { label: String },
+
+    /// See [`MessageBuilder::collapse_group`](crate::MessageBuilder::collapse_group).
+    #[error("Invalid collapse group {group:?}: {reason}")]
+    InvalidCollapseGroup { group: String, reason: &'static str },
+
+    #[error("FCM message is {size} bytes, which exceeds the limit of {limit} bytes")]
+    PayloadTooLarge { size: usize, limit: usize },
+
+    #[error("Data payload exceeds the maximum nesting depth of {limit}")]
+    PayloadTooDeep { limit: usize },
+
+    #[error("Data payload key {key:?} contains control characters")]
+    InvalidDataKey { key: String },
+
+    #[error("Data payload key {key:?} is reserved by FCM")]
+    ReservedDataKey { key: String },
+
+    #[error("Data payload must be a JSON object, but is {found_type}")]
+    DataPayloadNotAnObject { found_type: &'static str },
+
+    #[error("Data payload value of key {key:?} is {found_type}, but FCM only accepts strings")]
+    InvalidDataPayload {
+        key: String,
+        found_type: &'static str,
+    },
+
+    /// The [`PayloadValidator`](crate::PayloadValidator) of the client
+    /// rejected the message, so it wasn't sent.
+    #[error("FCM message was rejected by the payload validator: {0}")]
+    PayloadRejected(String),
+
+    /// No FCM request could be started within the acquire timeout of
+    /// [`FcmClientBuilder::max_in_flight`](crate::FcmClientBuilder::max_in_flight),
+    /// because too many requests were in flight.
+    #[error("Timed out after {0:?} waiting for an in-flight request slot")]
+    InFlightLimitTimeout(Duration),
+
     #[error("Failed to serialize data: {0}")]
     SerializationError(#[from] serde_json::Error),
 
@@ -24,6 +151,344 @@ pub enum FcmError {
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[error("OAuth token is not a valid header value: {0}")]
+    InvalidAuthorizationHeader(reqwest::header::InvalidHeaderValue),
+
+    #[error("No project ID is set and the credentials don't contain one")]
+    MissingProjectId,
+
+    #[error("No client is registered for the Firebase project {project_id:?}")]
+    UnknownProject { project_id: String },
+
+    /// The project ID passed to
+    /// [`FcmClient::send_to_project`](crate::FcmClient::send_to_project) is
+    /// empty or contains a `/`.
+    #[error("Invalid Firebase project ID {project_id:?}")]
+    InvalidProjectId { project_id: String },
+
+    #[error("Invalid FcmClient configuration: {0}")]
+    InvalidClientConfig(&'static str),
+
+    #[error("Invalid service account credentials: {0}")]
+    InvalidCredentials(String),
+
+    #[error("Failed to load credentials from {path}: {source}")]
+    CredentialsFileError {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error(
+        "No Application Default Credentials found. Set GOOGLE_APPLICATION_CREDENTIALS to the path \
+         of a service account key or run on Google Cloud"
+    )]
+    DefaultCredentialsNotFound,
+
+    #[error("Failed to load credentials from environment variable {var}: {source}")]
+    CredentialsEnvError {
+        var: String,
+        source: std::env::VarError,
+    },
+
+    /// An environment variable read by
+    /// [`FcmClientBuilder::from_env`](crate::FcmClientBuilder::from_env) is
+    /// missing or invalid.
+    #[error("Invalid environment variable {var}: {reason}")]
+    InvalidEnvVar { var: &'static str, reason: String },
+}
+
+impl FcmError {
+    /// Returns the HTTP status code a server should respond with when this
+    /// error occurs while handling one of its own requests.
+    ///
+    /// This is a framework independent mapping, which can be used to convert
+    /// an `FcmError` into a response:
+    ///
+    /// * `400` for invalid payloads (`FcmInvalidPayloadError`,
+    ///   `InvalidMessageTarget`, `InvalidDeviceToken`, `InvalidCondition`,
+    ///   `InvalidMessage`, `InvalidTopicName`, `InvalidRawMessage`,
+    ///   `InvalidAnalyticsLabel`, `InvalidCollapseGroup`, `PayloadTooLarge`,
+    ///   `PayloadTooDeep`, `InvalidDataKey`, `ReservedDataKey`,
+    ///   `DataPayloadNotAnObject`, `InvalidDataPayload`, `PayloadRejected`,
+    ///   `SerializationError`, FCM `INVALID_ARGUMENT`)
+    /// * `410` if FCM rejected the device token, see
+    ///   [`is_token_invalid`](Self::is_token_invalid)
+    /// * `429` if the FCM quota was exceeded
+    /// * `502` for OAuth and credential errors, other FCM 401, 403, 404 and 5xx
+    ///   responses and other network errors
+    /// * `503` if the in-flight limit of the client was exhausted
+    ///   (`InFlightLimitTimeout`)
+    /// * `504` if a request to FCM or the OAuth server timed out
+    /// * `500` for everything else
+    ///
+    /// For `RetriesExhausted`, the code of the last error is returned.
+    #[must_use]
+    pub fn suggested_status_code(&self) -> u16 {
+        match self {
+            Self::RetriesExhausted { error, .. } => error.suggested_status_code(),
+            Self::FcmInvalidPayloadError
+            | Self::InvalidMessageTarget(_)
+            | Self::InvalidDeviceToken(_)
+            | Self::InvalidCondition { .. }
+            | Self::InvalidMessage(_)
+            | Self::InvalidTopicName { .. }
+            | Self::InvalidRawMessage(_)
+            | Self::InvalidAnalyticsLabel { .. }
+            | Self::InvalidCollapseGroup { .. }
+            | Self::PayloadTooLarge { .. }
+            | Self::PayloadTooDeep { .. }
+            | Self::InvalidDataKey { .. }
+            | Self::ReservedDataKey { .. }
+            | Self::DataPayloadNotAnObject { .. }
+            | Self::InvalidDataPayload { .. }
+            | Self::PayloadRejected(_)
+            | Self::SerializationError(_) => 400,
+            Self::OAuthNetworkError(error) | Self::FcmNetworkError(error) if error.is_timeout() => {
+                504
+            }
+            Self::FcmNetworkError(NetworkError::ServerError(..))
+            | Self::FcmResponseError { .. } => {
+                if self.is_token_invalid() {
+                    return 410;
+                }
+                let (status, code) = self.fcm_response().unwrap_or_default();
+                server_error_status_code(status, code.as_ref())
+            }
+            Self::OAuthNetworkError(_)
+            | Self::OAuthServerError { .. }
+            | Self::InvalidTokenLifetime(_)
+            | Self::FcmNetworkError(_)
+            | Self::JwtEncodeError(_)
+            | Self::InvalidAuthorizationHeader(_)
+            | Self::InvalidCredentials(_)
+            | Self::CredentialsFileError { .. }
+            | Self::CredentialsEnvError { .. } => 502,
+            Self::IoError(_)
+            | Self::MissingProjectId
+            | Self::UnknownProject { .. }
+            | Self::InvalidProjectId { .. }
+            | Self::InvalidClientConfig(_)
+            | Self::InvalidEnvVar { .. }
+            | Self::DefaultCredentialsNotFound => 500,
+            Self::InFlightLimitTimeout(_) => 503,
+        }
+    }
+
+    /// Returns `true` if sending the message again may succeed.
+    ///
+    /// This follows the FCM guidance:
+    ///
+    /// * Connection errors, OAuth network errors, `429` and `5xx` responses
+    ///   (`QUOTA_EXCEEDED`, `UNAVAILABLE`, `INTERNAL`) are retryable. Retries
+    ///   should use an exponential backoff.
+    /// * `InFlightLimitTimeout` is retryable, once fewer requests of the client
+    ///   are in flight.
+    /// * All other FCM responses, e.g. `UNREGISTERED`, `INVALID_ARGUMENT` or
+    ///   `SENDER_ID_MISMATCH`, OAuth error responses like `invalid_grant` and
+    ///   errors in the message itself are not.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RetriesExhausted { error, .. } => error.is_retryable(),
+            Self::OAuthNetworkError(_)
+            | Self::InFlightLimitTimeout(_)
+            | Self::FcmNetworkError(
+                NetworkError::SendRequestError(_)
+                | NetworkError::ResponseError(_)
+                | NetworkError::Timeout(_)
+                | NetworkError::Transport(_),
+            ) => true,
+            Self::OAuthServerError { status, .. } => matches!(status, 429 | 500..=599),
+            Self::FcmNetworkError(NetworkError::ServerError(..))
+            | Self::FcmResponseError { .. } => {
+                let (status, code) = self.fcm_response().unwrap_or_default();
+                matches!(status, 429 | 500..=599)
+                    || matches!(
+                        code,
+                        Some(
+                            FcmErrorCode::QuotaExceeded
+                                | FcmErrorCode::Unavailable
+                                | FcmErrorCode::Internal
+                        )
+                    )
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if FCM rejected the device token, so it should be
+    /// removed and not be used again.
+    ///
+    /// This is the case for `UNREGISTERED` and `SENDER_ID_MISMATCH` responses,
+    /// and for Google RPC error bodies with the status `NOT_FOUND` but no FCM
+    /// error code. A `404` without such a body, e.g. from a proxy or a wrong
+    /// endpoint, says nothing about the token.
+    #[must_use]
+    pub fn is_token_invalid(&self) -> bool {
+        match self.fcm_response() {
+            Some((_, Some(code))) => match code {
+                FcmErrorCode::Unregistered | FcmErrorCode::SenderIdMismatch => true,
+                FcmErrorCode::Unknown(code) => code == "NOT_FOUND",
+                _ => false,
+            },
+            Some((_, None)) | None => false,
+        }
+    }
+
+    /// Returns the name of the variant, e.g. `"FcmResponseError"`.
+    ///
+    /// The name is stable, so it can be included in responses and logs.
+    #[must_use]
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::OAuthNetworkError(_) => "OAuthNetworkError",
+            Self::OAuthServerError { .. } => "OAuthServerError",
+            Self::InvalidTokenLifetime(_) => "InvalidTokenLifetime",
+            Self::FcmNetworkError(_) => "FcmNetworkError",
+            Self::FcmResponseError { .. } => "FcmResponseError",
+            Self::RetriesExhausted { .. } => "RetriesExhausted",
+            Self::FcmInvalidPayloadError => "FcmInvalidPayloadError",
+            Self::InvalidMessageTarget(_) => "InvalidMessageTarget",
+            Self::InvalidDeviceToken(_) => "InvalidDeviceToken",
+            Self::InvalidCondition { .. } => "InvalidCondition",
+            Self::InvalidMessage(_) => "InvalidMessage",
+            Self::InvalidTopicName { .. } => "InvalidTopicName",
+            Self::InvalidRawMessage(_) => "InvalidRawMessage",
+            Self::InvalidAnalyticsLabel { .. } => "InvalidAnalyticsLabel",
+            Self::InvalidCollapseGroup { .. } => "InvalidCollapseGroup",
+            Self::PayloadTooLarge { .. } => "PayloadTooLarge",
+            Self::PayloadTooDeep { .. } => "PayloadTooDeep",
+            Self::InvalidDataKey { .. } => "InvalidDataKey",
+            Self::ReservedDataKey { .. } => "ReservedDataKey",
+            Self::DataPayloadNotAnObject { .. } => "DataPayloadNotAnObject",
+            Self::InvalidDataPayload { .. } => "InvalidDataPayload",
+            Self::PayloadRejected(_) => "PayloadRejected",
+            Self::InFlightLimitTimeout(_) => "InFlightLimitTimeout",
+            Self::SerializationError(_) => "SerializationError",
+            Self::JwtEncodeError(_) => "JwtEncodeError",
+            Self::IoError(_) => "IoError",
+            Self::InvalidAuthorizationHeader(_) => "InvalidAuthorizationHeader",
+            Self::MissingProjectId => "MissingProjectId",
+            Self::UnknownProject { .. } => "UnknownProject",
+            Self::InvalidProjectId { .. } => "InvalidProjectId",
+            Self::InvalidClientConfig(_) => "InvalidClientConfig",
+            Self::InvalidCredentials(_) => "InvalidCredentials",
+            Self::CredentialsFileError { .. } => "CredentialsFileError",
+            Self::DefaultCredentialsNotFound => "DefaultCredentialsNotFound",
+            Self::CredentialsEnvError { .. } => "CredentialsEnvError",
+            Self::InvalidEnvVar { .. } => "InvalidEnvVar",
+        }
+    }
+
+    /// Returns the attempts of a send, which failed after it was retried.
+    #[must_use]
+    pub const fn retry_info(&self) -> Option<&RetryInfo> {
+        match self {
+            Self::RetriesExhausted { retry_info, .. } => Some(retry_info),
+            _ => None,
+        }
+    }
+
+    /// Returns the delay FCM asked for before the message is sent again.
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RetriesExhausted { error, .. } => error.retry_after(),
+            Self::FcmResponseError { retry_after, .. }
+            | Self::FcmNetworkError(NetworkError::ServerError(_, _, retry_after)) => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Returns the status and the error code of an unsuccessful FCM response.
+    pub(crate) fn fcm_response(&self) -> Option<(u16, Option<FcmErrorCode>)> {
+        match self {
+            Self::RetriesExhausted { error, .. } => error.fcm_response(),
+            Self::FcmNetworkError(NetworkError::ServerError(status, text, _)) => {
+                let code = text
+                    .as_deref()
+                    .and_then(|text| serde_json::from_str::<GoogleRpcErrorResponse>(text).ok())
+                    .map(|response| response.error.code());
+                Some((*status, code))
+            }
+            Self::FcmResponseError { status, code, .. } => Some((*status, Some(code.clone()))),
+            _ => None,
+        }
+    }
+
+    /// Returns the FCM error code, or the [`kind`](Self::kind) if FCM didn't
+    /// send one.
+    pub(crate) fn code_or_kind(&self) -> String {
+        self.fcm_response()
+            .and_then(|(_, code)| code)
+            .map_or_else(|| self.kind().to_string(), |code| code.as_str().to_string())
+    }
+}
+
+const fn server_error_status_code(status: u16, code: Option<&FcmErrorCode>) -> u16 {
+    match (code, status) {
+        (Some(FcmErrorCode::QuotaExceeded), _) | (_, 429) => 429,
+        (Some(FcmErrorCode::InvalidArgument), _) | (_, 400) => 400,
+        (_, 504) => 504,
+        (_, 401 | 403 | 404 | 500..=599) => 502,
+        _ => 500,
+    }
+}
+
+/// The reason a message could not be sent, classified by what to do next.
+///
+/// Returned by
+/// [`FcmClient::send_classified`](crate::FcmClient::send_classified)
+/// and created from any [`FcmError`] with `From`. The errors are classified in
+/// this order:
+///
+/// | Variant        | Errors                                                           | Next step               |
+/// |----------------|------------------------------------------------------------------|-------------------------|
+/// | `TokenInvalid` | `UNREGISTERED`, `SENDER_ID_MISMATCH`, `NOT_FOUND` without a code | Delete the device token |
+/// | `Throttled`    | `QUOTA_EXCEEDED`, `429`                                          | Retry after the delay   |
+/// | `Transient`    | `UNAVAILABLE`, `INTERNAL`, `5xx`, network and OAuth errors       | Retry later             |
+/// | `Permanent`    | `INVALID_ARGUMENT`, `THIRD_PARTY_AUTH_ERROR`, invalid messages   | Don't retry the message |
+///
+/// `Transient` contains every error for which [`FcmError::is_retryable`]
+/// returns `true`, `Permanent` all remaining errors.
+#[derive(thiserror::Error, Debug)]
+pub enum SendFailure {
+    /// FCM rejected the device token, so it should be removed.
+    #[error("FCM rejected the device token: {0}")]
+    TokenInvalid(FcmError),
+    /// The sending quota was exceeded.
+    #[error("FCM throttled the message")]
+    Throttled {
+        /// The delay requested by FCM, if any.
+        retry_after: Option<Duration>,
+    },
+    /// The send failed temporarily and can be retried.
+    #[error(transparent)]
+    Transient(FcmError),
+    /// The message can't be sent, retrying won't help.
+    #[error(transparent)]
+    Permanent(FcmError),
+}
+
+impl From<FcmError> for SendFailure {
+    fn from(error: FcmError) -> Self {
+        let throttled = error.fcm_response().is_some_and(|(status, code)| {
+            status == 429 || code == Some(FcmErrorCode::QuotaExceeded)
+        });
+
+        if error.is_token_invalid() {
+            Self::TokenInvalid(error)
+        } else if throttled {
+            Self::Throttled {
+                retry_after: error.retry_after(),
+            }
+        } else if error.is_retryable() {
+            Self::Transient(error)
+        } else {
+            Self::Permanent(error)
+        }
+    }
 }
 
 /// Enum representing the possible network errors that can occur when sending
@@ -36,8 +501,218 @@ pub enum NetworkError {
     #[error("Failed to evaluate server response: {0}")]
     ResponseError(reqwest::Error),
 
-    #[error("Server returned status: {0}, opt text:")]
-    ServerError(u16, Option<String>),
+    /// The server responded with an unsuccessful status, the response body
+    /// and the delay requested by the `Retry-After` header, if present.
+    #[error("Server returned status {0}: {}", server_error_text(.1.as_deref()))]
+    ServerError(u16, Option<String>, Option<Duration>),
+
+    #[error("Request timed out: {0}")]
+    Timeout(reqwest::Error),
+
+    /// A custom [`HttpTransport`](crate::HttpTransport) failed to send the
+    /// request.
+    #[error("HTTP transport failed: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Failed to parse server response: {0}")]
+    InvalidResponse(serde_json::Error),
+}
+
+/// The maximum number of characters of a response body in error messages.
+const MAX_DISPLAYED_BODY_CHARS: usize = 1024;
+
+/// Formats the response body of a `ServerError` for its error message.
+///
+/// Long bodies, e.g. HTML error pages of proxies, are truncated to keep log
+/// lines readable.
+fn server_error_text(text: Option<&str>) -> String {
+    let text = text.map(str::trim).unwrap_or_default();
+    if text.is_empty() {
+        return "<no body>".to_string();
+    }
+
+    match text.char_indices().nth(MAX_DISPLAYED_BODY_CHARS) {
+        Some((end, _)) => format!("{}... ({} more bytes)", &text[..end], text.len() - end),
+        None => text.to_string(),
+    }
+}
+
+/// The error code of an FCM error response.
+///
+/// See the [FCM documentation](https://firebase.google.com/docs/reference/fcm/rest/v1/ErrorCode)
+/// for the meaning of each code. Codes unknown to this crate are kept in
+/// [`FcmErrorCode::Unknown`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FcmErrorCode {
+    /// No more information is available about this error.
+    UnspecifiedError,
+    /// The request contained invalid parameters, e.g. a malformed token.
+    InvalidArgument,
+    /// The device token is no longer valid and should be removed.
+    Unregistered,
+    /// The device token doesn't belong to the sender.
+    SenderIdMismatch,
+    /// The sending limit was exceeded.
+    QuotaExceeded,
+    /// FCM is temporarily unavailable.
+    Unavailable,
+    /// An unknown internal error occurred in FCM.
+    Internal,
+    /// The APNs certificate or web push auth key was invalid or missing.
+    ThirdPartyAuthError,
+    /// Any other code, e.g. a canonical status like `NOT_FOUND` if FCM didn't
+    /// send a more specific one.
+    Unknown(String),
+}
+
+impl FcmErrorCode {
+    /// Returns the code as sent by FCM, e.g. `"UNREGISTERED"`.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::UnspecifiedError => "UNSPECIFIED_ERROR",
+            Self::InvalidArgument => "INVALID_ARGUMENT",
+            Self::Unregistered => "UNREGISTERED",
+            Self::SenderIdMismatch => "SENDER_ID_MISMATCH",
+            Self::QuotaExceeded => "QUOTA_EXCEEDED",
+            Self::Unavailable => "UNAVAILABLE",
+            Self::Internal => "INTERNAL",
+            Self::ThirdPartyAuthError => "THIRD_PARTY_AUTH_ERROR",
+            Self::Unknown(code) => code,
+        }
+    }
+}
+
+impl From<&str> for FcmErrorCode {
+    fn from(code: &str) -> Self {
+        match code {
+            "UNSPECIFIED_ERROR" => Self::UnspecifiedError,
+            "INVALID_ARGUMENT" => Self::InvalidArgument,
+            "UNREGISTERED" => Self::Unregistered,
+            "SENDER_ID_MISMATCH" => Self::SenderIdMismatch,
+            "QUOTA_EXCEEDED" => Self::QuotaExceeded,
+            "UNAVAILABLE" => Self::Unavailable,
+            "INTERNAL" => Self::Internal,
+            "THIRD_PARTY_AUTH_ERROR" => Self::ThirdPartyAuthError,
+            code => Self::Unknown(code.to_string()),
+        }
+    }
+}
+
+impl Display for FcmErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Converts an unsuccessful FCM response into an error.
+///
+/// Structured error bodies become an [`FcmError::FcmResponseError`], all other
+/// bodies a [`NetworkError::ServerError`]. Both keep the `Retry-After` delay.
+pub fn fcm_response_error(status: u16, body: String, retry_after: Option<Duration>) -> FcmError {
+    match serde_json::from_str::<GoogleRpcErrorResponse>(&body) {
+        Ok(response) => FcmError::FcmResponseError {
+            status,
+            code: response.error.code(),
+            message: response.error.message,
+            body,
+            retry_after,
+        },
+        Err(_) => {
+            FcmError::FcmNetworkError(NetworkError::ServerError(status, Some(body), retry_after))
+        }
+    }
+}
+
+/// Converts an unsuccessful response of the OAuth token endpoint into an
+/// error.
+///
+/// Standard OAuth error bodies become an [`FcmError::OAuthServerError`], all
+/// other bodies a [`NetworkError::ServerError`].
+pub fn oauth_response_error(status: u16, body: String) -> FcmError {
+    match serde_json::from_str::<OAuthErrorResponse>(&body) {
+        Ok(response) => FcmError::OAuthServerError {
+            status,
+            error: response.error,
+            error_description: response.error_description,
+        },
+        Err(_) => FcmError::OAuthNetworkError(NetworkError::ServerError(status, Some(body), None)),
+    }
+}
+
+/// The error body of an OAuth token endpoint (RFC 6749, section 5.2).
+#[derive(Deserialize)]
+struct OAuthErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// The `google.rpc.Status` shaped error body returned by the FCM v1 API.
+#[derive(Deserialize)]
+pub struct GoogleRpcErrorResponse {
+    pub error: GoogleRpcError,
+}
+
+#[derive(Deserialize)]
+pub struct GoogleRpcError {
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    details: Vec<GoogleRpcErrorDetail>,
+}
+
+#[derive(Deserialize)]
+pub struct GoogleRpcErrorDetail {
+    #[serde(rename = "errorCode")]
+    error_code: Option<String>,
+}
+
+impl GoogleRpcError {
+    /// Returns the FCM specific error code (e.g. `UNREGISTERED`), if present.
+    pub fn fcm_error_code(&self) -> Option<&str> {
+        self.details
+            .iter()
+            .find_map(|detail| detail.error_code.as_deref())
+    }
+
+    /// Returns the FCM specific error code, falling back to the canonical
+    /// status.
+    pub fn code(&self) -> FcmErrorCode {
+        FcmErrorCode::from(self.fcm_error_code().unwrap_or(&self.status))
+    }
+}
+
+impl NetworkError {
+    /// Returns `true` if the request failed because it timed out.
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Self::SendRequestError(error) | Self::ResponseError(error) => error.is_timeout(),
+            Self::Timeout(_) => true,
+            Self::ServerError(..) | Self::Transport(_) | Self::InvalidResponse(_) => false,
+        }
+    }
+
+    /// Wraps an error of sending a request, keeping timeouts apart.
+    pub(crate) fn from_send_error(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            Self::Timeout(error)
+        } else {
+            Self::SendRequestError(error)
+        }
+    }
+
+    /// Wraps an error of reading a response, keeping timeouts apart.
+    pub(crate) fn from_response_error(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            Self::Timeout(error)
+        } else {
+            Self::ResponseError(error)
+        }
+    }
 }
 
 pub trait ResultMapError<T> {
@@ -64,3 +739,563 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn reqwest_error() -> reqwest::Error {
+        reqwest::Client::new().get("not a url").build().unwrap_err()
+    }
+
+    async fn reqwest_timeout_error() -> reqwest::Error {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        reqwest::Client::new()
+            .get(url)
+            .timeout(Duration::from_millis(10))
+            .send()
+            .await
+            .unwrap_err()
+    }
+
+    #[test]
+    fn test_server_error_display_contains_body() {
+        let error = NetworkError::ServerError(503, Some("Service Unavailable".to_string()), None);
+
+        assert_eq!(
+            error.to_string(),
+            "Server returned status 503: Service Unavailable"
+        );
+    }
+
+    #[test]
+    fn test_server_error_display_without_body() {
+        assert_eq!(
+            NetworkError::ServerError(502, None, None).to_string(),
+            "Server returned status 502: <no body>"
+        );
+        assert_eq!(
+            NetworkError::ServerError(502, Some("\n".to_string()), None).to_string(),
+            "Server returned status 502: <no body>"
+        );
+    }
+
+    #[test]
+    fn test_server_error_display_truncates_long_body() {
+        let body = "ä".repeat(MAX_DISPLAYED_BODY_CHARS + 10);
+
+        let message = NetworkError::ServerError(500, Some(body), None).to_string();
+
+        assert!(message.starts_with(&format!(
+            "Server returned status 500: {}...",
+            "ä".repeat(MAX_DISPLAYED_BODY_CHARS)
+        )));
+        assert!(message.ends_with("(20 more bytes)"));
+    }
+
+    fn fcm_server_error(status: u16, error_code: &str) -> FcmError {
+        let body = serde_json::json!({
+            "error": {
+                "code": status,
+                "message": "message",
+                "status": "STATUS",
+                "details": [{ "errorCode": error_code }]
+            }
+        });
+
+        FcmError::FcmNetworkError(NetworkError::ServerError(
+            status,
+            Some(body.to_string()),
+            None,
+        ))
+    }
+
+    #[test]
+    fn test_payload_errors_map_to_bad_request() {
+        let serialization_error = serde_json::from_str::<u8>("x").unwrap_err();
+
+        assert_eq!(
+            FcmError::FcmInvalidPayloadError.suggested_status_code(),
+            400
+        );
+        assert_eq!(
+            FcmError::InvalidMessageTarget("no token, topic or condition is set")
+                .suggested_status_code(),
+            400
+        );
+        assert_eq!(
+            FcmError::SerializationError(serialization_error).suggested_status_code(),
+            400
+        );
+        assert_eq!(
+            FcmError::PayloadTooLarge {
+                size: 5000,
+                limit: 4096
+            }
+            .suggested_status_code(),
+            400
+        );
+        assert_eq!(
+            FcmError::PayloadTooDeep { limit: 32 }.suggested_status_code(),
+            400
+        );
+        assert_eq!(
+            FcmError::InvalidDataKey {
+                key: "\u{7}".to_string()
+            }
+            .suggested_status_code(),
+            400
+        );
+        assert_eq!(
+            FcmError::DataPayloadNotAnObject {
+                found_type: "a string"
+            }
+            .suggested_status_code(),
+            400
+        );
+        assert_eq!(
+            FcmError::InvalidDataPayload {
+                key: "count".to_string(),
+                found_type: "number"
+            }
+            .suggested_status_code(),
+            400
+        );
+        assert_eq!(
+            fcm_server_error(400, "INVALID_ARGUMENT").suggested_status_code(),
+            400
+        );
+    }
+
+    #[test]
+    fn test_kind_is_variant_name() {
+        assert_eq!(FcmError::MissingProjectId.kind(), "MissingProjectId");
+        assert_eq!(
+            fcm_server_error(404, "UNREGISTERED").kind(),
+            "FcmNetworkError"
+        );
+    }
+
+    #[test]
+    fn test_fcm_response_error_parses_error_code() {
+        let body = serde_json::json!({
+            "error": {
+                "code": 404,
+                "message": "Requested entity was not found.",
+                "status": "NOT_FOUND",
+                "details": [{ "errorCode": "UNREGISTERED" }]
+            }
+        })
+        .to_string();
+
+        let error = fcm_response_error(404, body.clone(), None);
+        assert!(matches!(
+            &error,
+            FcmError::FcmResponseError {
+                status: 404,
+                code: FcmErrorCode::Unregistered,
+                message,
+                body: raw,
+                retry_after: None,
+            } if message == "Requested entity was not found." && raw == &body
+        ));
+        assert_eq!(
+            error.to_string(),
+            "FCM returned status 404 (UNREGISTERED): Requested entity was not found."
+        );
+        assert_eq!(error.suggested_status_code(), 410);
+    }
+
+    #[test]
+    fn test_fcm_response_error_falls_back_to_status() {
+        let body = serde_json::json!({
+            "error": { "code": 403, "message": "message", "status": "PERMISSION_DENIED" }
+        })
+        .to_string();
+
+        let error = fcm_response_error(403, body, None);
+        assert!(matches!(
+            error,
+            FcmError::FcmResponseError { code: FcmErrorCode::Unknown(code), .. } if code == "PERMISSION_DENIED"
+        ));
+    }
+
+    #[test]
+    fn test_fcm_response_error_without_json_body() {
+        let error = fcm_response_error(500, "Internal Server Error".to_string(), None);
+
+        assert!(matches!(
+            error,
+            FcmError::FcmNetworkError(NetworkError::ServerError(500, Some(text), _)) if text == "Internal Server Error"
+        ));
+    }
+
+    #[test]
+    fn test_oauth_response_error_is_parsed() {
+        let body = serde_json::json!({
+            "error": "invalid_grant",
+            "error_description": "Invalid JWT Signature."
+        })
+        .to_string();
+
+        let error = oauth_response_error(400, body);
+
+        assert!(matches!(
+            &error,
+            FcmError::OAuthServerError { status: 400, error, error_description: Some(description) }
+                if error == "invalid_grant" && description == "Invalid JWT Signature."
+        ));
+        assert_eq!(
+            error.to_string(),
+            "OAuth server returned status 400: invalid_grant (Invalid JWT Signature.)"
+        );
+        assert!(!error.is_retryable());
+        assert_eq!(error.suggested_status_code(), 502);
+    }
+
+    #[test]
+    fn test_oauth_response_error_without_json_body() {
+        let error = oauth_response_error(503, "Service Unavailable".to_string());
+
+        assert!(matches!(
+            &error,
+            FcmError::OAuthNetworkError(NetworkError::ServerError(503, Some(text), _)) if text == "Service Unavailable"
+        ));
+        assert!(error.is_retryable());
+        assert!(oauth_response_error(
+            503,
+            serde_json::json!({ "error": "unavailable" }).to_string()
+        )
+        .is_retryable());
+    }
+
+    #[test]
+    fn test_fcm_error_code_round_trip() {
+        for code in [
+            "UNSPECIFIED_ERROR",
+            "INVALID_ARGUMENT",
+            "UNREGISTERED",
+            "SENDER_ID_MISMATCH",
+            "QUOTA_EXCEEDED",
+            "UNAVAILABLE",
+            "INTERNAL",
+            "THIRD_PARTY_AUTH_ERROR",
+            "SOMETHING_NEW",
+        ] {
+            assert_eq!(FcmErrorCode::from(code).as_str(), code);
+        }
+        assert_eq!(
+            FcmErrorCode::from("SOMETHING_NEW"),
+            FcmErrorCode::Unknown("SOMETHING_NEW".to_string())
+        );
+    }
+
+    #[test]
+    fn test_transient_errors_are_retryable() {
+        assert!(fcm_server_error(429, "QUOTA_EXCEEDED").is_retryable());
+        assert_eq!(
+            fcm_response_error(
+                429,
+                serde_json::json!({ "error": { "status": "RESOURCE_EXHAUSTED" } }).to_string(),
+                Some(Duration::from_secs(2))
+            )
+            .retry_after(),
+            Some(Duration::from_secs(2))
+        );
+        assert!(fcm_server_error(500, "INTERNAL").is_retryable());
+        assert!(fcm_server_error(503, "UNAVAILABLE").is_retryable());
+        assert!(fcm_response_error(503, "Service Unavailable".to_string(), None).is_retryable());
+        assert_eq!(
+            fcm_response_error(
+                503,
+                "Service Unavailable".to_string(),
+                Some(Duration::from_secs(2))
+            )
+            .retry_after(),
+            Some(Duration::from_secs(2))
+        );
+        assert!(
+            FcmError::FcmNetworkError(NetworkError::ServerError(502, None, None)).is_retryable()
+        );
+        assert!(
+            FcmError::FcmNetworkError(NetworkError::SendRequestError(reqwest_error()))
+                .is_retryable()
+        );
+        assert!(
+            FcmError::FcmNetworkError(NetworkError::ResponseError(reqwest_error())).is_retryable()
+        );
+        assert!(
+            FcmError::OAuthNetworkError(NetworkError::SendRequestError(reqwest_error()))
+                .is_retryable()
+        );
+        assert!(
+            FcmError::OAuthNetworkError(NetworkError::ServerError(500, None, None)).is_retryable()
+        );
+    }
+
+    #[test]
+    fn test_permanent_errors_are_not_retryable() {
+        let serialization_error = serde_json::from_str::<u8>("x").unwrap_err();
+        let jwt_error = jsonwebtoken::errors::Error::from(
+            jsonwebtoken::errors::ErrorKind::InvalidRsaKey("invalid"),
+        );
+
+        assert!(!fcm_server_error(400, "INVALID_ARGUMENT").is_retryable());
+        assert!(!fcm_server_error(403, "SENDER_ID_MISMATCH").is_retryable());
+        assert!(!fcm_server_error(404, "UNREGISTERED").is_retryable());
+        assert!(!fcm_server_error(401, "THIRD_PARTY_AUTH_ERROR").is_retryable());
+        assert!(
+            !FcmError::FcmNetworkError(NetworkError::ServerError(404, None, None)).is_retryable()
+        );
+        assert!(!FcmError::FcmInvalidPayloadError.is_retryable());
+        assert!(
+            !FcmError::InvalidMessageTarget("no token, topic or condition is set").is_retryable()
+        );
+        assert!(!FcmError::PayloadTooLarge {
+            size: 5000,
+            limit: 4096
+        }
+        .is_retryable());
+        assert!(!FcmError::PayloadTooDeep { limit: 32 }.is_retryable());
+        assert!(!FcmError::InvalidDataKey {
+            key: "\u{7}".to_string()
+        }
+        .is_retryable());
+        assert!(!FcmError::SerializationError(serialization_error).is_retryable());
+        assert!(!FcmError::JwtEncodeError(jwt_error).is_retryable());
+        assert!(
+            !FcmError::IoError(std::io::Error::from(std::io::ErrorKind::NotFound)).is_retryable()
+        );
+        assert!(!FcmError::CredentialsFileError {
+            path: "credentials.json".into(),
+            source: std::io::Error::from(std::io::ErrorKind::NotFound),
+        }
+        .is_retryable());
+        assert!(!FcmError::InvalidAuthorizationHeader(
+            reqwest::header::HeaderValue::from_str("\n").unwrap_err()
+        )
+        .is_retryable());
+    }
+
+    #[test]
+    fn test_token_invalid() {
+        let unregistered = serde_json::json!({
+            "error": {
+                "code": 404,
+                "message": "message",
+                "status": "NOT_FOUND",
+                "details": [{ "errorCode": "UNREGISTERED" }]
+            }
+        });
+
+        assert!(fcm_response_error(404, unregistered.to_string(), None).is_token_invalid());
+        assert!(fcm_server_error(404, "UNREGISTERED").is_token_invalid());
+        assert!(fcm_server_error(403, "SENDER_ID_MISMATCH").is_token_invalid());
+        assert!(
+            !FcmError::FcmNetworkError(NetworkError::ServerError(404, None, None))
+                .is_token_invalid()
+        );
+        assert!(!fcm_server_error(400, "INVALID_ARGUMENT").is_token_invalid());
+        assert!(!fcm_server_error(503, "UNAVAILABLE").is_token_invalid());
+        assert!(
+            !FcmError::OAuthNetworkError(NetworkError::ServerError(404, None, None))
+                .is_token_invalid()
+        );
+        assert!(!FcmError::FcmInvalidPayloadError.is_token_invalid());
+    }
+
+    #[test]
+    fn test_unregistered_maps_to_gone() {
+        assert_eq!(
+            fcm_server_error(404, "UNREGISTERED").suggested_status_code(),
+            410
+        );
+        assert_eq!(
+            fcm_server_error(403, "SENDER_ID_MISMATCH").suggested_status_code(),
+            410
+        );
+    }
+
+    #[test]
+    fn test_plain_text_not_found_is_not_token_invalid() {
+        let error = FcmError::FcmNetworkError(NetworkError::ServerError(
+            404,
+            Some("404 page not found".to_string()),
+            None,
+        ));
+
+        assert!(!error.is_token_invalid());
+        assert_eq!(error.suggested_status_code(), 502);
+        assert!(matches!(
+            SendFailure::from(error),
+            SendFailure::Permanent(FcmError::FcmNetworkError(_))
+        ));
+    }
+
+    #[test]
+    fn test_not_found_without_error_code_is_gone() {
+        let body = serde_json::json!({
+            "error": {
+                "code": 404,
+                "message": "Requested entity was not found.",
+                "status": "NOT_FOUND"
+            }
+        })
+        .to_string();
+        let error = fcm_response_error(404, body, None);
+
+        assert!(error.is_token_invalid());
+        assert_eq!(error.suggested_status_code(), 410);
+    }
+
+    #[test]
+    fn test_quota_exceeded_maps_to_too_many_requests() {
+        assert_eq!(
+            fcm_server_error(429, "QUOTA_EXCEEDED").suggested_status_code(),
+            429
+        );
+    }
+
+    #[test]
+    fn test_oauth_and_credential_errors_map_to_bad_gateway() {
+        let jwt_error = jsonwebtoken::errors::Error::from(
+            jsonwebtoken::errors::ErrorKind::InvalidRsaKey("invalid"),
+        );
+        let credentials_error = FcmError::CredentialsFileError {
+            path: "credentials.json".into(),
+            source: std::io::Error::from(std::io::ErrorKind::NotFound),
+        };
+
+        assert_eq!(
+            FcmError::OAuthNetworkError(NetworkError::SendRequestError(reqwest_error()))
+                .suggested_status_code(),
+            502
+        );
+        assert_eq!(
+            FcmError::OAuthNetworkError(NetworkError::ServerError(400, None, None))
+                .suggested_status_code(),
+            502
+        );
+        assert_eq!(
+            FcmError::JwtEncodeError(jwt_error).suggested_status_code(),
+            502
+        );
+        assert_eq!(credentials_error.suggested_status_code(), 502);
+        assert_eq!(
+            FcmError::InvalidAuthorizationHeader(
+                reqwest::header::HeaderValue::from_str("\n").unwrap_err()
+            )
+            .suggested_status_code(),
+            502
+        );
+        assert_eq!(
+            fcm_server_error(401, "THIRD_PARTY_AUTH_ERROR").suggested_status_code(),
+            502
+        );
+    }
+
+    #[test]
+    fn test_fcm_server_and_network_errors_map_to_bad_gateway() {
+        assert_eq!(
+            fcm_server_error(500, "INTERNAL").suggested_status_code(),
+            502
+        );
+        assert_eq!(
+            fcm_server_error(503, "UNAVAILABLE").suggested_status_code(),
+            502
+        );
+        assert_eq!(
+            FcmError::FcmNetworkError(NetworkError::ServerError(
+                500,
+                Some("Internal Server Error".to_string()),
+                None
+            ))
+            .suggested_status_code(),
+            502
+        );
+        assert_eq!(
+            FcmError::FcmNetworkError(NetworkError::SendRequestError(reqwest_error()))
+                .suggested_status_code(),
+            502
+        );
+        assert_eq!(
+            FcmError::FcmNetworkError(NetworkError::ResponseError(reqwest_error()))
+                .suggested_status_code(),
+            502
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_map_to_gateway_timeout() {
+        assert_eq!(
+            FcmError::FcmNetworkError(NetworkError::SendRequestError(
+                reqwest_timeout_error().await
+            ))
+            .suggested_status_code(),
+            504
+        );
+        assert_eq!(
+            FcmError::OAuthNetworkError(NetworkError::SendRequestError(
+                reqwest_timeout_error().await
+            ))
+            .suggested_status_code(),
+            504
+        );
+        assert_eq!(
+            FcmError::FcmNetworkError(NetworkError::from_send_error(reqwest_timeout_error().await))
+                .suggested_status_code(),
+            504
+        );
+        assert_eq!(
+            FcmError::FcmNetworkError(NetworkError::ServerError(504, None, None))
+                .suggested_status_code(),
+            504
+        );
+    }
+
+    #[test]
+    fn test_other_errors_map_to_internal_server_error() {
+        assert_eq!(
+            FcmError::IoError(std::io::Error::from(std::io::ErrorKind::Other))
+                .suggested_status_code(),
+            500
+        );
+        assert_eq!(
+            FcmError::FcmNetworkError(NetworkError::ServerError(418, None, None))
+                .suggested_status_code(),
+            500
+        );
+    }
+
+    #[test]
+    fn test_send_failure_classifies_errors_without_fcm_response() {
+        assert!(matches!(
+            SendFailure::from(FcmError::FcmInvalidPayloadError),
+            SendFailure::Permanent(FcmError::FcmInvalidPayloadError)
+        ));
+        assert!(matches!(
+            SendFailure::from(FcmError::OAuthServerError {
+                status: 503,
+                error: "temporarily_unavailable".to_string(),
+                error_description: None,
+            }),
+            SendFailure::Transient(FcmError::OAuthServerError { .. })
+        ));
+        assert!(matches!(
+            SendFailure::from(FcmError::FcmNetworkError(NetworkError::ServerError(
+                404, None, None
+            ))),
+            SendFailure::Permanent(FcmError::FcmNetworkError(_))
+        ));
+        assert!(matches!(
+            SendFailure::from(FcmError::FcmNetworkError(NetworkError::ServerError(
+                429, None, None
+            ))),
+            SendFailure::Throttled { retry_after: None }
+        ));
+    }
+}
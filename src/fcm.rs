@@ -1,26 +1,159 @@
+use std::time::Duration;
+
+use reqwest::header::HeaderValue;
 use serde::Serialize;
 use serde_json::json;
-use tracing::debug;
-use tracing::error;
-use tracing::info;
+use tokio::time::Instant;
 use tracing::instrument;
 
-use crate::error::NetworkError;
+use crate::error::fcm_response_error;
+use crate::error::GoogleRpcErrorResponse;
 use crate::error::ResultMapError;
+use crate::http::HttpRequest;
+use crate::http::HttpResponse;
+use crate::http::HttpTransport;
+use crate::in_flight::InFlightLimit;
+use crate::interceptor::Intercepted;
+use crate::interceptor::SharedInterceptor;
+use crate::message::redact_token;
+use crate::message::topic_name;
+use crate::message::validate_device_token;
+use crate::retry::retry_after;
+use crate::AndroidConfig;
+use crate::ApnsConfig;
+use crate::FcmEndpoint;
 use crate::FcmError;
-use crate::SharedTokenManager;
+use crate::Message;
+use crate::MessageTarget;
+use crate::RateLimiter;
+use crate::RetryConfig;
+use crate::RetryInfo;
+use crate::TokenProvider;
+use crate::WebpushConfig;
+
+/// The header, which names the project billed for the quota of a request.
+const QUOTA_PROJECT_HEADER: &str = "x-goog-user-project";
 
 /// A wrapper for Firebase Cloud Messaging (FCM) notifications.
+///
+/// Create it with [`new`](Self::new) and set optional fields with the `with_`
+/// methods, so new fields can be added without breaking changes.
+///
+/// Implements `schemars::JsonSchema` with the `schemars` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::FcmNotification;
+///
+/// let notification = FcmNotification::new("Sale", "Everything is 50% off")
+///     .with_image("https://example.com/sale.png");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[non_exhaustive]
 pub struct FcmNotification {
     pub title: String,
     pub body: String,
+    /// The URL of an image, which is shown in the notification. It is only
+    /// sent if set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+impl FcmNotification {
+    /// Creates a notification with a title and a body.
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+            image: None,
+        }
+    }
+
+    /// Sets the URL of an image, which is shown in the notification.
+    ///
+    /// FCM downloads the image, so it must be publicly reachable. Android
+    /// and Webpush show it as is, iOS requires a notification service
+    /// extension.
+    #[must_use]
+    pub fn with_image(mut self, image: impl Into<String>) -> Self {
+        self.image = Some(image.into());
+        self
+    }
+}
+
+/// Platform specific options of an FCM message.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::ApnsConfig;
+/// use oauth_fcm::PlatformConfig;
+///
+/// let config = PlatformConfig {
+///     apns: Some(ApnsConfig::default()),
+///     ..PlatformConfig::default()
+/// };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlatformConfig {
+    /// Options for Android devices, sent as `android` section.
+    pub android: Option<AndroidConfig>,
+    /// Options for Apple devices, sent as `apns` section.
+    pub apns: Option<ApnsConfig>,
+    /// Options for browsers, sent as `webpush` section.
+    pub webpush: Option<WebpushConfig>,
+}
+
+impl PlatformConfig {
+    /// Returns `true` if no platform specific options are set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.android.as_ref().is_none_or(AndroidConfig::is_empty)
+            && self.apns.as_ref().is_none_or(ApnsConfig::is_empty)
+            && self.webpush.as_ref().is_none_or(WebpushConfig::is_empty)
+    }
+}
+
+/// The response of FCM to a successfully sent message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FcmResponse {
+    /// The ID of the sent message, e.g.
+    /// `projects/my-project/messages/0:1500415314455276%31bd1c9631bd1c96`.
+    ///
+    /// This is `None` if the response body didn't contain the ID. The message
+    /// was sent nonetheless.
+    pub message_id: Option<String>,
+    /// The raw JSON response body, or `Value::Null` if the body wasn't valid
+    /// JSON.
+    pub raw: serde_json::Value,
+}
+
+impl FcmResponse {
+    fn from_body(body: &str) -> Self {
+        let raw = serde_json::from_str::<serde_json::Value>(body).unwrap_or_else(|error| {
+            warn!("FCM returned a malformed success response: {}", error);
+            serde_json::Value::Null
+        });
+        let message_id = raw
+            .get("name")
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_owned);
+
+        Self { message_id, raw }
+    }
 }
 
 /// Sends a Firebase Cloud Messaging (FCM) message.
 ///
 /// This function sends an FCM message to the device with the provided device
 /// token. You can provide either a data payload or a notification payload, or
-/// both. It uses the provided `SharedTokenManager` to handle OAuth tokens.
+/// both. It gets the OAuth token from the provided [`TokenProvider`], usually
+/// a `SharedTokenManager`.
+///
+/// Consider using an [`FcmClient`](crate::FcmClient) instead, which keeps the
+/// token manager and the project ID together.
 ///
 /// # Arguments
 ///
@@ -29,13 +162,26 @@ pub struct FcmNotification {
 ///   body of the notification.
 /// * `data_payload` - Optional data represented as a Map. This can be any type
 ///   that implements the `Serialize` trait.
-/// * `token_manager` - A `SharedTokenManager` to handle OAuth tokens.
+/// * `token_provider` - The [`TokenProvider`] of the OAuth token, usually a
+///   `SharedTokenManager`.
 /// * `project_id` - The ID of the Firebase project, where the device token is
 ///   registered.
 ///
 /// # Errors
 ///
 /// This function will return an error if the FCM message could not be sent.
+/// On success, it returns the [`FcmResponse`] containing the message ID.
+///
+/// # Cancellation
+///
+/// This function is cancellation safe in the sense that dropping the future
+/// never leaves the `SharedTokenManager` locked or in an inconsistent state.
+/// The token manager is only locked while the token is obtained, and the lock
+/// is released before the FCM request is sent.
+///
+/// Once the request has been sent, FCM may deliver the message even if the
+/// future is dropped before the response arrives. A cancelled send therefore
+/// has an unknown outcome, and retrying it may deliver the message twice.
 ///
 /// # Example
 ///
@@ -49,10 +195,7 @@ pub struct FcmNotification {
 /// let data = serde_json::json!({
 ///    "key": "value"
 /// });
-/// let notification = oauth_fcm::FcmNotification {
-///    title: "Test Title".to_string(),
-///   body: "Test Body".to_string(),
-/// };
+/// let notification = oauth_fcm::FcmNotification::new("Test Title", "Test Body");
 /// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
 /// let project_id = "project_id";
 /// send_fcm_message(device_token, Some(notification), Some(data), &token_manager, project_id)
@@ -61,23 +204,87 @@ pub struct FcmNotification {
 ///
 /// # });
 /// ```
-#[instrument(level = "info", skip(data_payload, notification, token_manager))]
+#[instrument(
+    level = "info",
+    skip(device_token, data_payload, notification, token_provider),
+    fields(device_token = %redact_token(device_token))
+)]
 pub async fn send_fcm_message<T: Serialize>(
     device_token: &str,
     notification: Option<FcmNotification>,
     data_payload: Option<T>,
-    token_manager: &SharedTokenManager,
+    token_provider: &(impl TokenProvider + ?Sized),
+    project_id: &str,
+) -> Result<FcmResponse, FcmError> {
+    let target = MessageTarget::Token(device_token.to_string());
+    info!("Sending FCM message to {}", target.for_log(false));
+
+    let payload = build_fcm_payload(
+        &target,
+        notification,
+        data_payload,
+        &PlatformConfig::default(),
+    )?;
+    send_payload(
+        &payload,
+        token_provider,
+        &fcm_url(project_id),
+        RequestOptions::default(),
+    )
+    .await
+}
+
+/// Sends a Firebase Cloud Messaging (FCM) message to the given target.
+///
+/// This function behaves exactly as [`send_fcm_message`], but the message can
+/// be sent to a topic instead of a single device.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::fs::File;
+///
+/// use oauth_fcm::{create_shared_token_manager, send_fcm_message_to_target, FcmNotification, MessageTarget};
+///
+/// # tokio_test::block_on(async {
+/// let notification = FcmNotification::new("Breaking news", "Something happened");
+/// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+/// send_fcm_message_to_target(
+///     &MessageTarget::Topic("news".to_string()),
+///     Some(notification),
+///     None::<serde_json::Value>,
+///     &token_manager,
+///     "project_id",
+/// )
+/// .await
+/// .expect("Error while sending FCM message");
+/// # });
+/// ```
+#[instrument(
+    level = "info",
+    skip(target, data_payload, notification, token_provider),
+    fields(target = %target.for_log(false))
+)]
+pub async fn send_fcm_message_to_target<T: Serialize>(
+    target: &MessageTarget,
+    notification: Option<FcmNotification>,
+    data_payload: Option<T>,
+    token_provider: &(impl TokenProvider + ?Sized),
     project_id: &str,
-) -> Result<(), FcmError> {
-    info!("Sending FCM message to device: {}", device_token);
-    let url = format!("https://fcm.googleapis.com/v1/projects/{project_id}/messages:send");
+) -> Result<FcmResponse, FcmError> {
+    info!("Sending FCM message to {}", target.for_log(false));
 
-    send_fcm_message_with_url(
-        device_token,
+    let payload = build_fcm_payload(
+        target,
         notification,
         data_payload,
-        token_manager,
-        &url,
+        &PlatformConfig::default(),
+    )?;
+    send_payload(
+        &payload,
+        token_provider,
+        &fcm_url(project_id),
+        RequestOptions::default(),
     )
     .await
 }
@@ -89,111 +296,632 @@ pub async fn send_fcm_message<T: Serialize>(
 ///
 /// Normally, you would use `send_fcm` instead of this function. This is only
 /// useful for testing, such as for mocking the FCM URL.
-#[instrument(level = "debug", skip(data_payload, notification, token_manager))]
+#[instrument(
+    level = "debug",
+    skip(device_token, data_payload, notification, token_provider),
+    fields(device_token = %redact_token(device_token))
+)]
 pub async fn send_fcm_message_with_url<T: Serialize>(
     device_token: &str,
     notification: Option<FcmNotification>,
     data_payload: Option<T>,
-    token_manager: &SharedTokenManager,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+) -> Result<FcmResponse, FcmError> {
+    let payload = build_fcm_payload(
+        &MessageTarget::Token(device_token.to_string()),
+        notification,
+        data_payload,
+        &PlatformConfig::default(),
+    )?;
+    send_payload(&payload, token_provider, fcm_url, RequestOptions::default()).await
+}
+
+/// Sends a Firebase Cloud Messaging (FCM) message to the given target and a
+/// specific URL.
+///
+/// This function behaves exactly as [`send_fcm_message_to_target`], but allows
+/// specifying a custom FCM URL. This is only useful for testing.
+#[instrument(
+    level = "debug",
+    skip(target, data_payload, notification, token_provider),
+    fields(target = %target.for_log(false))
+)]
+pub async fn send_fcm_message_to_target_with_url<T: Serialize>(
+    target: &MessageTarget,
+    notification: Option<FcmNotification>,
+    data_payload: Option<T>,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+) -> Result<FcmResponse, FcmError> {
+    let payload = build_fcm_payload(
+        target,
+        notification,
+        data_payload,
+        &PlatformConfig::default(),
+    )?;
+    send_payload(&payload, token_provider, fcm_url, RequestOptions::default()).await
+}
+
+/// Sends a Firebase Cloud Messaging (FCM) message with platform specific
+/// options.
+///
+/// This function behaves exactly as [`send_fcm_message_to_target`], but also
+/// sends the given [`PlatformConfig`]. If the config isn't empty, neither a
+/// notification nor a data payload is required, e.g. for a silent APNs push.
+#[instrument(
+    level = "info",
+    skip(target, data_payload, notification, config, token_provider),
+    fields(target = %target.for_log(false))
+)]
+pub async fn send_fcm_message_with_config<T: Serialize>(
+    target: &MessageTarget,
+    notification: Option<FcmNotification>,
+    data_payload: Option<T>,
+    config: &PlatformConfig,
+    token_provider: &(impl TokenProvider + ?Sized),
+    project_id: &str,
+) -> Result<FcmResponse, FcmError> {
+    info!("Sending FCM message to {}", target.for_log(false));
+
+    let payload = build_fcm_payload(target, notification, data_payload, config)?;
+    send_payload(
+        &payload,
+        token_provider,
+        &fcm_url(project_id),
+        RequestOptions::default(),
+    )
+    .await
+}
+
+/// Sends a Firebase Cloud Messaging (FCM) message with platform specific
+/// options to a specific URL.
+///
+/// This function behaves exactly as [`send_fcm_message_with_config`], but
+/// allows specifying a custom FCM URL. This is only useful for testing.
+#[instrument(
+    level = "debug",
+    skip(target, data_payload, notification, config, token_provider),
+    fields(target = %target.for_log(false))
+)]
+pub async fn send_fcm_message_with_config_and_url<T: Serialize>(
+    target: &MessageTarget,
+    notification: Option<FcmNotification>,
+    data_payload: Option<T>,
+    config: &PlatformConfig,
+    token_provider: &(impl TokenProvider + ?Sized),
     fcm_url: &str,
-) -> Result<(), FcmError> {
-    let access_token = {
-        let mut token_manager_guard = token_manager.lock().await;
-        token_manager_guard.get_token().await?
+) -> Result<FcmResponse, FcmError> {
+    let payload = build_fcm_payload(target, notification, data_payload, config)?;
+    send_payload(&payload, token_provider, fcm_url, RequestOptions::default()).await
+}
+
+/// Sends a [`Message`].
+///
+/// This is the most flexible way to send a message, since a [`Message`] can
+/// hold every supported option. The other send functions build a [`Message`]
+/// from their arguments and send it the same way.
+///
+/// # Errors
+///
+/// This function will return an error if the FCM message could not be sent.
+/// On success, it returns the [`FcmResponse`] containing the message ID.
+///
+/// # Cancellation
+///
+/// See [`send_fcm_message`].
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::fs::File;
+///
+/// use oauth_fcm::{create_shared_token_manager, send_message, FcmNotification, Message};
+///
+/// # tokio_test::block_on(async {
+/// let message = Message::builder()
+///     .topic("news")
+///     .notification(FcmNotification::new("Breaking news", "Something happened"))
+///     .build()
+///     .expect("Invalid message");
+/// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+/// send_message(&message, &token_manager, "project_id")
+///     .await
+///     .expect("Error while sending FCM message");
+/// # });
+/// ```
+#[instrument(level = "info", skip(message, token_provider))]
+pub async fn send_message(
+    message: &Message,
+    token_provider: &(impl TokenProvider + ?Sized),
+    project_id: &str,
+) -> Result<FcmResponse, FcmError> {
+    info!("Sending FCM message to {}", message.target().for_log(false));
+
+    send_request(
+        message,
+        token_provider,
+        &fcm_url(project_id),
+        RequestOptions::default(),
+    )
+    .await
+}
+
+/// Sends a [`Message`] to a specific URL.
+///
+/// This function behaves exactly as [`send_message`], but allows specifying a
+/// custom FCM URL. This is only useful for testing.
+#[instrument(level = "debug", skip(message, token_provider))]
+pub async fn send_message_with_url(
+    message: &Message,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+) -> Result<FcmResponse, FcmError> {
+    send_request(message, token_provider, fcm_url, RequestOptions::default()).await
+}
+
+/// Sends a prebuilt FCM message.
+///
+/// This is an escape hatch for fields, which aren't supported by [`Message`]
+/// yet. `message` is either the `message` object of the FCM v1 API, which is
+/// sent verbatim as `{"message": message}`, or the whole request body
+/// containing the `message` field, e.g. to set `validate_only`.
+///
+/// Only basic checks are done before the message is sent: it must be a JSON
+/// object with exactly one of `token`, `topic` or `condition`. Everything else
+/// is validated by FCM. The response and errors are handled exactly as for
+/// [`send_message`].
+///
+/// # Errors
+///
+/// Returns `InvalidRawMessage` if the message is not an object,
+/// `InvalidMessageTarget` if it has no or multiple targets, or any error of
+/// [`send_message`].
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::fs::File;
+///
+/// use oauth_fcm::{create_shared_token_manager, send_fcm_raw_message};
+/// use serde_json::json;
+///
+/// # tokio_test::block_on(async {
+/// let message = json!({
+///     "token": "device_token",
+///     "notification": { "title": "Sale", "body": "Everything is 50% off" },
+///     "fcm_options": { "analytics_label": "sale" },
+/// });
+/// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+/// send_fcm_raw_message(message, &token_manager, "project_id")
+///     .await
+///     .expect("Error while sending FCM message");
+/// # });
+/// ```
+#[instrument(level = "info", skip(message, token_provider))]
+pub async fn send_fcm_raw_message(
+    message: serde_json::Value,
+    token_provider: &(impl TokenProvider + ?Sized),
+    project_id: &str,
+) -> Result<FcmResponse, FcmError> {
+    send_fcm_raw_message_with_url(message, token_provider, &fcm_url(project_id)).await
+}
+
+/// Sends a prebuilt FCM message to a specific URL.
+///
+/// This function behaves exactly as [`send_fcm_raw_message`], but allows
+/// specifying a custom FCM URL. This is only useful for testing.
+#[instrument(level = "debug", skip(message, token_provider))]
+pub async fn send_fcm_raw_message_with_url(
+    message: serde_json::Value,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+) -> Result<FcmResponse, FcmError> {
+    let (payload, target) = raw_request_body(message)?;
+    info!("Sending raw FCM message to {}", target.for_log(false));
+
+    send_payload(&payload, token_provider, fcm_url, RequestOptions::default()).await
+}
+
+/// Wraps a raw message into a request body, unless it already is one, and
+/// returns the body with the target of the message.
+///
+/// The `/topics/` prefix of a topic is removed, as for built messages.
+fn raw_request_body(
+    message: serde_json::Value,
+) -> Result<(serde_json::Value, MessageTarget), FcmError> {
+    let mut body = match message {
+        serde_json::Value::Object(ref object) if object.contains_key("message") => message,
+        serde_json::Value::Object(_) => json!({ "message": message }),
+        _ => {
+            return Err(FcmError::InvalidRawMessage(
+                "the message is not a JSON object",
+            ))
+        }
+    };
+    let Some(message) = body["message"].as_object() else {
+        return Err(FcmError::InvalidRawMessage(
+            "the field `message` is not a JSON object",
+        ));
     };
 
-    let client = reqwest::Client::new();
+    let mut targets = [
+        ("token", MessageTarget::Token as fn(String) -> MessageTarget),
+        ("topic", MessageTarget::Topic),
+        ("condition", MessageTarget::Condition),
+    ]
+    .into_iter()
+    .filter_map(|(field, target)| Some(target(message.get(field)?.as_str()?.to_string())));
 
-    let payload = create_payload(device_token, notification, data_payload)?;
+    match (targets.next(), targets.next()) {
+        (Some(MessageTarget::Topic(topic)), None) => {
+            let topic = topic_name(&topic)?.to_string();
+            body["message"]["topic"] = topic.as_str().into();
+            Ok((body, MessageTarget::Topic(topic)))
+        }
+        (Some(target), None) => {
+            if let MessageTarget::Token(token) = &target {
+                validate_device_token(token)?;
+            }
+            Ok((body, target))
+        }
+        (None, _) => Err(FcmError::InvalidMessageTarget(
+            "no token, topic or condition is set",
+        )),
+        (Some(_), Some(_)) => Err(FcmError::InvalidMessageTarget(
+            "only one of token, topic or condition can be set",
+        )),
+    }
+}
+
+/// The options of a client, which apply to each FCM request.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RequestOptions<'a> {
+    /// Limits the duration of each FCM request.
+    pub timeout: Option<Duration>,
+    /// Acquired before each FCM request.
+    pub rate_limiter: Option<&'a RateLimiter>,
+    /// Held during each FCM request.
+    pub in_flight: Option<&'a InFlightLimit>,
+    /// Run around each FCM request.
+    pub interceptors: &'a [SharedInterceptor],
+    /// Sent as `x-goog-user-project` header.
+    pub quota_project_id: Option<&'a HeaderValue>,
+    /// Retries transient failures of the whole send.
+    pub retry: Option<&'a RetryConfig>,
+}
 
+/// Sends the request for a [`Message`].
+///
+/// All send functions end up here. Transient failures are retried if
+/// `options.retry` is set, and every attempt applies all options.
+pub async fn send_request(
+    message: &Message,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+    options: RequestOptions<'_>,
+) -> Result<FcmResponse, FcmError> {
+    let payload = message.to_request_body();
+    let started = Instant::now();
+    let mut retry_info = RetryInfo::new();
+    let mut attempt = 1;
+
+    loop {
+        debug!(attempt = attempt, "Sending FCM message");
+        let result = send_payload(&payload, token_provider, fcm_url, options).await;
+        let Some(retry) = options.retry else {
+            return result;
+        };
+        let error = match result {
+            Ok(response) => return Ok(response),
+            Err(error) => error,
+        };
+
+        retry_info.record(attempt, &error, started.elapsed());
+        if !error.is_retryable() || attempt >= retry.max_attempts {
+            return Err(retry_info.attach_to(error));
+        }
+
+        let delay = error.retry_after().map_or_else(
+            || retry.backoff(attempt),
+            |delay| delay.min(retry.max_backoff),
+        );
+        retry_info.last_delay = delay;
+        warn!(
+            attempt = attempt,
+            delay_ms = delay.as_millis(),
+            error = %error,
+            "FCM send failed with a transient error, retrying"
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Sends a request body to FCM and reads the response.
+async fn send_payload(
+    payload: &serde_json::Value,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+    options: RequestOptions<'_>,
+) -> Result<FcmResponse, FcmError> {
+    // A `SharedTokenManager` is neither locked across a token refresh nor the
+    // FCM request, so other sends aren't blocked and a cancelled send can't
+    // leave the token manager locked.
     debug!("Requesting access token");
+    let access_token = token_provider.get_token().await?;
+    let transport = token_provider.http_transport().await;
+
+    let mut res =
+        post_message(transport.as_ref(), fcm_url, &access_token, payload, options).await?;
+
+    // FCM rejects tokens, which were revoked or are expired due to clock
+    // drift, even though the token manager still considers them valid. A new
+    // token fixes this, so the request is retried once.
+    if res.status == 401 {
+        if let Some(access_token) = token_provider.refresh_rejected_token(&access_token).await? {
+            warn!("FCM rejected the access token, retrying once with a new token");
+            res =
+                post_message(transport.as_ref(), fcm_url, &access_token, payload, options).await?;
+        }
+    }
 
-    let res = client
-        .post(fcm_url)
-        .bearer_auth(access_token)
-        .json(&payload)
-        .send()
-        .await
-        .map_err(NetworkError::SendRequestError)
-        .map_fcm_err()?;
+    read_response(&res)
+}
 
-    if res.status().is_success() {
+/// Reads the response of an FCM send request.
+pub fn read_response(res: &HttpResponse) -> Result<FcmResponse, FcmError> {
+    if res.is_success() {
         debug!("FCM message sent successfully");
-        Ok(())
+        Ok(FcmResponse::from_body(&res.text()))
+    } else {
+        let text = res.text();
+        log_fcm_error_response(res.status, &text);
+        Err(fcm_response_error(
+            res.status,
+            text,
+            retry_after(&res.headers),
+        ))
+    }
+}
+
+/// Sends a [`Message`] and retries transient failures.
+///
+/// This function behaves exactly as [`send_message`], but sends the message
+/// again if the send failed with an error for which
+/// [`FcmError::is_retryable`] returns `true`, until `retry.max_attempts` is
+/// reached. The last error is returned if all attempts fail.
+///
+/// Note that a retried message may be delivered twice, e.g. if the response
+/// of a successful send got lost.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::fs::File;
+///
+/// use oauth_fcm::{create_shared_token_manager, send_message_with_retry, FcmNotification, Message, RetryConfig};
+///
+/// # tokio_test::block_on(async {
+/// let message = Message::builder()
+///     .token("device_token")
+///     .notification(FcmNotification::new("Test Title", "Test Body"))
+///     .build()
+///     .expect("Invalid message");
+/// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+/// send_message_with_retry(&message, &token_manager, "project_id", &RetryConfig::default())
+///     .await
+///     .expect("Error while sending FCM message");
+/// # });
+/// ```
+#[instrument(level = "info", skip(message, token_provider, retry))]
+pub async fn send_message_with_retry(
+    message: &Message,
+    token_provider: &(impl TokenProvider + ?Sized),
+    project_id: &str,
+    retry: &RetryConfig,
+) -> Result<FcmResponse, FcmError> {
+    info!("Sending FCM message to {}", message.target().for_log(false));
+
+    send_message_with_retry_and_url(message, token_provider, &fcm_url(project_id), retry).await
+}
+
+/// Sends a [`Message`] to a specific URL and retries transient failures.
+///
+/// This function behaves exactly as [`send_message_with_retry`], but allows
+/// specifying a custom FCM URL. This is only useful for testing.
+#[instrument(level = "debug", skip(message, token_provider, retry))]
+pub async fn send_message_with_retry_and_url(
+    message: &Message,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+    retry: &RetryConfig,
+) -> Result<FcmResponse, FcmError> {
+    let options = RequestOptions {
+        retry: Some(retry),
+        ..RequestOptions::default()
+    };
+    send_request(message, token_provider, fcm_url, options).await
+}
+
+pub async fn post_message(
+    transport: &dyn HttpTransport,
+    fcm_url: &str,
+    access_token: &str,
+    payload: &serde_json::Value,
+    options: RequestOptions<'_>,
+) -> Result<HttpResponse, FcmError> {
+    let mut request = HttpRequest::post_json(fcm_url, payload)?
+        .bearer_auth(access_token)?
+        .timeout(options.timeout);
+    if let Some(quota_project_id) = options.quota_project_id {
+        request
+            .headers
+            .insert(QUOTA_PROJECT_HEADER, quota_project_id.clone());
+    }
+
+    if let Some(rate_limiter) = options.rate_limiter {
+        rate_limiter.acquire().await;
+    }
+    let _permit = match options.in_flight {
+        Some(in_flight) => Some(in_flight.acquire().await?),
+        None => None,
+    };
+    let transport = Intercepted {
+        transport,
+        interceptors: options.interceptors,
+    };
+    transport.send(request).await.map_fcm_err()
+}
+
+/// Returns the send URL for the project, which can be redirected with
+/// [`FCM_ENDPOINT_OVERRIDE`](crate::FCM_ENDPOINT_OVERRIDE).
+pub fn fcm_url(project_id: &str) -> String {
+    FcmEndpoint::from_env(project_id).send_url()
+}
+
+fn log_fcm_error_response(status: u16, text: &str) {
+    if let Ok(response) = serde_json::from_str::<GoogleRpcErrorResponse>(text) {
+        let error = response.error;
+        error!(
+            http.status = status,
+            fcm.status = %error.status,
+            fcm.error_code = error.fcm_error_code().unwrap_or_default(),
+            fcm.message = %error.message,
+            "FCM message send successfully, but server returned an error"
+        );
+        debug!(http.status = status, body = %text, "FCM error response body");
     } else {
-        let status = res.status().as_u16();
-        let text = res
-            .text()
-            .await
-            .map_err(NetworkError::ResponseError)
-            .map_fcm_err()?;
         error!(
             "FCM message send successfully, but server returned an error. Status: {}, Response: {}",
             status, text
         );
-        Err(NetworkError::ServerError(status, Some(text))).map_fcm_err()
     }
 }
 
-fn create_payload<T: Serialize>(
-    device_token: &str,
+/// Builds the request body, which is sent to FCM for the given arguments.
+///
+/// The send functions taking these arguments, like [`send_fcm_message`] and
+/// [`send_fcm_message_with_config`], post exactly this body, so it can be used
+/// to test the JSON a service sends without a server. The message is
+/// validated as by [`MessageBuilder::build`](crate::MessageBuilder::build).
+///
+/// For a [`Message`], use [`Message::to_request_body`].
+///
+/// # Errors
+///
+/// Returns an error if the message is invalid, see
+/// [`MessageBuilder::build`](crate::MessageBuilder::build).
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::build_fcm_payload;
+/// use oauth_fcm::FcmNotification;
+/// use oauth_fcm::MessageTarget;
+/// use oauth_fcm::PlatformConfig;
+/// use serde_json::json;
+///
+/// let payload = build_fcm_payload(
+///     &MessageTarget::Token("device_token".to_string()),
+///     Some(FcmNotification::new("Sale", "Everything is 50% off")),
+///     Some(json!({ "sale_id": "42" })),
+///     &PlatformConfig::default(),
+/// )
+/// .expect("Invalid message");
+///
+/// assert_eq!(
+///     payload,
+///     json!({
+///         "message": {
+///             "token": "device_token",
+///             "notification": { "title": "Sale", "body": "Everything is 50% off" },
+///             "data": { "sale_id": "42" },
+///         }
+///     })
+/// );
+/// ```
+pub fn build_fcm_payload<T: Serialize>(
+    target: &MessageTarget,
     notification: Option<FcmNotification>,
     data_payload: Option<T>,
+    config: &PlatformConfig,
 ) -> Result<serde_json::Value, FcmError> {
-    let payload = match (notification, data_payload) {
-        (Some(notification), Some(data_payload)) => {
-            let data = serde_json::to_value(data_payload).map_err(FcmError::SerializationError)?;
-            json!({
-                "message": {
-                    "token": device_token,
-                    "notification": {
-                        "title": notification.title,
-                        "body": notification.body
-                    },
-                    "data": data
-                }
-            })
-        }
-        (None, Some(data_payload)) => {
-            let data = serde_json::to_value(data_payload).map_err(FcmError::SerializationError)?;
-            json!({
-                "message": {
-                    "token": device_token,
-                    "data": data
-                }
-            })
-        }
-        (Some(notification), None) => json!({
-            "message": {
-                "token": device_token,
-                "notification": {
-                    "title": notification.title,
-                    "body": notification.body
-                }
-            }
-        }),
-        _ => return Err(FcmError::FcmInvalidPayloadError),
-    };
+    create_message(target, notification, data_payload, config)
+        .map(|message| message.to_request_body())
+}
 
-    Ok(payload)
+/// Builds the [`Message`] for the arguments of the legacy send functions.
+///
+/// Empty platform configs are left out, so they don't count as content.
+pub fn create_message<T: Serialize>(
+    target: &MessageTarget,
+    notification: Option<FcmNotification>,
+    data_payload: Option<T>,
+    config: &PlatformConfig,
+) -> Result<Message, FcmError> {
+    let mut builder = Message::builder().target(target.clone());
+    if let Some(notification) = notification {
+        builder = builder.notification(notification);
+    }
+    if let Some(data) = data_payload {
+        builder = builder.data(&data);
+    }
+    if let Some(android) = config
+        .android
+        .as_ref()
+        .filter(|android| !android.is_empty())
+    {
+        builder = builder.android(android.clone());
+    }
+    if let Some(apns) = config.apns.as_ref().filter(|apns| !apns.is_empty()) {
+        builder = builder.apns(apns.clone());
+    }
+    if let Some(webpush) = config
+        .webpush
+        .as_ref()
+        .filter(|webpush| !webpush.is_empty())
+    {
+        builder = builder.webpush(webpush.clone());
+    }
+
+    builder.build()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use proptest::prelude::*;
+    use serde_json::json;
+
     use super::*;
+    use crate::message::MAX_DATA_DEPTH;
+    use crate::message::MAX_DEVICE_TOKEN_LENGTH;
+    use crate::message::MAX_PAYLOAD_SIZE;
+    use crate::ApnsPayload;
+    use crate::Aps;
+    use crate::DataValue;
+
+    fn token(device_token: &str) -> MessageTarget {
+        MessageTarget::Token(device_token.to_string())
+    }
 
     #[tokio::test]
     async fn test_create_payload_with_notification_and_data() {
         let device_token = "test_device_token";
-        let notification = Some(FcmNotification {
-            title: "Test Title".to_string(),
-            body: "Test Body".to_string(),
-        });
+        let notification = Some(FcmNotification::new("Test Title", "Test Body"));
         let data_payload = Some(json!({
             "key": "value"
         }));
 
-        let payload = create_payload(device_token, notification, data_payload).unwrap();
+        let payload = build_fcm_payload(
+            &MessageTarget::Token(device_token.to_string()),
+            notification,
+            data_payload,
+            &PlatformConfig::default(),
+        )
+        .unwrap();
         assert_eq!(payload["message"]["token"], device_token);
         assert_eq!(payload["message"]["notification"]["title"], "Test Title");
         assert_eq!(payload["message"]["notification"]["body"], "Test Body");
@@ -203,19 +931,63 @@ mod tests {
     #[tokio::test]
     async fn test_create_payload_with_only_notification() {
         let device_token = "test_device_token";
-        let notification = Some(FcmNotification {
-            title: "Test Title".to_string(),
-            body: "Test Body".to_string(),
-        });
+        let notification = Some(FcmNotification::new("Test Title", "Test Body"));
         let data_payload: Option<serde_json::Value> = None;
 
-        let payload = create_payload(device_token, notification, data_payload).unwrap();
+        let payload = build_fcm_payload(
+            &MessageTarget::Token(device_token.to_string()),
+            notification,
+            data_payload,
+            &PlatformConfig::default(),
+        )
+        .unwrap();
         assert_eq!(payload["message"]["token"], device_token);
         assert_eq!(payload["message"]["notification"]["title"], "Test Title");
         assert_eq!(payload["message"]["notification"]["body"], "Test Body");
         assert!(payload["message"]["data"].is_null());
     }
 
+    #[test]
+    fn test_create_payload_without_image() {
+        let notification = Some(FcmNotification::new("Test Title", "Test Body"));
+
+        let payload = build_fcm_payload(
+            &token("test_device_token"),
+            notification,
+            None::<serde_json::Value>,
+            &PlatformConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            payload["message"]["notification"],
+            json!({ "title": "Test Title", "body": "Test Body" })
+        );
+    }
+
+    #[test]
+    fn test_create_payload_with_image() {
+        let notification = Some(
+            FcmNotification::new("Test Title", "Test Body")
+                .with_image("https://example.com/image.png"),
+        );
+
+        let payload = build_fcm_payload(
+            &token("test_device_token"),
+            notification,
+            None::<serde_json::Value>,
+            &PlatformConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            payload["message"]["notification"],
+            json!({
+                "title": "Test Title",
+                "body": "Test Body",
+                "image": "https://example.com/image.png"
+            })
+        );
+    }
+
     #[tokio::test]
     async fn test_create_payload_with_only_data() {
         let device_token = "test_device_token";
@@ -224,7 +996,13 @@ mod tests {
             "key": "value"
         }));
 
-        let payload = create_payload(device_token, notification, data_payload).unwrap();
+        let payload = build_fcm_payload(
+            &MessageTarget::Token(device_token.to_string()),
+            notification,
+            data_payload,
+            &PlatformConfig::default(),
+        )
+        .unwrap();
         assert_eq!(payload["message"]["token"], device_token);
         assert!(payload["message"]["notification"].is_null());
         assert_eq!(payload["message"]["data"]["key"], "value");
@@ -245,7 +1023,13 @@ mod tests {
             key2: "value2".to_string(),
         };
 
-        let payload = create_payload(device_token, notification, Some(data_payload)).unwrap();
+        let payload = build_fcm_payload(
+            &MessageTarget::Token(device_token.to_string()),
+            notification,
+            Some(data_payload),
+            &PlatformConfig::default(),
+        )
+        .unwrap();
         assert_eq!(payload["message"]["token"], device_token);
         assert!(payload["message"]["notification"].is_null());
         assert_eq!(payload["message"]["data"]["key1"], "value1");
@@ -258,7 +1042,388 @@ mod tests {
         let notification: Option<FcmNotification> = None;
         let data_payload: Option<serde_json::Value> = None;
 
-        let payload = create_payload(device_token, notification, data_payload);
+        let payload = build_fcm_payload(
+            &MessageTarget::Token(device_token.to_string()),
+            notification,
+            data_payload,
+            &PlatformConfig::default(),
+        );
         assert!(payload.is_err());
     }
+
+    #[test]
+    fn test_fcm_response_from_body() {
+        let body =
+            r#"{"name": "projects/my-project/messages/0:1500415314455276%31bd1c9631bd1c96"}"#;
+
+        let response = FcmResponse::from_body(body);
+        assert_eq!(
+            response.message_id.as_deref(),
+            Some("projects/my-project/messages/0:1500415314455276%31bd1c9631bd1c96")
+        );
+        assert_eq!(response.raw["name"], response.message_id.unwrap());
+    }
+
+    #[test]
+    fn test_fcm_response_from_malformed_body() {
+        let response = FcmResponse::from_body("not json");
+        assert!(response.message_id.is_none());
+        assert!(response.raw.is_null());
+
+        let response = FcmResponse::from_body(r#"{"name": 42}"#);
+        assert!(response.message_id.is_none());
+        assert_eq!(response.raw["name"], 42);
+
+        let response = FcmResponse::from_body("");
+        assert!(response.message_id.is_none());
+    }
+
+    #[test]
+    fn test_create_payload_with_apns_only() {
+        let config = PlatformConfig {
+            apns: Some(ApnsConfig {
+                headers: HashMap::from([("apns-push-type".to_string(), "background".to_string())]),
+                payload: Some(ApnsPayload {
+                    aps: Aps {
+                        content_available: true,
+                        ..Aps::default()
+                    },
+                    ..ApnsPayload::default()
+                }),
+                ..ApnsConfig::default()
+            }),
+            ..PlatformConfig::default()
+        };
+
+        let payload = build_fcm_payload(
+            &token("test_device_token"),
+            None,
+            None::<serde_json::Value>,
+            &config,
+        )
+        .unwrap();
+        assert_eq!(payload["message"]["token"], "test_device_token");
+        assert!(payload["message"].get("notification").is_none());
+        assert!(payload["message"].get("data").is_none());
+        assert_eq!(
+            payload["message"]["apns"]["headers"]["apns-push-type"],
+            "background"
+        );
+        assert_eq!(
+            payload["message"]["apns"]["payload"]["aps"]["content-available"],
+            1
+        );
+    }
+
+    #[test]
+    fn test_create_payload_with_empty_apns_config() {
+        let config = PlatformConfig {
+            android: Some(AndroidConfig::default()),
+            apns: Some(ApnsConfig::default()),
+            webpush: Some(WebpushConfig::default()),
+        };
+
+        let error = build_fcm_payload(
+            &token("test_device_token"),
+            None,
+            None::<serde_json::Value>,
+            &config,
+        )
+        .unwrap_err();
+        assert!(matches!(error, FcmError::FcmInvalidPayloadError));
+    }
+
+    #[test]
+    fn test_create_payload_with_topic() {
+        let target = MessageTarget::Topic("news".to_string());
+        let data_payload = Some(json!({ "key": "value" }));
+
+        let payload =
+            build_fcm_payload(&target, None, data_payload, &PlatformConfig::default()).unwrap();
+        assert_eq!(payload["message"]["topic"], "news");
+        assert!(payload["message"].get("token").is_none());
+        assert_eq!(payload["message"]["data"]["key"], "value");
+    }
+
+    #[test]
+    fn test_create_payload_with_condition() {
+        let condition = "'stock-GOOG' in topics || 'industry-tech' in topics";
+        let target = MessageTarget::Condition(condition.to_string());
+        let data_payload = Some(json!({ "key": "value" }));
+
+        let payload =
+            build_fcm_payload(&target, None, data_payload, &PlatformConfig::default()).unwrap();
+        assert_eq!(payload["message"]["condition"], condition);
+        assert!(payload["message"].get("token").is_none());
+        assert!(payload["message"].get("topic").is_none());
+    }
+
+    #[test]
+    fn test_create_payload_rejects_non_object_data() {
+        let cases = [
+            (json!("just a string"), "a string"),
+            (json!(["key", "value"]), "an array"),
+            (json!(3), "a number"),
+            (json!(true), "a boolean"),
+        ];
+
+        for (data, expected_type) in cases {
+            let error = build_fcm_payload(
+                &token("test_device_token"),
+                None,
+                Some(data),
+                &PlatformConfig::default(),
+            )
+            .unwrap_err();
+            assert!(
+                matches!(error, FcmError::DataPayloadNotAnObject { found_type } if found_type == expected_type)
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_payload_accepts_map_data() {
+        let data_payload = HashMap::from([("key", "value")]);
+
+        let payload = build_fcm_payload(
+            &token("test_device_token"),
+            None,
+            Some(data_payload),
+            &PlatformConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(payload["message"]["data"], json!({ "key": "value" }));
+    }
+
+    #[test]
+    fn test_create_payload_rejects_too_large_payload() {
+        let data_payload = Some(json!({ "key": "x".repeat(MAX_PAYLOAD_SIZE) }));
+
+        let error = build_fcm_payload(
+            &token("test_device_token"),
+            None,
+            data_payload,
+            &PlatformConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            FcmError::PayloadTooLarge { size, limit: MAX_PAYLOAD_SIZE } if size > MAX_PAYLOAD_SIZE
+        ));
+    }
+
+    #[test]
+    fn test_create_payload_rejects_too_deep_payload() {
+        let mut data = json!("value");
+        for _ in 0..MAX_DATA_DEPTH {
+            data = json!({ "key": data });
+        }
+        assert!(Message::builder()
+            .token("test_device_token")
+            .data(&data)
+            .stringify_data(true)
+            .build()
+            .is_ok());
+
+        let data = json!({ "key": data });
+        let error = build_fcm_payload(
+            &token("test_device_token"),
+            None,
+            Some(data),
+            &PlatformConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            FcmError::PayloadTooDeep {
+                limit: MAX_DATA_DEPTH
+            }
+        ));
+    }
+
+    #[test]
+    fn test_create_payload_rejects_control_characters_in_keys() {
+        let data_payload = Some(json!({ "nested": { "bad\nkey": "value" } }));
+
+        let error = build_fcm_payload(
+            &token("test_device_token"),
+            None,
+            data_payload,
+            &PlatformConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, FcmError::InvalidDataKey { key } if key == "bad\nkey"));
+    }
+
+    #[test]
+    fn test_create_payload_with_non_finite_floats() {
+        #[derive(serde::Serialize)]
+        struct FloatData {
+            nan: f64,
+            infinity: f64,
+        }
+
+        let data_payload = FloatData {
+            nan: f64::NAN,
+            infinity: f64::INFINITY,
+        };
+
+        // Non-finite floats serialize as `null`, which FCM doesn't accept.
+        let error = build_fcm_payload(
+            &token("test_device_token"),
+            None,
+            Some(data_payload),
+            &PlatformConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            FcmError::InvalidDataPayload {
+                found_type: "null",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_create_payload_with_binary_data() {
+        use base64::engine::general_purpose::STANDARD;
+        use base64::Engine;
+
+        let bytes: Vec<u8> = (0..=255).cycle().take(1024).collect();
+        let data_payload = HashMap::from([("blob", DataValue::binary(&bytes).unwrap())]);
+
+        let payload = build_fcm_payload(
+            &token("test_device_token"),
+            None,
+            Some(data_payload),
+            &PlatformConfig::default(),
+        )
+        .unwrap();
+        let encoded = payload["message"]["data"]["blob"].as_str().unwrap();
+        assert_eq!(STANDARD.decode(encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_create_payload_size_accounts_for_binary_encoding() {
+        // The 4080 byte encoding of 3060 bytes fits into the limit on its own,
+        // but not together with the rest of the data payload.
+        let data_payload = HashMap::from([("blob", DataValue::binary(&[0; 3060]).unwrap())]);
+
+        let error = build_fcm_payload(
+            &token("test_device_token"),
+            None,
+            Some(data_payload),
+            &PlatformConfig::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, FcmError::PayloadTooLarge { size, .. } if size > 4080));
+    }
+
+    fn arbitrary_json() -> impl Strategy<Value = serde_json::Value> {
+        let leaf = prop_oneof![
+            Just(serde_json::Value::Null),
+            any::<bool>().prop_map(serde_json::Value::from),
+            any::<i64>().prop_map(serde_json::Value::from),
+            any::<f64>().prop_map(serde_json::Value::from),
+            ".{0,64}".prop_map(serde_json::Value::from),
+            (0..8192_usize).prop_map(|len| serde_json::Value::from("x".repeat(len))),
+        ];
+
+        leaf.prop_recursive(48, 256, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(serde_json::Value::from),
+                prop::collection::hash_map(any::<String>(), inner, 0..4)
+                    .prop_map(|map| serde_json::Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    fn depth(value: &serde_json::Value) -> usize {
+        match value {
+            serde_json::Value::Object(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+            serde_json::Value::Array(values) => 1 + values.iter().map(depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn has_control_character_key(value: &serde_json::Value) -> bool {
+        match value {
+            serde_json::Value::Object(map) => map.iter().any(|(key, value)| {
+                key.chars().any(char::is_control) || has_control_character_key(value)
+            }),
+            serde_json::Value::Array(values) => values.iter().any(has_control_character_key),
+            _ => false,
+        }
+    }
+
+    fn has_non_string_value(value: &serde_json::Value) -> bool {
+        value
+            .as_object()
+            .is_some_and(|map| map.values().any(|value| !value.is_string()))
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_create_payload_never_panics(
+            device_token in any::<String>(),
+            title in any::<String>(),
+            data in prop::option::of(arbitrary_json()),
+        ) {
+            let notification = Some(FcmNotification::new(title, String::new()));
+            let result = build_fcm_payload(&MessageTarget::Token(device_token.clone()), notification, data.as_ref(), &PlatformConfig::default());
+
+            match result {
+                Ok(payload) => {
+                    let mut limited = payload["message"].as_object().unwrap().clone();
+                    limited.retain(|key, _| key == "notification" || key == "data");
+                    prop_assert!(serde_json::to_vec(&limited).unwrap().len() <= MAX_PAYLOAD_SIZE);
+                    if let Some(data) = &data {
+                        prop_assert!(depth(data) <= MAX_DATA_DEPTH);
+                        prop_assert!(!has_control_character_key(data));
+                        prop_assert!(data.is_object());
+                        prop_assert!(!has_non_string_value(data));
+                        prop_assert_eq!(&payload["message"]["data"], data);
+                    }
+                }
+                Err(FcmError::InvalidDeviceToken(_)) => {
+                    prop_assert!(
+                        device_token.is_empty()
+                            || device_token.chars().any(|c| c.is_whitespace() || c.is_control())
+                            || device_token.len() > MAX_DEVICE_TOKEN_LENGTH
+                    );
+                }
+                Err(FcmError::PayloadTooLarge { size, limit }) => {
+                    prop_assert_eq!(limit, MAX_PAYLOAD_SIZE);
+                    prop_assert!(size > MAX_PAYLOAD_SIZE);
+                }
+                Err(FcmError::DataPayloadNotAnObject { .. }) => {
+                    prop_assert!(!data.as_ref().unwrap().is_object());
+                }
+                Err(FcmError::PayloadTooDeep { .. }) => {
+                    prop_assert!(depth(data.as_ref().unwrap()) > MAX_DATA_DEPTH);
+                }
+                Err(FcmError::InvalidDataKey { key }) => {
+                    prop_assert!(key.chars().any(char::is_control));
+                }
+                Err(FcmError::InvalidDataPayload { key, .. }) => {
+                    prop_assert!(!data.as_ref().unwrap()[key.as_str()].is_string());
+                }
+                Err(error) => prop_assert!(false, "Unexpected error: {}", error),
+            }
+        }
+
+        #[test]
+        fn proptest_control_characters_in_keys_are_rejected(
+            prefix in "[a-z]{0,8}",
+            control in "\\PC*\\p{Cc}",
+        ) {
+            let key = format!("{prefix}{control}");
+            let data = json!({ key.clone(): "value" });
+
+            let error = build_fcm_payload(&token("test_device_token"), None, Some(data), &PlatformConfig::default()).unwrap_err();
+            let rejected = matches!(error, FcmError::InvalidDataKey { key: rejected } if rejected == key);
+            prop_assert!(rejected);
+        }
+    }
 }
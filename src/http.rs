@@ -0,0 +1,297 @@
+//! The HTTP layer of OAuth, FCM and Instance ID requests.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderValue;
+use reqwest::header::AUTHORIZATION;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use reqwest::Method;
+use serde::Serialize;
+
+use crate::FcmError;
+use crate::NetworkError;
+
+/// The default timeout for establishing a connection to the OAuth or FCM
+/// server.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The default timeout of a whole OAuth or FCM request, from connecting until
+/// the response body was read.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Returns a client with the default timeouts.
+pub fn default_client() -> Client {
+    client_with_timeouts(DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT)
+}
+
+/// Returns a client with the given connect and request timeouts.
+///
+/// # Panics
+///
+/// Panics if the TLS backend can't be initialized, like `Client::new`.
+pub fn client_with_timeouts(connect_timeout: Duration, request_timeout: Duration) -> Client {
+    Client::builder()
+        .connect_timeout(connect_timeout)
+        .timeout(request_timeout)
+        .build()
+        .expect("Failed to create the HTTP client")
+}
+
+/// An HTTP request to the OAuth server, FCM or the Instance ID API.
+///
+/// Requests are created by this crate and sent with an [`HttpTransport`].
+/// The body is already encoded and the `Content-Type` header is set, so a
+/// transport only has to send the request as it is.
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+    /// The HTTP method, e.g. `POST`.
+    pub method: Method,
+    /// The URL including the query.
+    pub url: String,
+    /// The request headers. The `Authorization` header is marked as
+    /// sensitive, so it isn't printed by `Debug`.
+    pub headers: HeaderMap,
+    /// The encoded body, which is empty for `GET` requests.
+    pub body: Vec<u8>,
+    /// The timeout of this request, which overrides the timeout of the
+    /// transport.
+    pub timeout: Option<Duration>,
+}
+
+impl HttpRequest {
+    fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    /// Creates a `GET` request.
+    pub(crate) fn get(url: impl Into<String>) -> Self {
+        Self::new(Method::GET, url)
+    }
+
+    /// Creates a `POST` request with a JSON body.
+    pub(crate) fn post_json(
+        url: impl Into<String>,
+        body: &impl Serialize,
+    ) -> Result<Self, FcmError> {
+        let mut request = Self::new(Method::POST, url);
+        request.body = serde_json::to_vec(body)?;
+        request
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        Ok(request)
+    }
+
+    /// Creates a `POST` request with a form encoded body.
+    pub(crate) fn post_form(url: impl Into<String>, params: &[(&str, &str)]) -> Self {
+        let mut request = Self::new(Method::POST, url);
+        request.body = serde_urlencoded::to_string(params)
+            .unwrap_or_default()
+            .into_bytes();
+        request.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        request
+    }
+
+    /// Adds a header.
+    #[must_use]
+    pub(crate) fn header(mut self, name: &'static str, value: &'static str) -> Self {
+        self.headers.insert(name, HeaderValue::from_static(value));
+        self
+    }
+
+    /// Sets the `Authorization` header to the bearer token.
+    pub(crate) fn bearer_auth(mut self, token: &str) -> Result<Self, FcmError> {
+        let mut value = HeaderValue::from_str(&format!("Bearer {token}"))
+            .map_err(FcmError::InvalidAuthorizationHeader)?;
+        value.set_sensitive(true);
+        self.headers.insert(AUTHORIZATION, value);
+        Ok(self)
+    }
+
+    /// Sets the timeout of this request.
+    #[must_use]
+    pub(crate) const fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// The response to an [`HttpRequest`].
+#[derive(Clone, Debug, Default)]
+pub struct HttpResponse {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The response headers, e.g. `Retry-After`.
+    pub headers: HeaderMap,
+    /// The complete response body.
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Creates a response with the status and body and without headers.
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Adds a header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` isn't a valid header value.
+    #[must_use]
+    pub fn with_header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.insert(
+            name,
+            HeaderValue::from_str(value).expect("Invalid header value"),
+        );
+        self
+    }
+
+    /// Returns `true` for `2xx` responses.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        matches!(self.status, 200..=299)
+    }
+
+    /// Returns the body as text, replacing invalid UTF-8.
+    #[must_use]
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Sends the HTTP requests of this crate.
+///
+/// All OAuth, FCM and Instance ID requests are sent through a transport,
+/// which is set with
+/// [`TokenManager::with_http_transport`](crate::TokenManager::with_http_transport).
+/// The default is [`ReqwestTransport`]. A custom transport can record the
+/// requests and return canned responses, so code sending messages can be
+/// tested without binding a local port.
+///
+/// Only the discovery of [Application Default
+/// Credentials](crate::TokenManager::from_adc) uses `reqwest` directly.
+///
+/// # Example
+///
+/// ```rust
+/// use async_trait::async_trait;
+/// use oauth_fcm::HttpRequest;
+/// use oauth_fcm::HttpResponse;
+/// use oauth_fcm::HttpTransport;
+/// use oauth_fcm::NetworkError;
+///
+/// #[derive(Default)]
+/// struct CapturingTransport {
+///     requests: std::sync::Mutex<Vec<HttpRequest>>,
+/// }
+///
+/// #[async_trait]
+/// impl HttpTransport for CapturingTransport {
+///     async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+///         self.requests.lock().unwrap().push(request);
+///         Ok(HttpResponse::new(
+///             200,
+///             r#"{"name": "projects/p/messages/1"}"#,
+///         ))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    /// Sends the request and reads the whole response.
+    ///
+    /// Unsuccessful statuses are returned as response, not as error. Return
+    /// [`NetworkError::Transport`] if the request couldn't be sent.
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError>;
+}
+
+/// Allows to keep a reference to a transport, e.g. to inspect the recorded
+/// requests.
+#[async_trait]
+impl<T: HttpTransport + ?Sized> HttpTransport for Arc<T> {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        T::send(self, request).await
+    }
+}
+
+/// The default [`HttpTransport`], which sends requests with a
+/// `reqwest::Client`.
+#[derive(Clone, Debug)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    /// Creates a transport, which sends requests with the client.
+    #[must_use]
+    pub const fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// Returns the client of the transport.
+    #[must_use]
+    pub const fn client(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl Default for ReqwestTransport {
+    /// Creates a transport with the default timeouts.
+    fn default() -> Self {
+        Self::new(default_client())
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReqwestTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        let mut builder = self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers)
+            .body(request.body);
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        let response = builder
+            .send()
+            .await
+            .map_err(NetworkError::from_send_error)?;
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let body = match response.bytes().await {
+            Ok(body) => body.to_vec(),
+            // The request took effect at this point, e.g. the message has been
+            // delivered, so a broken body must not turn it into an error.
+            Err(error) if matches!(status, 200..=299) => {
+                warn!("Failed to read success response: {}", error);
+                Vec::new()
+            }
+            Err(error) => return Err(NetworkError::from_response_error(error)),
+        };
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
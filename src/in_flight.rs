@@ -0,0 +1,39 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+use tokio::sync::SemaphorePermit;
+
+use crate::FcmError;
+
+/// Limits the number of concurrent FCM requests of a client and its clones.
+#[derive(Clone, Debug)]
+pub struct InFlightLimit {
+    semaphore: Arc<Semaphore>,
+    /// The maximum time to wait for a permit.
+    acquire_timeout: Option<Duration>,
+}
+
+impl InFlightLimit {
+    /// Allows `limit` concurrent requests, at least 1.
+    pub fn new(limit: usize, acquire_timeout: Option<Duration>) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit.clamp(1, Semaphore::MAX_PERMITS))),
+            acquire_timeout,
+        }
+    }
+
+    /// Waits until fewer than `limit` requests are in flight. The request
+    /// counts as in flight until the permit is dropped.
+    pub async fn acquire(&self) -> Result<SemaphorePermit<'_>, FcmError> {
+        let acquire = self.semaphore.acquire();
+        let permit = match self.acquire_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire)
+                .await
+                .map_err(|_| FcmError::InFlightLimitTimeout(timeout))?,
+            None => acquire.await,
+        };
+        // The semaphore is never closed.
+        Ok(permit.expect("in-flight semaphore closed"))
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use reqwest::Method;
+
+use crate::HttpRequest;
+use crate::HttpResponse;
+use crate::HttpTransport;
+use crate::NetworkError;
+
+/// Hooks, which run around every outbound HTTP request, e.g. to add headers
+/// or to log a correlation ID.
+///
+/// Register an interceptor with
+/// [`FcmClientBuilder::interceptor`](crate::FcmClientBuilder::interceptor) for
+/// the FCM requests and with
+/// [`TokenManager::with_interceptor`](crate::TokenManager::with_interceptor)
+/// for the token requests. Both methods do nothing by default.
+///
+/// Interceptors can change the request, but only observe the response, so
+/// they can't turn a failed request into a successful one.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::FcmInterceptor;
+/// use oauth_fcm::HttpRequest;
+/// use oauth_fcm::ResponseContext;
+/// use reqwest::header::HeaderValue;
+///
+/// struct CorrelationId;
+///
+/// impl FcmInterceptor for CorrelationId {
+///     fn on_request(&self, request: &mut HttpRequest) {
+///         request
+///             .headers
+///             .insert("x-correlation-id", HeaderValue::from_static("4711"));
+///     }
+///
+///     fn on_response(&self, response: &ResponseContext<'_>) {
+///         println!(
+///             "{} {} returned {:?} after {:?}",
+///             response.method,
+///             response.url,
+///             response.status(),
+///             response.latency
+///         );
+///     }
+/// }
+/// ```
+pub trait FcmInterceptor: Send + Sync {
+    /// Called before the request is sent.
+    fn on_request(&self, request: &mut HttpRequest) {
+        let _ = request;
+    }
+
+    /// Called after the response was received or the request failed.
+    fn on_response(&self, response: &ResponseContext<'_>) {
+        let _ = response;
+    }
+}
+
+/// The outcome of a request, see [`FcmInterceptor::on_response`].
+#[derive(Debug)]
+pub struct ResponseContext<'a> {
+    /// The HTTP method of the request.
+    pub method: &'a Method,
+    /// The URL of the request.
+    pub url: &'a str,
+    /// The response, or the error if no response was received.
+    pub result: Result<&'a HttpResponse, &'a NetworkError>,
+    /// The time until the response was received.
+    pub latency: Duration,
+}
+
+impl ResponseContext<'_> {
+    /// Returns the HTTP status of the response, if one was received.
+    #[must_use]
+    pub fn status(&self) -> Option<u16> {
+        self.result.ok().map(|response| response.status)
+    }
+}
+
+/// A registered [`FcmInterceptor`], which can be printed by `Debug`.
+#[derive(Clone)]
+pub struct SharedInterceptor(pub Arc<dyn FcmInterceptor>);
+
+impl std::fmt::Debug for SharedInterceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FcmInterceptor")
+    }
+}
+
+/// A transport, which runs the interceptors around the requests of another
+/// transport.
+pub struct Intercepted<'a> {
+    pub transport: &'a dyn HttpTransport,
+    pub interceptors: &'a [SharedInterceptor],
+}
+
+#[async_trait]
+impl HttpTransport for Intercepted<'_> {
+    async fn send(&self, mut request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        if self.interceptors.is_empty() {
+            return self.transport.send(request).await;
+        }
+
+        for SharedInterceptor(interceptor) in self.interceptors {
+            interceptor.on_request(&mut request);
+        }
+        let method = request.method.clone();
+        let url = request.url.clone();
+
+        let started = Instant::now();
+        let result = self.transport.send(request).await;
+
+        let context = ResponseContext {
+            method: &method,
+            url: &url,
+            result: result.as_ref(),
+            latency: started.elapsed(),
+        };
+        for SharedInterceptor(interceptor) in self.interceptors {
+            interceptor.on_response(&context);
+        }
+        result
+    }
+}
@@ -13,33 +13,170 @@
     clippy::future_not_send
 )]
 
-use std::fmt::Debug;
-use std::io::Read;
-
+pub use android::AndroidConfig;
+pub use android::AndroidFcmOptions;
+pub use android::AndroidNotification;
+pub use apns::ApnsConfig;
+pub use apns::ApnsFcmOptions;
+pub use apns::ApnsPayload;
+pub use apns::Aps;
+pub use apns::ApsAlert;
+pub use audit::AuditEntry;
+pub use audit::AuditOutcome;
+pub use audit::AuditSink;
+pub use audit::InMemoryAuditSink;
+pub use auto_refresh::RefreshHandle;
+#[cfg(feature = "test-util")]
+pub use chaos::ChaosTransport;
+pub use client::FcmClient;
+pub use client::FcmClientBuilder;
+pub use client_config::FcmClientConfig;
+pub use client_config::RateLimitSettings;
+pub use client_config::RetrySettings;
+pub use credentials::CredentialsReader;
+pub use credentials::IntoCredentials;
+pub use credentials::ServiceAccountKey;
+pub use data::DataValue;
+pub use data::ToDataPayload;
+pub use delivery::DeliveryPriority;
+pub use endpoint::FcmEndpoint;
+pub use endpoint::FCM_ENDPOINT_OVERRIDE;
 pub use error::FcmError;
+pub use error::FcmErrorCode;
 pub use error::NetworkError;
+pub use error::SendFailure;
+pub use fcm::build_fcm_payload;
 pub use fcm::send_fcm_message;
+pub use fcm::send_fcm_message_to_target;
+pub use fcm::send_fcm_message_to_target_with_url;
+pub use fcm::send_fcm_message_with_config;
+pub use fcm::send_fcm_message_with_config_and_url;
 pub use fcm::send_fcm_message_with_url;
+pub use fcm::send_fcm_raw_message;
+pub use fcm::send_fcm_raw_message_with_url;
+pub use fcm::send_message;
+pub use fcm::send_message_with_retry;
+pub use fcm::send_message_with_retry_and_url;
+pub use fcm::send_message_with_url;
 pub use fcm::FcmNotification;
+pub use fcm::FcmResponse;
+pub use fcm::PlatformConfig;
+pub use http::HttpRequest;
+pub use http::HttpResponse;
+pub use http::HttpTransport;
+pub use http::ReqwestTransport;
+pub use http::DEFAULT_CONNECT_TIMEOUT;
+pub use http::DEFAULT_REQUEST_TIMEOUT;
+pub use interceptor::FcmInterceptor;
+pub use interceptor::ResponseContext;
+pub use localization::LocalizedNotification;
+pub use localization::NotificationLocKeys;
+pub use message::Message;
+pub use message::MessageBuilder;
+pub use message::MessageTarget;
+pub use message::MAX_PAYLOAD_SIZE;
+pub use multi_project::MultiProjectFcm;
+pub use multicast::send_fcm_localized_multicast;
+pub use multicast::send_fcm_localized_multicast_with_url;
+pub use multicast::send_fcm_multicast;
+pub use multicast::send_fcm_multicast_with_url;
+pub use multicast::send_fcm_stream;
+pub use multicast::send_fcm_stream_with_url;
+pub use multicast::MulticastResult;
+#[cfg(feature = "derive")]
+pub use oauth_fcm_derive::FcmData;
+pub use observer::FcmObserver;
+pub use observer::SendOutcome;
+pub use rate_limit::RateLimiter;
+#[cfg(feature = "test-util")]
+pub use replay::RecordingTransport;
+#[cfg(feature = "test-util")]
+pub use replay::ReplayTransport;
+pub use retry::AttemptError;
+pub use retry::RetryConfig;
+pub use retry::RetryInfo;
+pub use secret::SecretString;
+pub use token_cache::CachedToken;
+pub use token_cache::InMemoryTokenCache;
+pub use token_cache::TokenCache;
+pub use token_manager::get_shared_token;
 pub use token_manager::SharedTokenManager;
 pub use token_manager::TokenManager;
-use tracing::info;
+pub use token_manager::TokenState;
+pub use token_provider::StaticTokenProvider;
+pub use token_provider::TokenProvider;
+pub use token_status::TokenStatus;
+pub use topic::subscribe_to_topic;
+pub use topic::subscribe_to_topic_with_url;
+pub use topic::unsubscribe_from_topic;
+pub use topic::unsubscribe_from_topic_with_url;
+pub use topic::TopicManagementResult;
+pub use topic::MAX_TOPIC_BATCH_SIZE;
 use tracing::instrument;
+pub use validator::PayloadValidator;
+pub use webpush::WebpushConfig;
+pub use webpush::WebpushFcmOptions;
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls-tls")))]
+compile_error!(
+    "oauth_fcm requires a TLS backend. Enable either the `native-tls` (default) or the \
+     `rustls-tls` feature."
+);
 
+#[macro_use]
+mod logging;
+
+mod adc;
+mod android;
+mod apns;
+mod audit;
+mod auto_refresh;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "test-util")]
+mod chaos;
+mod client;
+mod client_config;
+mod credentials;
+mod data;
+mod delivery;
+mod endpoint;
 mod error;
 mod fcm;
+mod http;
+mod in_flight;
+mod interceptor;
+mod localization;
+mod message;
+mod multi_project;
+mod multicast;
+mod observer;
+mod rate_limit;
+#[cfg(feature = "test-util")]
+mod replay;
+mod retry;
+mod secret;
+mod token_cache;
 mod token_manager;
+mod token_provider;
+mod token_status;
+mod topic;
+mod validator;
+#[cfg(feature = "warp")]
+pub mod warp;
+mod webpush;
 
 /// Creates a new `SharedTokenManager`.
 ///
-/// This function is a helper for creating a `SharedTokenManager` from a given
-/// Google credentials location. It creates a new `TokenManager` and wraps it in
+/// This function is a helper for creating a `SharedTokenManager` from the
+/// given Google credentials. It creates a new `TokenManager` and wraps it in
 /// an `Arc<Mutex<_>>` to allow shared, mutable access from multiple threads.
 ///
 /// # Arguments
 ///
-/// * `google_credentials_location` - A string slice that holds the path to the
-///   Google credentials JSON file.
+/// * `credentials` - The Google service account credentials. This can be any
+///   [`IntoCredentials`] source, such as a `File`, a path or the JSON content
+///   itself.
 ///
 /// # Returns
 ///
@@ -59,10 +196,67 @@ mod token_manager;
 /// # }
 /// ```
 #[instrument(level = "info", skip_all)]
-pub fn create_shared_token_manager<T: Read + Debug>(
-    credentials: T,
+pub fn create_shared_token_manager(
+    credentials: impl IntoCredentials,
 ) -> Result<SharedTokenManager, FcmError> {
     info!("Creating shared token manager");
     let manager = TokenManager::new(credentials)?;
     Ok(std::sync::Arc::new(tokio::sync::Mutex::new(manager)))
 }
+
+/// Creates a new `SharedTokenManager` from the Application Default
+/// Credentials.
+///
+/// See [`TokenManager::from_adc`] for the search order.
+///
+/// # Errors
+///
+/// Returns `DefaultCredentialsNotFound` if no credentials were found, or the
+/// error of the key file, if one was found but couldn't be read or parsed.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use oauth_fcm::create_shared_token_manager_from_adc;
+///
+/// # tokio_test::block_on(async {
+/// let shared_token_manager = create_shared_token_manager_from_adc()
+///     .await
+///     .expect("Failed to create SharedTokenManager");
+/// # });
+/// ```
+pub async fn create_shared_token_manager_from_adc() -> Result<SharedTokenManager, FcmError> {
+    let manager = TokenManager::from_adc().await?;
+    Ok(std::sync::Arc::new(tokio::sync::Mutex::new(manager)))
+}
+
+/// Creates a new `SharedTokenManager` from credentials stored in an
+/// environment variable.
+///
+/// The variable can contain the JSON key itself or the base64 encoded JSON
+/// key, e.g. a Kubernetes secret mounted as environment variable. JSON is
+/// detected by its leading `{`.
+///
+/// # Errors
+///
+/// Returns `CredentialsEnvError` if the variable isn't set or isn't valid
+/// unicode. Returns `InvalidCredentials` naming the failed step if the value
+/// isn't valid base64, isn't valid JSON or doesn't contain a valid service
+/// account key.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use oauth_fcm::create_shared_token_manager_from_env;
+///
+/// let shared_token_manager = create_shared_token_manager_from_env("GOOGLE_CREDENTIALS")
+///     .expect("Failed to create SharedTokenManager");
+/// ```
+#[instrument(level = "info", skip_all, fields(var = var_name))]
+pub fn create_shared_token_manager_from_env(
+    var_name: &str,
+) -> Result<SharedTokenManager, FcmError> {
+    info!("Creating shared token manager from environment variable");
+    let manager = TokenManager::new(credentials::key_from_env(var_name)?)?;
+    Ok(std::sync::Arc::new(tokio::sync::Mutex::new(manager)))
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use crate::AndroidConfig;
+use crate::AndroidNotification;
+use crate::ApnsConfig;
+use crate::ApnsPayload;
+use crate::ApsAlert;
+use crate::FcmNotification;
+
+/// A set of translated notifications with a default fallback.
+///
+/// Translations are stored per language tag (e.g. `"pt-BR"`, `"de"`) and
+/// looked up with [`LocalizedNotification::resolve`]. Language tags are
+/// matched case-insensitively and `_` is treated like `-`, so the Android
+/// style `"pt_BR"` resolves the same way as `"pt-BR"`.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::FcmNotification;
+/// use oauth_fcm::LocalizedNotification;
+///
+/// let notification = LocalizedNotification::new(FcmNotification::new("Hello", "World"))
+///     .with_translation("pt", FcmNotification::new("Olá", "Mundo"));
+///
+/// assert_eq!(notification.resolve("pt-BR").title, "Olá");
+/// assert_eq!(notification.resolve("fr").title, "Hello");
+/// ```
+#[derive(Clone)]
+pub struct LocalizedNotification {
+    default: FcmNotification,
+    translations: HashMap<String, FcmNotification>,
+}
+
+impl LocalizedNotification {
+    /// Creates a new `LocalizedNotification` with the given default
+    /// notification and no translations.
+    #[must_use]
+    pub fn new(default: FcmNotification) -> Self {
+        Self {
+            default,
+            translations: HashMap::new(),
+        }
+    }
+
+    /// Adds a translation for the given language tag and returns `self`.
+    #[must_use]
+    pub fn with_translation(mut self, locale: &str, notification: FcmNotification) -> Self {
+        self.insert(locale, notification);
+        self
+    }
+
+    /// Adds a translation for the given language tag.
+    ///
+    /// Returns the previously stored translation for this tag, if any.
+    pub fn insert(
+        &mut self,
+        locale: &str,
+        notification: FcmNotification,
+    ) -> Option<FcmNotification> {
+        self.translations
+            .insert(normalize_locale(locale), notification)
+    }
+
+    /// Returns the default notification.
+    #[must_use]
+    pub const fn default_notification(&self) -> &FcmNotification {
+        &self.default
+    }
+
+    /// Returns the notification best matching the given language tag.
+    ///
+    /// The lookup first tries the exact tag and then removes subtags from the
+    /// end one at a time, so `"zh-Hant-TW"` tries `"zh-hant-tw"`, `"zh-hant"`
+    /// and `"zh"`. If none of them has a translation, the default
+    /// notification is returned.
+    #[must_use]
+    pub fn resolve(&self, locale: &str) -> &FcmNotification {
+        self.resolve_tag(locale)
+            .map_or(&self.default, |tag| &self.translations[tag])
+    }
+
+    /// Returns the language tag of the translation `resolve` returns, or
+    /// `None` for the default notification.
+    pub(crate) fn resolve_tag(&self, locale: &str) -> Option<&str> {
+        let mut tag = normalize_locale(locale);
+
+        loop {
+            if let Some((tag, _)) = self.translations.get_key_value(&tag) {
+                return Some(tag);
+            }
+
+            let index = tag.rfind('-')?;
+            tag.truncate(index);
+        }
+    }
+}
+
+fn normalize_locale(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+/// Localization keys of a notification, whose strings are resolved by the
+/// app.
+///
+/// Set it with [`MessageBuilder::loc_keys`](crate::MessageBuilder::loc_keys),
+/// which sends the keys in the Android notification (`title_loc_key`,
+/// `body_loc_key`, ...) and in the APNs alert (`title-loc-key`, `loc-key`,
+/// ...), so both platforms show the same strings.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::Message;
+/// use oauth_fcm::NotificationLocKeys;
+///
+/// let message = Message::builder()
+///     .token("device_token")
+///     .loc_keys(
+///         NotificationLocKeys::new()
+///             .title("new_message_title", Vec::<String>::new())
+///             .body("new_message_body", ["Alice"]),
+///     )
+///     .build()
+///     .expect("Invalid message");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NotificationLocKeys {
+    title_key: Option<String>,
+    title_args: Vec<String>,
+    body_key: Option<String>,
+    body_args: Vec<String>,
+}
+
+impl NotificationLocKeys {
+    /// Creates an empty set of localization keys.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the key of the title string and its format arguments.
+    #[must_use]
+    pub fn title(
+        mut self,
+        key: impl Into<String>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.title_key = Some(key.into());
+        self.title_args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the key of the body string and its format arguments.
+    #[must_use]
+    pub fn body(
+        mut self,
+        key: impl Into<String>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.body_key = Some(key.into());
+        self.body_args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the keys in the Android notification and the APNs alert, keeping
+    /// their other options.
+    pub(crate) fn apply(&self, android: &mut AndroidConfig, apns: &mut ApnsConfig) {
+        let notification = android
+            .notification
+            .get_or_insert_with(AndroidNotification::default);
+        notification.title_loc_key.clone_from(&self.title_key);
+        notification.title_loc_args.clone_from(&self.title_args);
+        notification.body_loc_key.clone_from(&self.body_key);
+        notification.body_loc_args.clone_from(&self.body_args);
+
+        let alert = apns
+            .payload
+            .get_or_insert_with(ApnsPayload::default)
+            .aps
+            .alert
+            .get_or_insert_with(ApsAlert::default);
+        alert.title_loc_key.clone_from(&self.title_key);
+        alert.title_loc_args.clone_from(&self.title_args);
+        alert.loc_key.clone_from(&self.body_key);
+        alert.loc_args.clone_from(&self.body_args);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(title: &str) -> FcmNotification {
+        FcmNotification::new(title, format!("{title} body"))
+    }
+
+    fn localized() -> LocalizedNotification {
+        LocalizedNotification::new(notification("default"))
+            .with_translation("pt", notification("pt"))
+            .with_translation("pt-BR", notification("pt-BR"))
+            .with_translation("zh", notification("zh"))
+    }
+
+    #[test]
+    fn test_resolve_exact_tag() {
+        assert_eq!(localized().resolve("pt-BR").title, "pt-BR");
+        assert_eq!(localized().resolve("pt").title, "pt");
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_primary_subtag() {
+        assert_eq!(localized().resolve("pt-PT").title, "pt");
+        assert_eq!(localized().resolve("zh-Hant-TW").title, "zh");
+    }
+
+    #[test]
+    fn test_resolve_unknown_locale_uses_default() {
+        assert_eq!(localized().resolve("fr-FR").title, "default");
+        assert_eq!(localized().resolve("").title, "default");
+    }
+
+    #[test]
+    fn test_resolve_ignores_case_and_underscores() {
+        assert_eq!(localized().resolve("PT_br").title, "pt-BR");
+    }
+
+    #[test]
+    fn test_insert_replaces_existing_translation() {
+        let mut localized = localized();
+        let previous = localized.insert("PT", notification("new pt"));
+
+        assert_eq!(previous.unwrap().title, "pt");
+        assert_eq!(localized.resolve("pt-PT").title, "new pt");
+    }
+}
@@ -0,0 +1,51 @@
+//! Internal logging macros.
+//!
+//! Events are emitted through `tracing` by default. With the `log` feature
+//! they are emitted through the `log` facade instead, so call sites don't have
+//! to care about the feature. Structured fields are appended to the message
+//! as `key=value` pairs in that case. Spans created with `#[instrument]` are
+//! always `tracing` spans.
+
+#[cfg(not(feature = "log"))]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        ::tracing::$level!($($arg)+)
+    };
+}
+
+#[cfg(feature = "log")]
+macro_rules! event {
+    ($level:ident, $($($key:ident).+ = $(%)? $value:expr),+ , $message:literal) => {
+        ::log::$level!(
+            concat!($message $(, " ", stringify!($($key).+), "={}")+),
+            $($value),+
+        )
+    };
+    ($level:ident, $($arg:tt)+) => {
+        ::log::$level!($($arg)+)
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        event!(debug, $($arg)+)
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)+) => {
+        event!(info, $($arg)+)
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        event!(warn, $($arg)+)
+    };
+}
+
+macro_rules! error {
+    ($($arg:tt)+) => {
+        event!(error, $($arg)+)
+    };
+}
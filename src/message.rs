@@ -0,0 +1,1898 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::delivery::DeliveryOptions;
+use crate::AndroidConfig;
+use crate::ApnsConfig;
+use crate::DeliveryPriority;
+use crate::FcmError;
+use crate::FcmNotification;
+use crate::NotificationLocKeys;
+use crate::ToDataPayload;
+use crate::WebpushConfig;
+
+/// The maximum size of a serialized FCM message in bytes.
+///
+/// FCM limits the size of the notification and the data payload, so the
+/// target, the platform configs and `validate_only` don't count. See
+/// [`MessageBuilder::max_payload_size`].
+pub const MAX_PAYLOAD_SIZE: usize = 4096;
+
+/// The maximum length of an analytics label.
+const MAX_ANALYTICS_LABEL_LENGTH: usize = 50;
+
+/// The maximum length of a device token. Actual tokens are a few hundred
+/// characters long, so longer ones are certainly malformed.
+pub const MAX_DEVICE_TOKEN_LENGTH: usize = 1024;
+
+/// The maximum nesting depth of objects and arrays in the data payload.
+pub const MAX_DATA_DEPTH: usize = 32;
+
+/// The maximum number of topics in a condition.
+const MAX_CONDITION_TOPICS: usize = 5;
+
+/// Keys, which FCM doesn't accept at the top level of the data payload.
+const RESERVED_DATA_KEYS: &[&str] = &["from", "notification", "message_type"];
+
+/// Prefixes of keys, which FCM doesn't accept at the top level of the data
+/// payload.
+const RESERVED_DATA_KEY_PREFIXES: &[&str] = &["google", "gcm"];
+
+/// The recipient of an FCM message.
+///
+/// FCM accepts exactly one target per message.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum MessageTarget {
+    /// A single device, identified by its registration token.
+    Token(String),
+    /// All devices subscribed to the topic, e.g. `"news"`.
+    Topic(String),
+    /// All devices matching a boolean expression of topics, e.g.
+    /// `"'stock-GOOG' in topics || 'industry-tech' in topics"`.
+    Condition(String),
+}
+
+impl Display for MessageTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Token(token) => write!(f, "device {token}"),
+            Self::Topic(topic) => write!(f, "topic {topic}"),
+            Self::Condition(condition) => write!(f, "condition {condition}"),
+        }
+    }
+}
+
+impl MessageTarget {
+    /// Returns a [`Display`] implementation for log messages, which redacts
+    /// device tokens unless `full_tokens` is set.
+    pub(crate) const fn for_log(&self, full_tokens: bool) -> LoggedTarget<'_> {
+        LoggedTarget {
+            target: self,
+            full_tokens,
+        }
+    }
+}
+
+/// A [`MessageTarget`] as it is logged, see [`MessageTarget::for_log`].
+pub struct LoggedTarget<'a> {
+    target: &'a MessageTarget,
+    full_tokens: bool,
+}
+
+impl Display for LoggedTarget<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.target {
+            MessageTarget::Token(token) if !self.full_tokens => {
+                write!(f, "device {}", redact_token(token))
+            }
+            target => target.fmt(f),
+        }
+    }
+}
+
+/// Redacts a device token for logging.
+///
+/// Device tokens identify a user's device, so only the first and last four
+/// characters are kept. Shorter tokens are redacted completely.
+pub fn redact_token(token: &str) -> String {
+    let chars = token.chars().count();
+    if chars <= 8 {
+        return "…".to_string();
+    }
+
+    let start: String = token.chars().take(4).collect();
+    let end: String = token.chars().skip(chars - 4).collect();
+    format!("{start}…{end}")
+}
+
+/// A validated FCM message.
+///
+/// Create it with [`Message::builder`] and send it with
+/// [`send_message`](crate::send_message). The message serializes to the
+/// `message` object of the FCM v1 API.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::FcmNotification;
+/// use oauth_fcm::Message;
+///
+/// let message = Message::builder()
+///     .topic("news")
+///     .notification(FcmNotification::new("Breaking news", "Something happened"))
+///     .data(&serde_json::json!({ "article_id": "42" }))
+///     .build()
+///     .expect("Invalid message");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Message {
+    #[serde(flatten)]
+    target: MessageTarget,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notification: Option<FcmNotification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    android: Option<AndroidConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    apns: Option<ApnsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    webpush: Option<WebpushConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fcm_options: Option<FcmOptions>,
+    #[serde(skip)]
+    validate_only: bool,
+    /// The size limit of the notification and data payload, checked again
+    /// after the client defaults are merged.
+    #[serde(skip)]
+    max_payload_size: usize,
+}
+
+/// The platform independent `fcm_options` of a message.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+struct FcmOptions {
+    analytics_label: String,
+}
+
+impl Message {
+    /// Returns a new [`MessageBuilder`].
+    #[must_use]
+    pub fn builder() -> MessageBuilder {
+        MessageBuilder::default()
+    }
+
+    /// Returns the recipient of the message.
+    #[must_use]
+    pub const fn target(&self) -> &MessageTarget {
+        &self.target
+    }
+
+    /// Returns the notification of the message.
+    #[must_use]
+    pub const fn notification(&self) -> Option<&FcmNotification> {
+        self.notification.as_ref()
+    }
+
+    /// Returns the serialized data payload of the message.
+    #[must_use]
+    pub const fn data(&self) -> Option<&serde_json::Value> {
+        self.data.as_ref()
+    }
+
+    /// Returns the Android options of the message.
+    #[must_use]
+    pub const fn android(&self) -> Option<&AndroidConfig> {
+        self.android.as_ref()
+    }
+
+    /// Returns the APNs options of the message.
+    #[must_use]
+    pub const fn apns(&self) -> Option<&ApnsConfig> {
+        self.apns.as_ref()
+    }
+
+    /// Returns the Webpush options of the message.
+    #[must_use]
+    pub const fn webpush(&self) -> Option<&WebpushConfig> {
+        self.webpush.as_ref()
+    }
+
+    /// Returns the analytics label of the message.
+    #[must_use]
+    pub fn analytics_label(&self) -> Option<&str> {
+        self.fcm_options
+            .as_ref()
+            .map(|options| options.analytics_label.as_str())
+    }
+
+    /// Returns `true` if the message is only validated by FCM, but not
+    /// delivered.
+    #[must_use]
+    pub const fn validate_only(&self) -> bool {
+        self.validate_only
+    }
+
+    /// Returns the body of the FCM send request for this message.
+    ///
+    /// This is exactly what the send functions post, so it can be used to test
+    /// the JSON a service sends without a server. `validate_only` is a field
+    /// of the request, so it is set next to the message instead of inside it.
+    #[must_use]
+    pub fn to_request_body(&self) -> serde_json::Value {
+        if self.validate_only {
+            json!({ "validate_only": true, "message": self })
+        } else {
+            json!({ "message": self })
+        }
+    }
+
+    /// Runs all client-side checks of [`MessageBuilder::build`] on the
+    /// message and returns every violation instead of only the first one.
+    ///
+    /// A built message is valid, so this is mainly useful for messages with
+    /// a changed size limit or for tests. To get all violations of a message
+    /// while building it, see
+    /// [`MessageBuilder::report_all_violations`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the violations in the order of the checks of
+    /// [`MessageBuilder::build`]. [`FcmError::kind`] names the kind of each
+    /// violation, `Display` describes it.
+    pub fn validate(&self) -> Result<(), Vec<FcmError>> {
+        let mut violations: Vec<_> = validate_target(&self.target).err().into_iter().collect();
+        violations.extend(self.content_violations(true));
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Returns the violations of all checks except the target.
+    ///
+    /// `check_data` is `false` if the data payload was already checked before
+    /// its values were converted to strings.
+    fn content_violations(&self, check_data: bool) -> Vec<FcmError> {
+        let mut violations = Vec::new();
+        if let Some(data) = &self.data {
+            if data.is_object() {
+                if check_data {
+                    violations.extend(validate_data(data).err());
+                }
+                violations.extend(validate_data_values(data).err());
+            } else {
+                violations.push(FcmError::DataPayloadNotAnObject {
+                    found_type: json_type(data),
+                });
+            }
+        }
+
+        violations.extend(
+            validate_platform_labels(
+                self.android.as_ref(),
+                self.apns.as_ref(),
+                self.webpush.as_ref(),
+            )
+            .err(),
+        );
+        if let Some(options) = &self.fcm_options {
+            violations.extend(validate_analytics_label(&options.analytics_label).err());
+        }
+
+        let has_platform_config = self
+            .android
+            .as_ref()
+            .is_some_and(|android| !android.is_empty())
+            || self.apns.as_ref().is_some_and(|apns| !apns.is_empty())
+            || self
+                .webpush
+                .as_ref()
+                .is_some_and(|webpush| !webpush.is_empty());
+        if self.notification.is_none() && self.data.is_none() && !has_platform_config {
+            violations.push(FcmError::FcmInvalidPayloadError);
+        }
+
+        violations.extend(self.validate_payload_size().err());
+        violations
+    }
+
+    fn validate_payload_size(&self) -> Result<(), FcmError> {
+        let size = self.payload_size()?;
+        if size > self.max_payload_size {
+            return Err(FcmError::PayloadTooLarge {
+                size,
+                limit: self.max_payload_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the size of the part of the message, which FCM limits: the
+    /// serialized notification and data payload.
+    fn payload_size(&self) -> Result<usize, serde_json::Error> {
+        #[derive(Serialize)]
+        struct Payload<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            notification: Option<&'a FcmNotification>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            data: Option<&'a serde_json::Value>,
+        }
+
+        let payload = Payload {
+            notification: self.notification.as_ref(),
+            data: self.data.as_ref(),
+        };
+        Ok(serde_json::to_vec(&payload)?.len())
+    }
+}
+
+/// A builder for [`Message`].
+///
+/// All validation happens in [`build`](Self::build).
+#[derive(Debug, Default)]
+pub struct MessageBuilder {
+    targets: Vec<MessageTarget>,
+    notification: Option<FcmNotification>,
+    data: Option<Result<serde_json::Value, serde_json::Error>>,
+    android: Option<AndroidConfig>,
+    apns: Option<ApnsConfig>,
+    webpush: Option<WebpushConfig>,
+    loc_keys: Option<NotificationLocKeys>,
+    delivery: DeliveryOptions,
+    analytics_label: Option<String>,
+    validate_only: bool,
+    stringify_data: bool,
+    max_payload_size: Option<usize>,
+    report_all_violations: bool,
+}
+
+impl MessageBuilder {
+    /// Sends the message to the given target.
+    #[must_use]
+    pub fn target(mut self, target: MessageTarget) -> Self {
+        self.targets.push(target);
+        self
+    }
+
+    /// Sends the message to a single device.
+    #[must_use]
+    pub fn token(self, token: impl Into<String>) -> Self {
+        self.target(MessageTarget::Token(token.into()))
+    }
+
+    /// Sends the message to all devices subscribed to the topic.
+    ///
+    /// The `/topics/` prefix is optional and removed by [`build`](Self::build).
+    #[must_use]
+    pub fn topic(self, topic: impl Into<String>) -> Self {
+        self.target(MessageTarget::Topic(topic.into()))
+    }
+
+    /// Sends the message to all devices matching the condition.
+    #[must_use]
+    pub fn condition(self, condition: impl Into<String>) -> Self {
+        self.target(MessageTarget::Condition(condition.into()))
+    }
+
+    /// Sets the notification.
+    #[must_use]
+    pub fn notification(mut self, notification: FcmNotification) -> Self {
+        self.notification = Some(notification);
+        self
+    }
+
+    /// Sets the data payload. This can be any type that implements the
+    /// `Serialize` trait. Serialization errors are returned by
+    /// [`build`](Self::build).
+    ///
+    /// The payload must serialize to a JSON object, e.g. a map or a struct.
+    /// FCM only accepts strings as data values, so other values are rejected
+    /// unless [`stringify_data`](Self::stringify_data) is set.
+    #[must_use]
+    pub fn data<T: Serialize + ?Sized>(mut self, data: &T) -> Self {
+        self.data = Some(serde_json::to_value(data));
+        self
+    }
+
+    /// Sets the data payload rendered by [`ToDataPayload`], e.g. a type
+    /// deriving `FcmData`.
+    ///
+    /// The rendered payload only contains strings, so it never needs
+    /// [`stringify_data`](Self::stringify_data).
+    #[must_use]
+    pub fn data_payload<T: ToDataPayload + ?Sized>(mut self, data: &T) -> Self {
+        let payload = data
+            .to_data_payload()
+            .into_iter()
+            .map(|(key, value)| (key, serde_json::Value::String(value)))
+            .collect();
+        self.data = Some(Ok(serde_json::Value::Object(payload)));
+        self
+    }
+
+    /// Sets the Android options.
+    #[must_use]
+    pub fn android(mut self, android: AndroidConfig) -> Self {
+        self.android = Some(android);
+        self
+    }
+
+    /// Sets the APNs options.
+    #[must_use]
+    pub fn apns(mut self, apns: ApnsConfig) -> Self {
+        self.apns = Some(apns);
+        self
+    }
+
+    /// Sets the Webpush options.
+    #[must_use]
+    pub fn webpush(mut self, webpush: WebpushConfig) -> Self {
+        self.webpush = Some(webpush);
+        self
+    }
+
+    /// Sends a notification, whose strings are resolved by the app from the
+    /// given localization keys.
+    ///
+    /// The keys are set in the Android and the APNs options, see
+    /// [`NotificationLocKeys`]. Other Android and APNs options are kept.
+    #[must_use]
+    pub fn loc_keys(mut self, loc_keys: NotificationLocKeys) -> Self {
+        self.loc_keys = Some(loc_keys);
+        self
+    }
+
+    /// Discards the message if it can't be delivered within `expires_in`,
+    /// e.g. because the device is offline.
+    ///
+    /// Sets the Android `ttl`, the `apns-expiration` header, which is the
+    /// point in time `expires_in` after [`build`](Self::build), and the
+    /// Webpush `TTL` header. A zero duration delivers the message now or
+    /// never. Durations above 28 days, the maximum of FCM and the Webpush
+    /// services, are clamped. Values set in the platform configs are kept.
+    #[must_use]
+    pub const fn expires_in(mut self, expires_in: Duration) -> Self {
+        self.delivery.expires_in = Some(expires_in);
+        self
+    }
+
+    /// Sets the delivery priority.
+    ///
+    /// Sets the Android `priority`, the `apns-priority` header (`10` for
+    /// [`High`](DeliveryPriority::High), `5` for
+    /// [`Normal`](DeliveryPriority::Normal)) and the Webpush `Urgency` header.
+    /// Values set in the platform configs are kept. APNs only accepts
+    /// priority `5` for background pushes, whose `aps` dictionary has only
+    /// `content-available`, so they are sent with `5` and a warning is logged.
+    #[must_use]
+    pub const fn priority(mut self, priority: DeliveryPriority) -> Self {
+        self.delivery.priority = Some(priority);
+        self
+    }
+
+    /// Sets the number shown in the badge of the app icon.
+    ///
+    /// Sets the Android `notification_count` and the APNs `badge`, where `0`
+    /// removes the badge. Webpush has no badge count, so it isn't changed.
+    /// Values set in the platform configs are kept.
+    #[must_use]
+    pub const fn badge(mut self, badge: u32) -> Self {
+        self.delivery.badge = Some(badge);
+        self
+    }
+
+    /// Lets messages with the same group replace each other while the device
+    /// is offline, so only the last one is delivered.
+    ///
+    /// Sets the Android `collapse_key`, the `apns-collapse-id` header and the
+    /// Webpush `Topic` header. Values set in the platform configs are kept.
+    ///
+    /// The group must not be empty. APNs limits it to 64 bytes, Webpush to 32
+    /// letters, digits, `-` or `_`. These limits are only checked for the
+    /// platforms, whose config is set with [`apns`](Self::apns) or
+    /// [`webpush`](Self::webpush), and [`build`](Self::build) returns
+    /// `InvalidCollapseGroup` if the group violates them. A platform without a
+    /// config doesn't get a group, which violates its limits.
+    #[must_use]
+    pub fn collapse_group(mut self, group: impl Into<String>) -> Self {
+        self.delivery.collapse_group = Some(group.into());
+        self
+    }
+
+    /// Labels the message in the delivery metrics of the Firebase console.
+    ///
+    /// The label must have 1 to 50 characters, which are letters, digits or
+    /// one of `-_.~%`. Otherwise [`build`](Self::build) returns
+    /// `InvalidAnalyticsLabel`. The label can be overridden per platform with
+    /// the `fcm_options` of the platform configs, which are validated the same
+    /// way.
+    #[must_use]
+    pub fn analytics_label(mut self, label: impl Into<String>) -> Self {
+        self.analytics_label = Some(label.into());
+        self
+    }
+
+    /// Only validates the message instead of delivering it (dry run).
+    ///
+    /// FCM checks the target and the payload as usual and returns a message
+    /// ID, but no notification is sent. This is useful for staging
+    /// environments and for checking stored device tokens.
+    #[must_use]
+    pub const fn validate_only(mut self, validate_only: bool) -> Self {
+        self.validate_only = validate_only;
+        self
+    }
+
+    /// Converts data values, which aren't strings, to strings instead of
+    /// rejecting them.
+    ///
+    /// Numbers and booleans are converted to their JSON representation, e.g.
+    /// `42` to `"42"`, nested objects and arrays are encoded as JSON strings
+    /// and `null` values are left out.
+    #[must_use]
+    pub const fn stringify_data(mut self, stringify_data: bool) -> Self {
+        self.stringify_data = stringify_data;
+        self
+    }
+
+    /// Sets the size limit of the serialized notification and data payload in
+    /// bytes.
+    ///
+    /// The limit defaults to [`MAX_PAYLOAD_SIZE`], the limit of FCM. A
+    /// different limit is only useful for testing, e.g. against an emulator.
+    #[must_use]
+    pub const fn max_payload_size(mut self, limit: usize) -> Self {
+        self.max_payload_size = Some(limit);
+        self
+    }
+
+    /// Makes [`build`](Self::build) run all checks and return every
+    /// violation as `InvalidMessage`, instead of returning the first
+    /// violation.
+    #[must_use]
+    pub const fn report_all_violations(mut self, report_all_violations: bool) -> Self {
+        self.report_all_violations = report_all_violations;
+        self
+    }
+
+    /// Validates and builds the message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if
+    ///
+    /// * not exactly one target is set (`InvalidMessageTarget`),
+    /// * the device token is empty, contains whitespace or is too long
+    ///   (`InvalidDeviceToken`),
+    /// * the topic name is empty or contains characters other than
+    ///   `[a-zA-Z0-9-_.~%]` (`InvalidTopicName`),
+    /// * the condition is malformed or has more than 5 topics
+    ///   (`InvalidCondition`),
+    /// * the data payload could not be serialized (`SerializationError`),
+    /// * the data payload is invalid (`DataPayloadNotAnObject`,
+    ///   `PayloadTooDeep`, `InvalidDataKey`, `ReservedDataKey`,
+    ///   `InvalidDataPayload`),
+    /// * an analytics label is invalid (`InvalidAnalyticsLabel`),
+    /// * the collapse group is invalid for a platform config
+    ///   (`InvalidCollapseGroup`), see
+    ///   [`collapse_group`](Self::collapse_group),
+    /// * neither a notification, a data payload nor a non-empty platform config
+    ///   is set (`FcmInvalidPayloadError`) or
+    /// * the serialized notification and data payload exceed the size limit
+    ///   (`PayloadTooLarge`), see [`max_payload_size`](Self::max_payload_size).
+    ///
+    /// The first violation is returned, or all of them as `InvalidMessage` if
+    /// [`report_all_violations`](Self::report_all_violations) is set.
+    pub fn build(self) -> Result<Message, FcmError> {
+        let mut violations = Vec::new();
+        let mut targets = self.targets;
+        let target = match targets.len() {
+            1 => Some(targets.remove(0)),
+            0 => {
+                violations.push(FcmError::InvalidMessageTarget(
+                    "no token, topic or condition is set",
+                ));
+                None
+            }
+            _ => {
+                violations.push(FcmError::InvalidMessageTarget(
+                    "only one of token, topic or condition can be set",
+                ));
+                None
+            }
+        };
+        let target = target.map(|target| match validate_target(&target) {
+            Ok(()) => match target {
+                MessageTarget::Topic(topic) => {
+                    MessageTarget::Topic(topic_name(&topic).unwrap_or(&topic).to_string())
+                }
+                target => target,
+            },
+            Err(error) => {
+                violations.push(error);
+                target
+            }
+        });
+
+        let mut data = self.data.transpose().unwrap_or_else(|error| {
+            violations.push(error.into());
+            None
+        });
+        if let Some(data) = data.as_mut().filter(|_| self.stringify_data) {
+            // Stringifying hides nested keys, so they are checked before.
+            if data.is_object() {
+                violations.extend(validate_data(data).err());
+            }
+            stringify_data_values(data);
+        }
+
+        violations.extend(
+            self.delivery
+                .validate(self.apns.is_some(), self.webpush.is_some())
+                .err(),
+        );
+
+        let mut android = self.android;
+        let mut apns = self.apns;
+        if let Some(loc_keys) = &self.loc_keys {
+            loc_keys.apply(
+                android.get_or_insert_with(AndroidConfig::default),
+                apns.get_or_insert_with(ApnsConfig::default),
+            );
+        }
+        let mut webpush = self.webpush;
+        self.delivery.apply(&mut android, &mut apns, &mut webpush);
+
+        let message = Message {
+            // Without a valid target, the message is only built for the
+            // remaining checks.
+            target: target.unwrap_or_else(|| MessageTarget::Token(String::new())),
+            notification: self.notification,
+            data,
+            android,
+            apns,
+            webpush,
+            fcm_options: self
+                .analytics_label
+                .map(|analytics_label| FcmOptions { analytics_label }),
+            validate_only: self.validate_only,
+            max_payload_size: self.max_payload_size.unwrap_or(MAX_PAYLOAD_SIZE),
+        };
+        violations.extend(message.content_violations(!self.stringify_data));
+
+        if violations.is_empty() {
+            Ok(message)
+        } else if self.report_all_violations {
+            Err(FcmError::InvalidMessage(violations))
+        } else {
+            Err(violations.remove(0))
+        }
+    }
+}
+
+/// Platform configs, data and options, which a client merges into every
+/// message it sends.
+#[derive(Clone, Debug, Default)]
+pub struct MessageDefaults {
+    pub android: Option<AndroidConfig>,
+    pub apns: Option<ApnsConfig>,
+    pub webpush: Option<WebpushConfig>,
+    pub data: serde_json::Map<String, serde_json::Value>,
+    /// Forces `validate_only` on every message.
+    pub validate_only: bool,
+}
+
+impl MessageDefaults {
+    fn is_empty(&self) -> bool {
+        self.android.is_none()
+            && self.apns.is_none()
+            && self.webpush.is_none()
+            && self.data.is_empty()
+            && !self.validate_only
+    }
+}
+
+impl Message {
+    /// Returns the message with the client defaults merged into its platform
+    /// configs, see [`AndroidConfig::merge_defaults`],
+    /// [`ApnsConfig::merge_defaults`] and [`WebpushConfig::merge_defaults`],
+    /// and into its data payload, keeping the keys of the message. With
+    /// `defaults.validate_only`, the message is only validated by FCM.
+    ///
+    /// The merged data payload is validated like in
+    /// [`MessageBuilder::build`].
+    pub(crate) fn with_defaults(
+        &self,
+        defaults: &MessageDefaults,
+    ) -> Result<Cow<'_, Self>, FcmError> {
+        if defaults.is_empty() {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        let mut message = self.clone();
+        message.validate_only |= defaults.validate_only;
+        merge_config(
+            &mut message.android,
+            defaults.android.as_ref(),
+            AndroidConfig::merge_defaults,
+        );
+        merge_config(
+            &mut message.apns,
+            defaults.apns.as_ref(),
+            ApnsConfig::merge_defaults,
+        );
+        merge_config(
+            &mut message.webpush,
+            defaults.webpush.as_ref(),
+            WebpushConfig::merge_defaults,
+        );
+        validate_platform_labels(
+            message.android.as_ref(),
+            message.apns.as_ref(),
+            message.webpush.as_ref(),
+        )?;
+
+        if !defaults.data.is_empty() {
+            let data = message
+                .data
+                .get_or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+            if let serde_json::Value::Object(map) = data {
+                for (key, value) in &defaults.data {
+                    map.entry(key.clone()).or_insert_with(|| value.clone());
+                }
+            }
+            validate_data(data)?;
+            validate_data_values(data)?;
+            message.validate_payload_size()?;
+        }
+        Ok(Cow::Owned(message))
+    }
+}
+
+fn merge_config<T: Clone>(config: &mut Option<T>, defaults: Option<&T>, merge: fn(&mut T, &T)) {
+    match (config.as_mut(), defaults) {
+        (Some(config), Some(defaults)) => merge(config, defaults),
+        (None, Some(defaults)) => *config = Some(defaults.clone()),
+        (_, None) => {}
+    }
+}
+
+/// Rejects device tokens, which are obviously malformed, so they fail before
+/// a token is requested instead of with a `400` from FCM.
+pub fn validate_device_token(token: &str) -> Result<(), FcmError> {
+    let reason = if token.is_empty() {
+        "the device token is empty".to_string()
+    } else if token.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        "the device token contains whitespace or control characters".to_string()
+    } else if token.len() > MAX_DEVICE_TOKEN_LENGTH {
+        format!(
+            "the device token is {} bytes long, which exceeds the limit of \
+             {MAX_DEVICE_TOKEN_LENGTH} bytes",
+            token.len()
+        )
+    } else {
+        return Ok(());
+    };
+
+    Err(FcmError::InvalidDeviceToken(reason))
+}
+
+/// Checks the device token, topic name or condition of the target.
+fn validate_target(target: &MessageTarget) -> Result<(), FcmError> {
+    match target {
+        MessageTarget::Token(token) => validate_device_token(token),
+        MessageTarget::Topic(topic) => topic_name(topic).map(drop),
+        MessageTarget::Condition(condition) => validate_condition(condition),
+    }
+}
+
+/// Checks a condition for the mistakes FCM rejects: no topic or more than
+/// [`MAX_CONDITION_TOPICS`] topics, unbalanced quotes or parentheses and
+/// invalid topic names, e.g. `"'news' in topics && !('sports' in topics)"`.
+fn validate_condition(condition: &str) -> Result<(), FcmError> {
+    let invalid = |reason| {
+        Err(FcmError::InvalidCondition {
+            condition: condition.to_string(),
+            reason,
+        })
+    };
+
+    // Every second part is a quoted topic name.
+    let parts: Vec<_> = condition.split('\'').collect();
+    if parts.len() % 2 == 0 {
+        return invalid("a quote is not closed");
+    }
+    let topics = parts.iter().skip(1).step_by(2);
+    if topics.len() == 0 {
+        return invalid("it contains no topic");
+    }
+    if topics.len() > MAX_CONDITION_TOPICS {
+        return invalid("it contains more than 5 topics");
+    }
+    if topics.clone().any(|topic| topic_name(topic).is_err()) {
+        return invalid("it contains an invalid topic name");
+    }
+
+    let mut depth = 0_usize;
+    for c in parts.iter().step_by(2).flat_map(|part| part.chars()) {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return invalid("the parentheses are unbalanced"),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return invalid("the parentheses are unbalanced");
+    }
+    Ok(())
+}
+
+/// Returns the topic name without the optional `/topics/` prefix, after
+/// checking it against the format accepted by FCM, `^[a-zA-Z0-9-_.~%]+$`.
+///
+/// The v1 API expects the bare name, the Instance ID API the prefixed one.
+pub fn topic_name(topic: &str) -> Result<&str, FcmError> {
+    let name = topic.strip_prefix("/topics/").unwrap_or(topic);
+    let character = name.chars().find(|&c| !is_name_char(c));
+
+    if name.is_empty() || character.is_some() {
+        return Err(FcmError::InvalidTopicName {
+            topic: topic.to_string(),
+            character,
+        });
+    }
+    Ok(name)
+}
+
+/// Returns `true` for the characters allowed in topic names and analytics
+/// labels.
+const fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~' | '%')
+}
+
+/// Checks an analytics label against the format accepted by FCM,
+/// `^[a-zA-Z0-9-_.~%]{1,50}$`.
+fn validate_analytics_label(label: &str) -> Result<(), FcmError> {
+    let valid =
+        (1..=MAX_ANALYTICS_LABEL_LENGTH).contains(&label.len()) && label.chars().all(is_name_char);
+
+    if valid {
+        Ok(())
+    } else {
+        Err(FcmError::InvalidAnalyticsLabel {
+            label: label.to_string(),
+        })
+    }
+}
+
+/// Checks the analytics labels of the platform configs.
+fn validate_platform_labels(
+    android: Option<&AndroidConfig>,
+    apns: Option<&ApnsConfig>,
+    webpush: Option<&WebpushConfig>,
+) -> Result<(), FcmError> {
+    let labels = [
+        android.and_then(|android| android.fcm_options.as_ref()?.analytics_label.as_ref()),
+        apns.and_then(|apns| apns.fcm_options.as_ref()?.analytics_label.as_ref()),
+        webpush.and_then(|webpush| webpush.fcm_options.as_ref()?.analytics_label.as_ref()),
+    ];
+    for label in labels.into_iter().flatten() {
+        validate_analytics_label(label)?;
+    }
+    Ok(())
+}
+
+fn is_reserved_data_key(key: &str) -> bool {
+    RESERVED_DATA_KEYS.contains(&key)
+        || RESERVED_DATA_KEY_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
+
+/// Checks the nesting depth and the keys of the data payload.
+///
+/// The payload is walked iteratively, so deeply nested payloads can't overflow
+/// the stack.
+fn validate_data(data: &serde_json::Value) -> Result<(), FcmError> {
+    let mut pending = vec![(data, 1)];
+
+    while let Some((value, depth)) = pending.pop() {
+        match value {
+            serde_json::Value::Object(map) => {
+                if depth > MAX_DATA_DEPTH {
+                    return Err(FcmError::PayloadTooDeep {
+                        limit: MAX_DATA_DEPTH,
+                    });
+                }
+                for (key, value) in map {
+                    if key.chars().any(char::is_control) {
+                        return Err(FcmError::InvalidDataKey { key: key.clone() });
+                    }
+                    if depth == 1 && is_reserved_data_key(key) {
+                        return Err(FcmError::ReservedDataKey { key: key.clone() });
+                    }
+                    pending.push((value, depth + 1));
+                }
+            }
+            serde_json::Value::Array(values) => {
+                if depth > MAX_DATA_DEPTH {
+                    return Err(FcmError::PayloadTooDeep {
+                        limit: MAX_DATA_DEPTH,
+                    });
+                }
+                pending.extend(values.iter().map(|value| (value, depth + 1)));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that all values of the data payload are strings.
+fn validate_data_values(data: &serde_json::Value) -> Result<(), FcmError> {
+    let serde_json::Value::Object(map) = data else {
+        return Ok(());
+    };
+
+    match map.iter().find(|(_, value)| !value.is_string()) {
+        Some((key, value)) => Err(FcmError::InvalidDataPayload {
+            key: key.clone(),
+            found_type: json_type(value),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Converts all values of the data payload to strings, see
+/// [`MessageBuilder::stringify_data`].
+fn stringify_data_values(data: &mut serde_json::Value) {
+    let serde_json::Value::Object(map) = data else {
+        return;
+    };
+
+    map.retain(|_, value| !value.is_null());
+    for value in map.values_mut() {
+        if !value.is_string() {
+            *value = serde_json::Value::String(value.to_string());
+        }
+    }
+}
+
+const fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification() -> FcmNotification {
+        FcmNotification::new("Test Title", "Test Body")
+    }
+
+    #[test]
+    fn test_builder_serializes_message() {
+        let message = Message::builder()
+            .token("test_device_token")
+            .notification(notification())
+            .data(&json!({ "key": "value" }))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({
+                "token": "test_device_token",
+                "notification": { "title": "Test Title", "body": "Test Body" },
+                "data": { "key": "value" }
+            })
+        );
+        assert_eq!(
+            message.target(),
+            &MessageTarget::Token("test_device_token".to_string())
+        );
+    }
+
+    #[test]
+    fn test_loc_keys_are_set_for_android_and_apns() {
+        let message = Message::builder()
+            .token("test_device_token")
+            .loc_keys(
+                NotificationLocKeys::new()
+                    .title("title_key", ["Alice"])
+                    .body("body_key", ["Alice", "3"]),
+            )
+            .apns(ApnsConfig {
+                headers: std::collections::HashMap::from([(
+                    "apns-priority".to_string(),
+                    "10".to_string(),
+                )]),
+                ..ApnsConfig::default()
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({
+                "token": "test_device_token",
+                "android": {
+                    "notification": {
+                        "title_loc_key": "title_key",
+                        "title_loc_args": ["Alice"],
+                        "body_loc_key": "body_key",
+                        "body_loc_args": ["Alice", "3"]
+                    }
+                },
+                "apns": {
+                    "headers": { "apns-priority": "10" },
+                    "payload": {
+                        "aps": {
+                            "alert": {
+                                "title-loc-key": "title_key",
+                                "title-loc-args": ["Alice"],
+                                "loc-key": "body_key",
+                                "loc-args": ["Alice", "3"]
+                            }
+                        }
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_loc_keys_without_args() {
+        let message = Message::builder()
+            .topic("news")
+            .notification(notification())
+            .loc_keys(NotificationLocKeys::new().body("body_key", Vec::<String>::new()))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(message.android()).unwrap(),
+            json!({ "notification": { "body_loc_key": "body_key" } })
+        );
+        assert_eq!(
+            serde_json::to_value(message.apns()).unwrap(),
+            json!({ "payload": { "aps": { "alert": { "loc-key": "body_key" } } } })
+        );
+    }
+
+    #[test]
+    fn test_expires_in_is_set_for_all_platforms() {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let message = Message::builder()
+            .token("test_device_token")
+            .notification(notification())
+            .expires_in(Duration::from_secs(3600))
+            .build()
+            .unwrap();
+
+        let body = serde_json::to_value(&message).unwrap();
+        assert_eq!(body["android"], json!({ "ttl": "3600s" }));
+        assert_eq!(body["webpush"], json!({ "headers": { "TTL": "3600" } }));
+        let expiration: u64 = body["apns"]["headers"]["apns-expiration"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((now + 3600..=now + 3601).contains(&expiration));
+    }
+
+    #[test]
+    fn test_expires_in_zero_and_clamping() {
+        let message = Message::builder()
+            .token("test_device_token")
+            .notification(notification())
+            .expires_in(Duration::ZERO)
+            .build()
+            .unwrap();
+        let body = serde_json::to_value(&message).unwrap();
+        assert_eq!(body["android"], json!({ "ttl": "0s" }));
+        assert_eq!(
+            body["apns"],
+            json!({ "headers": { "apns-expiration": "0" } })
+        );
+        assert_eq!(body["webpush"], json!({ "headers": { "TTL": "0" } }));
+
+        let message = Message::builder()
+            .token("test_device_token")
+            .notification(notification())
+            .expires_in(Duration::from_secs(365 * 24 * 60 * 60))
+            .build()
+            .unwrap();
+        let body = serde_json::to_value(&message).unwrap();
+        assert_eq!(body["android"], json!({ "ttl": "2419200s" }));
+        assert_eq!(body["webpush"], json!({ "headers": { "TTL": "2419200" } }));
+    }
+
+    #[test]
+    fn test_expires_in_keeps_platform_values() {
+        let message = Message::builder()
+            .token("test_device_token")
+            .notification(notification())
+            .android(AndroidConfig {
+                ttl: Some(Duration::from_secs(60)),
+                ..AndroidConfig::default()
+            })
+            .apns(ApnsConfig {
+                headers: std::collections::HashMap::from([(
+                    "apns-expiration".to_string(),
+                    "1700000000".to_string(),
+                )]),
+                ..ApnsConfig::default()
+            })
+            .webpush(WebpushConfig {
+                headers: std::collections::HashMap::from([("ttl".to_string(), "60".to_string())]),
+                ..WebpushConfig::default()
+            })
+            .expires_in(Duration::from_secs(3600))
+            .build()
+            .unwrap();
+
+        let body = serde_json::to_value(&message).unwrap();
+        assert_eq!(body["android"], json!({ "ttl": "60s" }));
+        assert_eq!(
+            body["apns"],
+            json!({ "headers": { "apns-expiration": "1700000000" } })
+        );
+        assert_eq!(body["webpush"], json!({ "headers": { "ttl": "60" } }));
+    }
+
+    #[test]
+    fn test_priority_is_set_for_all_platforms() {
+        for (priority, android, apns, urgency) in [
+            (DeliveryPriority::High, "HIGH", "10", "high"),
+            (DeliveryPriority::Normal, "NORMAL", "5", "normal"),
+        ] {
+            let message = Message::builder()
+                .token("test_device_token")
+                .notification(notification())
+                .priority(priority)
+                .build()
+                .unwrap();
+
+            let body = serde_json::to_value(&message).unwrap();
+            assert_eq!(body["android"], json!({ "priority": android }));
+            assert_eq!(
+                body["apns"],
+                json!({ "headers": { "apns-priority": apns } })
+            );
+            assert_eq!(
+                body["webpush"],
+                json!({ "headers": { "Urgency": urgency } })
+            );
+        }
+    }
+
+    #[test]
+    fn test_priority_keeps_platform_values() {
+        let message = Message::builder()
+            .token("test_device_token")
+            .notification(notification())
+            .android(AndroidConfig {
+                priority: Some(DeliveryPriority::Normal),
+                ..AndroidConfig::default()
+            })
+            .apns(ApnsConfig {
+                headers: std::collections::HashMap::from([(
+                    "apns-priority".to_string(),
+                    "5".to_string(),
+                )]),
+                ..ApnsConfig::default()
+            })
+            .priority(DeliveryPriority::High)
+            .build()
+            .unwrap();
+
+        let body = serde_json::to_value(&message).unwrap();
+        assert_eq!(body["android"], json!({ "priority": "NORMAL" }));
+        assert_eq!(body["apns"], json!({ "headers": { "apns-priority": "5" } }));
+        assert_eq!(body["webpush"], json!({ "headers": { "Urgency": "high" } }));
+    }
+
+    #[test]
+    fn test_high_priority_background_push_uses_apns_priority_5() {
+        let message = Message::builder()
+            .token("test_device_token")
+            .apns(ApnsConfig {
+                payload: Some(crate::ApnsPayload {
+                    aps: crate::Aps {
+                        content_available: true,
+                        ..crate::Aps::default()
+                    },
+                    ..crate::ApnsPayload::default()
+                }),
+                ..ApnsConfig::default()
+            })
+            .priority(DeliveryPriority::High)
+            .build()
+            .unwrap();
+
+        let body = serde_json::to_value(&message).unwrap();
+        assert_eq!(body["android"], json!({ "priority": "HIGH" }));
+        assert_eq!(body["apns"]["headers"], json!({ "apns-priority": "5" }));
+    }
+
+    #[test]
+    fn test_badge_is_set_for_android_and_apns() {
+        for badge in [0, 7] {
+            let message = Message::builder()
+                .token("test_device_token")
+                .notification(notification())
+                .badge(badge)
+                .build()
+                .unwrap();
+
+            let body = serde_json::to_value(&message).unwrap();
+            assert_eq!(
+                body["android"],
+                json!({ "notification": { "notification_count": badge } })
+            );
+            assert_eq!(
+                body["apns"],
+                json!({ "payload": { "aps": { "badge": badge } } })
+            );
+            assert!(body.get("webpush").is_none());
+        }
+    }
+
+    #[test]
+    fn test_badge_keeps_platform_values() {
+        let message = Message::builder()
+            .token("test_device_token")
+            .notification(notification())
+            .apns(ApnsConfig {
+                payload: Some(crate::ApnsPayload {
+                    aps: crate::Aps {
+                        badge: Some(0),
+                        ..crate::Aps::default()
+                    },
+                    ..crate::ApnsPayload::default()
+                }),
+                ..ApnsConfig::default()
+            })
+            .badge(7)
+            .build()
+            .unwrap();
+
+        let body = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            body["android"],
+            json!({ "notification": { "notification_count": 7 } })
+        );
+        assert_eq!(
+            body["apns"],
+            json!({ "payload": { "aps": { "badge": 0 } } })
+        );
+    }
+
+    #[test]
+    fn test_collapse_group_is_set_for_all_platforms() {
+        let message = Message::builder()
+            .token("test_device_token")
+            .notification(notification())
+            .collapse_group("score_update")
+            .build()
+            .unwrap();
+
+        let body = serde_json::to_value(&message).unwrap();
+        assert_eq!(body["android"], json!({ "collapse_key": "score_update" }));
+        assert_eq!(
+            body["apns"],
+            json!({ "headers": { "apns-collapse-id": "score_update" } })
+        );
+        assert_eq!(
+            body["webpush"],
+            json!({ "headers": { "Topic": "score_update" } })
+        );
+    }
+
+    #[test]
+    fn test_collapse_group_is_validated_for_present_platforms() {
+        let builder = || {
+            Message::builder()
+                .token("test_device_token")
+                .notification(notification())
+                .collapse_group("score update")
+        };
+
+        let error = builder()
+            .webpush(WebpushConfig::default())
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(&error, FcmError::InvalidCollapseGroup { group, reason } if group == "score update" && reason.contains("Webpush")),
+            "{error:?}"
+        );
+
+        let body = serde_json::to_value(builder().build().unwrap()).unwrap();
+        assert_eq!(body["android"], json!({ "collapse_key": "score update" }));
+        assert_eq!(
+            body["apns"],
+            json!({ "headers": { "apns-collapse-id": "score update" } })
+        );
+        assert!(body.get("webpush").is_none());
+    }
+
+    #[test]
+    fn test_analytics_label_is_sent_in_fcm_options() {
+        let message = Message::builder()
+            .topic("news")
+            .notification(notification())
+            .analytics_label("summer_sale-2024.v1~%20")
+            .build()
+            .unwrap();
+
+        assert_eq!(message.analytics_label(), Some("summer_sale-2024.v1~%20"));
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({
+                "topic": "news",
+                "notification": { "title": "Test Title", "body": "Test Body" },
+                "fcm_options": { "analytics_label": "summer_sale-2024.v1~%20" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_analytics_label_length_boundaries() {
+        let build = |label: String| {
+            Message::builder()
+                .topic("news")
+                .notification(notification())
+                .analytics_label(label)
+                .build()
+        };
+
+        assert!(build("a".to_string()).is_ok());
+        assert!(build("a".repeat(MAX_ANALYTICS_LABEL_LENGTH)).is_ok());
+        assert!(matches!(
+            build("a".repeat(MAX_ANALYTICS_LABEL_LENGTH + 1)),
+            Err(FcmError::InvalidAnalyticsLabel { .. })
+        ));
+        assert!(matches!(
+            build(String::new()),
+            Err(FcmError::InvalidAnalyticsLabel { .. })
+        ));
+    }
+
+    #[test]
+    fn test_analytics_label_rejects_invalid_characters() {
+        for label in ["summer sale", "sale!", "ü", "a/b", "label\n"] {
+            let error = Message::builder()
+                .topic("news")
+                .notification(notification())
+                .analytics_label(label)
+                .build()
+                .unwrap_err();
+
+            assert!(
+                matches!(&error, FcmError::InvalidAnalyticsLabel { label: invalid } if invalid == label),
+                "{label:?} was not rejected: {error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_platform_analytics_labels_are_validated() {
+        let error = Message::builder()
+            .topic("news")
+            .notification(notification())
+            .android(AndroidConfig {
+                fcm_options: Some(crate::AndroidFcmOptions {
+                    analytics_label: Some("android label".to_string()),
+                }),
+                ..AndroidConfig::default()
+            })
+            .build()
+            .unwrap_err();
+        assert!(matches!(error, FcmError::InvalidAnalyticsLabel { .. }));
+
+        let message = Message::builder()
+            .topic("news")
+            .notification(notification())
+            .apns(ApnsConfig {
+                fcm_options: Some(crate::ApnsFcmOptions {
+                    analytics_label: Some("ios".to_string()),
+                    image: None,
+                }),
+                ..ApnsConfig::default()
+            })
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(message.apns()).unwrap(),
+            json!({ "fcm_options": { "analytics_label": "ios" } })
+        );
+    }
+
+    #[test]
+    fn test_request_body_with_validate_only() {
+        let message = Message::builder()
+            .topic("news")
+            .notification(notification())
+            .validate_only(true)
+            .build()
+            .unwrap();
+
+        assert!(message.validate_only());
+        assert_eq!(
+            message.to_request_body(),
+            json!({
+                "validate_only": true,
+                "message": {
+                    "topic": "news",
+                    "notification": { "title": "Test Title", "body": "Test Body" }
+                }
+            })
+        );
+        assert!(serde_json::to_value(&message)
+            .unwrap()
+            .get("validate_only")
+            .is_none());
+    }
+
+    #[test]
+    fn test_builder_without_target() {
+        let error = Message::builder()
+            .notification(notification())
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(error, FcmError::InvalidMessageTarget(_)));
+    }
+
+    #[test]
+    fn test_builder_with_multiple_targets() {
+        let error = Message::builder()
+            .token("test_device_token")
+            .topic("news")
+            .notification(notification())
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(error, FcmError::InvalidMessageTarget(_)));
+        assert_eq!(
+            error.to_string(),
+            "Invalid FCM message target: only one of token, topic or condition can be set"
+        );
+    }
+
+    #[test]
+    fn test_builder_removes_topic_prefix() {
+        for topic in ["news", "/topics/news"] {
+            let message = Message::builder()
+                .topic(topic)
+                .notification(notification())
+                .build()
+                .unwrap();
+
+            assert_eq!(message.target(), &MessageTarget::Topic("news".to_string()));
+            assert_eq!(message.to_request_body()["message"]["topic"], "news");
+        }
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_topic_names() {
+        let build = |topic: &str| {
+            Message::builder()
+                .topic(topic)
+                .notification(notification())
+                .build()
+                .unwrap_err()
+        };
+
+        for (topic, invalid) in [
+            ("", None),
+            ("/topics/", None),
+            ("breaking news", Some(' ')),
+            ("/topics/nachrichten-äöü", Some('ä')),
+            ("news/sports", Some('/')),
+            ("topics/news", Some('/')),
+            ("ニュース", Some('ニ')),
+        ] {
+            let error = build(topic);
+            assert!(
+                matches!(
+                    &error,
+                    FcmError::InvalidTopicName { topic: name, character }
+                        if name == topic && *character == invalid
+                ),
+                "{topic:?}: {error:?}"
+            );
+        }
+
+        assert_eq!(
+            build("/topics/ä").to_string(),
+            "Topic name \"/topics/ä\" must be non-empty and only contain letters, digits and \
+             `-_.~%`, but contains 'ä'"
+        );
+    }
+
+    #[test]
+    fn test_topic_name_accepts_all_allowed_characters() {
+        assert_eq!(topic_name("aZ09-_.~%").unwrap(), "aZ09-_.~%");
+        assert_eq!(topic_name("/topics/aZ09-_.~%").unwrap(), "aZ09-_.~%");
+    }
+
+    #[test]
+    fn test_builder_rejects_empty_device_token() {
+        let error = Message::builder()
+            .token("")
+            .notification(notification())
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(error, FcmError::InvalidDeviceToken(_)));
+        assert_eq!(
+            error.to_string(),
+            "Invalid device token: the device token is empty"
+        );
+    }
+
+    #[test]
+    fn test_builder_rejects_device_token_with_whitespace() {
+        for token in [
+            " device_token",
+            "device_token\n",
+            "device token",
+            "device\ttoken",
+        ] {
+            let error = Message::builder()
+                .token(token)
+                .notification(notification())
+                .build()
+                .unwrap_err();
+
+            assert!(
+                matches!(error, FcmError::InvalidDeviceToken(_)),
+                "{token:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn test_builder_rejects_too_long_device_token() {
+        let build = |token: String| {
+            Message::builder()
+                .token(token)
+                .notification(notification())
+                .build()
+        };
+
+        assert!(build("a".repeat(MAX_DEVICE_TOKEN_LENGTH)).is_ok());
+        let error = build("a".repeat(100_000)).unwrap_err();
+        assert!(matches!(error, FcmError::InvalidDeviceToken(_)));
+    }
+
+    #[test]
+    fn test_builder_without_content() {
+        let error = Message::builder().topic("news").build().unwrap_err();
+
+        assert!(matches!(error, FcmError::FcmInvalidPayloadError));
+    }
+
+    #[test]
+    fn test_builder_with_empty_apns_payload_has_no_content() {
+        let error = Message::builder()
+            .topic("news")
+            .apns(ApnsConfig {
+                payload: Some(crate::ApnsPayload::default()),
+                ..ApnsConfig::default()
+            })
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(error, FcmError::FcmInvalidPayloadError));
+    }
+
+    #[test]
+    fn test_builder_with_unserializable_data() {
+        let data = std::collections::HashMap::from([((1, 2), "value")]);
+
+        let error = Message::builder()
+            .topic("news")
+            .data(&data)
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(error, FcmError::SerializationError(_)));
+    }
+
+    #[test]
+    fn test_builder_rejects_non_string_data_values() {
+        let cases = [
+            (json!({ "count": 42 }), "count", "a number"),
+            (json!({ "enabled": true }), "enabled", "a boolean"),
+            (json!({ "reply_to": null }), "reply_to", "null"),
+            (json!({ "user": { "id": "1" } }), "user", "an object"),
+            (json!({ "tags": ["a", "b"] }), "tags", "an array"),
+        ];
+
+        for (data, expected_key, expected_type) in cases {
+            let error = Message::builder()
+                .topic("news")
+                .data(&data)
+                .build()
+                .unwrap_err();
+
+            assert!(
+                matches!(
+                    &error,
+                    FcmError::InvalidDataPayload { key, found_type }
+                        if key == expected_key && *found_type == expected_type
+                ),
+                "unexpected error for {data}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_builder_rejects_reserved_data_keys() {
+        for key in ["from", "message_type", "google.sender", "gcm.notification"] {
+            let error = Message::builder()
+                .topic("news")
+                .data(&json!({ key: "value" }))
+                .build()
+                .unwrap_err();
+
+            assert!(
+                matches!(&error, FcmError::ReservedDataKey { key: found } if found == key),
+                "unexpected error for {key}: {error}"
+            );
+        }
+
+        // Only top level keys are reserved.
+        Message::builder()
+            .topic("news")
+            .data(&json!({ "sender": { "from": "Alice" } }))
+            .stringify_data(true)
+            .build()
+            .expect("Nested keys aren't reserved");
+    }
+
+    #[test]
+    fn test_builder_validates_conditions() {
+        let valid = [
+            "'news' in topics",
+            "'stock-GOOG' in topics && ('industry-tech' in topics || !('a' in topics))",
+            "'a' in topics || 'b' in topics || 'c' in topics || 'd' in topics || 'e' in topics",
+        ];
+        for condition in valid {
+            Message::builder()
+                .condition(condition)
+                .notification(notification())
+                .build()
+                .unwrap_or_else(|error| panic!("{condition} is valid: {error}"));
+        }
+
+        let invalid = [
+            ("", "it contains no topic"),
+            ("news in topics", "it contains no topic"),
+            ("'news in topics", "a quote is not closed"),
+            ("'a b' in topics", "it contains an invalid topic name"),
+            ("('a' in topics", "the parentheses are unbalanced"),
+            ("'a' in topics)", "the parentheses are unbalanced"),
+            (
+                "'a' in topics || 'b' in topics || 'c' in topics || 'd' in topics || 'e' in \
+                 topics || 'f' in topics",
+                "it contains more than 5 topics",
+            ),
+        ];
+        for (condition, expected_reason) in invalid {
+            let error = Message::builder()
+                .condition(condition)
+                .notification(notification())
+                .build()
+                .unwrap_err();
+
+            assert!(
+                matches!(error, FcmError::InvalidCondition { reason, .. } if reason == expected_reason),
+                "unexpected error for {condition:?}: {error}"
+            );
+        }
+    }
+
+    fn message_with_three_violations() -> MessageBuilder {
+        Message::builder()
+            .condition("('news' in topics")
+            .data(&json!({ "from": "Alice" }))
+            .analytics_label("not a label")
+    }
+
+    #[test]
+    fn test_builder_reports_first_violation_by_default() {
+        let error = message_with_three_violations().build().unwrap_err();
+
+        assert!(
+            matches!(error, FcmError::InvalidCondition { .. }),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_builder_reports_all_violations() {
+        let error = message_with_three_violations()
+            .report_all_violations(true)
+            .build()
+            .unwrap_err();
+
+        let FcmError::InvalidMessage(violations) = &error else {
+            panic!("Expected InvalidMessage, got {error:?}");
+        };
+        let kinds: Vec<_> = violations.iter().map(FcmError::kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                "InvalidCondition",
+                "ReservedDataKey",
+                "InvalidAnalyticsLabel"
+            ]
+        );
+        assert_eq!(error.suggested_status_code(), 400);
+        assert!(error.to_string().contains("\"from\""), "{error}");
+    }
+
+    #[test]
+    fn test_message_validate_reports_all_violations() {
+        let mut message = Message::builder()
+            .topic("news")
+            .notification(notification())
+            .build()
+            .expect("Failed to build message");
+        assert!(message.validate().is_ok());
+
+        message.target = MessageTarget::Topic("no spaces".to_string());
+        message.data = Some(json!({ "google.sender": "Alice" }));
+        message.max_payload_size = 10;
+
+        let kinds: Vec<_> = message
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(FcmError::kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            ["InvalidTopicName", "ReservedDataKey", "PayloadTooLarge"]
+        );
+    }
+
+    #[test]
+    fn test_builder_stringifies_data_values() {
+        let message = Message::builder()
+            .topic("news")
+            .data(&json!({
+                "text": "Hello",
+                "count": 42,
+                "ratio": 0.5,
+                "enabled": true,
+                "reply_to": null,
+                "user": { "id": "1" },
+                "tags": ["a", "b"],
+            }))
+            .stringify_data(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            message.data(),
+            Some(&json!({
+                "text": "Hello",
+                "count": "42",
+                "ratio": "0.5",
+                "enabled": "true",
+                "user": r#"{"id":"1"}"#,
+                "tags": r#"["a","b"]"#,
+            }))
+        );
+    }
+
+    /// Builds a message for the topic, whose notification and data payload
+    /// are exactly `size` bytes large.
+    fn message_of_size(size: usize, limit: Option<usize>) -> Result<Message, FcmError> {
+        let builder = |value: String| {
+            let builder = Message::builder()
+                .topic("news")
+                .notification(notification())
+                .data(&json!({ "key": value }));
+            match limit {
+                Some(limit) => builder.max_payload_size(limit),
+                None => builder,
+            }
+        };
+        let empty = builder(String::new()).build().unwrap();
+        let base_size = empty.payload_size().unwrap();
+
+        builder("x".repeat(size - base_size)).build()
+    }
+
+    #[test]
+    fn test_builder_accepts_payload_at_size_limit() {
+        let message = message_of_size(MAX_PAYLOAD_SIZE, None).unwrap();
+
+        assert_eq!(message.payload_size().unwrap(), MAX_PAYLOAD_SIZE);
+        let payload = json!({
+            "notification": message.to_request_body()["message"]["notification"],
+            "data": message.to_request_body()["message"]["data"],
+        });
+        assert_eq!(
+            serde_json::to_vec(&payload).unwrap().len(),
+            MAX_PAYLOAD_SIZE
+        );
+    }
+
+    #[test]
+    fn test_size_limit_ignores_target_and_validate_only() {
+        let data = json!({ "key": "x".repeat(MAX_PAYLOAD_SIZE - 19) });
+        let build = |builder: MessageBuilder| builder.data(&data).build();
+
+        let topic = build(Message::builder().topic("news")).unwrap();
+        let token = build(Message::builder().token("a".repeat(MAX_DEVICE_TOKEN_LENGTH))).unwrap();
+        let validate_only = build(Message::builder().topic("news").validate_only(true)).unwrap();
+
+        assert_eq!(topic.payload_size().unwrap(), MAX_PAYLOAD_SIZE);
+        assert_eq!(token.payload_size().unwrap(), topic.payload_size().unwrap());
+        assert_eq!(
+            validate_only.payload_size().unwrap(),
+            topic.payload_size().unwrap()
+        );
+        assert!(
+            serde_json::to_vec(&validate_only.to_request_body())
+                .unwrap()
+                .len()
+                > MAX_PAYLOAD_SIZE
+        );
+    }
+
+    #[test]
+    fn test_builder_rejects_payload_above_size_limit() {
+        let error = message_of_size(MAX_PAYLOAD_SIZE + 1, None).unwrap_err();
+
+        assert!(matches!(
+            error,
+            FcmError::PayloadTooLarge {
+                size: 4097,
+                limit: 4096
+            }
+        ));
+    }
+
+    #[test]
+    fn test_builder_with_custom_size_limit() {
+        assert!(message_of_size(8192, Some(8192)).is_ok());
+
+        let error = message_of_size(101, Some(100)).unwrap_err();
+        assert!(matches!(
+            error,
+            FcmError::PayloadTooLarge {
+                size: 101,
+                limit: 100
+            }
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use futures::future::join_all;
+
+use crate::FcmClient;
+use crate::FcmError;
+use crate::FcmResponse;
+use crate::IntoCredentials;
+use crate::Message;
+
+/// Sends FCM messages to several Firebase projects, e.g. one per tenant.
+///
+/// Every project has its own [`FcmClient`], so the access tokens of the
+/// projects are requested and refreshed independently and a message is
+/// always sent with the token of the project it is sent to. Cloning is cheap,
+/// as the clients are cloned.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use oauth_fcm::FcmNotification;
+/// use oauth_fcm::Message;
+/// use oauth_fcm::MultiProjectFcm;
+///
+/// # tokio_test::block_on(async {
+/// let mut fcm = MultiProjectFcm::new();
+/// fcm.register("tenant-a", std::path::Path::new("tenant_a_credentials.json"))
+///     .await
+///     .expect("Invalid credentials");
+/// fcm.register("tenant-b", std::path::Path::new("tenant_b_credentials.json"))
+///     .await
+///     .expect("Invalid credentials");
+///
+/// let message = Message::builder()
+///     .token("device_token")
+///     .notification(FcmNotification::new("Test Title", "Test Body"))
+///     .build()
+///     .expect("Invalid message");
+/// fcm.send("tenant-a", &message)
+///     .await
+///     .expect("Error while sending FCM message");
+/// # });
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MultiProjectFcm {
+    clients: HashMap<String, FcmClient>,
+}
+
+impl MultiProjectFcm {
+    /// Creates an instance without any projects.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the Google service account credentials of a project.
+    ///
+    /// A client registered before for the same project ID is replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the credentials could not be parsed.
+    pub async fn register(
+        &mut self,
+        project_id: impl Into<String>,
+        credentials: impl IntoCredentials,
+    ) -> Result<(), FcmError> {
+        let client = FcmClient::builder()
+            .credentials(credentials)
+            .project_id(project_id)
+            .build()
+            .await?;
+        self.insert(client);
+        Ok(())
+    }
+
+    /// Registers a client for its project, e.g. to configure the client
+    /// further. Returns the client registered before for the same project ID.
+    pub fn insert(&mut self, client: FcmClient) -> Option<FcmClient> {
+        self.clients.insert(client.project_id().to_string(), client)
+    }
+
+    /// Removes the client of a project and returns it.
+    pub fn remove(&mut self, project_id: &str) -> Option<FcmClient> {
+        self.clients.remove(project_id)
+    }
+
+    /// Returns the client of a project.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnknownProject` if no client is registered for the project ID.
+    pub fn client(&self, project_id: &str) -> Result<&FcmClient, FcmError> {
+        self.clients
+            .get(project_id)
+            .ok_or_else(|| FcmError::UnknownProject {
+                project_id: project_id.to_string(),
+            })
+    }
+
+    /// Returns the IDs of all registered projects in arbitrary order.
+    pub fn project_ids(&self) -> impl Iterator<Item = &str> {
+        self.clients.keys().map(String::as_str)
+    }
+
+    /// Sends a [`Message`] to a project.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnknownProject` if no client is registered for the project ID,
+    /// and the errors of [`FcmClient::send`] otherwise.
+    pub async fn send(&self, project_id: &str, message: &Message) -> Result<FcmResponse, FcmError> {
+        self.client(project_id)?.send(message).await
+    }
+
+    /// Sends the same [`Message`] to several projects concurrently, e.g.
+    /// while devices are migrated from one project to another.
+    ///
+    /// Returns the result of every project in the order of `project_ids`. A
+    /// failure in one project doesn't affect the others, and an unknown
+    /// project ID results in `UnknownProject` for that project only.
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// # use oauth_fcm::Message;
+    /// # use oauth_fcm::MultiProjectFcm;
+    /// # tokio_test::block_on(async {
+    /// # let fcm: MultiProjectFcm = unimplemented!();
+    /// # let message: Message = unimplemented!();
+    /// for (project_id, result) in fcm.send_to_projects(&["old-proj", "new-proj"], &message).await {
+    ///     if let Err(error) = result {
+    ///         println!("Failed to send to {project_id}: {error}");
+    ///     }
+    /// }
+    /// # });
+    /// ```
+    pub async fn send_to_projects<'a>(
+        &self,
+        project_ids: &[&'a str],
+        message: &Message,
+    ) -> Vec<(&'a str, Result<FcmResponse, FcmError>)> {
+        join_all(
+            project_ids.iter().map(|&project_id| async move {
+                (project_id, self.send(project_id, message).await)
+            }),
+        )
+        .await
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures::stream;
+use futures::Stream;
+use futures::StreamExt;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::fcm::create_message;
+use crate::fcm::fcm_url;
+use crate::fcm::post_message;
+use crate::fcm::read_response;
+use crate::fcm::send_request;
+use crate::fcm::RequestOptions;
+use crate::message::validate_device_token;
+use crate::FcmError;
+use crate::FcmNotification;
+use crate::FcmResponse;
+use crate::LocalizedNotification;
+use crate::Message;
+use crate::MessageTarget;
+use crate::PlatformConfig;
+use crate::TokenProvider;
+
+/// The token of the base message, which is replaced by the device tokens.
+const PLACEHOLDER_TOKEN: &str = "placeholder";
+
+/// The result of sending a message to multiple devices.
+#[derive(Debug)]
+pub struct MulticastResult {
+    /// The number of messages FCM accepted.
+    pub success_count: usize,
+    /// The number of messages that could not be sent.
+    pub failure_count: usize,
+    /// The result for every device token, paired with the index of the token.
+    /// The results are in the order of the tokens.
+    pub results: Vec<(usize, Result<FcmResponse, FcmError>)>,
+}
+
+impl MulticastResult {
+    fn new(mut results: Vec<(usize, Result<FcmResponse, FcmError>)>) -> Self {
+        results.sort_unstable_by_key(|(index, _)| *index);
+        let success_count = results.iter().filter(|(_, result)| result.is_ok()).count();
+
+        Self {
+            success_count,
+            failure_count: results.len() - success_count,
+            results,
+        }
+    }
+}
+
+/// Sends the same Firebase Cloud Messaging (FCM) message to multiple devices.
+///
+/// FCM has no endpoint for sending a message to multiple devices, so one
+/// request is sent per device token. The OAuth token is obtained and the
+/// payload is built only once for all requests. At most `concurrency` requests
+/// are sent at the same time.
+///
+/// Unlike [`send_fcm_message`](crate::send_fcm_message), a `401` response is
+/// not retried with a new OAuth token.
+///
+/// # Errors
+///
+/// This function returns an error if the payload is invalid or the OAuth
+/// token could not be obtained, since no message could be sent in that case.
+/// Errors of single messages are part of the returned [`MulticastResult`].
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::fs::File;
+///
+/// use oauth_fcm::{create_shared_token_manager, send_fcm_multicast, FcmNotification};
+///
+/// # tokio_test::block_on(async {
+/// let tokens = vec!["device_token_1".to_string(), "device_token_2".to_string()];
+/// let notification = FcmNotification::new("Test Title", "Test Body");
+/// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+/// let result = send_fcm_multicast(&tokens, Some(notification), None::<serde_json::Value>, &token_manager, "project_id", 16)
+///     .await
+///     .expect("Error while sending FCM messages");
+///
+/// for (index, result) in &result.results {
+///     if let Err(error) = result {
+///         if error.is_token_invalid() {
+///             println!("Remove token {}", tokens[*index]);
+///         }
+///     }
+/// }
+/// # });
+/// ```
+#[instrument(
+    level = "info",
+    skip(tokens, data_payload, notification, token_provider)
+)]
+pub async fn send_fcm_multicast<T: Serialize>(
+    tokens: &[String],
+    notification: Option<FcmNotification>,
+    data_payload: Option<T>,
+    token_provider: &(impl TokenProvider + ?Sized),
+    project_id: &str,
+    concurrency: usize,
+) -> Result<MulticastResult, FcmError> {
+    info!("Sending FCM message to {} devices", tokens.len());
+
+    send_fcm_multicast_with_url(
+        tokens,
+        notification,
+        data_payload,
+        token_provider,
+        &fcm_url(project_id),
+        concurrency,
+    )
+    .await
+}
+
+/// Sends the same Firebase Cloud Messaging (FCM) message to multiple devices
+/// using a specific URL.
+///
+/// This function behaves exactly as [`send_fcm_multicast`], but allows
+/// specifying a custom FCM URL. This is only useful for testing.
+#[instrument(
+    level = "debug",
+    skip(tokens, data_payload, notification, token_provider)
+)]
+pub async fn send_fcm_multicast_with_url<T: Serialize>(
+    tokens: &[String],
+    notification: Option<FcmNotification>,
+    data_payload: Option<T>,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+    concurrency: usize,
+) -> Result<MulticastResult, FcmError> {
+    // The message is built with a placeholder token, which is replaced for
+    // every request. This validates the payload, including its size, once for
+    // all tokens. The tokens themselves are validated per request.
+    let message = create_message(
+        &MessageTarget::Token(PLACEHOLDER_TOKEN.to_string()),
+        notification,
+        data_payload,
+        &PlatformConfig::default(),
+    )?;
+    let payload = message.to_request_body();
+
+    let recipients = tokens.iter().map(|token| (token.as_str(), &payload));
+    send_payloads(recipients, token_provider, fcm_url, concurrency).await
+}
+
+/// Sends a notification to multiple devices in the language of each device.
+///
+/// Every recipient is a device token paired with the language tag of the
+/// device, e.g. `"pt-BR"`. The notification is chosen per recipient with
+/// [`LocalizedNotification::resolve`], so recipients without a matching
+/// translation get the default notification. The data payload is the same
+/// for all recipients.
+///
+/// Otherwise this behaves like [`send_fcm_multicast`]: the payload of every
+/// translation is built once, the OAuth token is obtained once and at most
+/// `concurrency` requests are sent at the same time. The results are in the
+/// order of the recipients.
+///
+/// # Errors
+///
+/// This function returns an error if the payload of a used translation is
+/// invalid or the OAuth token could not be obtained. Errors of single
+/// messages are part of the returned [`MulticastResult`].
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::fs::File;
+///
+/// use oauth_fcm::{create_shared_token_manager, send_fcm_localized_multicast, FcmNotification, LocalizedNotification};
+///
+/// # tokio_test::block_on(async {
+/// let recipients = vec![
+///     ("device_token_1".to_string(), "pt-BR".to_string()),
+///     ("device_token_2".to_string(), "en".to_string()),
+/// ];
+/// let notification = LocalizedNotification::new(FcmNotification::new("Hello", "World"))
+///     .with_translation("pt", FcmNotification::new("Olá", "Mundo"));
+/// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+/// let result = send_fcm_localized_multicast(&recipients, &notification, None::<serde_json::Value>, &token_manager, "project_id", 16)
+///     .await
+///     .expect("Error while sending FCM messages");
+/// # });
+/// ```
+#[instrument(
+    level = "info",
+    skip(recipients, notification, data_payload, token_provider)
+)]
+pub async fn send_fcm_localized_multicast<T: Serialize>(
+    recipients: &[(String, String)],
+    notification: &LocalizedNotification,
+    data_payload: Option<T>,
+    token_provider: &(impl TokenProvider + ?Sized),
+    project_id: &str,
+    concurrency: usize,
+) -> Result<MulticastResult, FcmError> {
+    info!(
+        "Sending localized FCM message to {} devices",
+        recipients.len()
+    );
+
+    send_fcm_localized_multicast_with_url(
+        recipients,
+        notification,
+        data_payload,
+        token_provider,
+        &fcm_url(project_id),
+        concurrency,
+    )
+    .await
+}
+
+/// Sends a notification to multiple devices in the language of each device
+/// using a specific URL.
+///
+/// This function behaves exactly as [`send_fcm_localized_multicast`], but
+/// allows specifying a custom FCM URL. This is only useful for testing.
+#[instrument(
+    level = "debug",
+    skip(recipients, notification, data_payload, token_provider)
+)]
+pub async fn send_fcm_localized_multicast_with_url<T: Serialize>(
+    recipients: &[(String, String)],
+    notification: &LocalizedNotification,
+    data_payload: Option<T>,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+    concurrency: usize,
+) -> Result<MulticastResult, FcmError> {
+    let data_payload = data_payload
+        .map(|data| serde_json::to_value(data))
+        .transpose()?;
+
+    // Only the translations used by the recipients are built, each once.
+    let mut payloads = HashMap::new();
+    for (_, locale) in recipients {
+        let tag = notification.resolve_tag(locale);
+        if payloads.contains_key(&tag) {
+            continue;
+        }
+        let message = create_message(
+            &MessageTarget::Token(PLACEHOLDER_TOKEN.to_string()),
+            Some(notification.resolve(locale).clone()),
+            data_payload.as_ref(),
+            &PlatformConfig::default(),
+        )?;
+        payloads.insert(tag, message.to_request_body());
+    }
+
+    let recipients = recipients.iter().map(|(token, locale)| {
+        let payload = &payloads[&notification.resolve_tag(locale)];
+        (token.as_str(), payload)
+    });
+    send_payloads(recipients, token_provider, fcm_url, concurrency).await
+}
+
+/// Sends a prebuilt request body to every device token, replacing the
+/// placeholder token, and collects the results in the order of the tokens.
+async fn send_payloads<'a>(
+    recipients: impl ExactSizeIterator<Item = (&'a str, &'a serde_json::Value)>,
+    token_provider: &(impl TokenProvider + ?Sized),
+    fcm_url: &str,
+    concurrency: usize,
+) -> Result<MulticastResult, FcmError> {
+    if recipients.len() == 0 {
+        return Ok(MulticastResult::new(Vec::new()));
+    }
+
+    let access_token = token_provider.get_token().await?;
+    let transport = token_provider.http_transport().await;
+
+    let results = stream::iter(recipients.enumerate())
+        .map(|(index, (token, payload))| {
+            let transport = transport.as_ref();
+            let access_token = &access_token;
+            async move {
+                if let Err(error) = validate_device_token(token) {
+                    return (index, Err(error));
+                }
+
+                let mut payload = payload.clone();
+                payload["message"]["token"] = token.into();
+
+                let result = post_message(
+                    transport,
+                    fcm_url,
+                    access_token,
+                    &payload,
+                    RequestOptions::default(),
+                )
+                .await
+                .and_then(|res| read_response(&res));
+                (index, result)
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    let result = MulticastResult::new(results);
+    debug!(
+        success_count = result.success_count,
+        failure_count = result.failure_count,
+        "FCM multicast finished"
+    );
+    Ok(result)
+}
+
+/// Sends a stream of messages and streams the results as they complete.
+///
+/// Unlike [`send_fcm_multicast`], the results aren't collected, so this is
+/// suited for very large fan-outs. At most `concurrency` requests are sent at
+/// the same time and the results are returned in the order the requests
+/// complete, each paired with its message.
+///
+/// Every message is sent as by [`send_message`](crate::send_message), so the
+/// OAuth token is refreshed when it expires while the stream is running and a
+/// rejected token is replaced once.
+///
+/// # Cancellation
+///
+/// Messages are only sent while the stream is polled. Dropping the stream
+/// stops sending new messages and cancels the requests in flight, see
+/// [`send_fcm_message`](crate::send_fcm_message) for the outcome of cancelled
+/// sends.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::fs::File;
+///
+/// use futures::StreamExt;
+/// use oauth_fcm::{create_shared_token_manager, send_fcm_stream, FcmNotification, Message};
+///
+/// # tokio_test::block_on(async {
+/// let tokens = (0..100_000).map(|index| format!("device_token_{index}"));
+/// let messages = futures::stream::iter(tokens).map(|token| {
+///     Message::builder()
+///         .token(token)
+///         .notification(FcmNotification::new("Test Title", "Test Body"))
+///         .build()
+///         .expect("Invalid message")
+/// });
+/// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+///
+/// let mut results = std::pin::pin!(send_fcm_stream(messages, &token_manager, "project_id", 16));
+/// while let Some((message, result)) = results.next().await {
+///     if let Err(error) = result {
+///         println!("Failed to send to {:?}: {error}", message.target());
+///     }
+/// }
+/// # });
+/// ```
+pub fn send_fcm_stream<'a>(
+    messages: impl Stream<Item = Message> + 'a,
+    token_provider: &'a (impl TokenProvider + ?Sized),
+    project_id: &str,
+    concurrency: usize,
+) -> impl Stream<Item = (Message, Result<FcmResponse, FcmError>)> + 'a {
+    send_fcm_stream_with_url(messages, token_provider, &fcm_url(project_id), concurrency)
+}
+
+/// Sends a stream of messages to a specific URL and streams the results as
+/// they complete.
+///
+/// This function behaves exactly as [`send_fcm_stream`], but allows specifying
+/// a custom FCM URL. This is only useful for testing.
+pub fn send_fcm_stream_with_url<'a>(
+    messages: impl Stream<Item = Message> + 'a,
+    token_provider: &'a (impl TokenProvider + ?Sized),
+    fcm_url: &str,
+    concurrency: usize,
+) -> impl Stream<Item = (Message, Result<FcmResponse, FcmError>)> + 'a {
+    let fcm_url: Arc<str> = Arc::from(fcm_url);
+
+    messages
+        .map(move |message| {
+            let fcm_url = Arc::clone(&fcm_url);
+            async move {
+                let result = send_request(
+                    &message,
+                    token_provider,
+                    &fcm_url,
+                    RequestOptions::default(),
+                )
+                .await;
+                (message, result)
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+}
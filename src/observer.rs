@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::FcmError;
+use crate::FcmResponse;
+
+/// The result of a send, see [`FcmObserver::on_send`].
+#[derive(Clone, Copy, Debug)]
+pub enum SendOutcome<'a> {
+    /// FCM accepted the message.
+    Delivered(&'a FcmResponse),
+    /// The message could not be sent.
+    Failed(&'a FcmError),
+}
+
+impl SendOutcome<'_> {
+    /// Returns `true` if FCM accepted the message.
+    #[must_use]
+    pub const fn is_delivered(&self) -> bool {
+        matches!(self, Self::Delivered(_))
+    }
+}
+
+/// Hooks for collecting metrics about sends and token refreshes.
+///
+/// Register an observer with
+/// [`FcmClientBuilder::observer`](crate::FcmClientBuilder::observer) for sends
+/// and with [`TokenManager::with_observer`](crate::TokenManager::with_observer)
+/// for token refreshes. All methods do nothing by default, so only the
+/// needed ones have to be implemented. They are called on the sending task,
+/// so they should return quickly.
+///
+/// The time until the current token expires, e.g. for a gauge, is returned by
+/// [`TokenManager::time_until_expiry`](crate::TokenManager::time_until_expiry).
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::atomic::AtomicU64;
+/// use std::sync::atomic::Ordering;
+/// use std::time::Duration;
+///
+/// use oauth_fcm::FcmObserver;
+/// use oauth_fcm::SendOutcome;
+///
+/// #[derive(Default)]
+/// struct SendCounter {
+///     delivered: AtomicU64,
+///     failed: AtomicU64,
+/// }
+///
+/// impl FcmObserver for SendCounter {
+///     fn on_send(&self, outcome: SendOutcome<'_>, _latency: Duration) {
+///         let counter = if outcome.is_delivered() {
+///             &self.delivered
+///         } else {
+///             &self.failed
+///         };
+///         counter.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait FcmObserver: Send + Sync {
+    /// Called after a message was sent or failed to send.
+    ///
+    /// `latency` is the duration of the whole send, including getting the
+    /// token and the retry after a rejected token.
+    fn on_send(&self, outcome: SendOutcome<'_>, latency: Duration) {
+        let _ = (outcome, latency);
+    }
+
+    /// Called after the token manager requested a new token.
+    ///
+    /// This is only called for actual refreshes, not when a cached token is
+    /// used.
+    fn on_token_refresh(&self, result: Result<(), &FcmError>, latency: Duration) {
+        let _ = (result, latency);
+    }
+}
+
+/// A registered [`FcmObserver`], which can be printed by `Debug`.
+#[derive(Clone)]
+pub struct SharedObserver(pub Arc<dyn FcmObserver>);
+
+impl std::fmt::Debug for SharedObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FcmObserver")
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// A token bucket, which limits the rate of FCM requests.
+///
+/// The bucket holds up to `burst` permits and is refilled with
+/// `requests_per_second` permits per second. Every FCM request takes one
+/// permit, and waits until one is available if the bucket is empty. Waiting
+/// requests are served in order.
+///
+/// Cloning the rate limiter is cheap and all clones share the same bucket, so
+/// one rate limiter can be shared by several clients sending to the same
+/// project. Register it with
+/// [`FcmClientBuilder::rate_limiter`](crate::FcmClientBuilder::rate_limiter).
+///
+/// # Example
+///
+/// ```rust no_run
+/// use oauth_fcm::FcmClient;
+/// use oauth_fcm::RateLimiter;
+///
+/// # tokio_test::block_on(async {
+/// let client = FcmClient::builder()
+///     .credentials(std::path::Path::new("path_to_google_credentials.json"))
+///     .rate_limiter(RateLimiter::new(500, 100))
+///     .build()
+///     .await
+///     .expect("Failed to create FcmClient");
+/// # });
+/// ```
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// The time between two permits.
+    interval: Duration,
+    /// The maximum number of permits.
+    burst: u32,
+    bucket: Mutex<Bucket>,
+    /// Held by the request, which waits for the next permit.
+    queue: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// The permits in the bucket, as time worth `interval` per permit.
+    credit: Duration,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter, which allows `requests_per_second` requests
+    /// per second on average and up to `burst` requests at once.
+    ///
+    /// The bucket starts full. Both values are at least 1, and the rate is at
+    /// most one request per nanosecond.
+    #[must_use]
+    pub fn new(requests_per_second: u32, burst: u32) -> Self {
+        let interval =
+            (Duration::from_secs(1) / requests_per_second.max(1)).max(Duration::from_nanos(1));
+        let burst = burst.max(1);
+
+        Self {
+            inner: Arc::new(Inner {
+                interval,
+                burst,
+                bucket: Mutex::new(Bucket {
+                    credit: interval * burst,
+                    refilled_at: Instant::now(),
+                }),
+                queue: tokio::sync::Mutex::new(()),
+            }),
+        }
+    }
+
+    /// Waits until a permit is available and takes it.
+    ///
+    /// This is cancel safe: a cancelled call doesn't take a permit.
+    pub async fn acquire(&self) {
+        let _turn = self.inner.queue.lock().await;
+
+        loop {
+            let wait = {
+                let mut bucket = self.bucket();
+                if bucket.credit >= self.inner.interval {
+                    bucket.credit -= self.inner.interval;
+                    return;
+                }
+                self.inner.interval.saturating_sub(bucket.credit)
+            };
+            debug!(
+                wait_ms = wait.as_millis(),
+                "Rate limit reached, waiting for a permit"
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Returns the number of permits, which are available right now, e.g. for
+    /// a gauge.
+    #[must_use]
+    pub fn available_permits(&self) -> u32 {
+        let credit = self.bucket().credit;
+        let permits = credit.as_nanos() / self.inner.interval.as_nanos();
+        u32::try_from(permits).unwrap_or(u32::MAX)
+    }
+
+    /// Returns the maximum number of permits.
+    #[must_use]
+    pub fn burst(&self) -> u32 {
+        self.inner.burst
+    }
+
+    /// Locks the bucket and adds the permits since the last refill.
+    fn bucket(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        let mut bucket = self
+            .inner
+            .bucket
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let capacity = self.inner.interval * self.inner.burst;
+        bucket.credit = (bucket.credit + (now - bucket.refilled_at)).min(capacity);
+        bucket.refilled_at = now;
+        bucket
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bucket_starts_full_and_refills() {
+        let limiter = RateLimiter::new(2, 3);
+        assert_eq!(limiter.available_permits(), 3);
+
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert_eq!(limiter.available_permits(), 0);
+
+        tokio::time::advance(Duration::from_millis(600)).await;
+        assert_eq!(limiter.available_permits(), 1);
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(limiter.available_permits(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_clones_share_the_bucket() {
+        let limiter = RateLimiter::new(1, 2);
+        let clone = limiter.clone();
+
+        clone.acquire().await;
+        assert_eq!(limiter.available_permits(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_above_one_per_nanosecond() {
+        let limiter = RateLimiter::new(u32::MAX, 1);
+        assert_eq!(limiter.available_permits(), 1);
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(limiter.available_permits(), 0);
+    }
+}
@@ -0,0 +1,272 @@
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use async_trait::async_trait;
+use reqwest::header::HeaderValue;
+use reqwest::header::RETRY_AFTER;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::HttpRequest;
+use crate::HttpResponse;
+use crate::HttpTransport;
+use crate::NetworkError;
+
+/// The JSON fields and form parameters which are replaced by
+/// [`REDACTED`] before an exchange is written to a fixture.
+const REDACTED_FIELDS: [&str; 5] = [
+    "access_token",
+    "assertion",
+    "client_secret",
+    "id_token",
+    "refresh_token",
+];
+
+const REDACTED: &str = "redacted";
+
+/// The content of a fixture file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Fixture {
+    exchanges: Vec<Exchange>,
+}
+
+/// A recorded request and its response.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Exchange {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct RecordedRequest {
+    method: String,
+    /// The path and query, without scheme and host, so a fixture recorded
+    /// against a mock server can be replayed with any host.
+    path: String,
+    /// The FNV-1a hash of the redacted body.
+    body_hash: String,
+    /// The redacted body, for readers of the fixture. Not used for matching.
+    body: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_after: Option<String>,
+    body: String,
+}
+
+impl RecordedRequest {
+    fn new(request: &HttpRequest) -> Self {
+        let path = Url::parse(&request.url).map_or_else(
+            |_| request.url.clone(),
+            |url| {
+                url.query().map_or_else(
+                    || url.path().to_string(),
+                    |query| format!("{}?{query}", url.path()),
+                )
+            },
+        );
+        let body = redact(&String::from_utf8_lossy(&request.body));
+
+        Self {
+            method: request.method.to_string(),
+            path,
+            body_hash: format!("{:016x}", fnv1a(body.as_bytes())),
+            body,
+        }
+    }
+
+    /// Returns `true` if both requests have the same method, path and body
+    /// hash.
+    fn matches(&self, other: &Self) -> bool {
+        self.method == other.method && self.path == other.path && self.body_hash == other.body_hash
+    }
+}
+
+impl RecordedResponse {
+    fn new(response: &HttpResponse) -> Self {
+        Self {
+            status: response.status,
+            retry_after: response
+                .headers
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            body: redact(&response.text()),
+        }
+    }
+
+    fn to_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::new(self.status, self.body.clone());
+        if let Some(value) = self
+            .retry_after
+            .as_deref()
+            .and_then(|value| HeaderValue::from_str(value).ok())
+        {
+            response.headers.insert(RETRY_AFTER, value);
+        }
+        response
+    }
+}
+
+/// An [`HttpTransport`] writing every request and its response to a JSON
+/// fixture file, which can be replayed by a [`ReplayTransport`].
+///
+/// The fixture is rewritten after every exchange, so it is complete even if
+/// the test fails later. Credentials are redacted: request headers aren't
+/// recorded and secrets like `access_token` or the signed `assertion` are
+/// replaced in the request and response bodies. Of the response headers only
+/// `Retry-After` is recorded.
+///
+/// Requires the `test-util` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::RecordingTransport;
+/// use oauth_fcm::ReqwestTransport;
+///
+/// let transport =
+///     RecordingTransport::new(ReqwestTransport::default(), "tests/fixtures/send.json");
+/// ```
+pub struct RecordingTransport<T> {
+    inner: T,
+    path: PathBuf,
+    fixture: Mutex<Fixture>,
+}
+
+impl<T: HttpTransport> RecordingTransport<T> {
+    /// Wraps the transport, which sends the requests, and records to the
+    /// fixture at `path`. An existing fixture is overwritten.
+    pub fn new(inner: T, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            fixture: Mutex::new(Fixture::default()),
+        }
+    }
+}
+
+#[async_trait]
+impl<T: HttpTransport> HttpTransport for RecordingTransport<T> {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        let recorded = RecordedRequest::new(&request);
+        let response = self.inner.send(request).await?;
+
+        // The file is written while holding the lock, so concurrent requests
+        // can't overwrite it with an older state.
+        let mut fixture = self.fixture.lock().unwrap_or_else(PoisonError::into_inner);
+        fixture.exchanges.push(Exchange {
+            request: recorded,
+            response: RecordedResponse::new(&response),
+        });
+        let json = serde_json::to_vec_pretty(&*fixture).map_err(NetworkError::InvalidResponse)?;
+        std::fs::write(&self.path, json).map_err(|e| NetworkError::Transport(Box::new(e)))?;
+        drop(fixture);
+
+        Ok(response)
+    }
+}
+
+impl<T> std::fmt::Debug for RecordingTransport<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingTransport")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An [`HttpTransport`] answering requests from a fixture written by a
+/// [`RecordingTransport`], without any network access.
+///
+/// A request is answered by the first unused exchange with the same method,
+/// path and body hash, so repeated requests, e.g. retries, get the recorded
+/// responses in order. The host of the URL isn't compared. A request without
+/// a matching exchange fails with [`NetworkError::Transport`] naming the
+/// request.
+///
+/// Requires the `test-util` feature.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    exchanges: Mutex<Vec<Option<Exchange>>>,
+}
+
+impl ReplayTransport {
+    /// Reads the fixture at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let fixture: Fixture = serde_json::from_slice(&std::fs::read(path)?)?;
+        Ok(Self {
+            exchanges: Mutex::new(fixture.exchanges.into_iter().map(Some).collect()),
+        })
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReplayTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        let recorded = RecordedRequest::new(&request);
+        let mut exchanges = self
+            .exchanges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        exchanges
+            .iter_mut()
+            .find(|exchange| {
+                exchange
+                    .as_ref()
+                    .is_some_and(|exchange| exchange.request.matches(&recorded))
+            })
+            .and_then(Option::take)
+            .map(|exchange| exchange.response.to_response())
+            .ok_or_else(|| {
+                NetworkError::Transport(
+                    format!(
+                        "No recorded response for {} {} with body hash {}",
+                        recorded.method, recorded.path, recorded.body_hash
+                    )
+                    .into(),
+                )
+            })
+    }
+}
+
+/// Replaces the values of [`REDACTED_FIELDS`] in a JSON object or form
+/// encoded body. Other bodies are returned unchanged.
+fn redact(body: &str) -> String {
+    if let Ok(serde_json::Value::Object(mut object)) = serde_json::from_str(body) {
+        for field in REDACTED_FIELDS {
+            if let Some(value) = object.get_mut(field) {
+                *value = REDACTED.into();
+            }
+        }
+        return serde_json::Value::Object(object).to_string();
+    }
+
+    if body.contains('=') && !body.contains(char::is_whitespace) {
+        return body
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if REDACTED_FIELDS.contains(&name) => format!("{name}={REDACTED}"),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+    }
+
+    body.to_string()
+}
+
+/// The 64 bit FNV-1a hash, which is stable across Rust versions unlike the
+/// `std` hashers.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
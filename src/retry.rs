@@ -0,0 +1,296 @@
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use reqwest::header::HeaderMap;
+use reqwest::header::RETRY_AFTER;
+
+use crate::FcmError;
+
+/// Configures how transient send failures are retried.
+///
+/// Only errors for which
+/// [`FcmError::is_retryable`] returns `true` are
+/// retried. The delay before a retry doubles with every attempt, starting at
+/// `initial_backoff` and capped at `max_backoff`. If FCM sends a `Retry-After`
+/// header, its delay is used instead, capped at `max_backoff` as well.
+///
+/// If a retried send fails, the error of the last attempt is returned as
+/// [`FcmError::RetriesExhausted`] with the [`RetryInfo`] of all attempts.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use oauth_fcm::RetryConfig;
+///
+/// let retry = RetryConfig {
+///     max_attempts: 5,
+///     initial_backoff: Duration::from_millis(500),
+///     ..RetryConfig::default()
+/// };
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts, also for delays requested by
+    /// a `Retry-After` header.
+    pub max_backoff: Duration,
+    /// Randomizes each delay between half and the full backoff, so many
+    /// failed sends don't retry at the same time.
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Returns the delay after the given number of failed attempts.
+    pub(crate) fn backoff(&self, failed_attempts: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(failed_attempts.saturating_sub(1));
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+
+        if self.jitter {
+            let half = backoff.as_nanos() / 2;
+            let nanos = half + u128::from(random()) % (half + 1);
+            Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+        } else {
+            backoff
+        }
+    }
+}
+
+/// The number of failed attempts at the start of a send, which are kept in
+/// [`RetryInfo::attempt_errors`].
+const FIRST_ATTEMPT_ERRORS: usize = 2;
+/// The number of failed attempts at the end of a send, which are kept in
+/// [`RetryInfo::attempt_errors`].
+const LAST_ATTEMPT_ERRORS: usize = 3;
+
+/// The attempts of a send, which failed after it was retried.
+///
+/// Attached to the error of the last attempt as
+/// [`FcmError::RetriesExhausted`] and returned by
+/// [`FcmError::retry_info`]. To bound the memory of long retry sequences, only
+/// the first two and the last three failed attempts are kept.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryInfo {
+    /// The number of attempts, including the first one.
+    pub attempts: u32,
+    /// The time from the start of the first attempt to the end of the last
+    /// one, including the backoff.
+    pub total_elapsed: Duration,
+    /// The backoff before the last attempt.
+    pub last_delay: Duration,
+    /// The failed attempts in order, see above for the omitted ones.
+    pub attempt_errors: Vec<AttemptError>,
+}
+
+/// A failed attempt of a send, see [`RetryInfo`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttemptError {
+    /// The number of the attempt, starting at 1.
+    pub attempt: u32,
+    /// The HTTP status of the FCM response, if FCM responded.
+    pub status: Option<u16>,
+    /// The FCM error code, e.g. `UNAVAILABLE`, or the
+    /// [`kind`](FcmError::kind) of the error if FCM didn't send one.
+    pub error_code: String,
+    /// The time from the start of the first attempt to the failure.
+    pub elapsed: Duration,
+}
+
+impl RetryInfo {
+    pub(crate) const fn new() -> Self {
+        Self {
+            attempts: 0,
+            total_elapsed: Duration::ZERO,
+            last_delay: Duration::ZERO,
+            attempt_errors: Vec::new(),
+        }
+    }
+
+    /// Records a failed attempt, dropping the oldest of the last attempts if
+    /// the limit is reached.
+    pub(crate) fn record(&mut self, attempt: u32, error: &FcmError, elapsed: Duration) {
+        if self.attempt_errors.len() == FIRST_ATTEMPT_ERRORS + LAST_ATTEMPT_ERRORS {
+            self.attempt_errors.remove(FIRST_ATTEMPT_ERRORS);
+        }
+        self.attempt_errors.push(AttemptError {
+            attempt,
+            status: error.fcm_response().map(|(status, _)| status),
+            error_code: error.code_or_kind(),
+            elapsed,
+        });
+        self.attempts = attempt;
+        self.total_elapsed = elapsed;
+    }
+
+    /// Attaches the attempts to the error of the last attempt, unless the
+    /// send failed on its first attempt.
+    pub(crate) fn attach_to(self, error: FcmError) -> FcmError {
+        if self.attempts > 1 {
+            FcmError::RetriesExhausted {
+                error: Box::new(error),
+                retry_info: self,
+            }
+        } else {
+            error
+        }
+    }
+}
+
+impl Display for RetryInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} attempts in {:?}, last backoff {:?}:",
+            self.attempts, self.total_elapsed, self.last_delay
+        )?;
+        for (index, attempt) in self.attempt_errors.iter().enumerate() {
+            let separator = if index == 0 { " " } else { ", " };
+            write!(f, "{separator}#{} ", attempt.attempt)?;
+            if let Some(status) = attempt.status {
+                write!(f, "{status} ")?;
+            }
+            write!(f, "{} after {:?}", attempt.error_code, attempt.elapsed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the delay of the `Retry-After` header.
+///
+/// The header contains either a number of seconds or an HTTP date. Dates in
+/// the past result in no delay.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Returns a random number, which is good enough for jitter.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let retry = RetryConfig {
+            max_attempts: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            jitter: false,
+        };
+
+        assert_eq!(retry.backoff(1), Duration::from_secs(1));
+        assert_eq!(retry.backoff(2), Duration::from_secs(2));
+        assert_eq!(retry.backoff(3), Duration::from_secs(4));
+        assert_eq!(retry.backoff(4), Duration::from_secs(5));
+        assert_eq!(retry.backoff(u32::MAX), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_backoff_with_jitter_stays_in_range() {
+        let retry = RetryConfig {
+            initial_backoff: Duration::from_secs(4),
+            ..RetryConfig::default()
+        };
+
+        for _ in 0..100 {
+            let backoff = retry.backoff(1);
+            assert!(backoff >= Duration::from_secs(2));
+            assert!(backoff <= Duration::from_secs(4));
+        }
+    }
+
+    #[test]
+    fn test_retry_info_keeps_first_and_last_attempts() {
+        let mut retry_info = RetryInfo::new();
+        for attempt in 1..=10 {
+            retry_info.record(
+                attempt,
+                &FcmError::InvalidClientConfig("test"),
+                Duration::from_secs(u64::from(attempt)),
+            );
+        }
+
+        let attempts: Vec<_> = retry_info
+            .attempt_errors
+            .iter()
+            .map(|attempt| attempt.attempt)
+            .collect();
+        assert_eq!(attempts, [1, 2, 8, 9, 10]);
+        assert_eq!(retry_info.attempts, 10);
+        assert_eq!(retry_info.total_elapsed, Duration::from_secs(10));
+        assert_eq!(
+            retry_info.to_string(),
+            "10 attempts in 10s, last backoff 0ns: #1 InvalidClientConfig after 1s, #2 InvalidClientConfig after 2s, \
+             #8 InvalidClientConfig after 8s, #9 InvalidClientConfig after 9s, \
+             #10 InvalidClientConfig after 10s"
+        );
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("2"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_retry_after_http_date() {
+        let mut headers = HeaderMap::new();
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(120));
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(&date).unwrap());
+
+        // The date has a precision of one second.
+        let delay = retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(118));
+        assert!(delay <= Duration::from_secs(120));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,69 @@
+use serde::Deserialize;
+use serde::Deserializer;
+
+/// A string holding credential material, like the private key or an access
+/// token.
+///
+/// `Debug` never prints the value and there is no `Display` implementation,
+/// so it can't end up in logs by accident. With the `zeroize` feature, the
+/// memory is zeroed when the value is dropped, e.g. when a token is replaced
+/// by a refreshed one.
+///
+/// Strings copied out of it with [`expose`](Self::expose), e.g. the token
+/// returned by `TokenManager::get_token`, are owned by the caller and are not
+/// zeroed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wraps a secret value.
+    #[must_use]
+    pub const fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    /// Returns the secret value.
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_does_not_contain_the_secret() {
+        let secret = SecretString::new("ya29.secret".to_string());
+
+        assert_eq!(format!("{secret:?}"), "[REDACTED]");
+        assert_eq!(format!("{:?}", Some(&secret)), "Some([REDACTED])");
+        assert_eq!(secret.expose(), "ya29.secret");
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+
+use crate::SecretString;
+
+/// An OAuth token stored in a [`TokenCache`].
+///
+/// The expiry is a wall-clock `SystemTime`, so entries can be shared between
+/// processes.
+#[derive(Clone, Debug)]
+pub struct CachedToken {
+    /// The access token. With the `zeroize` feature, it is zeroed when the
+    /// entry is dropped.
+    pub token: SecretString,
+    pub expires_at: SystemTime,
+}
+
+impl CachedToken {
+    /// Checks if the token is expired.
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= SystemTime::now()
+    }
+}
+
+/// A cache for OAuth tokens, which can be shared between multiple
+/// `TokenManager`s.
+///
+/// Every `TokenManager` caches its own token in memory. When a `TokenCache` is
+/// set with [`TokenManager::with_token_cache`], the manager consults the cache
+/// before refreshing its token and stores every refreshed token in the cache.
+/// Implement this trait to share tokens between processes, e.g. with Redis or
+/// a local file.
+///
+/// Entries are keyed by the service account email and the OAuth scope. If two
+/// managers refresh at the same time, both store their token and the last write
+/// wins, which is fine as both tokens are valid.
+///
+/// The cache can't fail the token refresh. Implementations should handle their
+/// own errors, e.g. by logging them and returning `None` from `get`.
+///
+/// [`TokenManager::with_token_cache`]: crate::TokenManager::with_token_cache
+#[async_trait]
+pub trait TokenCache: Send + Sync {
+    /// Returns the cached token for the given key, if there is one.
+    async fn get(&self, key: &str) -> Option<CachedToken>;
+
+    /// Stores the token for the given key.
+    async fn put(&self, key: &str, token: CachedToken);
+}
+
+/// A [`TokenCache`] keeping tokens in memory.
+///
+/// Share it between multiple `TokenManager`s in the same process by wrapping
+/// it in an `Arc`.
+#[derive(Debug, Default)]
+pub struct InMemoryTokenCache {
+    tokens: tokio::sync::Mutex<HashMap<String, CachedToken>>,
+}
+
+impl InMemoryTokenCache {
+    /// Creates a new, empty `InMemoryTokenCache`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TokenCache for InMemoryTokenCache {
+    async fn get(&self, key: &str) -> Option<CachedToken> {
+        self.tokens.lock().await.get(key).cloned()
+    }
+
+    async fn put(&self, key: &str, token: CachedToken) {
+        self.tokens.lock().await.insert(key.to_string(), token);
+    }
+}
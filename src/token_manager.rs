@@ -1,29 +1,83 @@
 use std::fmt::Debug;
-use std::io::Read;
+use std::sync::Arc;
+use std::sync::PoisonError;
+use std::sync::RwLock;
+use std::sync::RwLockReadGuard;
+use std::sync::RwLockWriteGuard;
 use std::time::Duration;
 use std::time::Instant;
 use std::time::SystemTime;
 
 use jsonwebtoken::encode;
-use jsonwebtoken::EncodingKey;
 use jsonwebtoken::Header;
+use reqwest::header::HeaderValue;
 use reqwest::Client;
+use serde::de::Error as _;
 use serde::Deserialize;
+use serde::Deserializer;
 use serde_json::json;
-use tracing::debug;
-use tracing::info;
 use tracing::instrument;
 
+use crate::adc;
+use crate::auto_refresh::RefreshHandle;
+use crate::credentials::key_from_base64;
+use crate::credentials::key_from_json;
+use crate::credentials::IntoCredentials;
+use crate::credentials::ServiceAccountKey;
+use crate::error::oauth_response_error;
 use crate::error::FcmError;
 use crate::error::NetworkError;
 use crate::error::ResultMapError;
+use crate::http;
+use crate::http::HttpRequest;
+use crate::http::HttpResponse;
+use crate::http::HttpTransport;
+use crate::http::ReqwestTransport;
+use crate::interceptor::Intercepted;
+use crate::interceptor::SharedInterceptor;
+use crate::observer::FcmObserver;
+use crate::observer::SharedObserver;
+use crate::secret::SecretString;
+use crate::token_cache::CachedToken;
+use crate::token_cache::TokenCache;
+use crate::FcmInterceptor;
+
+const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+const FCM_AUDIENCE: &str = "https://fcm.googleapis.com/";
+const GOOGLE_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+const JWT_LIFETIME_SECS: u64 = 3600;
+const DEFAULT_TOKEN_LIFETIME_SECS: u64 = 3600;
+const MAX_TOKEN_LIFETIME_SECS: u64 = 24 * 3600;
+const DEFAULT_CLOCK_SKEW: Duration = Duration::from_secs(30);
 
 /// A thread-safe, shared reference to a `TokenManager`.
 ///
-/// Recommended, if the `TokenManager` is accessed from multiple threads.
 /// A helper function for creating a `SharedTokenManager` can be found in
-/// [`lib.rs`](../lib.rs).
-pub type SharedTokenManager = std::sync::Arc<tokio::sync::Mutex<TokenManager>>;
+/// [`lib.rs`](../lib.rs). As [`TokenManager::get_token`] only needs a shared
+/// reference, a `TokenManager` can also be shared as `Arc<TokenManager>`
+/// without any lock.
+pub type SharedTokenManager = Arc<tokio::sync::Mutex<TokenManager>>;
+
+/// Returns the token of a `SharedTokenManager`, refreshing it if necessary.
+///
+/// Unlike calling [`get_token`](TokenManager::get_token) on the locked
+/// manager, the lock is only held for a moment and not while the token is
+/// refreshed. If the token has to be refreshed, one caller performs the
+/// refresh and concurrent callers wait for its result instead of sending their
+/// own requests to the token endpoint.
+///
+/// The send functions get the token of a `SharedTokenManager` the same way,
+/// see [`TokenProvider`](crate::TokenProvider).
+///
+/// # Errors
+///
+/// This function will return an error if the token could not be refreshed.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_shared_token(token_manager: &SharedTokenManager) -> Result<String, FcmError> {
+    let token_manager = token_manager.lock().await.share();
+    token_manager.get_token().await
+}
 
 /// A manager for handling OAuth tokens.
 ///
@@ -32,6 +86,18 @@ pub type SharedTokenManager = std::sync::Arc<tokio::sync::Mutex<TokenManager>>;
 /// a new one if necessary. Each token is valid for one hour (the maximum
 /// provided by Google).
 ///
+/// The token is stored internally, so all methods only need a shared
+/// reference and the manager can be shared between tasks as
+/// `Arc<TokenManager>`. Tasks, which find a valid token, never wait for a
+/// refresh in progress. If the token has to be refreshed, only one task
+/// performs the refresh and the others wait for its result.
+///
+/// Cloning a `TokenManager` is cheap, as the parsed credentials are shared
+/// between clones. The cached token is not shared: every clone starts with the
+/// token state of the original at the time of cloning and refreshes its own
+/// token from then on. Share the manager itself if multiple tasks should use
+/// the same token.
+///
 /// # Example
 ///
 /// ```rust no_run
@@ -40,21 +106,67 @@ pub type SharedTokenManager = std::sync::Arc<tokio::sync::Mutex<TokenManager>>;
 /// use oauth_fcm::TokenManager;
 ///
 /// # tokio_test::block_on(async {
-/// let mut token_manager = TokenManager::new(File::open("./tests/mock_credentials.json").expect("Failed to open file")).expect("Failed to create TokenManager");
+/// let token_manager = TokenManager::new(File::open("./tests/mock_credentials.json").expect("Failed to open file")).expect("Failed to create TokenManager");
 /// let token = token_manager.get_token().await.expect("Failed to get token");
 /// # });
 /// ```
 pub struct TokenManager {
-    token: Option<String>,
+    state: Arc<RwLock<CurrentToken>>,
+    source: Arc<TokenSource>,
+    token_cache: Option<Arc<dyn TokenCache>>,
+    token_uri: String,
+    scope: String,
+    http_transport: Arc<dyn HttpTransport>,
+    refresh_margin: Duration,
+    clock_skew: Duration,
+    refresh_lock: Arc<tokio::sync::Mutex<()>>,
+    self_signed_jwt: bool,
+    wall_clock_expiry: bool,
+    observer: Option<SharedObserver>,
+    interceptors: Vec<SharedInterceptor>,
+}
+
+/// Where a `TokenManager` gets its tokens from.
+#[derive(Debug)]
+enum TokenSource {
+    /// Tokens are exchanged for a JWT signed with the service account key.
+    ServiceAccount(ServiceAccountKey),
+    /// Tokens are requested from the metadata server of GCE, GKE or Cloud
+    /// Run, which knows the service account of the instance.
+    MetadataServer { project_id: Option<String> },
+}
+
+/// The cached token of a `TokenManager`.
+#[derive(Clone, Default)]
+struct CurrentToken {
+    token: Option<SecretString>,
     expires_at: Option<Instant>,
-    service_account_key: ServiceAccountKey,
+    issued_at_wall_clock: Option<SystemTime>,
+    expires_at_wall_clock: Option<SystemTime>,
+    authorization_header: Option<HeaderValue>,
+    last_refresh_failed: bool,
+}
+
+/// The state of the token of a `TokenManager`, returned by
+/// [`TokenManager::token_state`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenState {
+    /// No token was obtained yet.
+    Uninitialized,
+    /// The token is valid for the remaining duration.
+    Valid { remaining: Duration },
+    /// The token is expired and is refreshed on the next `get_token` call.
+    Expired,
 }
 
-#[derive(Deserialize, Debug)]
-struct ServiceAccountKey {
-    private_key: String,
-    client_email: String,
-    private_key_id: String,
+impl Clone for TokenManager {
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(self.state().clone())),
+            refresh_lock: Arc::new(tokio::sync::Mutex::new(())),
+            ..self.share()
+        }
+    }
 }
 
 impl TokenManager {
@@ -65,24 +177,144 @@ impl TokenManager {
     ///
     /// # Arguments
     ///
-    /// * `google_credentials_location` - A string slice that holds the path to
-    ///   the Google credentials JSON file.
+    /// * `credentials` - The Google service account credentials. This can be
+    ///   any [`IntoCredentials`] source, such as a `File`, a path or the JSON
+    ///   content itself.
     ///
     /// # Errors
     ///
     /// This function will return an error if the Google credentials could not
     /// be read or parsed.
     #[instrument(level = "info", skip_all)]
-    pub fn new<T: Read + Debug>(credentials: T) -> Result<Self, FcmError> {
+    pub fn new(credentials: impl IntoCredentials) -> Result<Self, FcmError> {
         info!("Creating new TokenManager");
 
-        let service_account_key = serde_json::from_reader(credentials)?;
+        let service_account_key = credentials.into_credentials()?;
+        let token_uri = service_account_key
+            .token_uri
+            .clone()
+            .unwrap_or_else(|| GOOGLE_TOKEN_URI.to_string());
+
+        Ok(Self::with_source(
+            TokenSource::ServiceAccount(service_account_key),
+            token_uri,
+        ))
+    }
+
+    /// Creates a new `TokenManager`, which requests its tokens from the
+    /// metadata server of GCE, GKE or Cloud Run.
+    ///
+    /// The token belongs to the service account of the instance. The metadata
+    /// server is found at `metadata.google.internal`, unless the environment
+    /// variable `GCE_METADATA_HOST` sets another host. Use
+    /// [`with_token_uri`](Self::with_token_uri) to set the full token URL
+    /// instead.
+    ///
+    /// The project ID isn't known, so it has to be set explicitly, e.g. with
+    /// [`FcmClientBuilder::project_id`](crate::FcmClientBuilder::project_id).
+    /// [`from_adc`](Self::from_adc) reads it from the metadata server.
+    #[must_use]
+    pub fn from_metadata_server() -> Self {
+        Self::metadata_server(&adc::metadata_host(), None)
+    }
+
+    /// Creates a `TokenManager` for the metadata server at `host`.
+    pub(crate) fn metadata_server(host: &str, project_id: Option<String>) -> Self {
+        Self::with_source(
+            TokenSource::MetadataServer { project_id },
+            adc::metadata_token_uri(host),
+        )
+    }
+
+    /// Creates a new `TokenManager` from the Application Default Credentials.
+    ///
+    /// Like other Google client libraries, the credentials are searched in
+    /// this order:
+    ///
+    /// 1. The key file at the path of the environment variable
+    ///    `GOOGLE_APPLICATION_CREDENTIALS`
+    /// 2. The well-known file of gcloud,
+    ///    `~/.config/gcloud/application_default_credentials.json` or
+    ///    `%APPDATA%\gcloud\application_default_credentials.json` on Windows
+    /// 3. The metadata server of GCE, GKE or Cloud Run, see
+    ///    [`from_metadata_server`](Self::from_metadata_server)
+    ///
+    /// Only service account keys are supported. The user credentials created
+    /// by `gcloud auth application-default login` are rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DefaultCredentialsNotFound` if none of the sources is
+    /// available. If a key file is found, but can't be read or parsed, its
+    /// error is returned instead of trying the next source.
+    #[instrument(level = "info")]
+    pub async fn from_adc() -> Result<Self, FcmError> {
+        adc::find_default_credentials(&http::default_client()).await
+    }
 
-        Ok(Self {
-            token: None,
-            expires_at: None,
-            service_account_key,
-        })
+    fn with_source(source: TokenSource, token_uri: String) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(CurrentToken::default())),
+            source: Arc::new(source),
+            token_cache: None,
+            token_uri,
+            scope: FCM_SCOPE.to_string(),
+            http_transport: Arc::new(ReqwestTransport::default()),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            clock_skew: DEFAULT_CLOCK_SKEW,
+            refresh_lock: Arc::new(tokio::sync::Mutex::new(())),
+            self_signed_jwt: false,
+            wall_clock_expiry: false,
+            observer: None,
+            interceptors: Vec::new(),
+        }
+    }
+
+    /// Creates a new `TokenManager` from the content of a JSON key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidCredentials` if the string isn't valid JSON or doesn't
+    /// contain a valid service account key.
+    pub fn from_json_str(json: &str) -> Result<Self, FcmError> {
+        Self::new(key_from_json(json.as_bytes())?)
+    }
+
+    /// Creates a new `TokenManager` from a base64 encoded JSON key, e.g. from
+    /// a Kubernetes secret.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidCredentials` naming the failed step if the string isn't
+    /// valid base64, the decoded content isn't valid JSON or it doesn't contain
+    /// a valid service account key.
+    pub fn from_base64(encoded: &str) -> Result<Self, FcmError> {
+        Self::new(key_from_base64(encoded)?)
+    }
+
+    /// Sets a [`TokenCache`], which is shared with other `TokenManager`s.
+    ///
+    /// Before refreshing its token, the manager looks for an unexpired token in
+    /// the cache. Every refreshed token is stored in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// use std::fs::File;
+    /// use std::sync::Arc;
+    ///
+    /// use oauth_fcm::InMemoryTokenCache;
+    /// use oauth_fcm::TokenManager;
+    ///
+    /// let cache = Arc::new(InMemoryTokenCache::new());
+    /// let token_manager = TokenManager::new(File::open("path_to_google_credentials.json").expect("Failed to open file"))
+    ///     .expect("Failed to create TokenManager")
+    ///     .with_token_cache(cache);
+    /// ```
+    #[must_use]
+    pub fn with_token_cache(mut self, token_cache: Arc<dyn TokenCache>) -> Self {
+        self.token_cache = Some(token_cache);
+        self
     }
 
     /// Returns the current OAuth token.
@@ -94,30 +326,573 @@ impl TokenManager {
     /// # Errors
     ///
     /// This function will return an error if the token could not be refreshed.
+    ///
+    /// # Cancellation
+    ///
+    /// This function is cancellation safe. See
+    /// [`refresh_token_with_url`](Self::refresh_token_with_url).
     #[instrument(level = "debug", skip(self))]
-    pub async fn get_token(&mut self) -> Result<String, FcmError> {
-        if let Some(token) = &self.token {
-            if !self.is_token_expired() {
-                debug!("Using cached token");
-                return Ok(token.clone());
+    pub async fn get_token(&self) -> Result<String, FcmError> {
+        self.usable_or_refreshed_token(None)
+            .await
+            .map(|token| token.expose().to_string())
+    }
+
+    /// Replaces a token, which was rejected by FCM.
+    ///
+    /// If another task already replaced the rejected token, its new token is
+    /// returned without another refresh. The token cache is skipped, as it may
+    /// still contain the rejected token.
+    pub(crate) async fn replace_rejected_token(
+        &self,
+        rejected_token: &str,
+    ) -> Result<String, FcmError> {
+        self.usable_or_refreshed_token(Some(rejected_token))
+            .await
+            .map(|token| token.expose().to_string())
+    }
+
+    async fn usable_or_refreshed_token(
+        &self,
+        rejected_token: Option<&str>,
+    ) -> Result<SecretString, FcmError> {
+        if let Some(token) = self.usable_token(rejected_token) {
+            debug!("Using cached token");
+            return Ok(token);
+        }
+
+        // Only one task refreshes the token at a time. Tasks, which find a
+        // valid token above, never wait here.
+        let _refreshing = self.refresh_lock.lock().await;
+
+        // Another task may have refreshed the token while this one waited.
+        if let Some(token) = self.usable_token(rejected_token) {
+            debug!("Using token refreshed by another task");
+            return Ok(token);
+        }
+
+        if rejected_token.is_none() {
+            if let Some(token) = self.get_token_from_cache().await {
+                debug!("Using token from token cache");
+                return Ok(token);
             }
         }
 
         debug!("Refreshing token");
-        self.refresh_token().await
+        self.refresh_token_locked(&self.token_uri).await
     }
 
-    /// Checks if the current OAuth token is expired.
+    /// Refreshes the token of a `SharedTokenManager` in the background.
+    ///
+    /// A spawned task refreshes the token when less than the
+    /// [refresh margin](Self::with_refresh_margin) of its lifetime remains, so
+    /// sends never wait for a refresh. Tokens with a lifetime shorter than the
+    /// margin are refreshed after half of their lifetime. Failed refreshes are
+    /// retried with an exponential backoff. Until the first refresh succeeded,
+    /// `get_token` refreshes the token on demand as usual.
+    ///
+    /// The refresh stops when the returned handle is dropped or shut down.
+    /// This function must be called within a Tokio runtime.
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// use std::fs::File;
+    ///
+    /// use oauth_fcm::create_shared_token_manager;
+    /// use oauth_fcm::TokenManager;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+    /// let refresh = TokenManager::spawn_auto_refresh(token_manager.clone());
+    ///
+    /// // Send messages with `token_manager`.
+    ///
+    /// refresh.shutdown().await;
+    /// # });
+    /// ```
+    pub fn spawn_auto_refresh(token_manager: SharedTokenManager) -> RefreshHandle {
+        RefreshHandle::spawn(token_manager)
+    }
+
+    /// Returns the time until the token should be refreshed.
+    pub(crate) fn refresh_delay(&self) -> Duration {
+        let Some(remaining) = self.remaining_at(Instant::now(), SystemTime::now()) else {
+            return Duration::ZERO;
+        };
+
+        remaining
+            .checked_sub(self.refresh_margin)
+            .filter(|delay| !delay.is_zero())
+            .unwrap_or(remaining / 2)
+    }
+
+    /// Authenticates with self-signed JWTs instead of OAuth access tokens.
+    ///
+    /// In this mode the token is a JWT signed with the service account key
+    /// and the FCM API as audience. It is created locally, so no request to
+    /// the OAuth token endpoint is made. The JWT is valid for one hour and is
+    /// re-created when it expires, just like an access token. A token cache is
+    /// not used in this mode.
+    ///
+    /// If FCM rejects the JWT, the send fails with a `401` server error. In
+    /// that case use the default OAuth token exchange instead.
+    #[must_use]
+    pub const fn with_self_signed_jwt(mut self) -> Self {
+        self.self_signed_jwt = true;
+        self
+    }
+
+    /// Sets the URL of the OAuth token endpoint used by
+    /// [`refresh_token`](Self::refresh_token).
+    ///
+    /// Defaults to the `token_uri` of the credentials, or
+    /// `https://oauth2.googleapis.com/token` if they don't contain one. The
+    /// URL is also used as audience of the JWT, which is exchanged for the
+    /// token. This is useful for proxies and for testing automatic refreshes
+    /// against a mock server.
+    #[must_use]
+    pub fn with_token_uri(mut self, token_uri: impl Into<String>) -> Self {
+        self.token_uri = token_uri.into();
+        self
+    }
+
+    /// Sets the OAuth scopes of the access token.
+    ///
+    /// Defaults to `https://www.googleapis.com/auth/firebase.messaging`. The
+    /// token can only be used for FCM, if the scopes include FCM, e.g. with
+    /// the broader `https://www.googleapis.com/auth/cloud-platform` scope.
+    /// Other scopes allow to use the token manager for other Google APIs.
+    /// Self-signed JWTs are not affected by the scopes.
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// use std::fs::File;
+    ///
+    /// use oauth_fcm::TokenManager;
+    ///
+    /// let token_manager = TokenManager::new(File::open("path_to_google_credentials.json").expect("Failed to open file"))
+    ///     .expect("Failed to create TokenManager")
+    ///     .with_scopes(["https://www.googleapis.com/auth/cloud-platform"]);
+    /// ```
+    #[must_use]
+    pub fn with_scopes<S: AsRef<str>>(mut self, scopes: impl IntoIterator<Item = S>) -> Self {
+        self.scope = scopes
+            .into_iter()
+            .map(|scope| scope.as_ref().to_owned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        self
+    }
+
+    /// Sets the HTTP client used for OAuth and FCM requests.
+    ///
+    /// By default every `TokenManager` creates its own client with the
+    /// [default timeouts](Self::with_timeouts), which is shared by its
+    /// clones. All requests, which use this token manager, are
+    /// sent with this client, so connections and TLS sessions are reused.
+    /// Setting a client allows to share it with the rest of the application
+    /// or to configure it, e.g. with a proxy.
+    ///
+    /// This is a shorthand for
+    /// [`with_http_transport`](Self::with_http_transport) with a
+    /// [`ReqwestTransport`].
+    ///
+    /// # Example
+    ///
+    /// ```rust no_run
+    /// use std::fs::File;
+    ///
+    /// use oauth_fcm::TokenManager;
+    ///
+    /// let client = reqwest::Client::new();
+    /// let token_manager = TokenManager::new(File::open("path_to_google_credentials.json").expect("Failed to open file"))
+    ///     .expect("Failed to create TokenManager")
+    ///     .with_http_client(client);
+    /// ```
+    #[must_use]
+    pub fn with_http_client(self, http_client: Client) -> Self {
+        self.with_http_transport(ReqwestTransport::new(http_client))
+    }
+
+    /// Sets the transport, which sends the OAuth and FCM requests.
+    ///
+    /// The default is a [`ReqwestTransport`]. A custom transport allows to
+    /// test code, which sends messages, without a server, see
+    /// [`HttpTransport`].
+    #[must_use]
+    pub fn with_http_transport(mut self, http_transport: impl HttpTransport + 'static) -> Self {
+        self.http_transport = Arc::new(http_transport);
+        self
+    }
+
+    /// Sets the timeouts of OAuth and FCM requests.
+    ///
+    /// `connect_timeout` limits how long establishing a connection may take
+    /// and `request_timeout` limits the whole request including reading the
+    /// response. They default to [`DEFAULT_CONNECT_TIMEOUT`] (10 seconds) and
+    /// [`DEFAULT_REQUEST_TIMEOUT`] (30 seconds). Requests, which time out,
+    /// fail with [`NetworkError::Timeout`].
+    ///
+    /// This replaces the HTTP client, so a client or transport set with
+    /// [`with_http_client`](Self::with_http_client) or
+    /// [`with_http_transport`](Self::with_http_transport) is discarded.
+    /// Configure the timeouts of such a client on the client itself.
+    ///
+    /// [`DEFAULT_CONNECT_TIMEOUT`]: crate::DEFAULT_CONNECT_TIMEOUT
+    /// [`DEFAULT_REQUEST_TIMEOUT`]: crate::DEFAULT_REQUEST_TIMEOUT
+    #[must_use]
+    pub fn with_timeouts(self, connect_timeout: Duration, request_timeout: Duration) -> Self {
+        self.with_http_client(http::client_with_timeouts(connect_timeout, request_timeout))
+    }
+
+    /// Returns the ID of the Firebase project from the credentials, if they
+    /// contain one.
+    #[must_use]
+    pub fn project_id(&self) -> Option<&str> {
+        match self.source.as_ref() {
+            TokenSource::ServiceAccount(key) => key.project_id.as_deref(),
+            TokenSource::MetadataServer { project_id } => project_id.as_deref(),
+        }
+    }
+
+    /// Returns the ID of the project, which is billed for the quota of the
+    /// requests, if the credentials contain a `quota_project_id`.
+    #[must_use]
+    pub fn quota_project_id(&self) -> Option<&str> {
+        match self.source.as_ref() {
+            TokenSource::ServiceAccount(key) => key.quota_project_id.as_deref(),
+            TokenSource::MetadataServer { .. } => None,
+        }
+    }
+
+    /// Returns the transport used for OAuth and FCM requests.
+    #[must_use]
+    pub const fn http_transport(&self) -> &Arc<dyn HttpTransport> {
+        &self.http_transport
+    }
+
+    /// Sets how long before its expiry the token is refreshed.
+    ///
+    /// `get_token` refreshes the token once less than the margin remains, so
+    /// a token can't expire while a request using it is in flight. Defaults to
+    /// 60 seconds.
+    #[must_use]
+    pub const fn with_refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    /// Sets how far the issue time of JWTs is back-dated.
+    ///
+    /// Google rejects JWTs, which are issued in the future, with
+    /// `Token used too early`. This happens, if the system clock is ahead of
+    /// Google's. Back-dating the issue time tolerates such a clock skew. The
+    /// expiry is shortened by the same amount, as JWTs are valid for at most
+    /// one hour after their issue time. Defaults to 30 seconds.
+    #[must_use]
+    pub const fn with_clock_skew(mut self, clock_skew: Duration) -> Self {
+        self.clock_skew = clock_skew;
+        self
+    }
+
+    /// Returns the back-dated issue time of a new JWT.
+    fn jwt_issued_at(&self) -> u64 {
+        unix_now().saturating_sub(self.clock_skew.as_secs())
+    }
+
+    /// Additionally checks the token expiry against the system clock.
+    ///
+    /// By default the expiry is tracked with the monotonic clock, which is
+    /// unaffected by changes of the system time. On some platforms the
+    /// monotonic clock doesn't advance while the machine is suspended, so
+    /// after a resume an expired token would still be considered valid. With
+    /// this option, the token is also considered expired when its expiry has
+    /// passed according to the system clock.
+    ///
+    /// If the system clock moved backwards since the token was issued, the
+    /// token is treated as expired and a warning is logged.
+    #[must_use]
+    pub const fn with_wall_clock_expiry(mut self) -> Self {
+        self.wall_clock_expiry = true;
+        self
+    }
+
+    /// Registers an observer, which is notified about every token refresh,
+    /// e.g. to collect metrics.
+    ///
+    /// Sends are observed by the client, see
+    /// [`FcmClientBuilder::observer`](crate::FcmClientBuilder::observer).
+    #[must_use]
+    pub fn with_observer(mut self, observer: Arc<dyn FcmObserver>) -> Self {
+        self.observer = Some(SharedObserver(observer));
+        self
+    }
+
+    /// Registers an interceptor, which runs around every token request, e.g.
+    /// to add headers. Interceptors run in the order they are registered.
+    ///
+    /// FCM requests are intercepted by the client, see
+    /// [`FcmClientBuilder::interceptor`](crate::FcmClientBuilder::interceptor).
+    #[must_use]
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn FcmInterceptor>) -> Self {
+        self.interceptors.push(SharedInterceptor(interceptor));
+        self
+    }
+
+    /// Returns `true` if this manager uses self-signed JWTs instead of OAuth
+    /// access tokens.
+    #[must_use]
+    pub const fn uses_self_signed_jwt(&self) -> bool {
+        self.self_signed_jwt
+    }
+
+    /// Returns the `Authorization` header value for the current OAuth token.
+    ///
+    /// The header value has the form `Bearer <token>` and is marked as
+    /// sensitive, so its `Debug` output doesn't contain the token. It is
+    /// cached until the token changes. Like `get_token`, this function
+    /// refreshes the token if necessary.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the token could not be refreshed.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn authorization_header(&self) -> Result<HeaderValue, FcmError> {
+        let token = self.usable_or_refreshed_token(None).await?;
+
+        if let Some(header) = &self.state().authorization_header {
+            return Ok(header.clone());
+        }
+
+        let mut header = HeaderValue::try_from(format!("Bearer {}", token.expose()))
+            .map_err(FcmError::InvalidAuthorizationHeader)?;
+        header.set_sensitive(true);
+
+        // The token may have been refreshed by another task in the meantime.
+        {
+            let mut state = self.state_mut();
+            if state.token.as_ref() == Some(&token) {
+                state.authorization_header = Some(header.clone());
+            }
+        }
+        Ok(header)
+    }
+
+    /// Returns a manager, which shares the token with this one.
+    pub(crate) fn share(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+            source: Arc::clone(&self.source),
+            token_cache: self.token_cache.clone(),
+            token_uri: self.token_uri.clone(),
+            scope: self.scope.clone(),
+            http_transport: Arc::clone(&self.http_transport),
+            refresh_margin: self.refresh_margin,
+            clock_skew: self.clock_skew,
+            refresh_lock: Arc::clone(&self.refresh_lock),
+            self_signed_jwt: self.self_signed_jwt,
+            wall_clock_expiry: self.wall_clock_expiry,
+            observer: self.observer.clone(),
+            interceptors: self.interceptors.clone(),
+        }
+    }
+
+    // The lock is never held across an await point, so it can only be
+    // poisoned by a panic while setting a token, which leaves a usable state.
+    fn state(&self) -> RwLockReadGuard<'_, CurrentToken> {
+        self.state.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn state_mut(&self) -> RwLockWriteGuard<'_, CurrentToken> {
+        self.state.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set_token(&self, token: SecretString, lifetime: Duration) {
+        self.set_token_at(token, lifetime, Instant::now(), SystemTime::now());
+    }
+
+    fn set_token_at(
+        &self,
+        token: SecretString,
+        lifetime: Duration,
+        now: Instant,
+        wall_clock_now: SystemTime,
+    ) {
+        *self.state_mut() = CurrentToken {
+            token: Some(token),
+            expires_at: Some(now + lifetime),
+            issued_at_wall_clock: Some(wall_clock_now),
+            expires_at_wall_clock: Some(wall_clock_now + lifetime),
+            authorization_header: None,
+            last_refresh_failed: false,
+        };
+    }
+
+    async fn get_token_from_cache(&self) -> Option<SecretString> {
+        if self.self_signed_jwt {
+            return None;
+        }
+
+        let token_cache = self.token_cache.as_ref()?;
+        let cached_token = token_cache.get(&self.token_cache_key()).await?;
+        let remaining = cached_token
+            .expires_at
+            .duration_since(SystemTime::now())
+            .ok()
+            .filter(|remaining| *remaining > self.refresh_margin)?;
+
+        self.set_token(cached_token.token.clone(), remaining);
+        Some(cached_token.token)
+    }
+
+    fn token_cache_key(&self) -> String {
+        match self.source.as_ref() {
+            TokenSource::ServiceAccount(key) => format!("{}|{}", key.client_email, self.scope),
+            TokenSource::MetadataServer { .. } => format!("{}|{}", self.token_uri, self.scope),
+        }
+    }
+
+    /// Returns the current token, unless it needs to be refreshed or is the
+    /// rejected token.
+    fn usable_token(&self, rejected_token: Option<&str>) -> Option<SecretString> {
+        let token = self.state().token.clone()?;
+        if self.is_token_expired() || rejected_token == Some(token.expose()) {
+            return None;
+        }
+
+        Some(token)
+    }
+
+    /// Checks if the current OAuth token is expired, or expires within the
+    /// refresh margin.
     ///
     /// This function is used internally by `get_token` and is not typically
     /// needed by users.
     #[instrument(level = "debug", skip(self))]
     pub fn is_token_expired(&self) -> bool {
-        self.expires_at.map_or(true, |expires_at| {
-            let expired = expires_at <= Instant::now();
-            debug!("Token expired: {}", expired);
-            expired
-        })
+        let expired = self.needs_refresh_at(Instant::now(), SystemTime::now());
+        debug!("Token expired: {}", expired);
+        expired
+    }
+
+    /// Returns the state of the token.
+    ///
+    /// Unlike [`is_token_expired`](Self::is_token_expired), a token within the
+    /// refresh margin is still reported as valid.
+    #[must_use]
+    pub fn token_state(&self) -> TokenState {
+        if !self.has_token() {
+            return TokenState::Uninitialized;
+        }
+
+        self.time_until_expiry()
+            .map_or(TokenState::Expired, |remaining| TokenState::Valid {
+                remaining,
+            })
+    }
+
+    /// Returns `true` if a token was obtained, even if it is expired by now.
+    #[must_use]
+    pub fn has_token(&self) -> bool {
+        self.state().token.is_some()
+    }
+
+    /// Returns the point in time at which the token expires, according to the
+    /// system clock.
+    #[must_use]
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.state().expires_at_wall_clock
+    }
+
+    /// Returns the remaining validity of the token, or `None` if there is no
+    /// token or it is expired.
+    #[must_use]
+    pub fn time_until_expiry(&self) -> Option<Duration> {
+        self.remaining_at(Instant::now(), SystemTime::now())
+    }
+
+    /// Returns `true` if the last attempt to refresh the token failed.
+    ///
+    /// The previous token, if any, is kept after a failed refresh, so it may
+    /// still be valid.
+    #[must_use]
+    pub fn last_refresh_failed(&self) -> bool {
+        self.state().last_refresh_failed
+    }
+
+    /// Returns the cached OAuth token and its remaining validity, if more than
+    /// the [refresh margin](Self::with_refresh_margin) of its lifetime remains.
+    ///
+    /// A token within the margin is not returned, as `get_token` would refresh
+    /// it. Unlike `get_token`, this function never refreshes the token and
+    /// never performs a network request, which makes it suitable for cheap
+    /// checks like readiness probes.
+    ///
+    /// The returned token is a copy owned by the caller, which is not zeroed
+    /// by the `zeroize` feature.
+    #[must_use]
+    pub fn try_cached_token(&self) -> Option<(String, Duration)> {
+        self.cached_token_at(Instant::now(), SystemTime::now())
+    }
+
+    fn cached_token_at(
+        &self,
+        now: Instant,
+        wall_clock_now: SystemTime,
+    ) -> Option<(String, Duration)> {
+        let token = self.state().token.as_ref()?.expose().to_string();
+        let remaining = self
+            .remaining_at(now, wall_clock_now)
+            .filter(|remaining| *remaining > self.refresh_margin)?;
+
+        Some((token, remaining))
+    }
+
+    /// Returns `true` if the token is expired or expires within the refresh
+    /// margin.
+    fn needs_refresh_at(&self, now: Instant, wall_clock_now: SystemTime) -> bool {
+        self.remaining_at(now, wall_clock_now)
+            .is_none_or(|remaining| remaining <= self.refresh_margin)
+    }
+
+    /// Returns the remaining validity of the token, or `None` if it is expired.
+    fn remaining_at(&self, now: Instant, wall_clock_now: SystemTime) -> Option<Duration> {
+        let (expires_at, issued_at_wall_clock, expires_at_wall_clock) = {
+            let state = self.state();
+            (
+                state.expires_at,
+                state.issued_at_wall_clock,
+                state.expires_at_wall_clock,
+            )
+        };
+        let remaining = expires_at?
+            .checked_duration_since(now)
+            .filter(|remaining| !remaining.is_zero())?;
+
+        if !self.wall_clock_expiry {
+            return Some(remaining);
+        }
+
+        let (Some(issued_at), Some(expires_at)) = (issued_at_wall_clock, expires_at_wall_clock)
+        else {
+            return Some(remaining);
+        };
+
+        if wall_clock_now < issued_at {
+            warn!(
+                "System clock moved backwards since the token was issued, treating it as expired"
+            );
+            return None;
+        }
+
+        let wall_clock_remaining = expires_at
+            .duration_since(wall_clock_now)
+            .ok()
+            .filter(|remaining| !remaining.is_zero())?;
+
+        Some(remaining.min(wall_clock_remaining))
     }
 
     /// Refreshes the current OAuth token.
@@ -129,16 +904,16 @@ impl TokenManager {
     ///
     /// This function will return an error if the token could not be refreshed.
     #[instrument(level = "info", skip(self))]
-    pub async fn refresh_token(&mut self) -> Result<String, FcmError> {
+    pub async fn refresh_token(&self) -> Result<String, FcmError> {
         info!("Refreshing token");
-        self.refresh_token_with_url("https://oauth2.googleapis.com/token")
-            .await
+        self.refresh_token_with_url(&self.token_uri).await
     }
 
     /// Refreshes the current OAuth token with a custom auth server URL.
     ///
     /// This function exists for testing purposes and is not typically needed by
-    /// users.
+    /// users. When using self-signed JWTs, the token is created locally and
+    /// the URL is not used.
     ///
     /// # Arguments
     ///
@@ -148,82 +923,248 @@ impl TokenManager {
     /// # Errors
     ///
     /// This function will return an error if the token could not be refreshed.
+    ///
+    /// # Cancellation
+    ///
+    /// This function is cancellation safe. The cached token is only replaced
+    /// after the auth server responded with a new token. If the future is
+    /// dropped before that, the previous token and its expiry are kept.
     #[instrument(level = "info", skip(self))]
-    pub async fn refresh_token_with_url(
-        &mut self,
-        auth_server_url: &str,
-    ) -> Result<String, FcmError> {
-        info!("Refreshing token with URL: {}", auth_server_url);
-        let signed_jwt = create_signed_jwt(&self.service_account_key)?;
-        let access_token_response = get_access_token(&signed_jwt, auth_server_url).await?;
+    pub async fn refresh_token_with_url(&self, auth_server_url: &str) -> Result<String, FcmError> {
+        let _refreshing = self.refresh_lock.lock().await;
+        self.refresh_token_locked(auth_server_url)
+            .await
+            .map(|token| token.expose().to_string())
+    }
+
+    /// Refreshes the token, while the refresh lock is held.
+    async fn refresh_token_locked(&self, auth_server_url: &str) -> Result<SecretString, FcmError> {
+        let started = Instant::now();
+        let result = self.request_token(auth_server_url).await;
+        if result.is_err() {
+            self.state_mut().last_refresh_failed = true;
+        }
+        if let Some(SharedObserver(observer)) = &self.observer {
+            observer.on_token_refresh(result.as_ref().map(|_| ()), started.elapsed());
+        }
+        result
+    }
 
+    async fn request_token(&self, auth_server_url: &str) -> Result<SecretString, FcmError> {
+        let transport = Intercepted {
+            transport: self.http_transport.as_ref(),
+            interceptors: &self.interceptors,
+        };
+        let access_token_response = match self.source.as_ref() {
+            TokenSource::ServiceAccount(key) if self.self_signed_jwt => {
+                return self.refresh_self_signed_jwt(key);
+            }
+            TokenSource::ServiceAccount(key) => {
+                info!("Refreshing token with URL: {}", auth_server_url);
+                let signed_jwt =
+                    create_signed_jwt(key, &self.scope, &self.token_uri, self.jwt_issued_at())?;
+                get_access_token(&transport, &signed_jwt, auth_server_url).await?
+            }
+            TokenSource::MetadataServer { .. } if self.self_signed_jwt => {
+                return Err(FcmError::InvalidCredentials(
+                    "self-signed JWTs require a service account key, the metadata server can't \
+                     sign them"
+                        .to_string(),
+                ));
+            }
+            TokenSource::MetadataServer { .. } => {
+                info!(
+                    "Requesting token from the metadata server: {}",
+                    auth_server_url
+                );
+                get_metadata_access_token(&transport, auth_server_url, &self.scope).await?
+            }
+        };
+
+        // Everything after this point is synchronous until the token is set,
+        // so a cancelled refresh can't leave a partially updated state behind.
+        let expires_in = access_token_response.lifetime()?;
         let new_token = access_token_response.access_token;
-        self.token = Some(new_token.clone());
-        self.expires_at =
-            Some(Instant::now() + Duration::from_secs(access_token_response.expires_in));
+        self.set_token(new_token.clone(), expires_in);
+
+        if let Some(token_cache) = &self.token_cache {
+            let cached_token = CachedToken {
+                token: new_token.clone(),
+                expires_at: SystemTime::now() + expires_in,
+            };
+            token_cache.put(&self.token_cache_key(), cached_token).await;
+        }
 
         info!("Token refreshed successfully");
         Ok(new_token)
     }
+
+    fn refresh_self_signed_jwt(&self, key: &ServiceAccountKey) -> Result<SecretString, FcmError> {
+        info!("Creating self-signed JWT");
+        let issued_at = self.jwt_issued_at();
+        let signed_jwt = SecretString::new(create_self_signed_jwt(key, issued_at)?);
+        let lifetime = (issued_at + JWT_LIFETIME_SECS).saturating_sub(unix_now());
+        self.set_token(signed_jwt.clone(), Duration::from_secs(lifetime));
+
+        Ok(signed_jwt)
+    }
 }
 
 #[instrument(level = "debug")]
-fn create_signed_jwt(service_account_key: &ServiceAccountKey) -> Result<String, FcmError> {
+fn create_signed_jwt(
+    service_account_key: &ServiceAccountKey,
+    scope: &str,
+    audience: &str,
+    issued_at: u64,
+) -> Result<String, FcmError> {
     debug!("Creating signed JWT");
-    let mut header = Header::new(jsonwebtoken::Algorithm::RS256);
-    header.kid = Some(service_account_key.private_key_id.clone());
+    let claims = json!({
+        "iss": service_account_key.client_email,
+        "scope": scope,
+        "aud": audience,
+        "exp": issued_at + JWT_LIFETIME_SECS,
+        "iat": issued_at
+    });
 
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .expect("Clock moved backwards! The time is before UNIX EPOCH, this should not happen!")
-        .as_secs();
+    let signed_jwt = sign_jwt(service_account_key, &claims)?;
+    debug!("Signed JWT created");
+    Ok(signed_jwt)
+}
 
+/// Creates a JWT, which is directly used as bearer token for the FCM API.
+#[instrument(level = "debug")]
+fn create_self_signed_jwt(
+    service_account_key: &ServiceAccountKey,
+    issued_at: u64,
+) -> Result<String, FcmError> {
+    debug!("Creating self-signed JWT");
     let claims = json!({
         "iss": service_account_key.client_email,
-        "scope": "https://www.googleapis.com/auth/firebase.messaging",
-        "aud": "https://oauth2.googleapis.com/token",
-        "exp": now + 3600,
-        "iat": now
+        "sub": service_account_key.client_email,
+        "aud": FCM_AUDIENCE,
+        "exp": issued_at + JWT_LIFETIME_SECS,
+        "iat": issued_at
     });
 
-    let encoding_key = EncodingKey::from_rsa_pem(service_account_key.private_key.as_bytes())?;
-    let signed_jwt = encode(&header, &claims, &encoding_key).map_err(FcmError::JwtEncodeError)?;
-    debug!("Signed JWT created");
+    let signed_jwt = sign_jwt(service_account_key, &claims)?;
+    debug!("Self-signed JWT created");
     Ok(signed_jwt)
 }
 
+fn sign_jwt(
+    service_account_key: &ServiceAccountKey,
+    claims: &serde_json::Value,
+) -> Result<String, FcmError> {
+    let mut header = Header::new(jsonwebtoken::Algorithm::RS256);
+    header.kid = Some(service_account_key.private_key_id.clone());
+
+    encode(&header, claims, &service_account_key.encoding_key).map_err(FcmError::JwtEncodeError)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("Clock moved backwards! The time is before UNIX EPOCH, this should not happen!")
+        .as_secs()
+}
+
 #[derive(Deserialize)]
 struct AccessTokenResponse {
-    access_token: String,
+    access_token: SecretString,
+    /// The lifetime of the token in seconds. Some OAuth compatible servers
+    /// omit it or send it as string.
+    #[serde(
+        default = "default_expires_in",
+        deserialize_with = "deserialize_expires_in"
+    )]
     expires_in: u64,
 }
 
-#[instrument(level = "debug")]
+impl AccessTokenResponse {
+    /// Returns the lifetime of the token, if it is plausible.
+    const fn lifetime(&self) -> Result<Duration, FcmError> {
+        if self.expires_in == 0 || self.expires_in > MAX_TOKEN_LIFETIME_SECS {
+            return Err(FcmError::InvalidTokenLifetime(self.expires_in));
+        }
+
+        Ok(Duration::from_secs(self.expires_in))
+    }
+}
+
+const fn default_expires_in() -> u64 {
+    DEFAULT_TOKEN_LIFETIME_SECS
+}
+
+fn deserialize_expires_in<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ExpiresIn {
+        Seconds(u64),
+        Text(String),
+    }
+
+    match Option::<ExpiresIn>::deserialize(deserializer)? {
+        None => Ok(DEFAULT_TOKEN_LIFETIME_SECS),
+        Some(ExpiresIn::Seconds(seconds)) => Ok(seconds),
+        Some(ExpiresIn::Text(text)) => text
+            .trim()
+            .parse()
+            .map_err(|_| D::Error::custom(format!("invalid expires_in {text:?}"))),
+    }
+}
+
+#[instrument(level = "debug", skip(transport))]
 async fn get_access_token(
+    transport: &dyn HttpTransport,
     signed_jwt: &str,
     auth_url: &str,
 ) -> Result<AccessTokenResponse, FcmError> {
     debug!("Getting access token from: {}", auth_url);
-    let client = Client::new();
     let params = [
         ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
         ("assertion", signed_jwt),
     ];
 
-    let response = client
-        .post(auth_url)
-        .form(&params)
-        .send()
+    let response = transport
+        .send(HttpRequest::post_form(auth_url, &params))
         .await
-        .map_err(NetworkError::SendRequestError)
         .map_oauth_err()?;
 
-    debug!("Response status: {}", response.status());
+    read_access_token_response(&response)
+}
+
+/// Requests an access token from the token URL of a metadata server.
+#[instrument(level = "debug", skip(transport))]
+async fn get_metadata_access_token(
+    transport: &dyn HttpTransport,
+    token_uri: &str,
+    scope: &str,
+) -> Result<AccessTokenResponse, FcmError> {
+    debug!(
+        "Getting access token from the metadata server: {}",
+        token_uri
+    );
+    // The metadata server expects the scopes separated by commas.
+    let scopes = scope.split_whitespace().collect::<Vec<_>>().join(",");
+    let query = serde_urlencoded::to_string([("scopes", scopes)]).unwrap_or_default();
+    let separator = if token_uri.contains('?') { '&' } else { '?' };
 
-    let access_token_response = response
-        .json::<AccessTokenResponse>()
-        .await
-        .map_err(NetworkError::ResponseError)
+    let request = HttpRequest::get(format!("{token_uri}{separator}{query}"))
+        .header(adc::METADATA_FLAVOR_HEADER, adc::METADATA_FLAVOR);
+    let response = transport.send(request).await.map_oauth_err()?;
+
+    read_access_token_response(&response)
+}
+
+fn read_access_token_response(response: &HttpResponse) -> Result<AccessTokenResponse, FcmError> {
+    debug!("Response status: {}", response.status);
+
+    if !response.is_success() {
+        return Err(oauth_response_error(response.status, response.text()));
+    }
+
+    let access_token_response = serde_json::from_slice::<AccessTokenResponse>(&response.body)
+        .map_err(NetworkError::InvalidResponse)
         .map_oauth_err()?;
 
     debug!("Access token obtained");
@@ -232,10 +1173,432 @@ async fn get_access_token(
 
 impl Debug for TokenManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        // Only the length of the token is printed, the header value is marked as
+        // sensitive and the key redacts its private key.
+        let token = state
+            .token
+            .as_ref()
+            .map(|token| format!("<redacted, {} chars>", token.expose().len()));
         f.debug_struct("TokenManager")
-            .field("token", &("[REDACTED]".to_string()))
-            .field("service_account_key", &("[REDACTED]".to_string()))
-            .field("expires_at", &self.expires_at)
+            .field("token", &token)
+            .field("authorization_header", &state.authorization_header)
+            .field("source", &self.source)
+            .field("expires_at", &state.expires_at)
+            .field("issued_at_wall_clock", &state.issued_at_wall_clock)
+            .field("expires_at_wall_clock", &state.expires_at_wall_clock)
+            .field("last_refresh_failed", &state.last_refresh_failed)
+            .field("token_cache", &self.token_cache.is_some())
+            .field("token_uri", &self.token_uri)
+            .field("scope", &self.scope)
+            .field("refresh_margin", &self.refresh_margin)
+            .field("clock_skew", &self.clock_skew)
+            .field("refresh_lock", &self.refresh_lock)
+            .field("http_transport", &"dyn HttpTransport")
+            .field("self_signed_jwt", &self.self_signed_jwt)
+            .field("wall_clock_expiry", &self.wall_clock_expiry)
+            .field("observer", &self.observer)
+            .field("interceptors", &self.interceptors)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    fn jwt_claims(jwt: &str) -> serde_json::Value {
+        let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256);
+        validation.insecure_disable_signature_validation();
+        jsonwebtoken::decode(
+            jwt,
+            &jsonwebtoken::DecodingKey::from_secret(&[]),
+            &validation,
+        )
+        .unwrap()
+        .claims
+    }
+
+    fn service_account_key(token_manager: &TokenManager) -> &ServiceAccountKey {
+        match token_manager.source.as_ref() {
+            TokenSource::ServiceAccount(key) => key,
+            TokenSource::MetadataServer { .. } => panic!("No service account key"),
+        }
+    }
+
+    fn token_manager_expiring_at(expires_at: Instant) -> TokenManager {
+        let token_manager =
+            TokenManager::new(File::open("tests/mock_credentials.json").unwrap()).unwrap();
+        let now = Instant::now();
+        token_manager.set_token_at(
+            SecretString::new("cached_token".to_string()),
+            expires_at - now,
+            now,
+            SystemTime::now(),
+        );
+        token_manager
+    }
+
+    fn wall_clock_token_manager(now: Instant, wall_clock_now: SystemTime) -> TokenManager {
+        let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .unwrap()
+            .with_wall_clock_expiry();
+        token_manager.set_token_at(
+            SecretString::new("cached_token".to_string()),
+            Duration::from_secs(3600),
+            now,
+            wall_clock_now,
+        );
+        token_manager
+    }
+
+    #[test]
+    fn test_cached_token_without_token() {
+        let token_manager =
+            TokenManager::new(File::open("tests/mock_credentials.json").unwrap()).unwrap();
+
+        assert!(token_manager.try_cached_token().is_none());
+    }
+
+    #[test]
+    fn test_cached_token_fresh() {
+        let now = Instant::now();
+        let token_manager = token_manager_expiring_at(now + Duration::from_secs(3600));
+
+        let (token, remaining) = token_manager
+            .cached_token_at(now, SystemTime::now())
+            .unwrap();
+        assert_eq!(token, "cached_token");
+        assert_eq!(remaining, Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_cached_token_near_expiry() {
+        let now = Instant::now();
+        let token_manager = token_manager_expiring_at(now + Duration::from_secs(3600));
+
+        let token_manager = token_manager.with_refresh_margin(Duration::ZERO);
+        let later = now + Duration::from_secs(3599);
+        let (_, remaining) = token_manager
+            .cached_token_at(later, SystemTime::now())
+            .unwrap();
+        assert_eq!(remaining, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_cached_token_expired() {
+        let now = Instant::now();
+        let token_manager = token_manager_expiring_at(now + Duration::from_secs(3600));
+
+        assert!(token_manager
+            .cached_token_at(now + Duration::from_secs(3600), SystemTime::now())
+            .is_none());
+        assert!(token_manager
+            .cached_token_at(now + Duration::from_secs(7200), SystemTime::now())
+            .is_none());
+    }
+
+    #[test]
+    fn test_needs_refresh_within_margin() {
+        let now = Instant::now();
+        let token_manager = token_manager_expiring_at(now + Duration::from_secs(3600))
+            .with_refresh_margin(Duration::from_secs(300));
+
+        assert!(!token_manager.needs_refresh_at(now, SystemTime::now()));
+        assert!(!token_manager.needs_refresh_at(now + Duration::from_secs(3299), SystemTime::now()));
+        assert!(token_manager.needs_refresh_at(now + Duration::from_secs(3300), SystemTime::now()));
+    }
+
+    #[test]
+    fn test_cached_token_within_margin() {
+        let now = Instant::now();
+        let token_manager = token_manager_expiring_at(now + Duration::from_secs(3600))
+            .with_refresh_margin(Duration::from_secs(300));
+
+        let (_, remaining) = token_manager
+            .cached_token_at(now + Duration::from_secs(3299), SystemTime::now())
+            .unwrap();
+        assert_eq!(remaining, Duration::from_secs(301));
+        assert!(token_manager
+            .cached_token_at(now + Duration::from_secs(3300), SystemTime::now())
+            .is_none());
+        assert!(token_manager
+            .cached_token_at(now + Duration::from_secs(3540), SystemTime::now())
+            .is_none());
+    }
+
+    fn access_token_response(body: serde_json::Value) -> AccessTokenResponse {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn test_expires_in_as_number() {
+        let response =
+            access_token_response(json!({ "access_token": "token", "expires_in": 3599 }));
+
+        assert_eq!(response.lifetime().unwrap(), Duration::from_secs(3599));
+    }
+
+    #[test]
+    fn test_expires_in_as_string() {
+        let response =
+            access_token_response(json!({ "access_token": "token", "expires_in": " 1800 " }));
+
+        assert_eq!(response.lifetime().unwrap(), Duration::from_secs(1800));
+    }
+
+    #[test]
+    fn test_missing_expires_in_defaults_to_one_hour() {
+        let response = access_token_response(json!({ "access_token": "token" }));
+        assert_eq!(response.lifetime().unwrap(), Duration::from_secs(3600));
+
+        let response =
+            access_token_response(json!({ "access_token": "token", "expires_in": null }));
+        assert_eq!(response.lifetime().unwrap(), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_invalid_expires_in_is_rejected() {
+        let result = serde_json::from_value::<AccessTokenResponse>(
+            json!({ "access_token": "token", "expires_in": "soon" }),
+        );
+        assert!(result.is_err());
+
+        let result = serde_json::from_value::<AccessTokenResponse>(
+            json!({ "access_token": "token", "expires_in": -1 }),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_implausible_expires_in_is_rejected() {
+        for expires_in in [0, MAX_TOKEN_LIFETIME_SECS + 1] {
+            let response =
+                access_token_response(json!({ "access_token": "token", "expires_in": expires_in }));
+
+            assert!(matches!(
+                response.lifetime(),
+                Err(FcmError::InvalidTokenLifetime(seconds)) if seconds == expires_in
+            ));
+        }
+    }
+
+    #[test]
+    fn test_signed_jwt_is_back_dated_by_clock_skew() {
+        let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .unwrap()
+            .with_clock_skew(Duration::from_secs(60));
+        let now = unix_now();
+
+        let jwt = create_signed_jwt(
+            service_account_key(&token_manager),
+            &token_manager.scope,
+            &token_manager.token_uri,
+            token_manager.jwt_issued_at(),
+        )
+        .unwrap();
+
+        let claims = jwt_claims(&jwt);
+        let issued_at = claims["iat"].as_u64().unwrap();
+        assert!(issued_at <= now - 60);
+        assert!(issued_at >= now - 61);
+        assert_eq!(
+            claims["exp"].as_u64().unwrap(),
+            issued_at + JWT_LIFETIME_SECS
+        );
+    }
+
+    #[test]
+    fn test_self_signed_jwt_lifetime_accounts_for_clock_skew() {
+        let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .unwrap()
+            .with_self_signed_jwt();
+
+        let jwt = token_manager
+            .refresh_self_signed_jwt(service_account_key(&token_manager))
+            .unwrap();
+
+        let claims = jwt_claims(jwt.expose());
+        let issued_at = claims["iat"].as_u64().unwrap();
+        assert!(issued_at <= unix_now() - DEFAULT_CLOCK_SKEW.as_secs());
+        assert_eq!(
+            claims["exp"].as_u64().unwrap(),
+            issued_at + JWT_LIFETIME_SECS
+        );
+        let remaining = token_manager.time_until_expiry().unwrap();
+        assert!(remaining <= Duration::from_secs(JWT_LIFETIME_SECS - DEFAULT_CLOCK_SKEW.as_secs()));
+    }
+
+    #[test]
+    fn test_signed_jwt_has_fcm_scope_by_default() {
+        let token_manager =
+            TokenManager::new(File::open("tests/mock_credentials.json").unwrap()).unwrap();
+
+        let jwt = create_signed_jwt(
+            service_account_key(&token_manager),
+            &token_manager.scope,
+            &token_manager.token_uri,
+            token_manager.jwt_issued_at(),
+        )
+        .unwrap();
+
+        assert_eq!(jwt_claims(&jwt)["scope"], FCM_SCOPE);
+    }
+
+    #[test]
+    fn test_signed_jwt_audience_is_token_uri_of_credentials() {
+        let mut credentials: serde_json::Value =
+            serde_json::from_reader(File::open("tests/mock_credentials.json").unwrap()).unwrap();
+        credentials["token_uri"] = json!("https://oauth2.example.com/token");
+        let token_manager = TokenManager::new(credentials).unwrap();
+
+        let jwt = create_signed_jwt(
+            service_account_key(&token_manager),
+            &token_manager.scope,
+            &token_manager.token_uri,
+            token_manager.jwt_issued_at(),
+        )
+        .unwrap();
+
+        assert_eq!(jwt_claims(&jwt)["aud"], "https://oauth2.example.com/token");
+    }
+
+    #[test]
+    fn test_signed_jwt_joins_custom_scopes() {
+        let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .unwrap()
+            .with_scopes([
+                "https://www.googleapis.com/auth/cloud-platform",
+                "https://www.googleapis.com/auth/firebase.messaging",
+            ]);
+
+        let jwt = create_signed_jwt(
+            service_account_key(&token_manager),
+            &token_manager.scope,
+            &token_manager.token_uri,
+            token_manager.jwt_issued_at(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            jwt_claims(&jwt)["scope"],
+            "https://www.googleapis.com/auth/cloud-platform https://www.googleapis.com/auth/firebase.messaging"
+        );
+    }
+
+    #[test]
+    fn test_token_state() {
+        let token_manager =
+            TokenManager::new(File::open("tests/mock_credentials.json").unwrap()).unwrap();
+        assert_eq!(token_manager.token_state(), TokenState::Uninitialized);
+        assert!(!token_manager.has_token());
+        assert!(token_manager.expires_at().is_none());
+        assert!(token_manager.time_until_expiry().is_none());
+
+        let now = Instant::now();
+        let token_manager = token_manager_expiring_at(now + Duration::from_secs(30));
+        let TokenState::Valid { remaining } = token_manager.token_state() else {
+            panic!("Token should be valid");
+        };
+        assert!(remaining <= Duration::from_secs(30));
+        assert!(token_manager.has_token());
+        assert!(token_manager.expires_at().unwrap() > SystemTime::now());
+
+        let token_manager = token_manager_expiring_at(now);
+        assert_eq!(token_manager.token_state(), TokenState::Expired);
+        assert!(token_manager.has_token());
+    }
+
+    #[test]
+    fn test_refresh_delay() {
+        let token_manager =
+            TokenManager::new(File::open("tests/mock_credentials.json").unwrap()).unwrap();
+        assert_eq!(token_manager.refresh_delay(), Duration::ZERO);
+
+        let now = Instant::now();
+        let token_manager = token_manager_expiring_at(now + Duration::from_secs(3600));
+        let delay = token_manager.refresh_delay();
+        assert!(delay <= Duration::from_secs(3540));
+        assert!(delay > Duration::from_secs(3480));
+
+        let token_manager = token_manager_expiring_at(now + Duration::from_secs(30));
+        let delay = token_manager.refresh_delay();
+        assert!(delay <= Duration::from_secs(15));
+        assert!(delay > Duration::from_secs(14));
+    }
+
+    #[test]
+    fn test_wall_clock_expiry_fresh() {
+        let now = Instant::now();
+        let wall_clock_now = SystemTime::now();
+        let token_manager = wall_clock_token_manager(now, wall_clock_now);
+
+        let later = now + Duration::from_secs(600);
+        let wall_clock_later = wall_clock_now + Duration::from_secs(1200);
+        let (_, remaining) = token_manager
+            .cached_token_at(later, wall_clock_later)
+            .unwrap();
+        assert_eq!(remaining, Duration::from_secs(2400));
+    }
+
+    #[test]
+    fn test_wall_clock_expiry_after_suspend() {
+        let now = Instant::now();
+        let wall_clock_now = SystemTime::now();
+        let token_manager = wall_clock_token_manager(now, wall_clock_now);
+
+        // The monotonic clock paused during a two hour suspend.
+        let after_resume = now + Duration::from_secs(600);
+        let wall_clock_after_resume = wall_clock_now + Duration::from_secs(7200);
+        assert!(token_manager
+            .remaining_at(after_resume, wall_clock_after_resume)
+            .is_none());
+        assert!(token_manager
+            .cached_token_at(after_resume, wall_clock_after_resume)
+            .is_none());
+    }
+
+    #[test]
+    fn test_wall_clock_expiry_after_backwards_jump() {
+        let now = Instant::now();
+        let wall_clock_now = SystemTime::now();
+        let token_manager = wall_clock_token_manager(now, wall_clock_now);
+
+        let later = now + Duration::from_secs(600);
+        let wall_clock_later = wall_clock_now - Duration::from_secs(300);
+        assert!(token_manager
+            .cached_token_at(later, wall_clock_later)
+            .is_none());
+    }
+
+    #[test]
+    fn test_wall_clock_ignored_by_default() {
+        let now = Instant::now();
+        let token_manager = token_manager_expiring_at(now + Duration::from_secs(3600));
+
+        let wall_clock_after_resume = SystemTime::now() + Duration::from_secs(7200);
+        assert!(token_manager
+            .cached_token_at(now + Duration::from_secs(600), wall_clock_after_resume)
+            .is_some());
+    }
+
+    #[test]
+    fn test_is_token_expired_uses_wall_clock() {
+        let now = Instant::now();
+        // Simulates a token, which was issued two hours ago according to the
+        // system clock, while the monotonic clock was paused.
+        let token_manager =
+            wall_clock_token_manager(now, SystemTime::now() - Duration::from_secs(7200));
+        assert!(token_manager.is_token_expired());
+
+        // Simulates a system clock, which moved backwards by an hour.
+        let token_manager =
+            wall_clock_token_manager(now, SystemTime::now() + Duration::from_secs(3600));
+        assert!(token_manager.is_token_expired());
+
+        let token_manager = wall_clock_token_manager(now, SystemTime::now());
+        assert!(!token_manager.is_token_expired());
+    }
+}
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+
+use crate::FcmError;
+use crate::HttpTransport;
+use crate::ReqwestTransport;
+use crate::TokenManager;
+
+/// A source of OAuth access tokens for the FCM API.
+///
+/// All send functions accept any `TokenProvider`. It is implemented for
+/// [`TokenManager`], [`SharedTokenManager`](crate::SharedTokenManager) and
+/// `Arc`s of other providers. Implement it to get tokens from somewhere else,
+/// e.g. an auth sidecar. [`StaticTokenProvider`] always returns the same
+/// token, which is useful in tests.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use async_trait::async_trait;
+/// use oauth_fcm::FcmError;
+/// use oauth_fcm::TokenProvider;
+///
+/// struct SidecarTokenProvider {
+///     client: reqwest::Client,
+/// }
+///
+/// #[async_trait]
+/// impl TokenProvider for SidecarTokenProvider {
+///     async fn get_token(&self) -> Result<String, FcmError> {
+///         let response = self
+///             .client
+///             .get("http://localhost:8081/token")
+///             .send()
+///             .await
+///             .map_err(|error| FcmError::OAuthNetworkError(oauth_fcm::NetworkError::SendRequestError(error)))?;
+///         response
+///             .text()
+///             .await
+///             .map_err(|error| FcmError::OAuthNetworkError(oauth_fcm::NetworkError::ResponseError(error)))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait TokenProvider: Send + Sync {
+    /// Returns a valid access token.
+    ///
+    /// This is called before every send, so implementations should cache the
+    /// token.
+    async fn get_token(&self) -> Result<String, FcmError>;
+
+    /// Returns a new token after FCM rejected `rejected_token` with `401`.
+    ///
+    /// The message is sent once more with the returned token. Return `None`
+    /// if a new token would be rejected as well. The default implementation
+    /// returns `None`, so rejected tokens aren't retried.
+    async fn refresh_rejected_token(
+        &self,
+        rejected_token: &str,
+    ) -> Result<Option<String>, FcmError> {
+        let _ = rejected_token;
+        Ok(None)
+    }
+
+    /// Returns the transport for FCM requests.
+    ///
+    /// The default implementation returns a [`ReqwestTransport`] with the
+    /// default timeouts, which is shared by all providers without their own
+    /// transport.
+    async fn http_transport(&self) -> Arc<dyn HttpTransport> {
+        static TRANSPORT: OnceLock<Arc<dyn HttpTransport>> = OnceLock::new();
+        Arc::clone(TRANSPORT.get_or_init(|| Arc::new(ReqwestTransport::default())))
+    }
+}
+
+#[async_trait]
+impl TokenProvider for TokenManager {
+    async fn get_token(&self) -> Result<String, FcmError> {
+        Self::get_token(self).await
+    }
+
+    async fn refresh_rejected_token(
+        &self,
+        rejected_token: &str,
+    ) -> Result<Option<String>, FcmError> {
+        // A new self-signed JWT would be rejected just like the old one.
+        if self.uses_self_signed_jwt() {
+            error!(
+                "FCM rejected the self-signed JWT. Use the OAuth token exchange by creating the \
+                 TokenManager without `with_self_signed_jwt`"
+            );
+            return Ok(None);
+        }
+
+        self.replace_rejected_token(rejected_token).await.map(Some)
+    }
+
+    async fn http_transport(&self) -> Arc<dyn HttpTransport> {
+        Arc::clone(Self::http_transport(self))
+    }
+}
+
+/// The manager is only locked for a moment and not while the token is
+/// refreshed, see [`get_shared_token`](crate::get_shared_token).
+#[async_trait]
+impl TokenProvider for tokio::sync::Mutex<TokenManager> {
+    async fn get_token(&self) -> Result<String, FcmError> {
+        let token_manager = self.lock().await.share();
+        token_manager.get_token().await
+    }
+
+    async fn refresh_rejected_token(
+        &self,
+        rejected_token: &str,
+    ) -> Result<Option<String>, FcmError> {
+        let token_manager = self.lock().await.share();
+        TokenProvider::refresh_rejected_token(&token_manager, rejected_token).await
+    }
+
+    async fn http_transport(&self) -> Arc<dyn HttpTransport> {
+        Arc::clone(self.lock().await.http_transport())
+    }
+}
+
+#[async_trait]
+impl<T: TokenProvider + ?Sized> TokenProvider for Arc<T> {
+    async fn get_token(&self) -> Result<String, FcmError> {
+        T::get_token(self).await
+    }
+
+    async fn refresh_rejected_token(
+        &self,
+        rejected_token: &str,
+    ) -> Result<Option<String>, FcmError> {
+        T::refresh_rejected_token(self, rejected_token).await
+    }
+
+    async fn http_transport(&self) -> Arc<dyn HttpTransport> {
+        T::http_transport(self).await
+    }
+}
+
+/// A [`TokenProvider`], which always returns the same token.
+///
+/// This is useful for testing code, which sends messages, against a mock
+/// server.
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::StaticTokenProvider;
+/// use oauth_fcm::TokenProvider;
+///
+/// # tokio_test::block_on(async {
+/// let token_provider = StaticTokenProvider::new("test-token");
+/// assert_eq!(token_provider.get_token().await.unwrap(), "test-token");
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct StaticTokenProvider(String);
+
+impl StaticTokenProvider {
+    /// Creates a provider, which always returns `token`.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+}
+
+impl std::fmt::Debug for StaticTokenProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("StaticTokenProvider")
+            .field(&("[REDACTED]".to_string()))
+            .finish()
+    }
+}
+
+#[async_trait]
+impl TokenProvider for StaticTokenProvider {
+    async fn get_token(&self) -> Result<String, FcmError> {
+        Ok(self.0.clone())
+    }
+}
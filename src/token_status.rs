@@ -0,0 +1,32 @@
+use crate::FcmError;
+use crate::FcmResponse;
+
+/// The status of a device token, see
+/// [`FcmClient::check_token_validity`](crate::FcmClient::check_token_validity).
+#[derive(Debug)]
+pub enum TokenStatus {
+    /// FCM accepted a message to the token.
+    Valid,
+    /// The token is unregistered or belongs to another project, see
+    /// [`FcmError::is_token_invalid`]. It should be deleted.
+    Invalid,
+    /// The status couldn't be determined, e.g. because FCM was unavailable
+    /// after all retries.
+    Unknown(FcmError),
+}
+
+impl TokenStatus {
+    pub(crate) fn new(result: Result<FcmResponse, FcmError>) -> Self {
+        match result {
+            Ok(_) => Self::Valid,
+            Err(error) if error.is_token_invalid() => Self::Invalid,
+            Err(error) => Self::Unknown(error),
+        }
+    }
+
+    /// Returns `true` if the token is valid.
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid)
+    }
+}
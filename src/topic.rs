@@ -0,0 +1,298 @@
+use serde::Deserialize;
+use serde_json::json;
+use tracing::instrument;
+
+use crate::error::fcm_response_error;
+use crate::error::NetworkError;
+use crate::error::ResultMapError;
+use crate::http::HttpRequest;
+use crate::message::topic_name;
+use crate::retry::retry_after;
+use crate::FcmError;
+use crate::HttpTransport;
+use crate::TokenProvider;
+
+const IID_ENDPOINT: &str = "https://iid.googleapis.com";
+
+/// The maximum number of device tokens per request of the Instance ID API.
+/// Larger batches are split into multiple requests.
+pub const MAX_TOPIC_BATCH_SIZE: usize = 1000;
+
+/// The result of subscribing devices to a topic or unsubscribing them.
+#[derive(Debug)]
+pub struct TopicManagementResult {
+    /// The number of devices, which were (un)subscribed.
+    pub success_count: usize,
+    /// The number of devices, which could not be (un)subscribed.
+    pub failure_count: usize,
+    /// The result for every device token, paired with the index of the token.
+    /// The results are in the order of the tokens.
+    ///
+    /// Errors contain the error of the Instance ID API, e.g. `NOT_FOUND` for
+    /// an unknown token or `INVALID_ARGUMENT` for a malformed one,
+    /// `MISSING_RESULT` if the response contained no result for the token, or
+    /// the error message of the request if the batch of the token failed as
+    /// a whole.
+    pub results: Vec<(usize, Result<(), String>)>,
+}
+
+impl TopicManagementResult {
+    fn new(results: Vec<(usize, Result<(), String>)>) -> Self {
+        let success_count = results.iter().filter(|(_, result)| result.is_ok()).count();
+
+        Self {
+            success_count,
+            failure_count: results.len() - success_count,
+            results,
+        }
+    }
+}
+
+/// The response of `batchAdd` and `batchRemove`.
+#[derive(Deserialize)]
+struct BatchResponse {
+    #[serde(default)]
+    results: Vec<BatchResult>,
+}
+
+/// The result for one token, which is empty on success.
+#[derive(Deserialize)]
+struct BatchResult {
+    error: Option<String>,
+}
+
+/// Subscribes devices to a topic.
+///
+/// The subscriptions are managed with the Instance ID API, which accepts at
+/// most [`MAX_TOPIC_BATCH_SIZE`] tokens per request. Larger batches are sent
+/// as multiple requests, one after another.
+///
+/// The topic can be given with or without the `/topics/` prefix.
+///
+/// # Errors
+///
+/// Returns `InvalidTopicName` before anything is sent if the topic name is
+/// invalid, and an error if the OAuth token could not be obtained or every
+/// request failed as a whole, e.g. due to missing permissions. Errors of
+/// single tokens are part of the returned [`TopicManagementResult`], as are
+/// the errors of failed batches, if other batches were applied.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use std::fs::File;
+///
+/// use oauth_fcm::{create_shared_token_manager, subscribe_to_topic};
+///
+/// # tokio_test::block_on(async {
+/// let tokens = vec!["device_token_1".to_string(), "device_token_2".to_string()];
+/// let token_manager = create_shared_token_manager(File::open("path_to_google_credentials.json").expect("Failed to open file")).expect("Failed to create SharedTokenManager");
+/// let result = subscribe_to_topic(&tokens, "news", &token_manager)
+///     .await
+///     .expect("Error while subscribing to the topic");
+///
+/// for (index, result) in &result.results {
+///     if let Err(error) = result {
+///         println!("Failed to subscribe {}: {}", tokens[*index], error);
+///     }
+/// }
+/// # });
+/// ```
+#[instrument(level = "info", skip(tokens, token_provider))]
+pub async fn subscribe_to_topic(
+    tokens: &[String],
+    topic: &str,
+    token_provider: &(impl TokenProvider + ?Sized),
+) -> Result<TopicManagementResult, FcmError> {
+    info!("Subscribing {} devices to topic {}", tokens.len(), topic);
+
+    subscribe_to_topic_with_url(
+        tokens,
+        topic,
+        token_provider,
+        &format!("{IID_ENDPOINT}/iid/v1:batchAdd"),
+    )
+    .await
+}
+
+/// Subscribes devices to a topic using a specific URL.
+///
+/// This function behaves exactly as [`subscribe_to_topic`], but allows
+/// specifying a custom `batchAdd` URL. This is only useful for testing.
+#[instrument(level = "debug", skip(tokens, token_provider))]
+pub async fn subscribe_to_topic_with_url(
+    tokens: &[String],
+    topic: &str,
+    token_provider: &(impl TokenProvider + ?Sized),
+    url: &str,
+) -> Result<TopicManagementResult, FcmError> {
+    manage_topic(tokens, topic, token_provider, url).await
+}
+
+/// Unsubscribes devices from a topic.
+///
+/// This function behaves exactly as [`subscribe_to_topic`], but removes the
+/// subscriptions.
+///
+/// # Errors
+///
+/// See [`subscribe_to_topic`].
+#[instrument(level = "info", skip(tokens, token_provider))]
+pub async fn unsubscribe_from_topic(
+    tokens: &[String],
+    topic: &str,
+    token_provider: &(impl TokenProvider + ?Sized),
+) -> Result<TopicManagementResult, FcmError> {
+    info!(
+        "Unsubscribing {} devices from topic {}",
+        tokens.len(),
+        topic
+    );
+
+    unsubscribe_from_topic_with_url(
+        tokens,
+        topic,
+        token_provider,
+        &format!("{IID_ENDPOINT}/iid/v1:batchRemove"),
+    )
+    .await
+}
+
+/// Unsubscribes devices from a topic using a specific URL.
+///
+/// This function behaves exactly as [`unsubscribe_from_topic`], but allows
+/// specifying a custom `batchRemove` URL. This is only useful for testing.
+#[instrument(level = "debug", skip(tokens, token_provider))]
+pub async fn unsubscribe_from_topic_with_url(
+    tokens: &[String],
+    topic: &str,
+    token_provider: &(impl TokenProvider + ?Sized),
+    url: &str,
+) -> Result<TopicManagementResult, FcmError> {
+    manage_topic(tokens, topic, token_provider, url).await
+}
+
+/// Sends the tokens to `batchAdd` or `batchRemove` in batches.
+///
+/// A failed batch doesn't stop the following ones, as the earlier batches are
+/// already applied. Its error is recorded for each of its tokens instead.
+async fn manage_topic(
+    tokens: &[String],
+    topic: &str,
+    token_provider: &(impl TokenProvider + ?Sized),
+    url: &str,
+) -> Result<TopicManagementResult, FcmError> {
+    let topic = format!("/topics/{}", topic_name(topic)?);
+    if tokens.is_empty() {
+        return Ok(TopicManagementResult::new(Vec::new()));
+    }
+
+    let mut access_token = token_provider.get_token().await?;
+    let transport = token_provider.http_transport().await;
+    let mut results = Vec::with_capacity(tokens.len());
+    let mut first_error = None;
+    let mut applied_batches = 0;
+
+    for (batch_index, batch) in tokens.chunks(MAX_TOPIC_BATCH_SIZE).enumerate() {
+        let offset = batch_index * MAX_TOPIC_BATCH_SIZE;
+        let batch_results = match send_batch(
+            batch,
+            &topic,
+            &mut access_token,
+            token_provider,
+            transport.as_ref(),
+            url,
+        )
+        .await
+        {
+            Ok(batch_results) => {
+                applied_batches += 1;
+                batch_results
+            }
+            Err(error) => {
+                let message = error.to_string();
+                first_error.get_or_insert(error);
+                vec![Err(message); batch.len()]
+            }
+        };
+        results.extend(
+            batch_results
+                .into_iter()
+                .enumerate()
+                .map(|(index, result)| (offset + index, result)),
+        );
+    }
+
+    // Nothing was applied, so the request error is more useful than the same
+    // error for every token.
+    if applied_batches == 0 {
+        if let Some(error) = first_error {
+            return Err(error);
+        }
+    }
+
+    let result = TopicManagementResult::new(results);
+    debug!(
+        success_count = result.success_count,
+        failure_count = result.failure_count,
+        "Topic management finished"
+    );
+    Ok(result)
+}
+
+/// Sends one batch of tokens and returns the result for every token.
+async fn send_batch(
+    batch: &[String],
+    topic: &str,
+    access_token: &mut String,
+    token_provider: &(impl TokenProvider + ?Sized),
+    transport: &dyn HttpTransport,
+    url: &str,
+) -> Result<Vec<Result<(), String>>, FcmError> {
+    let payload = json!({
+        "to": topic,
+        "registration_tokens": batch,
+    });
+    let post = |access_token: &str| -> Result<HttpRequest, FcmError> {
+        Ok(HttpRequest::post_json(url, &payload)?
+            .bearer_auth(access_token)?
+            .header("access_token_auth", "true"))
+    };
+
+    let mut res = transport.send(post(access_token)?).await.map_fcm_err()?;
+
+    // Rejected tokens are replaced once, as for sends.
+    if res.status == 401 {
+        if let Some(new_token) = token_provider.refresh_rejected_token(access_token).await? {
+            warn!("The Instance ID API rejected the access token, retrying once with a new token");
+            *access_token = new_token;
+            res = transport.send(post(access_token)?).await.map_fcm_err()?;
+        }
+    }
+
+    if !res.is_success() {
+        let text = res.text();
+        error!(
+            http.status = res.status,
+            body = %text,
+            "Instance ID API returned an error"
+        );
+        return Err(fcm_response_error(
+            res.status,
+            text,
+            retry_after(&res.headers),
+        ));
+    }
+
+    let response = serde_json::from_slice::<BatchResponse>(&res.body)
+        .map_err(NetworkError::InvalidResponse)
+        .map_fcm_err()?;
+    let mut batch_results = response.results.into_iter();
+    Ok((0..batch.len())
+        .map(|_| match batch_results.next() {
+            Some(BatchResult { error: None }) => Ok(()),
+            Some(BatchResult { error: Some(error) }) => Err(error),
+            None => Err("MISSING_RESULT".to_string()),
+        })
+        .collect())
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use crate::Message;
+
+/// Application specific checks of a message before it is sent.
+///
+/// Register a validator with
+/// [`FcmClientBuilder::payload_validator`](crate::FcmClientBuilder::payload_validator).
+/// It receives the message after the crate's own validation and runs once per
+/// call of [`FcmClient::send`](crate::FcmClient::send), before any request or
+/// retry. A rejection is returned as [`FcmError::PayloadRejected`] without
+/// sending the message.
+///
+/// Closures taking a `&Message` implement this trait.
+///
+/// [`FcmError::PayloadRejected`]: crate::FcmError::PayloadRejected
+///
+/// # Example
+///
+/// ```rust
+/// use oauth_fcm::Message;
+/// use oauth_fcm::MessageTarget;
+/// use oauth_fcm::PayloadValidator;
+///
+/// struct NoTopics;
+///
+/// impl PayloadValidator for NoTopics {
+///     fn validate(&self, message: &Message) -> Result<(), String> {
+///         match message.target() {
+///             MessageTarget::Topic(_) => Err("topics are not allowed".to_string()),
+///             _ => Ok(()),
+///         }
+///     }
+/// }
+/// ```
+pub trait PayloadValidator: Send + Sync {
+    /// Returns the reason, why the message must not be sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason of the rejection.
+    fn validate(&self, message: &Message) -> Result<(), String>;
+}
+
+impl<F> PayloadValidator for F
+where
+    F: Fn(&Message) -> Result<(), String> + Send + Sync,
+{
+    fn validate(&self, message: &Message) -> Result<(), String> {
+        self(message)
+    }
+}
+
+/// A registered [`PayloadValidator`], which can be printed by `Debug`.
+#[derive(Clone)]
+pub struct SharedValidator(pub Arc<dyn PayloadValidator>);
+
+impl std::fmt::Debug for SharedValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PayloadValidator")
+    }
+}
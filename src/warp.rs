@@ -0,0 +1,91 @@
+//! Integration with the [warp](https://docs.rs/warp) web framework.
+//!
+//! Requires the `warp` feature.
+
+use std::convert::Infallible;
+
+use ::warp::http::StatusCode;
+use ::warp::reject::Reject;
+use ::warp::Filter;
+use ::warp::Rejection;
+use ::warp::Reply;
+use serde_json::json;
+
+use crate::FcmClient;
+use crate::FcmError;
+
+impl Reject for FcmError {}
+
+/// Returns a filter, which provides the given `FcmClient` to the route
+/// handler.
+///
+/// Cloning the client is cheap, all clones share the token manager and the
+/// connection pool.
+///
+/// # Example
+///
+/// ```rust no_run
+/// use oauth_fcm::warp::with_fcm;
+/// use oauth_fcm::FcmClient;
+/// use oauth_fcm::FcmNotification;
+/// use oauth_fcm::Message;
+/// use warp::Filter;
+///
+/// async fn send(client: FcmClient) -> Result<impl warp::Reply, warp::Rejection> {
+///     let message = Message::builder()
+///         .token("DEVICE_TOKEN")
+///         .notification(FcmNotification::new("Title", "Body"))
+///         .build()
+///         .map_err(warp::reject::custom)?;
+///     client.send(&message).await.map_err(warp::reject::custom)?;
+///     Ok("FCM message sent successfully")
+/// }
+///
+/// # tokio_test::block_on(async {
+/// let client = FcmClient::builder()
+///     .credentials(std::path::Path::new("path_to_google_credentials.json"))
+///     .build()
+///     .await
+///     .expect("Failed to create FcmClient");
+/// let route = warp::post()
+///     .and(with_fcm(client))
+///     .and_then(send)
+///     .recover(oauth_fcm::warp::handle_rejection);
+/// # });
+/// ```
+pub fn with_fcm(
+    client: FcmClient,
+) -> impl Filter<Extract = (FcmClient,), Error = Infallible> + Clone {
+    ::warp::any().map(move || client.clone())
+}
+
+/// Converts an `FcmError` into a reply.
+///
+/// The status code is taken from [`FcmError::suggested_status_code`] and the
+/// body is a JSON object containing the error message and the
+/// [kind](FcmError::kind) of the error, as for axum.
+#[must_use]
+pub fn fcm_error_reply(error: &FcmError) -> impl Reply {
+    let status = StatusCode::from_u16(error.suggested_status_code())
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    ::warp::reply::with_status(
+        ::warp::reply::json(&json!({ "error": error.to_string(), "kind": error.kind() })),
+        status,
+    )
+}
+
+/// A rejection handler for use with [`Filter::recover`], which turns
+/// rejections caused by an `FcmError` into replies.
+///
+/// Route handlers can reject with an `FcmError` using
+/// `warp::reject::custom(error)`. All other rejections are passed on
+/// unchanged.
+// `Filter::recover` expects a function returning a future.
+#[allow(clippy::unused_async)]
+pub async fn handle_rejection(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    rejection
+        .find::<FcmError>()
+        .map(fcm_error_reply)
+        .ok_or(rejection)
+}
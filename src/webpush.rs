@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// Webpush specific options of an FCM message for browser clients.
+///
+/// Serialized into the `webpush` section of the message. It is combined with
+/// the `FcmNotification` and the data payload of the message, so only the
+/// browser specific parts need to be set here.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use oauth_fcm::WebpushConfig;
+/// use oauth_fcm::WebpushFcmOptions;
+///
+/// let webpush = WebpushConfig {
+///     headers: HashMap::from([
+///         ("TTL".to_string(), "3600".to_string()),
+///         ("Urgency".to_string(), "high".to_string()),
+///     ]),
+///     notification: Some(serde_json::json!({ "icon": "https://example.com/icon.png" })),
+///     fcm_options: Some(WebpushFcmOptions {
+///         link: Some("https://example.com/news".to_string()),
+///         ..WebpushFcmOptions::default()
+///     }),
+///     ..WebpushConfig::default()
+/// };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct WebpushConfig {
+    /// Webpush protocol headers, e.g. `TTL` or `Urgency`.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Data, which overrides the data payload of the message for browsers.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub data: HashMap<String, String>,
+    /// Web notification options, like `icon`, `badge` or `actions`. See the
+    /// [Notification API](https://developer.mozilla.org/en-US/docs/Web/API/Notification/Notification)
+    /// for the supported fields.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification: Option<serde_json::Value>,
+    /// Options for features provided by the FCM SDK for web.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fcm_options: Option<WebpushFcmOptions>,
+}
+
+impl WebpushConfig {
+    /// Returns `true` if no option is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+            && self.data.is_empty()
+            && self.notification.is_none()
+            && self.fcm_options.is_none()
+    }
+
+    /// Fills the fields, which aren't set, from `defaults`.
+    ///
+    /// Headers and data are merged by key, keeping the values of `self`. The
+    /// notification and the FCM options are kept as a whole, if set.
+    pub(crate) fn merge_defaults(&mut self, defaults: &Self) {
+        for (name, value) in &defaults.headers {
+            self.headers
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        for (key, value) in &defaults.data {
+            self.data
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        if self.notification.is_none() {
+            self.notification.clone_from(&defaults.notification);
+        }
+        if self.fcm_options.is_none() {
+            self.fcm_options.clone_from(&defaults.fcm_options);
+        }
+    }
+}
+
+/// Options for features provided by the FCM SDK for web.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct WebpushFcmOptions {
+    /// The link to open when the user clicks on the notification. Must be an
+    /// HTTPS URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    /// The label of the message in the delivery metrics of the Firebase
+    /// console, overriding the label of the message for browsers. See
+    /// [`MessageBuilder::analytics_label`](crate::MessageBuilder::analytics_label)
+    /// for the allowed labels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analytics_label: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_config_serializes_to_empty_object() {
+        let config = WebpushConfig::default();
+
+        assert!(config.is_empty());
+        assert_eq!(serde_json::to_value(&config).unwrap(), json!({}));
+    }
+
+    #[test]
+    fn test_config_serializes_all_sections() {
+        let config = WebpushConfig {
+            headers: HashMap::from([("TTL".to_string(), "60".to_string())]),
+            data: HashMap::from([("key".to_string(), "value".to_string())]),
+            notification: Some(json!({ "icon": "https://example.com/icon.png" })),
+            fcm_options: Some(WebpushFcmOptions {
+                link: Some("https://example.com".to_string()),
+                analytics_label: None,
+            }),
+        };
+
+        assert!(!config.is_empty());
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({
+                "headers": { "TTL": "60" },
+                "data": { "key": "value" },
+                "notification": { "icon": "https://example.com/icon.png" },
+                "fcm_options": { "link": "https://example.com" }
+            })
+        );
+    }
+}
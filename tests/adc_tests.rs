@@ -0,0 +1,169 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use mockito::Matcher;
+use oauth_fcm::create_shared_token_manager_from_adc;
+use oauth_fcm::FcmError;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+/// The tests change process wide environment variables, so they must not run
+/// concurrently.
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+const TOKEN_PATH: &str = "/computeMetadata/v1/instance/service-accounts/default/token";
+const PROJECT_ID_PATH: &str = "/computeMetadata/v1/project/project-id";
+
+/// Points every ADC source to `config_dir` and `metadata_host`.
+fn set_adc_env(credentials: Option<&str>, config_dir: &Path, metadata_host: &str) {
+    match credentials {
+        Some(path) => std::env::set_var("GOOGLE_APPLICATION_CREDENTIALS", path),
+        None => std::env::remove_var("GOOGLE_APPLICATION_CREDENTIALS"),
+    }
+    std::env::set_var("CLOUDSDK_CONFIG", config_dir);
+    std::env::set_var("GCE_METADATA_HOST", metadata_host);
+}
+
+fn config_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("oauth_fcm_adc_{}_{name}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn mock_metadata_token(server: &mut mockito::Server) -> mockito::Mock {
+    server
+        .mock("GET", TOKEN_PATH)
+        .match_header("Metadata-Flavor", "Google")
+        .match_query(Matcher::UrlEncoded(
+            "scopes".to_string(),
+            "https://www.googleapis.com/auth/firebase.messaging".to_string(),
+        ))
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": "metadata_access_token",
+                "expires_in": 3599,
+                "token_type": "Bearer",
+            })
+            .to_string(),
+        )
+        .create()
+}
+
+#[tokio::test]
+async fn metadata_server_token_is_requested_with_get() {
+    let mut server = mockito::Server::new_async().await;
+    let mock_token = mock_metadata_token(&mut server);
+
+    let token_manager = TokenManager::from_metadata_server()
+        .with_token_uri(format!("{}{TOKEN_PATH}", server.url()));
+
+    assert_eq!(token_manager.project_id(), None);
+    let token = token_manager.get_token().await.unwrap();
+    assert_eq!(token, "metadata_access_token");
+
+    mock_token.assert_async().await;
+}
+
+#[tokio::test]
+async fn adc_uses_google_application_credentials_first() {
+    let _env = ENV_LOCK.lock().await;
+    set_adc_env(
+        Some("tests/mock_credentials.json"),
+        &config_dir("env"),
+        "127.0.0.1:1",
+    );
+
+    let token_manager = create_shared_token_manager_from_adc().await.unwrap();
+    assert_eq!(
+        token_manager.lock().await.project_id(),
+        Some("mock_project_id")
+    );
+}
+
+#[tokio::test]
+async fn adc_reports_missing_credentials_file() {
+    let _env = ENV_LOCK.lock().await;
+    set_adc_env(
+        Some("tests/does_not_exist.json"),
+        &config_dir("missing"),
+        "127.0.0.1:1",
+    );
+
+    let error = TokenManager::from_adc().await.unwrap_err();
+    assert!(matches!(error, FcmError::CredentialsFileError { .. }));
+}
+
+#[tokio::test]
+async fn adc_uses_well_known_file() {
+    let _env = ENV_LOCK.lock().await;
+    let dir = config_dir("well_known");
+    std::fs::copy(
+        "tests/mock_credentials.json",
+        dir.join("application_default_credentials.json"),
+    )
+    .unwrap();
+    set_adc_env(None, &dir, "127.0.0.1:1");
+
+    let token_manager = TokenManager::from_adc().await.unwrap();
+    assert_eq!(token_manager.project_id(), Some("mock_project_id"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn adc_falls_back_to_metadata_server() {
+    let _env = ENV_LOCK.lock().await;
+    let mut server = mockito::Server::new_async().await;
+    let mock_project_id = server
+        .mock("GET", PROJECT_ID_PATH)
+        .match_header("Metadata-Flavor", "Google")
+        .with_status(200)
+        .with_header("Metadata-Flavor", "Google")
+        .with_body("metadata_project_id")
+        .create();
+    let mock_token = mock_metadata_token(&mut server);
+    set_adc_env(
+        None,
+        &config_dir("metadata"),
+        server.host_with_port().as_str(),
+    );
+
+    let token_manager = TokenManager::from_adc().await.unwrap();
+    assert_eq!(token_manager.project_id(), Some("metadata_project_id"));
+    let token = token_manager.get_token().await.unwrap();
+    assert_eq!(token, "metadata_access_token");
+
+    mock_project_id.assert_async().await;
+    mock_token.assert_async().await;
+}
+
+#[tokio::test]
+async fn adc_ignores_servers_without_metadata_flavor() {
+    let _env = ENV_LOCK.lock().await;
+    let mut server = mockito::Server::new_async().await;
+    let mock_project_id = server
+        .mock("GET", PROJECT_ID_PATH)
+        .with_status(200)
+        .with_body("<html>Login</html>")
+        .create();
+    set_adc_env(
+        None,
+        &config_dir("captive_portal"),
+        server.host_with_port().as_str(),
+    );
+
+    let error = TokenManager::from_adc().await.unwrap_err();
+    assert!(matches!(error, FcmError::DefaultCredentialsNotFound));
+
+    mock_project_id.assert_async().await;
+}
+
+#[tokio::test]
+async fn adc_without_any_source_is_an_error() {
+    let _env = ENV_LOCK.lock().await;
+    set_adc_env(None, &config_dir("none"), "127.0.0.1:1");
+
+    let error = TokenManager::from_adc().await.unwrap_err();
+    assert!(matches!(error, FcmError::DefaultCredentialsNotFound));
+}
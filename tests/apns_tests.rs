@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::fs::File;
+
+use mockito::Matcher;
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::send_fcm_message_with_config_and_url;
+use oauth_fcm::ApnsConfig;
+use oauth_fcm::ApnsPayload;
+use oauth_fcm::Aps;
+use oauth_fcm::MessageTarget;
+use oauth_fcm::PlatformConfig;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+use crate::test_helpers::TestData;
+
+mod test_helpers;
+
+#[tokio::test]
+async fn data_message_with_content_available() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Json(json!({
+            "message": {
+                "token": base.device_token,
+                "data": {
+                    "title": "Test title",
+                    "description": "Test description"
+                },
+                "apns": {
+                    "headers": {
+                        "apns-priority": "5",
+                        "apns-push-type": "background"
+                    },
+                    "payload": {
+                        "aps": { "content-available": 1 }
+                    }
+                }
+            }
+        })))
+        .with_status(200)
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let data = TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    };
+    let config = PlatformConfig {
+        apns: Some(ApnsConfig {
+            headers: HashMap::from([
+                ("apns-priority".to_string(), "5".to_string()),
+                ("apns-push-type".to_string(), "background".to_string()),
+            ]),
+            payload: Some(ApnsPayload {
+                aps: Aps {
+                    content_available: true,
+                    ..Aps::default()
+                },
+                ..ApnsPayload::default()
+            }),
+            ..ApnsConfig::default()
+        }),
+        ..PlatformConfig::default()
+    };
+
+    send_fcm_message_with_config_and_url(
+        &MessageTarget::Token(base.device_token.clone()),
+        None,
+        Some(data),
+        &config,
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send FCM message");
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
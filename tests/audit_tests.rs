@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use oauth_fcm::AuditEntry;
+use oauth_fcm::AuditOutcome;
+use oauth_fcm::AuditSink;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::HttpRequest;
+use oauth_fcm::HttpResponse;
+use oauth_fcm::HttpTransport;
+use oauth_fcm::InMemoryAuditSink;
+use oauth_fcm::Message;
+use oauth_fcm::NetworkError;
+use oauth_fcm::RetryConfig;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+const TOKEN_URI: &str = "https://oauth2.test/token";
+
+/// Answers the FCM requests with the given responses in order.
+struct ScriptedTransport {
+    responses: Mutex<VecDeque<HttpResponse>>,
+}
+
+impl ScriptedTransport {
+    fn new(responses: impl IntoIterator<Item = (u16, serde_json::Value)>) -> Self {
+        Self {
+            responses: Mutex::new(
+                responses
+                    .into_iter()
+                    .map(|(status, body)| HttpResponse::new(status, body.to_string()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ScriptedTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        if request.url == TOKEN_URI {
+            return Ok(HttpResponse::new(
+                200,
+                json!({
+                    "access_token": "mock_access_token",
+                    "token_type": "Bearer",
+                    "expires_in": 3600,
+                })
+                .to_string(),
+            ));
+        }
+
+        Ok(self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("Unexpected FCM request"))
+    }
+}
+
+fn delivered() -> (u16, serde_json::Value) {
+    (
+        200,
+        json!({ "name": "projects/mock_project_id/messages/1" }),
+    )
+}
+
+async fn client(transport: ScriptedTransport, audit_sink: Arc<dyn AuditSink>) -> FcmClient {
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(TOKEN_URI)
+        .with_http_transport(transport);
+
+    FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .project_id("mock_project_id")
+        .endpoint("https://fcm.test")
+        .retry(RetryConfig {
+            max_attempts: 3,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            jitter: false,
+        })
+        .audit_sink(audit_sink)
+        .build()
+        .await
+        .expect("Failed to create FcmClient")
+}
+
+fn message() -> Message {
+    Message::builder()
+        .token("mock_device_token")
+        .notification(FcmNotification::new("Secret title", "Secret body"))
+        .build()
+        .expect("Invalid message")
+}
+
+fn single_entry(sink: &InMemoryAuditSink) -> AuditEntry {
+    let entries = sink.entries();
+    assert_eq!(entries.len(), 1, "{entries:?}");
+    entries.into_iter().next().unwrap()
+}
+
+#[tokio::test]
+async fn successful_send_is_recorded() {
+    let sink = Arc::new(InMemoryAuditSink::new());
+    let client = client(ScriptedTransport::new([delivered()]), sink.clone()).await;
+    let message = message();
+
+    client.send(&message).await.expect("Failed to send");
+
+    let entry = single_entry(&sink);
+    assert_eq!(entry.project_id, "mock_project_id");
+    assert_eq!(entry.target, "device mock…oken");
+    assert_eq!(
+        entry.message_size,
+        message.to_request_body().to_string().len()
+    );
+    assert!(!entry.validate_only);
+    assert_eq!(
+        entry.outcome,
+        AuditOutcome::Delivered {
+            message_id: Some("projects/mock_project_id/messages/1".to_string())
+        }
+    );
+    let debug = format!("{entry:?}");
+    assert!(!debug.contains("mock_device_token"), "{debug}");
+    assert!(!debug.contains("Secret"), "{debug}");
+}
+
+#[tokio::test]
+async fn retried_send_is_recorded_once() {
+    let sink = Arc::new(InMemoryAuditSink::new());
+    let transport = ScriptedTransport::new([
+        (503, json!({ "error": { "status": "UNAVAILABLE" } })),
+        delivered(),
+    ]);
+    let client = client(transport, sink.clone()).await;
+
+    client.send(&message()).await.expect("Failed to send");
+
+    let entry = single_entry(&sink);
+    assert!(matches!(entry.outcome, AuditOutcome::Delivered { .. }));
+}
+
+#[tokio::test]
+async fn permanent_failure_is_recorded_with_error_code() {
+    let sink = Arc::new(InMemoryAuditSink::new());
+    let transport = ScriptedTransport::new([(
+        404,
+        json!({
+            "error": {
+                "code": 404,
+                "message": "Requested entity was not found.",
+                "status": "NOT_FOUND",
+                "details": [{
+                    "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                    "errorCode": "UNREGISTERED"
+                }]
+            }
+        }),
+    )]);
+    let client = client(transport, sink.clone()).await;
+
+    client.send(&message()).await.unwrap_err();
+
+    let entry = single_entry(&sink);
+    assert_eq!(
+        entry.outcome,
+        AuditOutcome::Failed {
+            error_code: "UNREGISTERED".to_string()
+        }
+    );
+}
+
+#[tokio::test]
+async fn panicking_sink_does_not_fail_the_send() {
+    struct PanickingSink;
+
+    impl AuditSink for PanickingSink {
+        fn record(&self, _entry: AuditEntry) {
+            panic!("audit log unavailable");
+        }
+    }
+
+    let client = client(
+        ScriptedTransport::new([delivered()]),
+        Arc::new(PanickingSink),
+    )
+    .await;
+
+    client.send(&message()).await.expect("Failed to send");
+}
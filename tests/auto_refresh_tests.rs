@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+#[tokio::test]
+async fn background_refresh_refreshes_short_lived_tokens() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    // Shorter than the refresh margin, so the token is refreshed after half
+    // of its lifetime.
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 2,
+            })
+            .to_string(),
+        )
+        .expect_at_least(2)
+        .create();
+
+    let token_manager = Arc::new(tokio::sync::Mutex::new(
+        TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create TokenManager")
+            .with_token_uri(base.mock_auth_url()),
+    ));
+
+    let refresh = TokenManager::spawn_auto_refresh(Arc::clone(&token_manager));
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    refresh.shutdown().await;
+
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test]
+async fn dropping_the_handle_stops_the_refresh() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 1,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let token_manager = Arc::new(tokio::sync::Mutex::new(
+        TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create TokenManager")
+            .with_token_uri(base.mock_auth_url()),
+    ));
+
+    let refresh = TokenManager::spawn_auto_refresh(Arc::clone(&token_manager));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    drop(refresh);
+    tokio::time::sleep(Duration::from_millis(800)).await;
+
+    mock_auth.assert_async().await;
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "axum")]
+
+use std::fs::File;
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+use axum::extract::FromRequestParts;
+use axum::http::Request;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use oauth_fcm::axum::TokenManagerExtractor;
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::FcmError;
+use oauth_fcm::NetworkError;
+use oauth_fcm::SharedTokenManager;
+use serde_json::json;
+
+async fn response_of(error: FcmError) -> (StatusCode, serde_json::Value) {
+    let response = error.into_response();
+    let status = response.status();
+    assert_eq!(response.headers()["content-type"], "application/json");
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn fcm_error(status: u16, error_code: &str) -> FcmError {
+    let body = json!({
+        "error": {
+            "code": status,
+            "message": "Error message",
+            "status": "INVALID_ARGUMENT",
+            "details": [{
+                "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                "errorCode": error_code
+            }]
+        }
+    });
+
+    FcmError::FcmNetworkError(NetworkError::ServerError(
+        status,
+        Some(body.to_string()),
+        None,
+    ))
+}
+
+#[tokio::test]
+async fn invalid_payload_is_bad_request() {
+    let (status, body) = response_of(FcmError::FcmInvalidPayloadError).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["kind"], "FcmInvalidPayloadError");
+    assert_eq!(body["error"], FcmError::FcmInvalidPayloadError.to_string());
+}
+
+#[tokio::test]
+async fn unregistered_token_is_gone() {
+    let (status, body) = response_of(fcm_error(404, "UNREGISTERED")).await;
+
+    assert_eq!(status, StatusCode::GONE);
+    assert_eq!(body["kind"], "FcmNetworkError");
+}
+
+#[tokio::test]
+async fn oauth_failure_is_bad_gateway() {
+    let error = FcmError::OAuthServerError {
+        status: 400,
+        error: "invalid_grant".to_string(),
+        error_description: None,
+    };
+
+    let (status, body) = response_of(error).await;
+
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["kind"], "OAuthServerError");
+}
+
+#[tokio::test]
+async fn missing_project_id_is_internal_server_error() {
+    let (status, body) = response_of(FcmError::MissingProjectId).await;
+
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["kind"], "MissingProjectId");
+}
+
+#[derive(Clone)]
+struct AppState {
+    token_manager: SharedTokenManager,
+}
+
+impl FromRef<AppState> for SharedTokenManager {
+    fn from_ref(state: &AppState) -> Self {
+        state.token_manager.clone()
+    }
+}
+
+#[tokio::test]
+async fn extractor_takes_token_manager_from_state() {
+    let token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    let state = AppState {
+        token_manager: token_manager.clone(),
+    };
+
+    let (mut parts, ()) = Request::new(()).into_parts();
+    let TokenManagerExtractor(extracted) =
+        TokenManagerExtractor::from_request_parts(&mut parts, &state)
+            .await
+            .unwrap();
+
+    assert!(Arc::ptr_eq(&extracted, &token_manager));
+}
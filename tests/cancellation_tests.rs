@@ -0,0 +1,198 @@
+use std::fs::File;
+use std::time::Duration;
+
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::send_fcm_message_with_url;
+use oauth_fcm::SharedTokenManager;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+
+use crate::test_helpers::FcmBaseTest;
+use crate::test_helpers::TestData;
+
+mod test_helpers;
+
+/// Starts a server, which accepts connections but never responds. The
+/// connections are kept open until the returned handle is aborted.
+async fn start_unresponsive_server() -> (String, tokio::task::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind listener");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let mut connections: Vec<TcpStream> = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    (url, handle)
+}
+
+fn mock_token_response(server: &mut mockito::Server, base: &FcmBaseTest) -> mockito::Mock {
+    server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create()
+}
+
+fn new_token_manager() -> TokenManager {
+    TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+}
+
+#[tokio::test]
+async fn cancelled_refresh_keeps_previous_token() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+    let mock_auth = mock_token_response(&mut server, &base);
+    let (unresponsive_url, handle) = start_unresponsive_server().await;
+
+    let token_manager = new_token_manager();
+    token_manager
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let cancelled = tokio::time::timeout(
+        Duration::from_millis(200),
+        token_manager.refresh_token_with_url(&unresponsive_url),
+    )
+    .await;
+    assert!(cancelled.is_err(), "Refresh should not have completed");
+
+    let (token, _) = token_manager
+        .try_cached_token()
+        .expect("Previous token should still be cached");
+    assert_eq!(token, base.access_token);
+    assert!(!token_manager.is_token_expired());
+
+    handle.abort();
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test]
+async fn cancelled_initial_refresh_leaves_manager_usable() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+    let mock_auth = mock_token_response(&mut server, &base);
+    let (unresponsive_url, handle) = start_unresponsive_server().await;
+
+    let token_manager: SharedTokenManager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+
+    let cancelled = tokio::time::timeout(Duration::from_millis(200), async {
+        token_manager
+            .lock()
+            .await
+            .refresh_token_with_url(&unresponsive_url)
+            .await
+    })
+    .await;
+    assert!(cancelled.is_err(), "Refresh should not have completed");
+
+    let guard = token_manager
+        .try_lock()
+        .expect("Token manager should not be locked after cancellation");
+    assert!(guard.try_cached_token().is_none());
+    let token = guard
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+    assert_eq!(token, base.access_token);
+
+    handle.abort();
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test]
+async fn token_manager_is_not_locked_during_fcm_request() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+    let mock_auth = mock_token_response(&mut server, &base);
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header(
+            "authorization",
+            format!("Bearer {}", base.access_token).as_str(),
+        )
+        .with_status(200)
+        .expect(1)
+        .create();
+    let (unresponsive_url, handle) = start_unresponsive_server().await;
+
+    let token_manager: SharedTokenManager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let data = TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    };
+
+    let mut send = Box::pin(send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(&data),
+        &token_manager,
+        &unresponsive_url,
+    ));
+
+    // Poll the send until the request is in flight, then check the lock.
+    let pending = tokio::time::timeout(Duration::from_millis(200), &mut send).await;
+    assert!(pending.is_err(), "Send should not have completed");
+    assert!(
+        token_manager.try_lock().is_ok(),
+        "Token manager should not be locked while the FCM request is in flight"
+    );
+
+    drop(send);
+
+    send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(&data),
+        &token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send FCM message after cancellation");
+
+    handle.abort();
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "test-util")]
+
+use std::fs::File;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use oauth_fcm::ChaosTransport;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::HttpRequest;
+use oauth_fcm::HttpResponse;
+use oauth_fcm::HttpTransport;
+use oauth_fcm::Message;
+use oauth_fcm::NetworkError;
+use oauth_fcm::RetryConfig;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+const TOKEN_URI: &str = "https://oauth2.test/token";
+
+/// Answers every request successfully.
+struct HealthyTransport;
+
+#[async_trait]
+impl HttpTransport for HealthyTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        let body = if request.url == TOKEN_URI {
+            json!({
+                "access_token": "mock_access_token",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+        } else {
+            json!({ "name": "projects/mock_project_id/messages/1" })
+        };
+        Ok(HttpResponse::new(200, body.to_string()))
+    }
+}
+
+async fn client(transport: &Arc<ChaosTransport<HealthyTransport>>, max_attempts: u32) -> FcmClient {
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(TOKEN_URI)
+        .with_http_transport(Arc::clone(transport));
+
+    FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .project_id("mock_project_id")
+        .endpoint("https://fcm.test")
+        .retry(RetryConfig {
+            max_attempts,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            jitter: false,
+        })
+        .build()
+        .await
+        .expect("Failed to create FcmClient")
+}
+
+fn message(token: &str) -> Message {
+    Message::builder()
+        .token(token)
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .build()
+        .expect("Invalid message")
+}
+
+#[tokio::test]
+async fn retry_recovers_from_injected_transient_failure() {
+    let transport = Arc::new(ChaosTransport::new(HealthyTransport).fail_nth(1, 503));
+    let client = client(&transport, 3).await;
+
+    let response = client
+        .send(&message("mock_device_token"))
+        .await
+        .expect("The retry should recover");
+
+    assert!(response.message_id.is_some());
+    assert_eq!(transport.request_count(), 2);
+}
+
+#[tokio::test]
+async fn injected_failure_is_returned_without_retry() {
+    let transport = Arc::new(ChaosTransport::new(HealthyTransport).fail_nth(1, 503));
+    let client = client(&transport, 1).await;
+
+    let error = client
+        .send(&message("mock_device_token"))
+        .await
+        .unwrap_err();
+
+    assert!(error.is_retryable());
+    assert_eq!(transport.request_count(), 1);
+}
+
+#[tokio::test]
+async fn matching_tokens_are_unregistered() {
+    let transport = Arc::new(
+        ChaosTransport::new(HealthyTransport).unregistered_if(|token| token.starts_with("stale")),
+    );
+    let client = client(&transport, 3).await;
+
+    let error = client
+        .send(&message("stale_device_token"))
+        .await
+        .unwrap_err();
+    client
+        .send(&message("fresh_device_token"))
+        .await
+        .expect("Failed to send");
+
+    assert!(error.is_token_invalid());
+    assert_eq!(transport.request_count(), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn every_request_is_delayed() {
+    let transport =
+        Arc::new(ChaosTransport::new(HealthyTransport).delay(Duration::from_millis(200)));
+    let client = client(&transport, 1).await;
+    let start = tokio::time::Instant::now();
+
+    client
+        .send(&message("mock_device_token"))
+        .await
+        .expect("Failed to send");
+
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
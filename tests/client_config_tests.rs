@@ -0,0 +1,146 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmClientConfig;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::Message;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::test_helpers::mock_token_endpoint;
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+/// A config file, which contains the FCM config in a section.
+#[derive(Deserialize)]
+struct AppConfig {
+    fcm: FcmClientConfig,
+}
+
+fn parse(toml: &str) -> FcmClientConfig {
+    toml::from_str::<AppConfig>(toml)
+        .expect("Failed to parse config")
+        .fcm
+}
+
+#[tokio::test]
+async fn client_from_toml_config_sends_message() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    mock_token_endpoint(&mut server, &base);
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create();
+
+    let mut credentials: serde_json::Value =
+        serde_json::from_slice(&std::fs::read("tests/mock_credentials.json").unwrap()).unwrap();
+    credentials["token_uri"] = base.mock_auth_url().into();
+    let config = parse(&format!(
+        r#"
+        [fcm]
+        credentials = "{}"
+        endpoint = "{}"
+        timeout_secs = 5
+        max_in_flight = 10
+
+        [fcm.retry]
+        max_attempts = 2
+        initial_backoff_ms = 10
+        "#,
+        STANDARD.encode(credentials.to_string()),
+        server.url(),
+    ));
+
+    let client = FcmClient::from_config(config)
+        .await
+        .expect("Failed to create FcmClient");
+    let message = Message::builder()
+        .token(base.device_token.as_str())
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .build()
+        .expect("Failed to build message");
+    client.send(&message).await.expect("Failed to send message");
+
+    assert_eq!(client.project_id(), "mock_project_id");
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn client_from_config_reads_credentials_file() {
+    let config = parse(
+        r#"
+        [fcm]
+        project_id = "config_project_id"
+        credentials_path = "tests/mock_credentials.json"
+        "#,
+    );
+
+    let client = FcmClient::from_config(config)
+        .await
+        .expect("Failed to create FcmClient");
+
+    assert_eq!(client.project_id(), "config_project_id");
+}
+
+#[tokio::test]
+async fn missing_credentials_file_fails_at_build_time() {
+    // Deserializing doesn't touch the file.
+    let config = parse(
+        r#"
+        [fcm]
+        credentials_path = "tests/missing_credentials.json"
+        "#,
+    );
+
+    let error = FcmClient::from_config(config).await.unwrap_err();
+
+    assert!(matches!(error, FcmError::CredentialsFileError { .. }));
+}
+
+#[test]
+fn config_rejects_invalid_settings() {
+    let cases = [
+        "",
+        r#"
+        credentials_path = "tests/mock_credentials.json"
+        credentials = "e30="
+        "#,
+        r#"
+        credentials_path = "tests/mock_credentials.json"
+        max_in_flight = 0
+        "#,
+        r#"
+        credentials_path = "tests/mock_credentials.json"
+        in_flight_timeout_ms = 100
+        "#,
+    ];
+
+    for case in cases {
+        let config = parse(&format!("[fcm]\n{case}"));
+        let error = config.into_builder().map(|_| ()).unwrap_err();
+
+        assert!(
+            matches!(error, FcmError::InvalidClientConfig(_)),
+            "unexpected error for {case}: {error}"
+        );
+    }
+}
+
+#[test]
+fn config_rejects_unknown_fields() {
+    let error = toml::from_str::<AppConfig>(
+        r#"
+        [fcm]
+        credentials_pth = "tests/mock_credentials.json"
+        "#,
+    )
+    .map(|_| ())
+    .unwrap_err();
+
+    assert!(error.to_string().contains("credentials_pth"), "{error}");
+}
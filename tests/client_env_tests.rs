@@ -0,0 +1,165 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmClientBuilder;
+use oauth_fcm::FcmError;
+
+/// The tests change process wide environment variables, so they must not run
+/// concurrently.
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+const VARS: [&str; 5] = [
+    "FCM_PROJECT_ID",
+    "GOOGLE_APPLICATION_CREDENTIALS",
+    "FCM_CREDENTIALS_B64",
+    "FCM_REQUEST_TIMEOUT_SECS",
+    "FCM_SEND_CONCURRENCY",
+];
+
+/// Sets the given variables and removes the other ones read by `from_env`.
+fn set_env(vars: &[(&str, &str)]) {
+    for var in VARS {
+        std::env::remove_var(var);
+    }
+    for (var, value) in vars {
+        std::env::set_var(var, value);
+    }
+}
+
+fn env_error(result: Result<FcmClientBuilder, FcmError>) -> (&'static str, String) {
+    match result {
+        Err(FcmError::InvalidEnvVar { var, reason }) => (var, reason),
+        result => panic!("Expected InvalidEnvVar, got {result:?}"),
+    }
+}
+
+#[tokio::test]
+async fn client_from_env_with_credentials_path() {
+    let _lock = ENV_LOCK.lock().await;
+    set_env(&[
+        (
+            "GOOGLE_APPLICATION_CREDENTIALS",
+            "tests/mock_credentials.json",
+        ),
+        ("FCM_PROJECT_ID", "env_project_id"),
+        ("FCM_REQUEST_TIMEOUT_SECS", "5"),
+        ("FCM_SEND_CONCURRENCY", "8"),
+    ]);
+
+    let client = FcmClient::from_env()
+        .await
+        .expect("Failed to create FcmClient");
+
+    assert_eq!(client.project_id(), "env_project_id");
+}
+
+#[tokio::test]
+async fn client_from_env_with_base64_credentials() {
+    let _lock = ENV_LOCK.lock().await;
+    let json = std::fs::read("tests/mock_credentials.json").unwrap();
+    set_env(&[("FCM_CREDENTIALS_B64", &STANDARD.encode(json))]);
+
+    let client = FcmClient::from_env()
+        .await
+        .expect("Failed to create FcmClient");
+
+    assert_eq!(client.project_id(), "mock_project_id");
+}
+
+#[tokio::test]
+async fn builder_from_env_can_be_overridden() {
+    let _lock = ENV_LOCK.lock().await;
+    set_env(&[
+        (
+            "GOOGLE_APPLICATION_CREDENTIALS",
+            "tests/mock_credentials.json",
+        ),
+        ("FCM_PROJECT_ID", "env_project_id"),
+    ]);
+
+    let client = FcmClientBuilder::from_env()
+        .expect("Invalid environment")
+        .project_id("code_project_id")
+        .build()
+        .await
+        .expect("Failed to create FcmClient");
+
+    assert_eq!(client.project_id(), "code_project_id");
+}
+
+#[test]
+fn from_env_requires_credentials() {
+    let _lock = ENV_LOCK.blocking_lock();
+    set_env(&[("FCM_PROJECT_ID", "env_project_id")]);
+
+    let (var, reason) = env_error(FcmClientBuilder::from_env());
+
+    assert_eq!(var, "GOOGLE_APPLICATION_CREDENTIALS");
+    assert!(reason.contains("FCM_CREDENTIALS_B64"), "{reason}");
+}
+
+#[test]
+fn from_env_rejects_conflicting_credentials() {
+    let _lock = ENV_LOCK.blocking_lock();
+    set_env(&[
+        (
+            "GOOGLE_APPLICATION_CREDENTIALS",
+            "tests/mock_credentials.json",
+        ),
+        ("FCM_CREDENTIALS_B64", "e30="),
+    ]);
+
+    let (var, reason) = env_error(FcmClientBuilder::from_env());
+
+    assert_eq!(var, "FCM_CREDENTIALS_B64");
+    assert!(
+        reason.contains("GOOGLE_APPLICATION_CREDENTIALS"),
+        "{reason}"
+    );
+}
+
+#[test]
+fn from_env_rejects_invalid_numbers() {
+    let _lock = ENV_LOCK.blocking_lock();
+    let cases = [
+        ("FCM_REQUEST_TIMEOUT_SECS", "five"),
+        ("FCM_REQUEST_TIMEOUT_SECS", "-1"),
+        ("FCM_SEND_CONCURRENCY", "0"),
+    ];
+
+    for (invalid_var, value) in cases {
+        set_env(&[
+            (
+                "GOOGLE_APPLICATION_CREDENTIALS",
+                "tests/mock_credentials.json",
+            ),
+            (invalid_var, value),
+        ]);
+
+        let (var, _) = env_error(FcmClientBuilder::from_env());
+
+        assert_eq!(var, invalid_var, "unexpected variable for {value:?}");
+    }
+}
+
+#[tokio::test]
+async fn client_from_env_names_missing_project_id() {
+    let _lock = ENV_LOCK.lock().await;
+    let mut credentials: serde_json::Value =
+        serde_json::from_slice(&std::fs::read("tests/mock_credentials.json").unwrap()).unwrap();
+    credentials.as_object_mut().unwrap().remove("project_id");
+    set_env(&[(
+        "FCM_CREDENTIALS_B64",
+        &STANDARD.encode(credentials.to_string()),
+    )]);
+
+    let error = FcmClient::from_env().await.unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::InvalidEnvVar {
+            var: "FCM_PROJECT_ID",
+            ..
+        }
+    ));
+}
@@ -0,0 +1,714 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use mockito::Matcher;
+use mockito::Mock;
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::AndroidConfig;
+use oauth_fcm::AndroidNotification;
+use oauth_fcm::ApnsConfig;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::Message;
+use oauth_fcm::MessageTarget;
+use oauth_fcm::TokenManager;
+use oauth_fcm::TokenStatus;
+use serde_json::json;
+
+use crate::test_helpers::mock_token_endpoint;
+use crate::test_helpers::refreshed_token_manager;
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+fn message(base: &FcmBaseTest) -> Message {
+    Message::builder()
+        .token(base.device_token.as_str())
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .build()
+        .expect("Failed to build message")
+}
+
+#[tokio::test]
+async fn client_sends_to_project_at_endpoint() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header(
+            "authorization",
+            format!("Bearer {}", base.access_token).as_str(),
+        )
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .create();
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(format!("{}/", server.url()))
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+
+    let response = client
+        .send(&message(&base))
+        .await
+        .expect("Failed to send FCM message");
+
+    assert_eq!(client.project_id(), "mock_project_id");
+    assert_eq!(
+        response.message_id.as_deref(),
+        Some("projects/mock_project_id/messages/1")
+    );
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn client_request_times_out() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+
+    server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body_from_request(|_| {
+            std::thread::sleep(Duration::from_millis(500));
+            Vec::new()
+        })
+        .create();
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .timeout(Duration::from_millis(50))
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+
+    let error = client.send(&message(&base)).await.unwrap_err();
+
+    assert!(matches!(&error, FcmError::FcmNetworkError(error) if error.is_timeout()));
+}
+
+#[tokio::test]
+async fn client_builder_fails_on_invalid_credentials() {
+    let error = FcmClient::builder()
+        .credentials("not a service account key")
+        .project_id("mock_project_id")
+        .build()
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, FcmError::SerializationError(_)));
+}
+
+#[tokio::test]
+async fn client_builder_requires_credentials() {
+    let error = FcmClient::builder()
+        .project_id("mock_project_id")
+        .build()
+        .await
+        .unwrap_err();
+    assert!(matches!(error, FcmError::InvalidClientConfig(_)));
+}
+
+#[tokio::test]
+async fn client_builder_uses_project_id_of_credentials() {
+    let client = FcmClient::builder()
+        .credentials(std::path::Path::new("tests/mock_credentials.json"))
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    assert_eq!(client.project_id(), "mock_project_id");
+
+    let client = FcmClient::builder()
+        .credentials(std::path::Path::new("tests/mock_credentials.json"))
+        .project_id("other_project_id")
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    assert_eq!(client.project_id(), "other_project_id");
+}
+
+#[tokio::test]
+async fn client_builder_waits_for_locked_token_manager() {
+    let token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    let guard = token_manager.lock().await;
+
+    let build = tokio::spawn(
+        FcmClient::builder()
+            .token_manager(Arc::clone(&token_manager))
+            .build(),
+    );
+    tokio::task::yield_now().await;
+    assert!(!build.is_finished());
+    drop(guard);
+
+    let client = build.await.unwrap().expect("Failed to build FcmClient");
+    assert_eq!(client.project_id(), "mock_project_id");
+}
+
+#[tokio::test]
+async fn client_builder_fails_without_project_id() {
+    let mut credentials: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("tests/mock_credentials.json").unwrap())
+            .unwrap();
+    credentials.as_object_mut().unwrap().remove("project_id");
+
+    let error = FcmClient::builder()
+        .credentials(credentials)
+        .build()
+        .await
+        .unwrap_err();
+    assert!(matches!(error, FcmError::MissingProjectId));
+}
+
+#[tokio::test]
+async fn client_sends_quota_project_header() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header("x-goog-user-project", "billing_project_id")
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create();
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .quota_project_id("billing_project_id")
+        .endpoint(server.url())
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    client
+        .send(&message(&base))
+        .await
+        .expect("Failed to send message");
+
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn client_uses_quota_project_of_credentials() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    mock_token_endpoint(&mut server, &base);
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header("x-goog-user-project", "billing_project_id")
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create();
+
+    let mut credentials: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("tests/mock_credentials.json").unwrap())
+            .unwrap();
+    credentials["quota_project_id"] = "billing_project_id".into();
+    let token_manager = TokenManager::new(credentials)
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url());
+    assert_eq!(token_manager.quota_project_id(), Some("billing_project_id"));
+
+    let client = FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .endpoint(server.url())
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    client
+        .send(&message(&base))
+        .await
+        .expect("Failed to send message");
+
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn client_sends_message_accepted_by_payload_validator() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create();
+
+    let validations = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&validations);
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .payload_validator(Arc::new(move |_: &Message| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }))
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    client
+        .send(&message(&base))
+        .await
+        .expect("Failed to send message");
+
+    assert_eq!(validations.load(Ordering::SeqCst), 1);
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn client_rejects_message_without_request() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .expect(0)
+        .create();
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .payload_validator(Arc::new(|message: &Message| match message.target() {
+            MessageTarget::Token(_) => Err("only topics are allowed".to_string()),
+            _ => Ok(()),
+        }))
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    let error = client.send(&message(&base)).await.unwrap_err();
+
+    assert!(
+        matches!(&error, FcmError::PayloadRejected(reason) if reason == "only topics are allowed")
+    );
+    assert_eq!(error.suggested_status_code(), 400);
+    assert!(!error.is_retryable());
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn client_merges_default_platform_configs() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+    let default_channel = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::PartialJson(json!({
+            "message": {
+                "android": { "notification": { "channel_id": "default" } },
+                "apns": { "headers": { "apns-topic": "com.example.app" } }
+            }
+        })))
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create();
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .default_android(AndroidConfig {
+            notification: Some(AndroidNotification {
+                channel_id: Some("default".to_string()),
+                ..AndroidNotification::default()
+            }),
+            ..AndroidConfig::default()
+        })
+        .default_apns(ApnsConfig {
+            headers: HashMap::from([("apns-topic".to_string(), "com.example.app".to_string())]),
+            ..ApnsConfig::default()
+        })
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    client
+        .send(&message(&base))
+        .await
+        .expect("Failed to send message");
+    default_channel.assert();
+
+    let overridden_channel = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::PartialJson(json!({
+            "message": {
+                "android": { "notification": { "channel_id": "alerts" } },
+                "apns": { "headers": { "apns-topic": "com.example.app" } }
+            }
+        })))
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/2" }).to_string())
+        .expect(1)
+        .create();
+    let message = Message::builder()
+        .token(base.device_token.as_str())
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .android(AndroidConfig {
+            notification: Some(AndroidNotification {
+                channel_id: Some("alerts".to_string()),
+                ..AndroidNotification::default()
+            }),
+            ..AndroidConfig::default()
+        })
+        .build()
+        .expect("Failed to build message");
+    client.send(&message).await.expect("Failed to send message");
+    overridden_channel.assert();
+}
+
+async fn client_with_default_data(server: &mut mockito::Server, base: &FcmBaseTest) -> FcmClient {
+    let token_manager = refreshed_token_manager(server, base).await;
+    FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .default_data([("app_min_version", "2.1"), ("sent_by", "backend")])
+        .build()
+        .await
+        .expect("Failed to build FcmClient")
+}
+
+fn mock_data(server: &mut mockito::Server, base: &FcmBaseTest, data: serde_json::Value) -> Mock {
+    server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::PartialJson(json!({ "message": { "data": data } })))
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create()
+}
+
+#[tokio::test]
+async fn client_merges_default_data() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let client = client_with_default_data(&mut server, &base).await;
+    let mock_fcm = mock_data(
+        &mut server,
+        &base,
+        json!({ "app_min_version": "3.0", "sent_by": "backend", "chat_id": "42" }),
+    );
+
+    let message = Message::builder()
+        .token(base.device_token.as_str())
+        .data(&json!({ "app_min_version": "3.0", "chat_id": "42" }))
+        .build()
+        .expect("Failed to build message");
+    client.send(&message).await.expect("Failed to send message");
+
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn client_adds_default_data_to_notification_only_message() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let client = client_with_default_data(&mut server, &base).await;
+    let mock_fcm = mock_data(
+        &mut server,
+        &base,
+        json!({ "app_min_version": "2.1", "sent_by": "backend" }),
+    );
+
+    client
+        .send(&message(&base))
+        .await
+        .expect("Failed to send message");
+
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn client_sends_message_with_only_default_data() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let client = client_with_default_data(&mut server, &base).await;
+    let mock_fcm = mock_data(
+        &mut server,
+        &base,
+        json!({ "app_min_version": "2.1", "sent_by": "backend" }),
+    );
+
+    let message = Message::builder()
+        .token(base.device_token.as_str())
+        .data(&json!({}))
+        .build()
+        .expect("Failed to build message");
+    client.send(&message).await.expect("Failed to send message");
+
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn client_rejects_reserved_default_data_key() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .expect(0)
+        .create();
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .default_data([("google.sender", "backend")])
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    let error = client.send(&message(&base)).await.unwrap_err();
+
+    assert!(matches!(error, FcmError::ReservedDataKey { key } if key == "google.sender"));
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn sandbox_client_sends_every_message_as_validate_only() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::PartialJson(json!({ "validate_only": true })))
+        .with_status(200)
+        .with_body(
+            json!({ "name": "projects/mock_project_id/messages/fake_message_id" }).to_string(),
+        )
+        .expect(3)
+        .create();
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .sandbox(true)
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    let topic_message = Message::builder()
+        .topic("news")
+        .data(&json!({ "key": "value" }))
+        .validate_only(false)
+        .build()
+        .expect("Failed to build message");
+    for message in [message(&base), topic_message.clone(), topic_message] {
+        assert!(!message.validate_only());
+        client.send(&message).await.expect("Failed to send message");
+    }
+
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn check_token_validity_maps_responses_to_statuses() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+    let mut mock_fcm = |token: &str, status: usize, body: serde_json::Value| {
+        server
+            .mock("POST", base.fcm_path.as_str())
+            .match_body(Matcher::PartialJson(json!({
+                "validate_only": true,
+                "message": { "token": token, "data": {} },
+            })))
+            .with_status(status)
+            .with_body(body.to_string())
+            .expect(1)
+            .create()
+    };
+    let mocks = [
+        mock_fcm(
+            "valid_token",
+            200,
+            json!({ "name": "projects/mock_project_id/messages/fake_message_id" }),
+        ),
+        mock_fcm(
+            "unregistered_token",
+            404,
+            json!({
+                "error": {
+                    "code": 404,
+                    "status": "NOT_FOUND",
+                    "details": [{
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "UNREGISTERED"
+                    }]
+                }
+            }),
+        ),
+        mock_fcm(
+            "forbidden_token",
+            403,
+            json!({ "error": { "code": 403, "status": "PERMISSION_DENIED" } }),
+        ),
+    ];
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    let tokens = ["valid_token", "unregistered_token", "forbidden_token"].map(String::from);
+    let statuses = client.check_token_validity(&tokens, 2).await;
+
+    let tokens_in_order: Vec<_> = statuses.iter().map(|(token, _)| token.as_str()).collect();
+    assert_eq!(tokens_in_order, tokens);
+    assert!(matches!(statuses[0].1, TokenStatus::Valid));
+    assert!(matches!(statuses[1].1, TokenStatus::Invalid));
+    assert!(matches!(
+        &statuses[2].1,
+        TokenStatus::Unknown(error) if error.suggested_status_code() == 502
+    ));
+    for mock in mocks {
+        mock.assert();
+    }
+}
+
+#[tokio::test]
+async fn client_sends_to_other_projects() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+    let mut mock_project = |project_id: &str| {
+        server
+            .mock(
+                "POST",
+                format!("/v1/projects/{project_id}/messages:send").as_str(),
+            )
+            .match_header("authorization", "Bearer mock_access_token")
+            .with_status(200)
+            .with_body(
+                json!({ "name": format!("projects/{project_id}/messages/fake_message_id") })
+                    .to_string(),
+            )
+            .expect(1)
+            .create()
+    };
+    let mocks = [mock_project("tenant_a"), mock_project("tenant_b")];
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    for project_id in ["tenant_a", "tenant_b"] {
+        let response = client
+            .send_to_project(project_id, &message(&base))
+            .await
+            .expect("Failed to send message");
+        assert_eq!(
+            response.message_id.as_deref(),
+            Some(format!("projects/{project_id}/messages/fake_message_id").as_str())
+        );
+    }
+    for project_id in ["", "tenant_a/messages"] {
+        let error = client
+            .send_to_project(project_id, &message(&base))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, FcmError::InvalidProjectId { .. }),
+            "{error:?}"
+        );
+    }
+
+    for mock in mocks {
+        mock.assert();
+    }
+}
+
+#[tokio::test]
+async fn send_each_returns_results_in_message_order() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+    let mut mock_fcm = |body: &str, status: usize, response: serde_json::Value| {
+        server
+            .mock("POST", base.fcm_path.as_str())
+            .match_body(Matcher::PartialJson(
+                json!({ "message": { "notification": { "body": body } } }),
+            ))
+            .with_status(status)
+            .with_body(response.to_string())
+            .expect(1)
+            .create()
+    };
+    let mocks = [
+        mock_fcm(
+            "first",
+            200,
+            json!({ "name": "projects/mock_project_id/messages/1" }),
+        ),
+        mock_fcm(
+            "second",
+            400,
+            json!({ "error": { "code": 400, "status": "INVALID_ARGUMENT" } }),
+        ),
+        mock_fcm(
+            "third",
+            200,
+            json!({ "name": "projects/mock_project_id/messages/3" }),
+        ),
+    ];
+
+    let client = FcmClient::builder()
+        .token_manager(token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+    let messages = ["first", "second", "third"]
+        .map(|body| {
+            Message::builder()
+                .token(base.device_token.as_str())
+                .notification(FcmNotification::new("Test title", body))
+                .build()
+                .expect("Failed to build message")
+        })
+        .to_vec();
+    let results = client.send_each(messages, 3).await;
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(
+        results[0].as_ref().unwrap().message_id.as_deref(),
+        Some("projects/mock_project_id/messages/1")
+    );
+    assert_eq!(
+        results[1].as_ref().unwrap_err().suggested_status_code(),
+        400
+    );
+    assert_eq!(
+        results[2].as_ref().unwrap().message_id.as_deref(),
+        Some("projects/mock_project_id/messages/3")
+    );
+    for mock in mocks {
+        mock.assert();
+    }
+}
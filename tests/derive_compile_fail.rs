@@ -0,0 +1,7 @@
+#![cfg(feature = "derive")]
+
+#[test]
+fn derive_compile_errors() {
+    let tests = trybuild::TestCases::new();
+    tests.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "derive")]
+
+use oauth_fcm::FcmData;
+use oauth_fcm::Message;
+use oauth_fcm::ToDataPayload;
+
+#[derive(FcmData)]
+struct AllTypes<'a> {
+    text: String,
+    borrowed: &'a str,
+    flag: bool,
+    count: u32,
+    delta: i64,
+    ratio: f64,
+    letter: char,
+}
+
+#[derive(FcmData)]
+struct WithAttributes {
+    #[fcm(rename = "chat_id")]
+    id: u64,
+    r#type: String,
+    reply_to: Option<u64>,
+    thread: Option<String>,
+}
+
+#[test]
+fn derive_renders_all_supported_types() {
+    let payload = AllTypes {
+        text: "text".to_string(),
+        borrowed: "borrowed",
+        flag: true,
+        count: 42,
+        delta: -7,
+        ratio: 0.5,
+        letter: 'x',
+    }
+    .to_data_payload();
+
+    assert_eq!(payload.len(), 7);
+    assert_eq!(payload["text"], "text");
+    assert_eq!(payload["borrowed"], "borrowed");
+    assert_eq!(payload["flag"], "true");
+    assert_eq!(payload["count"], "42");
+    assert_eq!(payload["delta"], "-7");
+    assert_eq!(payload["ratio"], "0.5");
+    assert_eq!(payload["letter"], "x");
+}
+
+#[test]
+fn derive_renames_keys_and_skips_none() {
+    let payload = WithAttributes {
+        id: 1,
+        r#type: "chat".to_string(),
+        reply_to: None,
+        thread: Some("main".to_string()),
+    }
+    .to_data_payload();
+
+    assert_eq!(payload.len(), 3);
+    assert_eq!(payload["chat_id"], "1");
+    assert_eq!(payload["type"], "chat");
+    assert_eq!(payload["thread"], "main");
+    assert!(!payload.contains_key("reply_to"));
+}
+
+#[test]
+fn derived_payload_is_sent_as_data() {
+    let message = Message::builder()
+        .topic("chat")
+        .data_payload(&WithAttributes {
+            id: 1,
+            r#type: "chat".to_string(),
+            reply_to: Some(2),
+            thread: None,
+        })
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        message.data(),
+        Some(&serde_json::json!({ "chat_id": "1", "type": "chat", "reply_to": "2" }))
+    );
+}
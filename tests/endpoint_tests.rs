@@ -0,0 +1,129 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use oauth_fcm::send_message;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmClientBuilder;
+use oauth_fcm::FcmEndpoint;
+use oauth_fcm::Message;
+use oauth_fcm::StaticTokenProvider;
+use oauth_fcm::TokenManager;
+use oauth_fcm::FCM_ENDPOINT_OVERRIDE;
+use serde_json::json;
+
+/// The tests change a process wide environment variable, so they must not run
+/// concurrently.
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+const FCM_PATH: &str = "/v1/projects/demo-project/messages:send";
+
+fn message() -> Message {
+    Message::builder()
+        .token("device_token")
+        .data(&json!({ "key": "value" }))
+        .build()
+        .expect("Invalid message")
+}
+
+fn mock_send(server: &mut mockito::Server) -> mockito::Mock {
+    server
+        .mock("POST", FCM_PATH)
+        .match_header("Authorization", "Bearer test-token")
+        .with_status(200)
+        .with_body(json!({ "name": "projects/demo-project/messages/1" }).to_string())
+        .create()
+}
+
+fn client_builder(server: &mut mockito::Server) -> FcmClientBuilder {
+    server
+        .mock("POST", "/token")
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": "test-token",
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(format!("{}/token", server.url()));
+    FcmClient::builder().token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+}
+
+#[tokio::test]
+async fn env_override_redirects_send_functions() {
+    let _env = ENV_LOCK.lock().await;
+    let mut server = mockito::Server::new_async().await;
+    let mock_fcm = mock_send(&mut server);
+    std::env::set_var(FCM_ENDPOINT_OVERRIDE, server.url());
+
+    let result = send_message(
+        &message(),
+        &StaticTokenProvider::new("test-token"),
+        "demo-project",
+    )
+    .await;
+    std::env::remove_var(FCM_ENDPOINT_OVERRIDE);
+
+    result.expect("Failed to send message");
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn env_override_is_used_by_client_without_endpoint() {
+    let _env = ENV_LOCK.lock().await;
+    let mut server = mockito::Server::new_async().await;
+    let mock_fcm = mock_send(&mut server);
+    std::env::set_var(FCM_ENDPOINT_OVERRIDE, server.url());
+
+    let client = client_builder(&mut server)
+        .project_id("demo-project")
+        .build()
+        .await;
+    std::env::remove_var(FCM_ENDPOINT_OVERRIDE);
+
+    client
+        .expect("Failed to build FcmClient")
+        .send(&message())
+        .await
+        .expect("Failed to send message");
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn client_endpoint_takes_precedence_over_env_override() {
+    let _env = ENV_LOCK.lock().await;
+    let mut server = mockito::Server::new_async().await;
+    let mock_fcm = mock_send(&mut server);
+    std::env::set_var(FCM_ENDPOINT_OVERRIDE, "http://127.0.0.1:1");
+
+    let client = client_builder(&mut server)
+        .fcm_endpoint(FcmEndpoint::new(server.url(), "demo-project"))
+        .build()
+        .await;
+    std::env::remove_var(FCM_ENDPOINT_OVERRIDE);
+
+    let client = client.expect("Failed to build FcmClient");
+    assert_eq!(client.project_id(), "demo-project");
+    client
+        .send(&message())
+        .await
+        .expect("Failed to send message");
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn unset_env_override_uses_production() {
+    let _env = ENV_LOCK.lock().await;
+    std::env::remove_var(FCM_ENDPOINT_OVERRIDE);
+
+    assert_eq!(
+        FcmEndpoint::from_env("demo-project"),
+        FcmEndpoint::production("demo-project")
+    );
+}
@@ -0,0 +1,177 @@
+#![cfg(not(feature = "log"))]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::send_fcm_message_with_url;
+use serde_json::json;
+use tracing::field::Field;
+use tracing::field::Visit;
+use tracing::Event;
+use tracing::Level;
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::Layer;
+
+use crate::test_helpers::FcmBaseTest;
+use crate::test_helpers::TestData;
+
+mod test_helpers;
+
+type CapturedEvents = Arc<Mutex<Vec<(Level, HashMap<String, String>)>>>;
+
+/// Captures the fields of every event emitted by this crate.
+struct CaptureLayer {
+    events: CapturedEvents,
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}"));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for CaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if !event.metadata().target().starts_with("oauth_fcm") {
+            return;
+        }
+
+        let mut fields = HashMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.events
+            .lock()
+            .unwrap()
+            .push((*event.metadata().level(), fields));
+    }
+}
+
+async fn send_with_error_response(
+    status: usize,
+    body: &str,
+) -> Vec<(Level, HashMap<String, String>)> {
+    let events = CapturedEvents::default();
+    let subscriber = tracing_subscriber::registry().with(CaptureLayer {
+        events: events.clone(),
+    });
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(status)
+        .with_body(body)
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let data = TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    };
+
+    let result = send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(data),
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await;
+    assert!(result.is_err());
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+
+    let events = events.lock().unwrap().clone();
+    events
+}
+
+#[tokio::test]
+async fn unregistered_error_is_logged_with_structured_fields() {
+    let body = json!({
+        "error": {
+            "code": 404,
+            "message": "Requested entity was not found.",
+            "status": "NOT_FOUND",
+            "details": [
+                {
+                    "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                    "errorCode": "UNREGISTERED"
+                }
+            ]
+        }
+    })
+    .to_string();
+
+    let events = send_with_error_response(404, &body).await;
+
+    let (_, fields) = events
+        .iter()
+        .find(|(level, _)| *level == Level::ERROR)
+        .expect("No error event emitted");
+    assert_eq!(fields["http.status"], "404");
+    assert_eq!(fields["fcm.status"], "NOT_FOUND");
+    assert_eq!(fields["fcm.error_code"], "UNREGISTERED");
+    assert_eq!(fields["fcm.message"], "Requested entity was not found.");
+    assert!(!fields.contains_key("body"));
+
+    let (_, debug_fields) = events
+        .iter()
+        .find(|(level, fields)| *level == Level::DEBUG && fields.contains_key("body"))
+        .expect("No debug event with the raw body emitted");
+    assert_eq!(debug_fields["body"], body);
+}
+
+#[tokio::test]
+async fn non_json_error_is_logged_as_text() {
+    let events = send_with_error_response(500, "Internal Server Error").await;
+
+    let (_, fields) = events
+        .iter()
+        .find(|(level, _)| *level == Level::ERROR)
+        .expect("No error event emitted");
+    assert!(!fields.contains_key("fcm.status"));
+    assert!(fields["message"].contains("Internal Server Error"));
+}
@@ -3,6 +3,7 @@ use std::fs::File;
 use oauth_fcm::create_shared_token_manager;
 use oauth_fcm::send_fcm_message_with_url;
 use oauth_fcm::FcmError;
+use oauth_fcm::FcmErrorCode;
 use oauth_fcm::NetworkError;
 use serde_json::json;
 
@@ -76,9 +77,98 @@ async fn test_fcm_server_error() {
     eprintln!("network_error: {:?}", network_error);
     assert!(matches!(
         network_error,
-        FcmError::FcmNetworkError(NetworkError::ServerError(500, _))
+        FcmError::FcmNetworkError(NetworkError::ServerError(500, ..))
     ));
 
     mock_auth.assert_async().await;
     mock_fcm.assert_async().await;
 }
+
+#[tokio::test]
+async fn test_fcm_error_response_is_parsed() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let fcm_body = json!({
+        "error": {
+            "code": 404,
+            "message": "Requested entity was not found.",
+            "status": "NOT_FOUND",
+            "details": [{
+                "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                "errorCode": "UNREGISTERED"
+            }]
+        }
+    })
+    .to_string();
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(404)
+        .with_body(&fcm_body)
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let data = TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    };
+
+    let error = send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(data),
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .unwrap_err();
+
+    match error {
+        FcmError::FcmResponseError {
+            status,
+            code,
+            message,
+            body,
+            retry_after,
+        } => {
+            assert_eq!(status, 404);
+            assert_eq!(code, FcmErrorCode::Unregistered);
+            assert_eq!(message, "Requested entity was not found.");
+            assert_eq!(body, fcm_body);
+            assert_eq!(retry_after, None);
+        }
+        error => panic!("Unexpected error: {error:?}"),
+    }
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
@@ -0,0 +1,184 @@
+use std::fs::File;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth_fcm::send_fcm_message_with_url;
+use oauth_fcm::FcmError;
+use oauth_fcm::NetworkError;
+use oauth_fcm::TokenManager;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderValue;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+use crate::test_helpers::TestData;
+
+mod test_helpers;
+
+#[tokio::test]
+async fn configured_client_is_used_for_oauth_and_fcm_requests() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .match_header("x-client-id", "shared")
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header("x-client-id", "shared")
+        .with_status(200)
+        .expect(2)
+        .create();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-client-id", HeaderValue::from_static("shared"));
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap();
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url())
+        .with_http_client(client);
+    let shared_token_manager = Arc::new(tokio::sync::Mutex::new(token_manager));
+
+    for _ in 0..2 {
+        send_fcm_message_with_url(
+            &base.device_token,
+            None,
+            Some(TestData {
+                title: "Test title".to_string(),
+                description: "Test description".to_string(),
+            }),
+            &shared_token_manager,
+            &base.mock_fcm_url(),
+        )
+        .await
+        .expect("Failed to send FCM message");
+    }
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
+
+fn delayed_body(body: String) -> impl Fn(&mockito::Request) -> Vec<u8> + Send + Sync + 'static {
+    move |_| {
+        std::thread::sleep(Duration::from_millis(500));
+        body.clone().into_bytes()
+    }
+}
+
+#[tokio::test]
+async fn oauth_request_times_out() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body_from_request(delayed_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        ))
+        .create();
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url())
+        .with_timeouts(Duration::from_secs(1), Duration::from_millis(100));
+
+    let error = token_manager.get_token().await.unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::OAuthNetworkError(NetworkError::Timeout(_))
+    ));
+}
+
+#[tokio::test]
+async fn fcm_request_times_out() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body_from_request(delayed_body(String::new()))
+        .create();
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url())
+        .with_timeouts(Duration::from_secs(1), Duration::from_millis(100));
+
+    let error = send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(TestData {
+            title: "Test title".to_string(),
+            description: "Test description".to_string(),
+        }),
+        &token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::FcmNetworkError(NetworkError::Timeout(_))
+    ));
+    assert!(error.is_retryable());
+    assert_eq!(error.suggested_status_code(), 504);
+}
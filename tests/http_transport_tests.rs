@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use oauth_fcm::send_message_with_url;
+use oauth_fcm::FcmError;
+use oauth_fcm::HttpRequest;
+use oauth_fcm::HttpResponse;
+use oauth_fcm::HttpTransport;
+use oauth_fcm::Message;
+use oauth_fcm::NetworkError;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+const TOKEN_URI: &str = "https://oauth2.test/token";
+const FCM_URL: &str = "https://fcm.test/v1/projects/mock_project_id/messages:send";
+
+/// Records the requests and answers them with canned responses.
+#[derive(Default)]
+struct MockTransport {
+    requests: Mutex<Vec<HttpRequest>>,
+    responses: Mutex<VecDeque<Result<HttpResponse, NetworkError>>>,
+}
+
+impl MockTransport {
+    fn new(responses: impl IntoIterator<Item = Result<HttpResponse, NetworkError>>) -> Arc<Self> {
+        Arc::new(Self {
+            requests: Mutex::new(Vec::new()),
+            responses: Mutex::new(responses.into_iter().collect()),
+        })
+    }
+
+    fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl HttpTransport for MockTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        self.requests.lock().unwrap().push(request);
+        self.responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("Unexpected request")
+    }
+}
+
+fn token_response() -> Result<HttpResponse, NetworkError> {
+    Ok(HttpResponse::new(
+        200,
+        json!({
+            "access_token": "mock_access_token",
+            "token_type": "Bearer",
+            "expires_in": 3600,
+        })
+        .to_string(),
+    ))
+}
+
+fn token_manager(transport: &Arc<MockTransport>) -> TokenManager {
+    TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(TOKEN_URI)
+        .with_http_transport(Arc::clone(transport))
+}
+
+fn message() -> Message {
+    Message::builder()
+        .token("mock_device_token")
+        .data(&json!({ "key": "value" }))
+        .build()
+        .expect("Invalid message")
+}
+
+#[tokio::test]
+async fn requests_are_sent_through_the_transport() {
+    let transport = MockTransport::new([
+        token_response(),
+        Ok(HttpResponse::new(
+            200,
+            json!({ "name": "projects/mock_project_id/messages/1" }).to_string(),
+        )),
+    ]);
+
+    let response = send_message_with_url(&message(), &token_manager(&transport), FCM_URL)
+        .await
+        .expect("Failed to send message");
+    assert_eq!(
+        response.message_id.as_deref(),
+        Some("projects/mock_project_id/messages/1")
+    );
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 2);
+
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].url, TOKEN_URI);
+    let form = String::from_utf8(requests[0].body.clone()).unwrap();
+    assert!(form.contains("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer"));
+
+    assert_eq!(requests[1].url, FCM_URL);
+    assert_eq!(
+        requests[1].headers["authorization"],
+        "Bearer mock_access_token"
+    );
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(body["message"]["token"], "mock_device_token");
+}
+
+#[tokio::test]
+async fn fcm_errors_are_parsed_from_the_response() {
+    let transport = MockTransport::new([
+        token_response(),
+        Ok(HttpResponse::new(
+            429,
+            json!({
+                "error": {
+                    "code": 429,
+                    "message": "Quota exceeded.",
+                    "status": "RESOURCE_EXHAUSTED",
+                    "details": [{ "errorCode": "QUOTA_EXCEEDED" }]
+                }
+            })
+            .to_string(),
+        )
+        .with_header("retry-after", "7")),
+    ]);
+
+    let error = send_message_with_url(&message(), &token_manager(&transport), FCM_URL)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::FcmResponseError { status: 429, .. }
+    ));
+    assert_eq!(error.retry_after(), Some(std::time::Duration::from_secs(7)));
+}
+
+#[tokio::test]
+async fn transport_errors_are_network_errors() {
+    let transport = MockTransport::new([Err(NetworkError::Transport("connection reset".into()))]);
+
+    let error = token_manager(&transport).get_token().await.unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::OAuthNetworkError(NetworkError::Transport(_))
+    ));
+    assert!(error.is_retryable());
+}
+
+#[tokio::test]
+async fn malformed_token_response_is_an_error() {
+    let transport = MockTransport::new([Ok(HttpResponse::new(200, "<html>Login</html>"))]);
+
+    let error = token_manager(&transport).get_token().await.unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::OAuthNetworkError(NetworkError::InvalidResponse(_))
+    ));
+}
@@ -0,0 +1,129 @@
+use std::fs::File;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::HttpRequest;
+use oauth_fcm::HttpResponse;
+use oauth_fcm::HttpTransport;
+use oauth_fcm::Message;
+use oauth_fcm::NetworkError;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+const TOKEN_URI: &str = "https://oauth2.test/token";
+
+/// Answers FCM requests after a delay and records how many were in flight at
+/// the same time.
+#[derive(Default)]
+struct SlowTransport {
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+#[async_trait]
+impl HttpTransport for SlowTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        if request.url == TOKEN_URI {
+            return Ok(HttpResponse::new(
+                200,
+                json!({
+                    "access_token": "mock_access_token",
+                    "token_type": "Bearer",
+                    "expires_in": 3600,
+                })
+                .to_string(),
+            ));
+        }
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        Ok(HttpResponse::new(
+            200,
+            json!({ "name": "projects/mock_project_id/messages/1" }).to_string(),
+        ))
+    }
+}
+
+async fn client(
+    transport: &Arc<SlowTransport>,
+    limit: usize,
+    acquire_timeout: Option<Duration>,
+) -> FcmClient {
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(TOKEN_URI)
+        .with_http_transport(Arc::clone(transport));
+
+    FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .project_id("mock_project_id")
+        .endpoint("https://fcm.test")
+        .max_in_flight(limit, acquire_timeout)
+        .build()
+        .await
+        .expect("Failed to create FcmClient")
+}
+
+fn message() -> Message {
+    Message::builder()
+        .token("mock_device_token")
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .build()
+        .expect("Invalid message")
+}
+
+#[tokio::test(start_paused = true)]
+async fn concurrent_sends_are_limited() {
+    let transport = Arc::new(SlowTransport::default());
+    let client = client(&transport, 5, None).await;
+    let message = message();
+
+    let results = join_all((0..20).map(|_| client.send(&message))).await;
+
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(transport.max_in_flight.load(Ordering::SeqCst), 5);
+}
+
+#[tokio::test(start_paused = true)]
+async fn clones_share_the_limit() {
+    let transport = Arc::new(SlowTransport::default());
+    let client = client(&transport, 2, None).await;
+    let clone = client.clone();
+    let message = message();
+
+    let results = join_all((0..4).map(|i| {
+        let client = if i % 2 == 0 { &client } else { &clone };
+        client.send(&message)
+    }))
+    .await;
+
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(transport.max_in_flight.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn send_fails_fast_after_acquire_timeout() {
+    let transport = Arc::new(SlowTransport::default());
+    let client = client(&transport, 1, Some(Duration::from_millis(10))).await;
+    let message = message();
+
+    let (first, second) = tokio::join!(client.send(&message), client.send(&message));
+
+    assert!(first.is_ok());
+    let error = second.unwrap_err();
+    assert!(
+        matches!(error, FcmError::InFlightLimitTimeout(timeout) if timeout == Duration::from_millis(10))
+    );
+    assert_eq!(error.suggested_status_code(), 503);
+    assert!(error.is_retryable());
+}
@@ -0,0 +1,177 @@
+use std::fs::File;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmInterceptor;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::HttpRequest;
+use oauth_fcm::Message;
+use oauth_fcm::ResponseContext;
+use oauth_fcm::TokenManager;
+use reqwest::header::HeaderValue;
+use serde_json::json;
+
+use crate::test_helpers::token_response;
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+/// Adds a header to every request and records the statuses of the responses.
+#[derive(Default)]
+struct HeaderInterceptor {
+    statuses: Mutex<Vec<(String, Option<u16>)>>,
+}
+
+impl FcmInterceptor for HeaderInterceptor {
+    fn on_request(&self, request: &mut HttpRequest) {
+        request
+            .headers
+            .insert("x-corporate-auth", HeaderValue::from_static("secret"));
+    }
+
+    fn on_response(&self, response: &ResponseContext<'_>) {
+        self.statuses
+            .lock()
+            .unwrap()
+            .push((response.url.to_string(), response.status()));
+    }
+}
+
+fn mock_auth(
+    server: &mut mockito::ServerGuard,
+    base: &FcmBaseTest,
+    header: Option<&str>,
+) -> mockito::Mock {
+    let mock = server.mock("POST", base.oauth_path.as_str());
+    let mock = match header {
+        Some(value) => mock.match_header("x-corporate-auth", value),
+        None => mock.match_header("x-corporate-auth", mockito::Matcher::Missing),
+    };
+    mock.with_status(200)
+        .with_body(token_response(base))
+        .expect(1)
+        .create()
+}
+
+async fn client(
+    server: &mockito::ServerGuard,
+    token_manager: TokenManager,
+    interceptor: Arc<HeaderInterceptor>,
+) -> FcmClient {
+    FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .interceptor(interceptor)
+        .build()
+        .await
+        .expect("Failed to create FcmClient")
+}
+
+fn token_manager(base: &FcmBaseTest) -> TokenManager {
+    TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url())
+}
+
+fn message() -> Message {
+    Message::builder()
+        .token("mock_device_token")
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .build()
+        .expect("Invalid message")
+}
+
+#[tokio::test]
+async fn client_interceptor_adds_header_to_fcm_requests() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+
+    let mock_auth = mock_auth(&mut server, &base, None);
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header("x-corporate-auth", "secret")
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create();
+
+    let interceptor = Arc::new(HeaderInterceptor::default());
+    let client = client(&server, token_manager(&base), Arc::clone(&interceptor)).await;
+    client.send(&message()).await.expect("Failed to send");
+
+    mock_auth.assert();
+    mock_fcm.assert();
+    assert_eq!(
+        *interceptor.statuses.lock().unwrap(),
+        [(base.mock_fcm_url(), Some(200))]
+    );
+}
+
+#[tokio::test]
+async fn token_manager_interceptor_adds_header_to_token_requests() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+
+    let mock_auth = mock_auth(&mut server, &base, Some("secret"));
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create();
+
+    let interceptor = Arc::new(HeaderInterceptor::default());
+    let token_manager = token_manager(&base).with_interceptor(interceptor.clone());
+    let client = FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .build()
+        .await
+        .expect("Failed to create FcmClient");
+    client.send(&message()).await.expect("Failed to send");
+
+    mock_auth.assert();
+    mock_fcm.assert();
+    assert_eq!(
+        *interceptor.statuses.lock().unwrap(),
+        [(base.mock_auth_url(), Some(200))]
+    );
+}
+
+#[tokio::test]
+async fn interceptor_observes_but_does_not_swallow_errors() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+
+    let _mock_auth = mock_auth(&mut server, &base, None);
+    let _mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(404)
+        .with_body(
+            json!({
+                "error": {
+                    "code": 404,
+                    "message": "Requested entity was not found.",
+                    "status": "NOT_FOUND",
+                    "details": [{ "errorCode": "UNREGISTERED" }]
+                }
+            })
+            .to_string(),
+        )
+        .create();
+
+    let interceptor = Arc::new(HeaderInterceptor::default());
+    let client = client(&server, token_manager(&base), Arc::clone(&interceptor)).await;
+    let error = client.send(&message()).await.expect_err("Send should fail");
+
+    assert!(matches!(error, FcmError::FcmResponseError { .. }));
+    assert!(error.is_token_invalid());
+    assert_eq!(
+        *interceptor.statuses.lock().unwrap(),
+        [(base.mock_fcm_url(), Some(404))]
+    );
+}
@@ -0,0 +1,173 @@
+#![cfg(feature = "log")]
+
+use std::fs::File;
+use std::sync::Mutex;
+
+use log::Level;
+use log::LevelFilter;
+use log::Log;
+use log::Metadata;
+use log::Record;
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::send_fcm_message_with_url;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+use crate::test_helpers::TestData;
+
+mod test_helpers;
+
+/// Captures every record logged by this crate.
+struct CaptureLogger {
+    records: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target().starts_with("oauth_fcm")
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.enabled(record.metadata()) {
+            self.records
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CaptureLogger = CaptureLogger {
+    records: Mutex::new(Vec::new()),
+};
+
+fn take_records() -> Vec<(Level, String)> {
+    std::mem::take(&mut *LOGGER.records.lock().unwrap())
+}
+
+fn has_record(records: &[(Level, String)], level: Level, text: &str) -> bool {
+    records
+        .iter()
+        .any(|(record_level, message)| *record_level == level && message.contains(text))
+}
+
+// All events are checked in a single test, as the logger is global and tests
+// run in parallel.
+#[tokio::test]
+async fn events_are_emitted_through_log() {
+    log::set_logger(&LOGGER).expect("Failed to set logger");
+    log::set_max_level(LevelFilter::Trace);
+
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let records = take_records();
+    assert!(has_record(
+        &records,
+        Level::Info,
+        "Token refreshed successfully"
+    ));
+
+    let data = TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    };
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .create();
+
+    send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(&data),
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send FCM message");
+
+    let records = take_records();
+    assert!(has_record(
+        &records,
+        Level::Debug,
+        "FCM message sent successfully"
+    ));
+    mock_fcm.remove_async().await;
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(404)
+        .with_body(
+            json!({
+                "error": {
+                    "code": 404,
+                    "message": "Requested entity was not found.",
+                    "status": "NOT_FOUND",
+                    "details": [
+                        {
+                            "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                            "errorCode": "UNREGISTERED"
+                        }
+                    ]
+                }
+            })
+            .to_string(),
+        )
+        .create();
+
+    let result = send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(&data),
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await;
+    assert!(result.is_err());
+
+    let records = take_records();
+    let (_, message) = records
+        .iter()
+        .find(|(level, _)| *level == Level::Error)
+        .expect("No error record logged");
+    assert!(message.contains("http.status=404"));
+    assert!(message.contains("fcm.status=NOT_FOUND"));
+    assert!(message.contains("fcm.error_code=UNREGISTERED"));
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
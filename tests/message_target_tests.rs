@@ -0,0 +1,176 @@
+use std::fs::File;
+
+use mockito::Matcher;
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::send_fcm_message_to_target_with_url;
+use oauth_fcm::send_message_with_url;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::Message;
+use oauth_fcm::MessageTarget;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+async fn send_to_target(target: &MessageTarget, expected_message: serde_json::Value) {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Json(json!({ "message": expected_message })))
+        .with_status(200)
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let notification = FcmNotification::new("Test title", "Test body");
+
+    send_fcm_message_to_target_with_url(
+        target,
+        Some(notification),
+        Some(json!({ "key": "value" })),
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send FCM message");
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn message_to_token_contains_token() {
+    send_to_target(
+        &MessageTarget::Token("mock_device_token".to_string()),
+        json!({
+            "token": "mock_device_token",
+            "notification": { "title": "Test title", "body": "Test body" },
+            "data": { "key": "value" }
+        }),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn message_to_topic_contains_topic() {
+    send_to_target(
+        &MessageTarget::Topic("news".to_string()),
+        json!({
+            "topic": "news",
+            "notification": { "title": "Test title", "body": "Test body" },
+            "data": { "key": "value" }
+        }),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn message_to_condition_contains_condition() {
+    let condition = "'stock-GOOG' in topics || 'industry-tech' in topics";
+
+    send_to_target(
+        &MessageTarget::Condition(condition.to_string()),
+        json!({
+            "condition": condition,
+            "notification": { "title": "Test title", "body": "Test body" },
+            "data": { "key": "value" }
+        }),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn built_message_is_sent_unchanged() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Json(json!({
+            "message": {
+                "topic": "news",
+                "notification": { "title": "Test title", "body": "Test body" }
+            }
+        })))
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let message = Message::builder()
+        .topic("news")
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .build()
+        .expect("Failed to build message");
+
+    let response = send_message_with_url(&message, &shared_token_manager, &base.mock_fcm_url())
+        .await
+        .expect("Failed to send FCM message");
+
+    assert_eq!(
+        response.message_id.as_deref(),
+        Some("projects/mock_project_id/messages/1")
+    );
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
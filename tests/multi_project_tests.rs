@@ -0,0 +1,174 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::Message;
+use oauth_fcm::MultiProjectFcm;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+/// A mock server with the token and the FCM endpoint of one project.
+struct MockProject {
+    server: mockito::ServerGuard,
+    project_id: &'static str,
+    access_token: &'static str,
+}
+
+impl MockProject {
+    async fn new(project_id: &'static str, access_token: &'static str) -> Self {
+        Self {
+            server: mockito::Server::new_async().await,
+            project_id,
+            access_token,
+        }
+    }
+
+    fn mock_token(&mut self) -> mockito::Mock {
+        self.server
+            .mock("POST", "/token")
+            .with_status(200)
+            .with_body(
+                json!({
+                    "access_token": self.access_token,
+                    "scope": "https://www.googleapis.com/auth/prediction",
+                    "token_type": "Bearer",
+                    "expires_in": 3600,
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create()
+    }
+
+    fn mock_send(&mut self, expected: usize) -> mockito::Mock {
+        let path = format!("/v1/projects/{}/messages:send", self.project_id);
+        self.server
+            .mock("POST", path.as_str())
+            .match_header(
+                "authorization",
+                format!("Bearer {}", self.access_token).as_str(),
+            )
+            .with_status(200)
+            .with_body(json!({ "name": "projects/mock/messages/1" }).to_string())
+            .expect(expected)
+            .create()
+    }
+
+    async fn client(&self) -> FcmClient {
+        let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create TokenManager")
+            .with_token_uri(format!("{}/token", self.server.url()));
+
+        FcmClient::builder()
+            .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+            .project_id(self.project_id)
+            .endpoint(self.server.url())
+            .build()
+            .await
+            .expect("Failed to create FcmClient")
+    }
+}
+
+fn message() -> Message {
+    Message::builder()
+        .token("mock_device_token")
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .build()
+        .expect("Invalid message")
+}
+
+#[tokio::test]
+async fn projects_use_their_own_tokens() {
+    let mut project_a = MockProject::new("project-a", "token_a").await;
+    let mut project_b = MockProject::new("project-b", "token_b").await;
+
+    let mock_token_a = project_a.mock_token();
+    let mock_send_a = project_a.mock_send(2);
+    let mock_token_b = project_b.mock_token();
+    let mock_send_b = project_b.mock_send(1);
+
+    let mut fcm = MultiProjectFcm::new();
+    fcm.insert(project_a.client().await);
+    fcm.insert(project_b.client().await);
+
+    let message = message();
+    fcm.send("project-a", &message)
+        .await
+        .expect("Failed to send to project A");
+    fcm.send("project-b", &message)
+        .await
+        .expect("Failed to send to project B");
+    fcm.send("project-a", &message)
+        .await
+        .expect("Failed to send to project A");
+
+    mock_token_a.assert();
+    mock_send_a.assert();
+    mock_token_b.assert();
+    mock_send_b.assert();
+}
+
+#[tokio::test]
+async fn unknown_project_is_an_error() {
+    let mut fcm = MultiProjectFcm::new();
+    fcm.register(
+        "project-a",
+        File::open("tests/mock_credentials.json").unwrap(),
+    )
+    .await
+    .expect("Failed to register project");
+
+    let error = fcm
+        .send("project-b", &message())
+        .await
+        .expect_err("Unknown project should fail");
+
+    assert!(matches!(
+        error,
+        FcmError::UnknownProject { ref project_id } if project_id == "project-b"
+    ));
+    assert_eq!(fcm.project_ids().collect::<Vec<_>>(), ["project-a"]);
+}
+
+#[tokio::test]
+async fn send_to_projects_isolates_failures() {
+    let mut project_a = MockProject::new("project-a", "token_a").await;
+    let mut project_b = MockProject::new("project-b", "token_b").await;
+
+    project_a.mock_token();
+    let mock_send_a = project_a.mock_send(1);
+    project_b.mock_token();
+    let mock_send_b = project_b
+        .server
+        .mock("POST", "/v1/projects/project-b/messages:send")
+        .with_status(500)
+        .with_body("Internal Server Error")
+        .expect(1)
+        .create();
+
+    let mut fcm = MultiProjectFcm::new();
+    fcm.insert(project_a.client().await);
+    fcm.insert(project_b.client().await);
+
+    let results = fcm
+        .send_to_projects(&["project-a", "project-b", "project-c"], &message())
+        .await;
+
+    let [(id_a, result_a), (id_b, result_b), (id_c, result_c)] = results.as_slice() else {
+        panic!("Expected three results, got {results:?}");
+    };
+    assert_eq!(
+        [*id_a, *id_b, *id_c],
+        ["project-a", "project-b", "project-c"]
+    );
+    assert!(result_a.is_ok(), "{result_a:?}");
+    assert!(
+        matches!(result_b, Err(error) if error.suggested_status_code() == 502 && error.is_retryable()),
+        "{result_b:?}"
+    );
+    assert!(matches!(result_c, Err(FcmError::UnknownProject { .. })));
+    mock_send_a.assert();
+    mock_send_b.assert();
+}
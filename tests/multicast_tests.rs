@@ -0,0 +1,226 @@
+use std::fs::File;
+
+use mockito::Matcher;
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::send_fcm_localized_multicast_with_url;
+use oauth_fcm::send_fcm_multicast_with_url;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::LocalizedNotification;
+use oauth_fcm::StaticTokenProvider;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+#[tokio::test]
+async fn multicast_returns_results_in_token_order() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let mock_success = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Regex(r#""token":"valid_\d""#.to_string()))
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(3)
+        .create();
+    let mock_unregistered = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::PartialJson(json!({
+            "message": {
+                "token": "stale",
+                "notification": { "title": "Test title", "body": "Test body" }
+            }
+        })))
+        .with_status(404)
+        .with_body(
+            json!({
+                "error": {
+                    "code": 404,
+                    "message": "Requested entity was not found.",
+                    "status": "NOT_FOUND",
+                    "details": [{ "errorCode": "UNREGISTERED" }]
+                }
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let tokens = ["valid_1", "stale", "valid_2", "valid_3"].map(str::to_string);
+    let notification = FcmNotification::new("Test title", "Test body");
+
+    let result = send_fcm_multicast_with_url(
+        &tokens,
+        Some(notification),
+        None::<serde_json::Value>,
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+        2,
+    )
+    .await
+    .expect("Failed to send FCM messages");
+
+    assert_eq!(result.success_count, 3);
+    assert_eq!(result.failure_count, 1);
+    let indices: Vec<usize> = result.results.iter().map(|(index, _)| *index).collect();
+    assert_eq!(indices, [0, 1, 2, 3]);
+    let stale = result.results[1].1.as_ref().unwrap_err();
+    assert!(stale.is_token_invalid());
+    assert!(matches!(stale, FcmError::FcmResponseError { .. }));
+
+    mock_auth.assert_async().await;
+    mock_success.assert_async().await;
+    mock_unregistered.assert_async().await;
+}
+
+#[tokio::test]
+async fn multicast_rejects_invalid_payload_before_sending() {
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+
+    let error = send_fcm_multicast_with_url(
+        &["device_token".to_string()],
+        None,
+        None::<serde_json::Value>,
+        &shared_token_manager,
+        "http://127.0.0.1:1",
+        4,
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(error, FcmError::FcmInvalidPayloadError));
+}
+
+#[tokio::test]
+async fn multicast_rejects_malformed_device_tokens_without_sending() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_success = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Regex(r#""token":"valid_1""#.to_string()))
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create();
+    let mock_malformed = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Regex(r#""token":"(| valid_2)""#.to_string()))
+        .expect(0)
+        .create();
+
+    let tokens = ["valid_1", "", " valid_2"].map(str::to_string);
+    let result = send_fcm_multicast_with_url(
+        &tokens,
+        Some(FcmNotification::new("Test title", "Test body")),
+        None::<serde_json::Value>,
+        &StaticTokenProvider::new("test-token"),
+        &base.mock_fcm_url(),
+        2,
+    )
+    .await
+    .expect("Failed to send FCM messages");
+
+    assert_eq!(result.success_count, 1);
+    assert_eq!(result.failure_count, 2);
+    for (_, result) in &result.results[1..] {
+        assert!(matches!(result, Err(FcmError::InvalidDeviceToken(_))));
+    }
+
+    mock_success.assert_async().await;
+    mock_malformed.assert_async().await;
+}
+
+#[tokio::test]
+async fn localized_multicast_resolves_notification_per_recipient() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+
+    let mut mock_notification = |token: &str, title: &str| {
+        server
+            .mock("POST", base.fcm_path.as_str())
+            .match_body(Matcher::PartialJson(json!({
+                "message": {
+                    "token": token,
+                    "notification": { "title": title },
+                    "data": { "kind": "greeting" }
+                }
+            })))
+            .with_status(200)
+            .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+            .expect(1)
+            .create()
+    };
+    let mock_pt = mock_notification("token_pt", "Olá");
+    let mock_pt_br = mock_notification("token_pt_br", "Olá");
+    let mock_default = mock_notification("token_fr", "Hello");
+
+    let notification = LocalizedNotification::new(FcmNotification::new("Hello", "World"))
+        .with_translation("pt", FcmNotification::new("Olá", "Mundo"));
+    let recipients = [
+        ("token_pt", "pt"),
+        ("token_fr", "fr-CA"),
+        ("token_pt_br", "pt_BR"),
+    ]
+    .map(|(token, locale)| (token.to_string(), locale.to_string()));
+
+    let result = send_fcm_localized_multicast_with_url(
+        &recipients,
+        &notification,
+        Some(json!({ "kind": "greeting" })),
+        &StaticTokenProvider::new("test-token"),
+        &base.mock_fcm_url(),
+        2,
+    )
+    .await
+    .expect("Failed to send FCM messages");
+
+    assert_eq!(result.success_count, 3);
+    let indices: Vec<usize> = result.results.iter().map(|(index, _)| *index).collect();
+    assert_eq!(indices, [0, 1, 2]);
+    mock_pt.assert_async().await;
+    mock_pt_br.assert_async().await;
+    mock_default.assert_async().await;
+}
@@ -1,6 +1,9 @@
 use std::fs::File;
 
 use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::FcmError;
+use oauth_fcm::TokenManager;
+use serde_json::json;
 
 use crate::test_helpers::FcmBaseTest;
 
@@ -31,7 +34,7 @@ async fn failing_oauth_token_refresh() {
             .expect("Failed to create SharedTokenManager");
 
     let res = {
-        let mut guard = shared_token_manager.lock().await;
+        let guard = shared_token_manager.lock().await;
 
         assert!(guard.is_token_expired());
 
@@ -45,3 +48,54 @@ async fn failing_oauth_token_refresh() {
 
     mock_auth.assert_async().await;
 }
+
+#[tokio::test]
+async fn oauth_error_response_is_parsed() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(400)
+        .with_body(
+            json!({
+                "error": "invalid_grant",
+                "error_description": "Invalid JWT: Token used too early"
+            })
+            .to_string(),
+        )
+        .create();
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager");
+
+    let error = token_manager
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .unwrap_err();
+
+    match &error {
+        FcmError::OAuthServerError {
+            status,
+            error,
+            error_description,
+        } => {
+            assert_eq!(*status, 400);
+            assert_eq!(error, "invalid_grant");
+            assert_eq!(
+                error_description.as_deref(),
+                Some("Invalid JWT: Token used too early")
+            );
+        }
+        error => panic!("Unexpected error: {error:?}"),
+    }
+    assert!(error.to_string().contains("invalid_grant"));
+
+    mock_auth.assert_async().await;
+}
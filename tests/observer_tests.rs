@@ -0,0 +1,192 @@
+use std::fs::File;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmObserver;
+use oauth_fcm::Message;
+use oauth_fcm::SendOutcome;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+use crate::test_helpers::mock_token_endpoint;
+use crate::test_helpers::token_response;
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Event {
+    Delivered(Option<String>),
+    SendFailed(&'static str),
+    TokenRefreshed,
+    TokenRefreshFailed(&'static str),
+}
+
+#[derive(Default)]
+struct RecordingObserver {
+    events: Mutex<Vec<Event>>,
+}
+
+impl RecordingObserver {
+    fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut self.events.lock().unwrap())
+    }
+}
+
+impl FcmObserver for RecordingObserver {
+    fn on_send(&self, outcome: SendOutcome<'_>, latency: Duration) {
+        assert!(latency < Duration::from_secs(10));
+        let event = match outcome {
+            SendOutcome::Delivered(response) => Event::Delivered(response.message_id.clone()),
+            SendOutcome::Failed(error) => Event::SendFailed(error.kind()),
+        };
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn on_token_refresh(&self, result: Result<(), &FcmError>, latency: Duration) {
+        assert!(latency < Duration::from_secs(10));
+        let event = match result {
+            Ok(()) => Event::TokenRefreshed,
+            Err(error) => Event::TokenRefreshFailed(error.kind()),
+        };
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+async fn client(
+    base: &FcmBaseTest,
+    server: &mockito::Server,
+    observer: &Arc<RecordingObserver>,
+) -> FcmClient {
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url())
+        .with_observer(observer.clone());
+
+    FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .observer(observer.clone())
+        .build()
+        .await
+        .expect("Failed to build FcmClient")
+}
+
+fn message(base: &FcmBaseTest) -> Message {
+    Message::builder()
+        .token(&base.device_token)
+        .data(&json!({ "key": "value" }))
+        .build()
+        .expect("Invalid message")
+}
+
+#[tokio::test]
+async fn successful_send_and_refresh_are_observed() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(token_response(&base))
+        .expect(1)
+        .create();
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(2)
+        .create();
+
+    let observer = Arc::new(RecordingObserver::default());
+    let client = client(&base, &server, &observer).await;
+    for _ in 0..2 {
+        client
+            .send(&message(&base))
+            .await
+            .expect("Failed to send message");
+    }
+
+    // The cached token is not reported as a refresh.
+    let message_id = Some("projects/mock_project_id/messages/1".to_string());
+    assert_eq!(
+        observer.take(),
+        vec![
+            Event::TokenRefreshed,
+            Event::Delivered(message_id.clone()),
+            Event::Delivered(message_id),
+        ]
+    );
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn failed_send_is_observed() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+
+    mock_token_endpoint(&mut server, &base);
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(404)
+        .with_body(
+            json!({
+                "error": {
+                    "code": 404,
+                    "message": "Requested entity was not found.",
+                    "status": "NOT_FOUND",
+                }
+            })
+            .to_string(),
+        )
+        .create();
+
+    let observer = Arc::new(RecordingObserver::default());
+    let error = client(&base, &server, &observer)
+        .await
+        .send(&message(&base))
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        observer.take(),
+        vec![Event::TokenRefreshed, Event::SendFailed(error.kind())]
+    );
+
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn failed_refresh_is_observed() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(400)
+        .with_body(json!({ "error": "invalid_grant" }).to_string())
+        .create();
+
+    let observer = Arc::new(RecordingObserver::default());
+    let error = client(&base, &server, &observer)
+        .await
+        .send(&message(&base))
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        observer.take(),
+        vec![
+            Event::TokenRefreshFailed(error.kind()),
+            Event::SendFailed(error.kind()),
+        ]
+    );
+
+    mock_auth.assert_async().await;
+}
@@ -0,0 +1,146 @@
+use std::fs::File;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmClientBuilder;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::HttpRequest;
+use oauth_fcm::HttpResponse;
+use oauth_fcm::HttpTransport;
+use oauth_fcm::Message;
+use oauth_fcm::NetworkError;
+use oauth_fcm::RateLimiter;
+use oauth_fcm::RetryConfig;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+use tokio::time::Instant;
+
+const TOKEN_URI: &str = "https://oauth2.test/token";
+
+/// Answers every request instantly, so only the rate limiter takes time.
+#[derive(Default)]
+struct InstantTransport {
+    fcm_requests: AtomicUsize,
+    /// Answers every other FCM request with `503 Service Unavailable`.
+    fail_every_other: bool,
+}
+
+#[async_trait]
+impl HttpTransport for InstantTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NetworkError> {
+        if request.url == TOKEN_URI {
+            return Ok(HttpResponse::new(
+                200,
+                json!({
+                    "access_token": "mock_access_token",
+                    "token_type": "Bearer",
+                    "expires_in": 3600,
+                })
+                .to_string(),
+            ));
+        }
+
+        let count = self.fcm_requests.fetch_add(1, Ordering::SeqCst);
+        if self.fail_every_other && count % 2 == 0 {
+            return Ok(HttpResponse::new(503, "Service Unavailable".to_string()));
+        }
+        Ok(HttpResponse::new(
+            200,
+            json!({ "name": "projects/mock_project_id/messages/1" }).to_string(),
+        ))
+    }
+}
+
+fn client_builder(
+    transport: &Arc<InstantTransport>,
+    rate_limiter: RateLimiter,
+) -> FcmClientBuilder {
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(TOKEN_URI)
+        .with_http_transport(Arc::clone(transport));
+
+    FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .project_id("mock_project_id")
+        .endpoint("https://fcm.test")
+        .rate_limiter(rate_limiter)
+}
+
+async fn client(transport: &Arc<InstantTransport>, rate_limiter: RateLimiter) -> FcmClient {
+    client_builder(transport, rate_limiter)
+        .build()
+        .await
+        .expect("Failed to create FcmClient")
+}
+
+fn message() -> Message {
+    Message::builder()
+        .token("mock_device_token")
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .build()
+        .expect("Invalid message")
+}
+
+#[tokio::test(start_paused = true)]
+async fn sends_are_limited_to_the_configured_rate() {
+    let transport = Arc::new(InstantTransport::default());
+    let client = client(&transport, RateLimiter::new(2, 1)).await;
+    let message = message();
+
+    let started = Instant::now();
+    for _ in 0..10 {
+        client.send(&message).await.expect("Failed to send");
+    }
+
+    assert!(started.elapsed() >= Duration::from_secs(4));
+    assert_eq!(transport.fcm_requests.load(Ordering::SeqCst), 10);
+}
+
+#[tokio::test(start_paused = true)]
+async fn retries_are_limited_to_the_configured_rate() {
+    let transport = Arc::new(InstantTransport {
+        fail_every_other: true,
+        ..InstantTransport::default()
+    });
+    let retry = RetryConfig {
+        max_attempts: 2,
+        initial_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+        jitter: false,
+    };
+    let client = client_builder(&transport, RateLimiter::new(2, 1))
+        .retry(retry)
+        .build()
+        .await
+        .expect("Failed to create FcmClient");
+    let message = message();
+
+    // Every send fails once and succeeds on the retry, so the five sends make
+    // ten requests.
+    let started = Instant::now();
+    for _ in 0..5 {
+        client.send(&message).await.expect("Failed to send");
+    }
+
+    assert!(started.elapsed() >= Duration::from_secs(4));
+    assert_eq!(transport.fcm_requests.load(Ordering::SeqCst), 10);
+}
+
+#[tokio::test(start_paused = true)]
+async fn clones_share_the_rate_limiter() {
+    let transport = Arc::new(InstantTransport::default());
+    let client = client(&transport, RateLimiter::new(2, 4)).await;
+    let clone = client.clone();
+    let message = message();
+
+    clone.send(&message).await.expect("Failed to send");
+
+    let rate_limiter = client.rate_limiter().expect("No rate limiter");
+    assert_eq!(rate_limiter.available_permits(), 3);
+    assert_eq!(rate_limiter.burst(), 4);
+}
@@ -0,0 +1,140 @@
+use mockito::Matcher;
+use oauth_fcm::send_fcm_raw_message_with_url;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmErrorCode;
+use oauth_fcm::StaticTokenProvider;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+#[tokio::test]
+async fn raw_message_is_sent_verbatim() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let message = json!({
+        "token": base.device_token,
+        "notification": { "title": "Sale", "body": "Everything is 50% off" },
+        "fcm_options": { "analytics_label": "sale" },
+        "apns": { "live_activity_token": "activity_token" },
+    });
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header("Authorization", "Bearer test-token")
+        .match_body(Matcher::Json(json!({ "message": message })))
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .create();
+
+    let response = send_fcm_raw_message_with_url(
+        message,
+        &StaticTokenProvider::new("test-token"),
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send raw message");
+
+    assert_eq!(
+        response.message_id.as_deref(),
+        Some("projects/mock_project_id/messages/1")
+    );
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn raw_request_body_is_not_wrapped_again() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let body = json!({
+        "validate_only": true,
+        "message": { "topic": "news", "data": { "key": "value" } },
+    });
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Json(body.clone()))
+        .with_status(200)
+        .with_body(
+            json!({ "name": "projects/mock_project_id/messages/fake_message_id" }).to_string(),
+        )
+        .create();
+
+    send_fcm_raw_message_with_url(
+        body,
+        &StaticTokenProvider::new("test-token"),
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send raw message");
+
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn raw_message_errors_are_parsed_like_typed_messages() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+
+    server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(404)
+        .with_body(
+            json!({
+                "error": {
+                    "code": 404,
+                    "message": "Requested entity was not found.",
+                    "status": "NOT_FOUND",
+                    "details": [{
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "UNREGISTERED",
+                    }],
+                }
+            })
+            .to_string(),
+        )
+        .create();
+
+    let error = send_fcm_raw_message_with_url(
+        json!({ "token": base.device_token, "data": { "key": "value" } }),
+        &StaticTokenProvider::new("test-token"),
+        &base.mock_fcm_url(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::FcmResponseError {
+            status: 404,
+            code: FcmErrorCode::Unregistered,
+            ..
+        }
+    ));
+    assert!(error.is_token_invalid());
+}
+
+#[tokio::test]
+async fn invalid_raw_messages_are_rejected_before_sending() {
+    let token_provider = StaticTokenProvider::new("test-token");
+    let send =
+        |message| send_fcm_raw_message_with_url(message, &token_provider, "http://127.0.0.1:1");
+
+    assert!(matches!(
+        send(json!(["not", "an", "object"])).await,
+        Err(FcmError::InvalidRawMessage(_))
+    ));
+    assert!(matches!(
+        send(json!({ "message": "not an object" })).await,
+        Err(FcmError::InvalidRawMessage(_))
+    ));
+    assert!(matches!(
+        send(json!({ "data": { "key": "value" } })).await,
+        Err(FcmError::InvalidMessageTarget(_))
+    ));
+    assert!(matches!(
+        send(json!({ "token": "device_token", "topic": "news" })).await,
+        Err(FcmError::InvalidMessageTarget(_))
+    ));
+}
@@ -0,0 +1,133 @@
+#![cfg(feature = "test-util")]
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use mockito::Matcher;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::FcmResponse;
+use oauth_fcm::HttpTransport;
+use oauth_fcm::Message;
+use oauth_fcm::NetworkError;
+use oauth_fcm::RecordingTransport;
+use oauth_fcm::ReplayTransport;
+use oauth_fcm::ReqwestTransport;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+use crate::test_helpers::mock_token_endpoint;
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+async fn client(transport: impl HttpTransport + 'static, url: &str) -> FcmClient {
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(format!("{url}/token"))
+        .with_http_transport(transport);
+
+    FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .project_id("mock_project_id")
+        .endpoint(url)
+        .build()
+        .await
+        .expect("Failed to create FcmClient")
+}
+
+fn message(token: &str) -> Message {
+    Message::builder()
+        .token(token)
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .build()
+        .expect("Invalid message")
+}
+
+async fn send_both(client: &FcmClient) -> (FcmResponse, FcmError) {
+    let response = client
+        .send(&message("fresh_device_token"))
+        .await
+        .expect("Failed to send");
+    let error = client
+        .send(&message("stale_device_token"))
+        .await
+        .unwrap_err();
+    (response, error)
+}
+
+async fn record(fixture: &Path) -> (FcmResponse, FcmError) {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    mock_token_endpoint(&mut server, &base);
+    server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::PartialJson(
+            json!({ "message": { "token": "fresh_device_token" } }),
+        ))
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .create_async()
+        .await;
+    server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::PartialJson(
+            json!({ "message": { "token": "stale_device_token" } }),
+        ))
+        .with_status(404)
+        .with_body(
+            json!({
+                "error": {
+                    "code": 404,
+                    "status": "NOT_FOUND",
+                    "details": [{
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "UNREGISTERED"
+                    }]
+                }
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let transport = RecordingTransport::new(ReqwestTransport::default(), fixture);
+    send_both(&client(transport, &server.url()).await).await
+}
+
+#[tokio::test]
+async fn replay_reproduces_recorded_outcomes() {
+    let fixture =
+        std::env::temp_dir().join(format!("oauth_fcm_replay_{}.json", std::process::id()));
+
+    let (recorded_response, recorded_error) = record(&fixture).await;
+
+    // The mock server is gone, the replay must not need the network.
+    let transport = ReplayTransport::from_file(&fixture).expect("Failed to read fixture");
+    let client = client(transport, "http://unreachable.invalid").await;
+    let (replayed_response, replayed_error) = send_both(&client).await;
+
+    assert_eq!(replayed_response.message_id, recorded_response.message_id);
+    assert!(recorded_error.is_token_invalid());
+    assert!(replayed_error.is_token_invalid());
+    assert_eq!(
+        replayed_error.suggested_status_code(),
+        recorded_error.suggested_status_code()
+    );
+
+    let error = client
+        .send(&message("unknown_device_token"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&error, FcmError::FcmNetworkError(NetworkError::Transport(e)) if e.to_string().contains("No recorded response for POST /v1/projects/mock_project_id/messages:send")),
+        "{error:?}"
+    );
+
+    let contents = std::fs::read_to_string(&fixture).unwrap();
+    std::fs::remove_file(&fixture).unwrap();
+    assert!(!contents.contains("mock_access_token"), "{contents}");
+    assert!(!contents.contains("assertion=ey"), "{contents}");
+}
@@ -0,0 +1,299 @@
+use std::time::Duration;
+
+use oauth_fcm::send_message_with_retry_and_url;
+use oauth_fcm::send_message_with_url;
+use oauth_fcm::FcmError;
+use oauth_fcm::Message;
+use oauth_fcm::RetryConfig;
+use oauth_fcm::StaticTokenProvider;
+use serde_json::json;
+
+use crate::test_helpers::refreshed_token_manager;
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+fn fast_retry() -> RetryConfig {
+    RetryConfig {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(10),
+        jitter: false,
+    }
+}
+
+fn message(base: &FcmBaseTest) -> Message {
+    Message::builder()
+        .token(base.device_token.as_str())
+        .data(&json!({ "key": "value" }))
+        .build()
+        .expect("Failed to build message")
+}
+
+#[tokio::test]
+async fn transient_errors_are_retried() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+
+    let mock_unavailable = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(503)
+        .expect(2)
+        .create();
+    let mock_success = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(1)
+        .create();
+
+    let response = send_message_with_retry_and_url(
+        &message(&base),
+        &token_manager,
+        &base.mock_fcm_url(),
+        &fast_retry(),
+    )
+    .await
+    .expect("Failed to send FCM message");
+
+    assert_eq!(
+        response.message_id.as_deref(),
+        Some("projects/mock_project_id/messages/1")
+    );
+    mock_unavailable.assert_async().await;
+    mock_success.assert_async().await;
+}
+
+#[tokio::test]
+async fn retries_stop_after_max_attempts() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+
+    let mock_unavailable = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(503)
+        .expect(3)
+        .create();
+
+    let error = send_message_with_retry_and_url(
+        &message(&base),
+        &token_manager,
+        &base.mock_fcm_url(),
+        &fast_retry(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(error.is_retryable());
+    mock_unavailable.assert_async().await;
+}
+
+#[tokio::test]
+async fn permanent_errors_are_not_retried() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+
+    let mock_unregistered = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(404)
+        .with_body(
+            json!({
+                "error": {
+                    "code": 404,
+                    "message": "Requested entity was not found.",
+                    "status": "NOT_FOUND",
+                    "details": [{ "errorCode": "UNREGISTERED" }]
+                }
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let error = send_message_with_retry_and_url(
+        &message(&base),
+        &token_manager,
+        &base.mock_fcm_url(),
+        &fast_retry(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(error, FcmError::FcmResponseError { .. }));
+    mock_unregistered.assert_async().await;
+}
+
+#[tokio::test]
+async fn retry_after_header_is_honored() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+
+    let mock_quota = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(429)
+        .with_header("Retry-After", "1")
+        .with_body(
+            json!({
+                "error": {
+                    "code": 429,
+                    "message": "Quota exceeded.",
+                    "status": "RESOURCE_EXHAUSTED",
+                    "details": [{ "errorCode": "QUOTA_EXCEEDED" }]
+                }
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+    let mock_success = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .expect(1)
+        .create();
+
+    let retry = RetryConfig {
+        max_backoff: Duration::from_secs(2),
+        ..fast_retry()
+    };
+    let started = std::time::Instant::now();
+    send_message_with_retry_and_url(
+        &message(&base),
+        &token_manager,
+        &base.mock_fcm_url(),
+        &retry,
+    )
+    .await
+    .expect("Failed to send FCM message");
+
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    mock_quota.assert_async().await;
+    mock_success.assert_async().await;
+}
+
+#[tokio::test]
+async fn retry_after_header_is_capped_at_max_backoff() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+
+    let mock_unavailable = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(503)
+        .with_header("Retry-After", "3600")
+        .with_body("Service Unavailable")
+        .expect(1)
+        .create();
+    let mock_success = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .expect(1)
+        .create();
+
+    let started = std::time::Instant::now();
+    send_message_with_retry_and_url(
+        &message(&base),
+        &token_manager,
+        &base.mock_fcm_url(),
+        &fast_retry(),
+    )
+    .await
+    .expect("Failed to send FCM message");
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    mock_unavailable.assert_async().await;
+    mock_success.assert_async().await;
+}
+
+#[tokio::test]
+async fn retry_after_header_is_part_of_the_error() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+
+    let mock_quota = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(429)
+        .with_header("Retry-After", "2")
+        .with_body(
+            json!({
+                "error": {
+                    "code": 429,
+                    "message": "Quota exceeded.",
+                    "status": "RESOURCE_EXHAUSTED",
+                    "details": [{ "errorCode": "QUOTA_EXCEEDED" }]
+                }
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let error = send_message_with_url(
+        &message(&base),
+        &StaticTokenProvider::new("test-token"),
+        &base.mock_fcm_url(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::FcmResponseError { status: 429, .. }
+    ));
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(2)));
+    mock_quota.assert_async().await;
+}
+
+#[tokio::test]
+async fn exhausted_retries_are_attached_to_the_error() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::for_server(&server);
+    let token_manager = refreshed_token_manager(&mut server, &base).await;
+
+    let mock_unavailable = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(503)
+        .with_body(json!({ "error": { "code": 503, "status": "UNAVAILABLE" } }).to_string())
+        .expect(4)
+        .create();
+
+    let retry = RetryConfig {
+        max_attempts: 4,
+        ..fast_retry()
+    };
+    let error = send_message_with_retry_and_url(
+        &message(&base),
+        &token_manager,
+        &base.mock_fcm_url(),
+        &retry,
+    )
+    .await
+    .unwrap_err();
+
+    let retry_info = error.retry_info().expect("Missing retry info");
+    assert_eq!(retry_info.attempts, 4);
+    // The backoff is 1, 2 and 4 milliseconds.
+    assert!(retry_info.total_elapsed >= Duration::from_millis(7));
+    assert_eq!(retry_info.last_delay, Duration::from_millis(4));
+    let attempts: Vec<_> = retry_info
+        .attempt_errors
+        .iter()
+        .map(|attempt| (attempt.attempt, attempt.status, attempt.error_code.as_str()))
+        .collect();
+    assert_eq!(
+        attempts,
+        (1..=4)
+            .map(|attempt| (attempt, Some(503), "UNAVAILABLE"))
+            .collect::<Vec<_>>()
+    );
+    assert!(error.is_retryable());
+    assert_eq!(error.suggested_status_code(), 502);
+    assert!(
+        error.to_string().contains("(after 4 attempts in"),
+        "{error}"
+    );
+    mock_unavailable.assert_async().await;
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "schemars")]
+
+use oauth_fcm::DeliveryPriority;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::Message;
+use serde_json::json;
+use serde_json::Value;
+
+fn schema_of<T: schemars::JsonSchema>() -> Value {
+    serde_json::to_value(schemars::schema_for!(T)).expect("Failed to serialize schema")
+}
+
+#[test]
+fn fcm_notification_schema_requires_title_and_body() {
+    let schema = schema_of::<FcmNotification>();
+
+    assert_eq!(schema["title"], "FcmNotification");
+    assert_eq!(schema["type"], "object");
+    assert_eq!(schema["required"], json!(["body", "title"]));
+    assert_eq!(schema["properties"]["title"]["type"], "string");
+    assert_eq!(schema["properties"]["body"]["type"], "string");
+}
+
+#[test]
+fn apns_config_schema_uses_apns_keys() {
+    let schema = schema_of::<oauth_fcm::Aps>();
+
+    assert_eq!(schema["type"], "object");
+    assert!(schema.get("required").is_none());
+    assert_eq!(schema["properties"]["content-available"]["type"], "integer");
+    assert!(schema["properties"].get("mutable-content").is_some());
+    assert!(schema["properties"].get("content_available").is_none());
+}
+
+#[test]
+fn message_schema_flattens_target_and_hides_validate_only() {
+    let schema = schema_of::<Message>();
+
+    assert_eq!(schema["title"], "Message");
+    assert_eq!(schema["type"], "object");
+    let targets: Vec<_> = schema["oneOf"]
+        .as_array()
+        .expect("The target should be flattened into one of its variants")
+        .iter()
+        .map(|variant| variant["required"].clone())
+        .collect();
+    assert_eq!(
+        targets,
+        [json!(["token"]), json!(["topic"]), json!(["condition"])]
+    );
+
+    assert!(schema.get("required").is_none());
+    for section in ["notification", "android", "apns", "webpush"] {
+        let types = &schema["properties"][section]["anyOf"];
+        assert_eq!(types[1], json!({ "type": "null" }), "{section}");
+    }
+    assert!(schema["properties"].get("token").is_none());
+    assert!(schema["properties"].get("validate_only").is_none());
+    assert!(schema["properties"].get("max_payload_size").is_none());
+}
+
+#[test]
+fn delivery_priority_schema_uses_android_values() {
+    let schema = schema_of::<DeliveryPriority>();
+
+    let values: Vec<_> = schema["oneOf"]
+        .as_array()
+        .expect("Failed to read the variants")
+        .iter()
+        .map(|variant| variant["enum"][0].clone())
+        .collect();
+    assert_eq!(values, [json!("HIGH"), json!("NORMAL")]);
+    assert_eq!(
+        schema_of::<Message>()["definitions"]["AndroidConfig"]["properties"]["priority"]["anyOf"]
+            [0]["$ref"],
+        "#/definitions/DeliveryPriority"
+    );
+}
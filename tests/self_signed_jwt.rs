@@ -0,0 +1,118 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use jsonwebtoken::DecodingKey;
+use jsonwebtoken::Validation;
+use mockito::Matcher;
+use oauth_fcm::send_fcm_message_with_url;
+use oauth_fcm::FcmError;
+use oauth_fcm::NetworkError;
+use oauth_fcm::SharedTokenManager;
+use oauth_fcm::TokenManager;
+
+use crate::test_helpers::FcmBaseTest;
+use crate::test_helpers::TestData;
+
+mod test_helpers;
+
+fn self_signed_token_manager() -> SharedTokenManager {
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_self_signed_jwt();
+    Arc::new(tokio::sync::Mutex::new(token_manager))
+}
+
+fn test_data() -> TestData {
+    TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn self_signed_jwt_is_sent_without_token_exchange() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .expect(0)
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header(
+            "authorization",
+            Matcher::Regex(r"^Bearer [\w-]+\.[\w-]+\.[\w-]+$".to_string()),
+        )
+        .with_status(200)
+        .create();
+
+    let token_manager = self_signed_token_manager();
+
+    let result = send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(test_data()),
+        &token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await;
+    assert!(result.is_ok());
+
+    let token = token_manager.lock().await.get_token().await.unwrap();
+    let mut validation = Validation::new(jsonwebtoken::Algorithm::RS256);
+    validation.insecure_disable_signature_validation();
+    validation.set_audience(&["https://fcm.googleapis.com/"]);
+    let claims = jsonwebtoken::decode::<serde_json::Value>(
+        &token,
+        &DecodingKey::from_secret(&[]),
+        &validation,
+    )
+    .expect("Failed to decode self-signed JWT")
+    .claims;
+    assert_eq!(claims["iss"], claims["sub"]);
+    assert!(claims.get("scope").is_none());
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn rejected_self_signed_jwt_returns_unauthorized() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(401)
+        .create();
+
+    let result = send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(test_data()),
+        &self_signed_token_manager(),
+        &base.mock_fcm_url(),
+    )
+    .await;
+
+    assert!(matches!(
+        result.unwrap_err(),
+        FcmError::FcmNetworkError(NetworkError::ServerError(401, ..))
+    ));
+
+    mock_fcm.assert_async().await;
+}
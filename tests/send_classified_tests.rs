@@ -0,0 +1,244 @@
+use std::fs::File;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmErrorCode;
+use oauth_fcm::FcmResponse;
+use oauth_fcm::Message;
+use oauth_fcm::SendFailure;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+const FCM_PATH: &str = "/v1/projects/mock_project_id/messages:send";
+
+/// Returns an error body as sent by FCM.
+fn fcm_error_body(code: u16, status: &str, message: &str, error_code: &str) -> String {
+    json!({
+        "error": {
+            "code": code,
+            "message": message,
+            "status": status,
+            "details": [{
+                "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                "errorCode": error_code,
+            }],
+        }
+    })
+    .to_string()
+}
+
+/// Sends a message to a mock FCM server, which responds with the given
+/// status, body and headers.
+async fn send_classified(
+    status: usize,
+    body: String,
+    headers: &[(&str, &str)],
+) -> Result<FcmResponse, SendFailure> {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/token")
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": "mock_access_token",
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+    let mut mock_fcm = server
+        .mock("POST", FCM_PATH)
+        .with_status(status)
+        .with_body(body);
+    for (name, value) in headers {
+        mock_fcm = mock_fcm.with_header(*name, value);
+    }
+    mock_fcm.create();
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(format!("{}/token", server.url()));
+    let client = FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+
+    let message = Message::builder()
+        .token("device_token")
+        .data(&json!({ "key": "value" }))
+        .build()
+        .expect("Invalid message");
+    client.send_classified(&message).await
+}
+
+#[tokio::test]
+async fn delivered_message_returns_response() {
+    let response = send_classified(
+        200,
+        json!({ "name": "projects/mock_project_id/messages/0:1500415314455276%31bd1c9631bd1c96" })
+            .to_string(),
+        &[],
+    )
+    .await
+    .expect("Message was not delivered");
+
+    assert_eq!(
+        response.message_id.as_deref(),
+        Some("projects/mock_project_id/messages/0:1500415314455276%31bd1c9631bd1c96")
+    );
+}
+
+#[tokio::test]
+async fn unregistered_token_is_invalid() {
+    let failure = send_classified(
+        404,
+        fcm_error_body(
+            404,
+            "NOT_FOUND",
+            "Requested entity was not found.",
+            "UNREGISTERED",
+        ),
+        &[],
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(failure, SendFailure::TokenInvalid(_)));
+}
+
+#[tokio::test]
+async fn sender_id_mismatch_is_invalid_token() {
+    let failure = send_classified(
+        403,
+        fcm_error_body(
+            403,
+            "PERMISSION_DENIED",
+            "SenderId mismatch",
+            "SENDER_ID_MISMATCH",
+        ),
+        &[],
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(failure, SendFailure::TokenInvalid(_)));
+}
+
+#[tokio::test]
+async fn quota_exceeded_is_throttled() {
+    let failure = send_classified(
+        429,
+        fcm_error_body(
+            429,
+            "RESOURCE_EXHAUSTED",
+            "Quota exceeded for quota metric 'Send requests' and limit 'Send requests per minute' \
+             of service 'fcm.googleapis.com'.",
+            "QUOTA_EXCEEDED",
+        ),
+        &[("Retry-After", "30")],
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        failure,
+        SendFailure::Throttled {
+            retry_after: Some(delay)
+        } if delay == Duration::from_secs(30)
+    ));
+}
+
+#[tokio::test]
+async fn unavailable_is_transient() {
+    let failure = send_classified(
+        503,
+        fcm_error_body(
+            503,
+            "UNAVAILABLE",
+            "The service is currently unavailable.",
+            "UNAVAILABLE",
+        ),
+        &[],
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        failure,
+        SendFailure::Transient(FcmError::FcmResponseError {
+            code: FcmErrorCode::Unavailable,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn internal_error_is_transient() {
+    let failure = send_classified(
+        500,
+        fcm_error_body(500, "INTERNAL", "Internal error encountered.", "INTERNAL"),
+        &[],
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        failure,
+        SendFailure::Transient(FcmError::FcmResponseError { status: 500, .. })
+    ));
+}
+
+#[tokio::test]
+async fn invalid_argument_is_permanent() {
+    let failure = send_classified(
+        400,
+        fcm_error_body(
+            400,
+            "INVALID_ARGUMENT",
+            "The registration token is not a valid FCM registration token",
+            "INVALID_ARGUMENT",
+        ),
+        &[],
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        failure,
+        SendFailure::Permanent(FcmError::FcmResponseError {
+            code: FcmErrorCode::InvalidArgument,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn third_party_auth_error_is_permanent() {
+    let failure = send_classified(
+        401,
+        fcm_error_body(
+            401,
+            "UNAUTHENTICATED",
+            "Auth error from APNS or Web Push Service",
+            "THIRD_PARTY_AUTH_ERROR",
+        ),
+        &[],
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        failure,
+        SendFailure::Permanent(FcmError::FcmResponseError {
+            code: FcmErrorCode::ThirdPartyAuthError,
+            ..
+        })
+    ));
+}
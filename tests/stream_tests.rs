@@ -0,0 +1,110 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use futures::stream;
+use futures::StreamExt;
+use oauth_fcm::send_fcm_stream_with_url;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::Message;
+use oauth_fcm::SharedTokenManager;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+fn setup(server: &mockito::ServerGuard) -> FcmBaseTest {
+    FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    )
+}
+
+fn token_manager(base: &FcmBaseTest) -> SharedTokenManager {
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url());
+    Arc::new(tokio::sync::Mutex::new(token_manager))
+}
+
+fn mock_auth(server: &mut mockito::ServerGuard, base: &FcmBaseTest) -> mockito::Mock {
+    server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create()
+}
+
+fn messages(count: usize) -> impl futures::Stream<Item = Message> {
+    stream::iter(0..count).map(|index| {
+        Message::builder()
+            .token(format!("device_token_{index}"))
+            .notification(FcmNotification::new("Test title", "Test body"))
+            .build()
+            .expect("Invalid message")
+    })
+}
+
+#[tokio::test]
+async fn stream_sends_all_messages_with_one_token_refresh() {
+    let mut server = mockito::Server::new_async().await;
+    let base = setup(&server);
+
+    let mock_auth = mock_auth(&mut server, &base);
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(100)
+        .create();
+
+    let token_manager = token_manager(&base);
+    let results: Vec<_> =
+        send_fcm_stream_with_url(messages(100), &token_manager, &base.mock_fcm_url(), 8)
+            .collect()
+            .await;
+
+    assert_eq!(results.len(), 100);
+    assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+    mock_auth.assert();
+    mock_fcm.assert();
+}
+
+#[tokio::test]
+async fn dropping_the_stream_stops_sending() {
+    let mut server = mockito::Server::new_async().await;
+    let base = setup(&server);
+
+    let mock_auth = mock_auth(&mut server, &base);
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(5)
+        .create();
+
+    let token_manager = token_manager(&base);
+    let results: Vec<_> =
+        send_fcm_stream_with_url(messages(100), &token_manager, &base.mock_fcm_url(), 1)
+            .take(5)
+            .collect()
+            .await;
+
+    assert_eq!(results.len(), 5);
+
+    mock_auth.assert();
+    mock_fcm.assert();
+}
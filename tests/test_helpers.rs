@@ -1,3 +1,4 @@
+#[allow(dead_code)]
 #[derive(serde::Serialize)]
 pub struct TestData {
     pub title: String,
@@ -16,6 +17,18 @@ pub struct FcmBaseTest {
 }
 
 impl FcmBaseTest {
+    /// Serves the OAuth and FCM endpoints of `mock_project_id` from one mock
+    /// server.
+    #[allow(dead_code)]
+    pub fn for_server(server: &mockito::Server) -> Self {
+        Self::new(
+            server.url(),
+            "/token".to_string(),
+            server.url(),
+            "/v1/projects/mock_project_id/messages:send".to_string(),
+        )
+    }
+
     pub fn new(oauth_host: String, oauth_path: String, fcm_host: String, fcm_path: String) -> Self {
         Self {
             oauth_host,
@@ -28,6 +41,7 @@ impl FcmBaseTest {
         }
     }
 
+    #[allow(dead_code)]
     pub fn mock_auth_url(&self) -> String {
         format!("{}{}", &self.oauth_host, &self.oauth_path)
     }
@@ -37,3 +51,47 @@ impl FcmBaseTest {
         format!("{}{}", &self.fcm_host, &self.fcm_path)
     }
 }
+
+/// Returns the body of a successful OAuth token response.
+#[allow(dead_code)]
+pub fn token_response(base: &FcmBaseTest) -> String {
+    serde_json::json!({
+        "access_token": base.access_token,
+        "scope": "https://www.googleapis.com/auth/prediction",
+        "token_type": "Bearer",
+        "expires_in": 3600,
+    })
+    .to_string()
+}
+
+/// Mocks the OAuth token endpoint, which returns `base.access_token`.
+#[allow(dead_code)]
+pub fn mock_token_endpoint(server: &mut mockito::Server, base: &FcmBaseTest) -> mockito::Mock {
+    server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(token_response(base))
+        .create()
+}
+
+/// Creates a shared token manager, which already holds the token of the
+/// mocked token endpoint.
+#[allow(dead_code)]
+pub async fn refreshed_token_manager(
+    server: &mut mockito::Server,
+    base: &FcmBaseTest,
+) -> oauth_fcm::SharedTokenManager {
+    mock_token_endpoint(server, base);
+
+    let token_manager = oauth_fcm::create_shared_token_manager(
+        std::fs::File::open("tests/mock_credentials.json").unwrap(),
+    )
+    .expect("Failed to create SharedTokenManager");
+    token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+    token_manager
+}
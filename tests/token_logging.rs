@@ -0,0 +1,190 @@
+#![cfg(not(feature = "log"))]
+
+use std::fs::File;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use oauth_fcm::send_fcm_message_with_url;
+use oauth_fcm::FcmClient;
+use oauth_fcm::Message;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+use tracing::field::Field;
+use tracing::field::Visit;
+use tracing::span::Attributes;
+use tracing::Event;
+use tracing::Id;
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::Layer;
+
+use crate::test_helpers::FcmBaseTest;
+use crate::test_helpers::TestData;
+
+mod test_helpers;
+
+type CapturedValues = Arc<Mutex<Vec<String>>>;
+
+/// Captures every field value of the events and spans emitted by this crate,
+/// including the messages of events.
+struct CaptureLayer {
+    values: CapturedValues,
+}
+
+struct ValueVisitor<'a>(&'a mut Vec<String>);
+
+impl Visit for ValueVisitor<'_> {
+    fn record_debug(&mut self, _field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.push(format!("{value:?}"));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for CaptureLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        if attrs.metadata().target().starts_with("oauth_fcm") {
+            attrs.record(&mut ValueVisitor(&mut self.values.lock().unwrap()));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target().starts_with("oauth_fcm") {
+            event.record(&mut ValueVisitor(&mut self.values.lock().unwrap()));
+        }
+    }
+}
+
+fn capture() -> (CapturedValues, tracing::subscriber::DefaultGuard) {
+    let values = CapturedValues::default();
+    let subscriber = tracing_subscriber::registry().with(CaptureLayer {
+        values: values.clone(),
+    });
+    (values, tracing::subscriber::set_default(subscriber))
+}
+
+fn mock_endpoints(
+    server: &mut mockito::Server,
+    base: &FcmBaseTest,
+) -> (mockito::Mock, mockito::Mock) {
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .create();
+
+    (mock_auth, mock_fcm)
+}
+
+async fn send_with_client(log_full_tokens: bool) -> Vec<String> {
+    let (values, _guard) = capture();
+
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+    let (mock_auth, mock_fcm) = mock_endpoints(&mut server, &base);
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url());
+    let client = FcmClient::builder()
+        .token_manager(Arc::new(tokio::sync::Mutex::new(token_manager)))
+        .endpoint(server.url())
+        .log_full_tokens(log_full_tokens)
+        .build()
+        .await
+        .expect("Failed to create FcmClient");
+
+    let message = Message::builder()
+        .token(&base.device_token)
+        .data(&json!({ "key": "value" }))
+        .build()
+        .expect("Invalid message");
+    client.send(&message).await.expect("Failed to send message");
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+
+    let values = values.lock().unwrap().clone();
+    values
+}
+
+#[tokio::test]
+async fn client_redacts_device_token_by_default() {
+    let values = send_with_client(false).await;
+
+    assert!(values
+        .iter()
+        .any(|value| value == "Sending FCM message to device mock…oken"));
+    assert!(!values
+        .iter()
+        .any(|value| value.contains("mock_device_token")));
+}
+
+#[tokio::test]
+async fn client_logs_full_device_token_if_enabled() {
+    let values = send_with_client(true).await;
+
+    assert!(values
+        .iter()
+        .any(|value| value == "Sending FCM message to device mock_device_token"));
+}
+
+#[tokio::test]
+async fn device_token_is_redacted_in_span_fields() {
+    let (values, _guard) = capture();
+
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+    let (mock_auth, mock_fcm) = mock_endpoints(&mut server, &base);
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url());
+    let shared_token_manager = Arc::new(tokio::sync::Mutex::new(token_manager));
+
+    let data = TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    };
+    send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(data),
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send message");
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+
+    let values = values.lock().unwrap().clone();
+    assert!(values.iter().any(|value| value == "mock…oken"));
+    assert!(!values
+        .iter()
+        .any(|value| value.contains("mock_device_token")));
+}
@@ -0,0 +1,517 @@
+use std::fs::File;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth_fcm::get_shared_token;
+use oauth_fcm::InMemoryTokenCache;
+use oauth_fcm::TokenManager;
+use oauth_fcm::TokenState;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+#[tokio::test]
+async fn cloned_token_manager_keeps_own_token_cache() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager");
+    let clone = token_manager.clone();
+
+    token_manager
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    assert!(!token_manager.is_token_expired());
+    assert!(clone.is_token_expired());
+
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test]
+async fn shared_token_cache_avoids_second_refresh() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let cache = Arc::new(InMemoryTokenCache::new());
+    let first = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_cache(cache.clone());
+    let second = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_cache(cache);
+
+    first
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    assert!(second.is_token_expired());
+    let token = second.get_token().await.expect("Failed to get token");
+    assert_eq!(token, base.access_token);
+    assert!(!second.is_token_expired());
+
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test]
+async fn authorization_header_is_cached_until_token_changes() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager");
+    token_manager
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let first = token_manager
+        .authorization_header()
+        .await
+        .expect("Failed to get authorization header");
+    let second = token_manager
+        .authorization_header()
+        .await
+        .expect("Failed to get authorization header");
+
+    assert_eq!(first, second);
+    assert_eq!(
+        first.to_str().unwrap(),
+        format!("Bearer {}", base.access_token)
+    );
+    assert!(first.is_sensitive());
+    assert!(!format!("{first:?}").contains(&base.access_token));
+
+    mock_auth.assert_async().await;
+}
+
+fn mock_short_lived_token(
+    server: &mut mockito::ServerGuard,
+    base: &FcmBaseTest,
+    expect: usize,
+) -> mockito::Mock {
+    server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 30,
+            })
+            .to_string(),
+        )
+        .expect(expect)
+        .create()
+}
+
+#[tokio::test]
+async fn token_is_refreshed_within_refresh_margin() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    // The token expires in 30 seconds, which is within the default margin.
+    let mock_auth = mock_short_lived_token(&mut server, &base, 2);
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url());
+
+    token_manager
+        .get_token()
+        .await
+        .expect("Failed to get token");
+    assert!(token_manager.is_token_expired());
+    token_manager
+        .get_token()
+        .await
+        .expect("Failed to get token");
+
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test]
+async fn token_is_reused_outside_refresh_margin() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = mock_short_lived_token(&mut server, &base, 1);
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url())
+        .with_refresh_margin(Duration::from_secs(10));
+
+    token_manager
+        .get_token()
+        .await
+        .expect("Failed to get token");
+    assert!(!token_manager.is_token_expired());
+    token_manager
+        .get_token()
+        .await
+        .expect("Failed to get token");
+
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_shared_token_requests_refresh_once() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let body = json!({
+        "access_token": base.access_token,
+        "scope": "https://www.googleapis.com/auth/prediction",
+        "token_type": "Bearer",
+        "expires_in": 3600,
+    })
+    .to_string();
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body_from_request(move |_| {
+            std::thread::sleep(Duration::from_millis(200));
+            body.clone().into_bytes()
+        })
+        .expect(1)
+        .create();
+
+    let token_manager = Arc::new(tokio::sync::Mutex::new(
+        TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create TokenManager")
+            .with_token_uri(base.mock_auth_url()),
+    ));
+
+    let tasks: Vec<_> = (0..32)
+        .map(|_| {
+            let token_manager = Arc::clone(&token_manager);
+            tokio::spawn(async move { get_shared_token(&token_manager).await })
+        })
+        .collect();
+
+    // The manager must not be locked while the refresh is in progress.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(token_manager.try_lock().is_ok());
+
+    for task in tasks {
+        let token = task.await.unwrap().expect("Failed to get token");
+        assert_eq!(token, base.access_token);
+    }
+
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn readers_do_not_wait_for_refresh_in_progress() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let body = json!({
+        "access_token": base.access_token,
+        "scope": "https://www.googleapis.com/auth/prediction",
+        "token_type": "Bearer",
+        "expires_in": 3600,
+    })
+    .to_string();
+    let mock_initial_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(body.clone())
+        .expect(1)
+        .create();
+    let mock_slow_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body_from_request(move |_| {
+            std::thread::sleep(Duration::from_millis(500));
+            body.clone().into_bytes()
+        })
+        .expect(1)
+        .create();
+
+    let token_manager = Arc::new(
+        TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create TokenManager")
+            .with_token_uri(base.mock_auth_url()),
+    );
+    token_manager
+        .get_token()
+        .await
+        .expect("Failed to get token");
+
+    let refresh = {
+        let token_manager = Arc::clone(&token_manager);
+        tokio::spawn(async move { token_manager.refresh_token().await })
+    };
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let readers: Vec<_> = (0..16)
+        .map(|_| {
+            let token_manager = Arc::clone(&token_manager);
+            tokio::spawn(async move { token_manager.get_token().await })
+        })
+        .collect();
+    for reader in readers {
+        let token = tokio::time::timeout(Duration::from_millis(200), reader)
+            .await
+            .expect("Reader waited for the refresh")
+            .unwrap()
+            .expect("Failed to get token");
+        assert_eq!(token, base.access_token);
+    }
+
+    refresh.await.unwrap().expect("Failed to refresh token");
+    mock_initial_auth.assert_async().await;
+    mock_slow_auth.assert_async().await;
+}
+
+#[tokio::test]
+async fn token_state_reports_failed_refresh() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_failed_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(400)
+        .expect(1)
+        .create();
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url());
+    assert_eq!(token_manager.token_state(), TokenState::Uninitialized);
+    assert!(!token_manager.last_refresh_failed());
+
+    assert!(token_manager.get_token().await.is_err());
+    assert_eq!(token_manager.token_state(), TokenState::Uninitialized);
+    assert!(token_manager.last_refresh_failed());
+
+    token_manager
+        .get_token()
+        .await
+        .expect("Failed to get token");
+    assert!(matches!(
+        token_manager.token_state(),
+        TokenState::Valid { remaining } if remaining > Duration::from_secs(3590)
+    ));
+    assert!(!token_manager.last_refresh_failed());
+
+    mock_failed_auth.assert_async().await;
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test]
+async fn token_uri_is_read_from_credentials() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let mut credentials: serde_json::Value =
+        serde_json::from_reader(File::open("tests/mock_credentials.json").unwrap()).unwrap();
+    credentials["token_uri"] = json!(base.mock_auth_url());
+    let token_manager = TokenManager::new(credentials).expect("Failed to create TokenManager");
+
+    let token = token_manager
+        .get_token()
+        .await
+        .expect("Failed to get token");
+    assert_eq!(token, base.access_token);
+
+    mock_auth.assert_async().await;
+}
+
+#[tokio::test]
+async fn debug_output_redacts_token_and_private_key() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let credentials: serde_json::Value =
+        serde_json::from_reader(File::open("tests/mock_credentials.json").unwrap()).unwrap();
+    let private_key = credentials["private_key"].as_str().unwrap().to_string();
+    let token_manager = TokenManager::new(credentials)
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url());
+
+    let uninitialized = format!("{token_manager:?}");
+    assert!(uninitialized.contains("token: None"));
+
+    token_manager
+        .get_token()
+        .await
+        .expect("Failed to get token");
+    token_manager
+        .authorization_header()
+        .await
+        .expect("Failed to get header");
+    let debug = format!("{token_manager:?}");
+
+    assert!(!debug.contains(&base.access_token));
+    for line in private_key
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+    {
+        assert!(!debug.contains(line));
+    }
+    assert!(debug.contains(&format!(
+        "token: Some(\"<redacted, {} chars>\")",
+        base.access_token.len()
+    )));
+    assert!(debug.contains("mock-service-account@mock-project.iam.gserviceaccount.com"));
+    assert!(debug.contains("mock_private_key_id"));
+
+    mock_auth.assert_async().await;
+}
@@ -0,0 +1,177 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use mockito::Matcher;
+use oauth_fcm::build_fcm_payload;
+use oauth_fcm::send_fcm_message_with_url;
+use oauth_fcm::send_message_with_url;
+use oauth_fcm::FcmError;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::Message;
+use oauth_fcm::MessageTarget;
+use oauth_fcm::NetworkError;
+use oauth_fcm::PlatformConfig;
+use oauth_fcm::StaticTokenProvider;
+use oauth_fcm::TokenManager;
+use oauth_fcm::TokenProvider;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+fn test_message(base: &FcmBaseTest) -> Message {
+    Message::builder()
+        .token(&base.device_token)
+        .data(&json!({ "key": "value" }))
+        .build()
+        .expect("Invalid message")
+}
+
+fn fcm_test_base(server: &mockito::Server) -> FcmBaseTest {
+    FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    )
+}
+
+#[tokio::test]
+async fn static_token_provider_sends_its_token() {
+    let mut server = mockito::Server::new_async().await;
+    let base = fcm_test_base(&server);
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header("Authorization", "Bearer test-token")
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .create();
+
+    let token_provider = StaticTokenProvider::new("test-token");
+    let response =
+        send_message_with_url(&test_message(&base), &token_provider, &base.mock_fcm_url())
+            .await
+            .expect("Failed to send message");
+    assert_eq!(
+        response.message_id.as_deref(),
+        Some("projects/mock_project_id/messages/1")
+    );
+
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn rejected_static_token_is_not_retried() {
+    let mut server = mockito::Server::new_async().await;
+    let base = fcm_test_base(&server);
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(401)
+        .expect(1)
+        .create();
+
+    // Trait objects can be used as well.
+    let token_provider: Arc<dyn TokenProvider> = Arc::new(StaticTokenProvider::new("test-token"));
+    let error = send_message_with_url(&test_message(&base), &token_provider, &base.mock_fcm_url())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        FcmError::FcmNetworkError(NetworkError::ServerError(401, ..))
+            | FcmError::FcmResponseError { status: 401, .. }
+    ));
+
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn token_manager_can_be_shared_without_lock() {
+    let mut server = mockito::Server::new_async().await;
+    let base = fcm_test_base(&server);
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_header("Authorization", "Bearer mock_access_token")
+        .with_status(200)
+        .with_body(json!({ "name": "projects/mock_project_id/messages/1" }).to_string())
+        .expect(2)
+        .create();
+
+    let token_manager = Arc::new(
+        TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create TokenManager")
+            .with_token_uri(base.mock_auth_url()),
+    );
+
+    for _ in 0..2 {
+        send_message_with_url(&test_message(&base), &token_manager, &base.mock_fcm_url())
+            .await
+            .expect("Failed to send message");
+    }
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn built_payload_is_sent_verbatim() {
+    let mut server = mockito::Server::new_async().await;
+    let base = fcm_test_base(&server);
+    let notification = FcmNotification::new("Sale", "Everything is 50% off");
+    let data = json!({ "sale_id": "42" });
+
+    let payload = build_fcm_payload(
+        &MessageTarget::Token(base.device_token.clone()),
+        Some(notification.clone()),
+        Some(&data),
+        &PlatformConfig::default(),
+    )
+    .expect("Invalid message");
+    let message = test_message(&base);
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Json(payload))
+        .with_status(200)
+        .create();
+    let mock_message = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Json(message.to_request_body()))
+        .with_status(200)
+        .create();
+
+    let token_provider = StaticTokenProvider::new("test-token");
+    send_fcm_message_with_url(
+        &base.device_token,
+        Some(notification),
+        Some(data),
+        &token_provider,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send message");
+    send_message_with_url(&message, &token_provider, &base.mock_fcm_url())
+        .await
+        .expect("Failed to send message");
+
+    mock_fcm.assert_async().await;
+    mock_message.assert_async().await;
+}
@@ -0,0 +1,211 @@
+use mockito::Matcher;
+use oauth_fcm::subscribe_to_topic_with_url;
+use oauth_fcm::unsubscribe_from_topic_with_url;
+use oauth_fcm::FcmError;
+use oauth_fcm::NetworkError;
+use oauth_fcm::StaticTokenProvider;
+use oauth_fcm::MAX_TOPIC_BATCH_SIZE;
+use serde_json::json;
+
+fn tokens(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|index| format!("device_token_{index}"))
+        .collect()
+}
+
+#[tokio::test]
+async fn subscribe_reports_result_per_token() {
+    let mut server = mockito::Server::new_async().await;
+    let tokens = tokens(3);
+
+    let mock_iid = server
+        .mock("POST", "/iid/v1:batchAdd")
+        .match_header("Authorization", "Bearer test-token")
+        .match_header("access_token_auth", "true")
+        .match_body(Matcher::Json(json!({
+            "to": "/topics/news",
+            "registration_tokens": tokens,
+        })))
+        .with_status(200)
+        .with_body(
+            json!({
+                "results": [{}, { "error": "NOT_FOUND" }, {}]
+            })
+            .to_string(),
+        )
+        .create();
+
+    let result = subscribe_to_topic_with_url(
+        &tokens,
+        "news",
+        &StaticTokenProvider::new("test-token"),
+        &format!("{}/iid/v1:batchAdd", server.url()),
+    )
+    .await
+    .expect("Failed to subscribe to topic");
+
+    assert_eq!(result.success_count, 2);
+    assert_eq!(result.failure_count, 1);
+    assert_eq!(
+        result.results,
+        vec![(0, Ok(())), (1, Err("NOT_FOUND".to_string())), (2, Ok(()))]
+    );
+
+    mock_iid.assert_async().await;
+}
+
+#[tokio::test]
+async fn unsubscribe_accepts_topic_with_prefix() {
+    let mut server = mockito::Server::new_async().await;
+    let tokens = tokens(1);
+
+    let mock_iid = server
+        .mock("POST", "/iid/v1:batchRemove")
+        .match_body(Matcher::PartialJson(json!({ "to": "/topics/news" })))
+        .with_status(200)
+        .with_body(json!({ "results": [{}] }).to_string())
+        .create();
+
+    let result = unsubscribe_from_topic_with_url(
+        &tokens,
+        "/topics/news",
+        &StaticTokenProvider::new("test-token"),
+        &format!("{}/iid/v1:batchRemove", server.url()),
+    )
+    .await
+    .expect("Failed to unsubscribe from topic");
+
+    assert_eq!(result.success_count, 1);
+
+    mock_iid.assert_async().await;
+}
+
+#[tokio::test]
+async fn large_batches_are_split() {
+    let mut server = mockito::Server::new_async().await;
+    let tokens = tokens(MAX_TOPIC_BATCH_SIZE + 1);
+
+    let first_batch = server
+        .mock("POST", "/iid/v1:batchAdd")
+        .match_body(Matcher::PartialJson(json!({
+            "registration_tokens": &tokens[..MAX_TOPIC_BATCH_SIZE],
+        })))
+        .with_status(200)
+        .with_body(json!({ "results": vec![json!({}); MAX_TOPIC_BATCH_SIZE] }).to_string())
+        .create();
+    let second_batch = server
+        .mock("POST", "/iid/v1:batchAdd")
+        .match_body(Matcher::PartialJson(json!({
+            "registration_tokens": &tokens[MAX_TOPIC_BATCH_SIZE..],
+        })))
+        .with_status(200)
+        .with_body(json!({ "results": [{ "error": "INVALID_ARGUMENT" }] }).to_string())
+        .create();
+
+    let result = subscribe_to_topic_with_url(
+        &tokens,
+        "news",
+        &StaticTokenProvider::new("test-token"),
+        &format!("{}/iid/v1:batchAdd", server.url()),
+    )
+    .await
+    .expect("Failed to subscribe to topic");
+
+    assert_eq!(result.success_count, MAX_TOPIC_BATCH_SIZE);
+    assert_eq!(
+        result.results.last(),
+        Some(&(MAX_TOPIC_BATCH_SIZE, Err("INVALID_ARGUMENT".to_string())))
+    );
+
+    first_batch.assert_async().await;
+    second_batch.assert_async().await;
+}
+
+#[tokio::test]
+async fn failed_batch_is_reported_per_token() {
+    let mut server = mockito::Server::new_async().await;
+    let tokens = tokens(MAX_TOPIC_BATCH_SIZE + 1);
+
+    let first_batch = server
+        .mock("POST", "/iid/v1:batchAdd")
+        .match_body(Matcher::PartialJson(json!({
+            "registration_tokens": &tokens[..MAX_TOPIC_BATCH_SIZE],
+        })))
+        .with_status(200)
+        .with_body(json!({ "results": vec![json!({}); MAX_TOPIC_BATCH_SIZE] }).to_string())
+        .create();
+    let second_batch = server
+        .mock("POST", "/iid/v1:batchAdd")
+        .match_body(Matcher::PartialJson(json!({
+            "registration_tokens": &tokens[MAX_TOPIC_BATCH_SIZE..],
+        })))
+        .with_status(500)
+        .with_body("Internal error")
+        .create();
+
+    let result = subscribe_to_topic_with_url(
+        &tokens,
+        "news",
+        &StaticTokenProvider::new("test-token"),
+        &format!("{}/iid/v1:batchAdd", server.url()),
+    )
+    .await
+    .expect("The applied batch should be reported");
+
+    assert_eq!(result.success_count, MAX_TOPIC_BATCH_SIZE);
+    assert_eq!(result.failure_count, 1);
+    let (index, error) = result.results.last().unwrap();
+    assert_eq!(*index, MAX_TOPIC_BATCH_SIZE);
+    assert!(error.as_ref().unwrap_err().contains("500"));
+
+    first_batch.assert_async().await;
+    second_batch.assert_async().await;
+}
+
+#[tokio::test]
+async fn failed_request_is_an_error() {
+    let mut server = mockito::Server::new_async().await;
+
+    let mock_iid = server
+        .mock("POST", "/iid/v1:batchAdd")
+        .with_status(403)
+        .with_body(json!({ "error": "PERMISSION_DENIED" }).to_string())
+        .create();
+
+    let error = subscribe_to_topic_with_url(
+        &tokens(1),
+        "news",
+        &StaticTokenProvider::new("test-token"),
+        &format!("{}/iid/v1:batchAdd", server.url()),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::FcmNetworkError(NetworkError::ServerError(403, ..))
+    ));
+    assert!(error.to_string().contains("PERMISSION_DENIED"));
+
+    mock_iid.assert_async().await;
+}
+
+#[tokio::test]
+async fn invalid_topic_is_rejected_before_sending() {
+    let error = subscribe_to_topic_with_url(
+        &tokens(1),
+        "/topics/breaking news",
+        &StaticTokenProvider::new("test-token"),
+        "http://127.0.0.1:1/iid/v1:batchAdd",
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::InvalidTopicName {
+            character: Some(' '),
+            ..
+        }
+    ));
+}
@@ -0,0 +1,11 @@
+use oauth_fcm::FcmData;
+
+#[derive(FcmData)]
+enum Payload {
+    Text(String),
+}
+
+#[derive(FcmData)]
+struct TuplePayload(String);
+
+fn main() {}
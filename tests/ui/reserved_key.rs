@@ -0,0 +1,14 @@
+use oauth_fcm::FcmData;
+
+#[derive(FcmData)]
+struct Payload {
+    from: String,
+}
+
+#[derive(FcmData)]
+struct RenamedPayload {
+    #[fcm(rename = "google.sender")]
+    sender: String,
+}
+
+fn main() {}
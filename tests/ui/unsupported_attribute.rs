@@ -0,0 +1,9 @@
+use oauth_fcm::FcmData;
+
+#[derive(FcmData)]
+struct Payload {
+    #[fcm(skip)]
+    text: String,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use oauth_fcm::FcmData;
+
+#[derive(FcmData)]
+struct Payload {
+    ids: Vec<u64>,
+}
+
+fn main() {}
@@ -0,0 +1,117 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use oauth_fcm::send_fcm_message_with_url;
+use oauth_fcm::FcmError;
+use oauth_fcm::NetworkError;
+use oauth_fcm::SharedTokenManager;
+use oauth_fcm::TokenManager;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+use crate::test_helpers::TestData;
+
+mod test_helpers;
+
+fn token_manager(base: &FcmBaseTest) -> SharedTokenManager {
+    let token_manager = TokenManager::new(File::open("tests/mock_credentials.json").unwrap())
+        .expect("Failed to create TokenManager")
+        .with_token_uri(base.mock_auth_url());
+    Arc::new(tokio::sync::Mutex::new(token_manager))
+}
+
+fn mock_auth(server: &mut mockito::ServerGuard, base: &FcmBaseTest) -> mockito::Mock {
+    server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .expect(2)
+        .create()
+}
+
+fn test_data() -> TestData {
+    TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn unauthorized_response_refreshes_token_and_retries() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = mock_auth(&mut server, &base);
+    let mock_unauthorized = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(401)
+        .expect(1)
+        .create();
+    let mock_success = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .expect(1)
+        .create();
+
+    send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(test_data()),
+        &token_manager(&base),
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send FCM message");
+
+    mock_auth.assert_async().await;
+    mock_unauthorized.assert_async().await;
+    mock_success.assert_async().await;
+}
+
+#[tokio::test]
+async fn unauthorized_response_is_retried_only_once() {
+    let mut server = mockito::Server::new_async().await;
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = mock_auth(&mut server, &base);
+    let mock_unauthorized = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(401)
+        .expect(2)
+        .create();
+
+    let error = send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(test_data()),
+        &token_manager(&base),
+        &base.mock_fcm_url(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        error,
+        FcmError::FcmNetworkError(NetworkError::ServerError(401, ..))
+    ));
+    mock_auth.assert_async().await;
+    mock_unauthorized.assert_async().await;
+}
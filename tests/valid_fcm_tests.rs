@@ -51,7 +51,7 @@ async fn successful_fcm_test() {
             .expect("Failed to create SharedTokenManager");
 
     {
-        let mut guard = shared_token_manager.lock().await;
+        let guard = shared_token_manager.lock().await;
 
         assert!(guard.is_token_expired());
 
@@ -125,7 +125,7 @@ async fn successful_fcm_test_from_string() {
         create_shared_token_manager(creds.as_bytes()).expect("Failed to create SharedTokenManager");
 
     {
-        let mut guard = shared_token_manager.lock().await;
+        let guard = shared_token_manager.lock().await;
 
         assert!(guard.is_token_expired());
 
@@ -159,3 +159,132 @@ async fn successful_fcm_test_from_string() {
     mock_auth.assert_async().await;
     mock_fcm.assert_async().await;
 }
+
+#[tokio::test]
+async fn successful_fcm_test_returns_message_id() {
+    let mut server = mockito::Server::new_async().await;
+
+    let project_id = "mock_project_id";
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        format!("/v1/projects/{}/messages:send", project_id),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let message_id = "projects/mock_project_id/messages/0:1500415314455276%31bd1c9631bd1c96";
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body(json!({ "name": message_id }).to_string())
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let data = TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    };
+
+    let response = send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(data),
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send FCM message");
+
+    assert_eq!(response.message_id.as_deref(), Some(message_id));
+    assert_eq!(response.raw, json!({ "name": message_id }));
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
+
+#[tokio::test]
+async fn successful_fcm_test_with_malformed_response() {
+    let mut server = mockito::Server::new_async().await;
+
+    let project_id = "mock_project_id";
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        format!("/v1/projects/{}/messages:send", project_id),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(200)
+        .with_body("<html>not json</html>")
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let data = TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    };
+
+    let response = send_fcm_message_with_url(
+        &base.device_token,
+        None,
+        Some(data),
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("A malformed success response should not fail the send");
+
+    assert!(response.message_id.is_none());
+    assert!(response.raw.is_null());
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
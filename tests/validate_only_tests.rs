@@ -0,0 +1,77 @@
+use std::fs::File;
+
+use mockito::Matcher;
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::send_message_with_url;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::Message;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+#[tokio::test]
+async fn validate_only_is_sent_next_to_message() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Json(json!({
+            "validate_only": true,
+            "message": {
+                "token": base.device_token,
+                "notification": { "title": "Test title", "body": "Test body" }
+            }
+        })))
+        .with_status(200)
+        .with_body(
+            json!({ "name": "projects/mock_project_id/messages/fake_message_id" }).to_string(),
+        )
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let message = Message::builder()
+        .token(base.device_token.as_str())
+        .notification(FcmNotification::new("Test title", "Test body"))
+        .validate_only(true)
+        .build()
+        .expect("Failed to build message");
+
+    send_message_with_url(&message, &shared_token_manager, &base.mock_fcm_url())
+        .await
+        .expect("Failed to validate FCM message");
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}
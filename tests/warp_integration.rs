@@ -0,0 +1,123 @@
+#![cfg(feature = "warp")]
+
+use std::fs::File;
+
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::warp::handle_rejection;
+use oauth_fcm::warp::with_fcm;
+use oauth_fcm::FcmClient;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::Message;
+use serde_json::json;
+use warp::Filter;
+
+use crate::test_helpers::FcmBaseTest;
+
+mod test_helpers;
+
+async fn send_through_route(fcm_status: usize, fcm_body: &str) -> (u16, serde_json::Value) {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .with_status(fcm_status)
+        .with_body(fcm_body)
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let client = FcmClient::builder()
+        .token_manager(shared_token_manager)
+        .project_id("mock_project_id")
+        .endpoint(server.url())
+        .build()
+        .await
+        .expect("Failed to build FcmClient");
+
+    let device_token = base.device_token.clone();
+    let route = warp::post()
+        .and(with_fcm(client))
+        .and_then(move |client: FcmClient| {
+            let device_token = device_token.clone();
+            async move {
+                let message = Message::builder()
+                    .token(device_token)
+                    .notification(FcmNotification::new("Test title", "Test body"))
+                    .build()
+                    .map_err(warp::reject::custom)?;
+                client.send(&message).await.map_err(warp::reject::custom)?;
+                Ok::<_, warp::Rejection>(warp::reply::json(&json!({ "sent": true })))
+            }
+        })
+        .recover(handle_rejection);
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/send")
+        .reply(&route)
+        .await;
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+
+    (
+        response.status().as_u16(),
+        serde_json::from_slice(response.body()).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn warp_route_sends_message() {
+    let (status, body) = send_through_route(200, "{}").await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body["sent"], true);
+}
+
+#[tokio::test]
+async fn warp_route_maps_fcm_error_to_status() {
+    let fcm_body = json!({
+        "error": {
+            "code": 404,
+            "message": "Requested entity was not found.",
+            "status": "NOT_FOUND",
+            "details": [{ "errorCode": "UNREGISTERED" }]
+        }
+    })
+    .to_string();
+
+    let (status, body) = send_through_route(404, &fcm_body).await;
+
+    assert_eq!(status, 410);
+    assert!(body["error"].as_str().unwrap().contains("404"));
+    assert_eq!(body["kind"], "FcmResponseError");
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::fs::File;
+
+use mockito::Matcher;
+use oauth_fcm::create_shared_token_manager;
+use oauth_fcm::send_fcm_message_with_config_and_url;
+use oauth_fcm::FcmNotification;
+use oauth_fcm::MessageTarget;
+use oauth_fcm::PlatformConfig;
+use oauth_fcm::WebpushConfig;
+use oauth_fcm::WebpushFcmOptions;
+use serde_json::json;
+
+use crate::test_helpers::FcmBaseTest;
+use crate::test_helpers::TestData;
+
+mod test_helpers;
+
+#[tokio::test]
+async fn webpush_config_is_combined_with_notification_and_data() {
+    let mut server = mockito::Server::new_async().await;
+
+    let base = FcmBaseTest::new(
+        server.url(),
+        "/token".to_string(),
+        server.url(),
+        "/v1/projects/mock_project_id/messages:send".to_string(),
+    );
+
+    let mock_auth = server
+        .mock("POST", base.oauth_path.as_str())
+        .with_status(200)
+        .with_body(
+            json!({
+                "access_token": base.access_token,
+                "scope": "https://www.googleapis.com/auth/prediction",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        )
+        .create();
+
+    let mock_fcm = server
+        .mock("POST", base.fcm_path.as_str())
+        .match_body(Matcher::Json(json!({
+            "message": {
+                "token": base.device_token,
+                "notification": {
+                    "title": "Test title",
+                    "body": "Test body"
+                },
+                "data": {
+                    "title": "Test title",
+                    "description": "Test description"
+                },
+                "webpush": {
+                    "headers": {
+                        "TTL": "3600",
+                        "Urgency": "high"
+                    },
+                    "notification": {
+                        "icon": "https://example.com/icon.png",
+                        "actions": [{ "action": "open", "title": "Open" }]
+                    },
+                    "fcm_options": {
+                        "link": "https://example.com/news"
+                    }
+                }
+            }
+        })))
+        .with_status(200)
+        .create();
+
+    let shared_token_manager =
+        create_shared_token_manager(File::open("tests/mock_credentials.json").unwrap())
+            .expect("Failed to create SharedTokenManager");
+    shared_token_manager
+        .lock()
+        .await
+        .refresh_token_with_url(&base.mock_auth_url())
+        .await
+        .expect("Failed to refresh token");
+
+    let notification = FcmNotification::new("Test title", "Test body");
+    let data = TestData {
+        title: "Test title".to_string(),
+        description: "Test description".to_string(),
+    };
+    let config = PlatformConfig {
+        webpush: Some(WebpushConfig {
+            headers: HashMap::from([
+                ("TTL".to_string(), "3600".to_string()),
+                ("Urgency".to_string(), "high".to_string()),
+            ]),
+            notification: Some(json!({
+                "icon": "https://example.com/icon.png",
+                "actions": [{ "action": "open", "title": "Open" }]
+            })),
+            fcm_options: Some(WebpushFcmOptions {
+                link: Some("https://example.com/news".to_string()),
+                ..WebpushFcmOptions::default()
+            }),
+            ..WebpushConfig::default()
+        }),
+        ..PlatformConfig::default()
+    };
+
+    send_fcm_message_with_config_and_url(
+        &MessageTarget::Token(base.device_token.clone()),
+        Some(notification),
+        Some(data),
+        &config,
+        &shared_token_manager,
+        &base.mock_fcm_url(),
+    )
+    .await
+    .expect("Failed to send FCM message");
+
+    mock_auth.assert_async().await;
+    mock_fcm.assert_async().await;
+}